@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use clap::ArgMatches;
+use serde_json::json;
+use vibe_core::analysis::{analyze, replay_gain, write_replay_gain, Loudness, LoudnessMeter};
+
+use crate::display::{format_db, json_number, to_db};
+use crate::CommandResult;
+
+/// Measure the loudness and the peaks of files, and write their ReplayGain tags
+pub fn run(args: &ArgMatches) -> CommandResult {
+    let paths: Vec<&Path> = args.values_of_os("FILES").unwrap().map(Path::new).collect();
+    let json = args.is_present("json");
+
+    let mut meters = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let meter = crate::open(path)
+            .and_then(|decoder| analyze(decoder).map_err(|err| err.to_string()))
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        meters.push(meter);
+    }
+    let tracks: Vec<Loudness> = meters.iter().map(LoudnessMeter::loudness).collect();
+    let album = Some(LoudnessMeter::album(&meters)).filter(|_| args.is_present("album"));
+
+    if json {
+        let tracks: Vec<_> = paths
+            .iter()
+            .zip(&tracks)
+            .map(|(path, loudness)| {
+                let mut value = to_json(loudness);
+                value["path"] = json!(path.display().to_string());
+                value
+            })
+            .collect();
+        let value = json!({
+            "tracks": tracks,
+            "album": album.as_ref().map(to_json),
+        });
+        println!("{:#}", value);
+    } else {
+        for (path, loudness) in paths.iter().zip(&tracks) {
+            println!("{}", path.display());
+            print_text(loudness);
+        }
+        if let Some(album) = &album {
+            println!("Album");
+            print_text(album);
+        }
+    }
+
+    if args.is_present("write") {
+        for (path, loudness) in paths.iter().zip(&tracks) {
+            write_replay_gain(path, &replay_gain(loudness, album.as_ref()))
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+        }
+    }
+    Ok(())
+}
+
+fn print_text(loudness: &Loudness) {
+    println!("  Integrated:  {}", format_db(loudness.integrated, "LUFS"));
+    println!("  Range:       {:.2} LU", loudness.range);
+    println!(
+        "  Sample peak: {}",
+        format_db(to_db(loudness.sample_peak), "dBFS")
+    );
+    println!(
+        "  True peak:   {}",
+        format_db(to_db(loudness.true_peak), "dBTP")
+    );
+    match loudness.gain() {
+        Some(gain) => println!("  Gain:        {:.2} dB", gain),
+        None => println!("  Gain:        none, the track is silent"),
+    }
+}
+
+fn to_json(loudness: &Loudness) -> serde_json::Value {
+    json!({
+        "integrated": json_number(loudness.integrated),
+        "range": json_number(loudness.range),
+        "sample_peak": json_number(loudness.sample_peak),
+        "true_peak": json_number(loudness.true_peak),
+        "gain": loudness.gain(),
+    })
+}
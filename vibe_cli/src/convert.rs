@@ -0,0 +1,101 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use clap::ArgMatches;
+use crossterm::tty::IsTty;
+use vibe_core::encoder::{EncoderFormat, FlacConfig, Mp3Bitrate, VorbisConfig, WavSampleFormat};
+use vibe_core::transcode::{transcode_file, Progress, TranscodeConfig};
+
+use crate::{parse_arg, CommandResult};
+
+/// Names of the output formats, also the extensions of their files
+pub const FORMATS: &[&str] = &["wav", "flac", "ogg", "mp3"];
+
+/// Convert a file to another format, sample rate or number of channels
+pub fn run(args: &ArgMatches) -> CommandResult {
+    let input = Path::new(args.value_of_os("INPUT").unwrap());
+    let output = Path::new(args.value_of_os("OUTPUT").unwrap());
+
+    let name = match args.value_of("format") {
+        Some(name) => name.to_owned(),
+        None => output
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase)
+            .filter(|extension| FORMATS.contains(&extension.as_str()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown format of {}, use --format to choose one",
+                    output.display()
+                )
+            })?,
+    };
+
+    let config = TranscodeConfig {
+        sample_rate: parse_arg(args, "sample-rate")?,
+        channels: parse_arg(args, "channels")?,
+        dither: !args.is_present("no-dither"),
+        ..TranscodeConfig::new(encoder_format(&name, args)?)
+    };
+
+    let quiet = args.is_present("quiet") || !io::stderr().is_tty();
+    let result = transcode_file(input, output, &config, |progress| {
+        if !quiet {
+            print_progress(progress);
+        }
+        true
+    });
+    if !quiet {
+        eprintln!();
+    }
+    result.map_err(|err| format!("{}: {}", input.display(), err))
+}
+
+/// Get the settings of the encoder from the arguments
+fn encoder_format(name: &str, args: &ArgMatches) -> Result<EncoderFormat, String> {
+    let bits = args.value_of("bits");
+    let format = match name {
+        "wav" => EncoderFormat::Wav(match bits {
+            None | Some("16") => WavSampleFormat::Pcm16,
+            Some("24") => WavSampleFormat::Pcm24,
+            Some("32") => WavSampleFormat::Pcm32,
+            Some("float") => WavSampleFormat::Float32,
+            Some(bits) => return Err(format!("WAV files can't have {} bits samples", bits)),
+        }),
+        "flac" => {
+            let default = FlacConfig::default();
+            EncoderFormat::Flac(FlacConfig {
+                bits_per_sample: match bits {
+                    None => default.bits_per_sample,
+                    Some(bits) => bits
+                        .parse()
+                        .map_err(|_| "FLAC files can't have float samples".to_owned())?,
+                },
+                compression_level: parse_arg(args, "compression")?
+                    .unwrap_or(default.compression_level),
+            })
+        }
+        "ogg" => EncoderFormat::Vorbis(VorbisConfig {
+            quality: parse_arg(args, "quality")?.unwrap_or(VorbisConfig::default().quality),
+        }),
+        "mp3" => EncoderFormat::Mp3(
+            match (parse_arg(args, "bitrate")?, parse_arg(args, "quality")?) {
+                (Some(bitrate), _) => Mp3Bitrate::Constant(bitrate),
+                (None, Some(quality)) => Mp3Bitrate::Variable(quality),
+                (None, None) => Mp3Bitrate::default(),
+            },
+        ),
+        _ => unreachable!("unknown format {}", name),
+    };
+    Ok(format)
+}
+
+/// Print the progress on the same line of the terminal
+fn print_progress(progress: Progress) {
+    let mut stderr = io::stderr();
+    let _ = match progress.fraction() {
+        Some(fraction) => write!(stderr, "\rConverting... {:3.0}%", fraction * 100.0),
+        None => write!(stderr, "\rConverting... {} frames", progress.frames),
+    };
+    let _ = stderr.flush();
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use vibe_engine::player::format_time;
+
+/// Format a duration as `m:ss.mmm`, or `h:mm:ss.mmm` past an hour
+pub fn format_duration(duration: Duration) -> String {
+    format!("{}.{:03}", format_time(duration), duration.subsec_millis())
+}
+
+/// Format a level in decibels, silence is shown as `-inf`
+pub fn format_db(value: f64, unit: &str) -> String {
+    if value.is_finite() {
+        format!("{:.2} {}", value, unit)
+    } else {
+        format!("-inf {}", unit)
+    }
+}
+
+/// Convert a linear amplitude to decibels
+#[inline]
+pub fn to_db(value: f64) -> f64 {
+    20.0 * value.log10()
+}
+
+/// Get the name of an ID3v2 picture type, also used by FLAC and Vorbis comments
+pub fn picture_type_name(picture_type: u8) -> &'static str {
+    match picture_type {
+        0 => "other",
+        1 => "file icon",
+        2 => "other file icon",
+        3 => "front cover",
+        4 => "back cover",
+        5 => "leaflet page",
+        6 => "media",
+        7 => "lead artist",
+        8 => "artist",
+        9 => "conductor",
+        10 => "band",
+        11 => "composer",
+        12 => "lyricist",
+        13 => "recording location",
+        14 => "during recording",
+        15 => "during performance",
+        16 => "screen capture",
+        17 => "bright coloured fish",
+        18 => "illustration",
+        19 => "band logotype",
+        20 => "publisher logotype",
+        _ => "unknown",
+    }
+}
+
+/// Get a JSON number, or null for the values JSON can't represent such as the loudness of silence
+pub fn json_number(value: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(value)
+        .map(serde_json::Value::Number)
+        .unwrap_or(serde_json::Value::Null)
+}
@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use clap::ArgMatches;
+use serde_json::json;
+use vibe_core::{AudioInfo, LoopRegion, Metadata};
+
+use crate::display::{format_duration, picture_type_name};
+use crate::CommandResult;
+
+/// Print the format, the tags and the pictures of a file
+pub fn run(args: &ArgMatches) -> CommandResult {
+    let path = Path::new(args.value_of_os("FILE").unwrap());
+    let decoder = crate::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let info = decoder.info();
+
+    if args.is_present("json") {
+        let value = to_json(path, &info, decoder.metadata(), decoder.loops());
+        println!("{:#}", value);
+    } else {
+        print_text(path, &info, decoder.metadata(), decoder.loops());
+    }
+    Ok(())
+}
+
+fn print_text(path: &Path, info: &AudioInfo, metadata: &Metadata, loops: &[LoopRegion]) {
+    println!("File:        {}", path.display());
+    println!("Format:      {}", info.format());
+    println!("Sample rate: {} Hz", info.sample_rate());
+    println!("Channels:    {}", info.channels());
+    match info.duration() {
+        Some(duration) => println!("Duration:    {}", format_duration(duration)),
+        None => println!("Duration:    unknown"),
+    }
+
+    let mut tags = metadata.iter().peekable();
+    if tags.peek().is_some() {
+        println!("Tags:");
+        for (key, value) in tags {
+            println!("  {}: {}", key, value);
+        }
+    }
+
+    if !metadata.pictures().is_empty() {
+        println!("Pictures:");
+        for picture in metadata.pictures() {
+            print!(
+                "  {}, {}, {} bytes",
+                picture_type_name(picture.picture_type),
+                picture.mime_type,
+                picture.data.len()
+            );
+            if picture.description.is_empty() {
+                println!();
+            } else {
+                println!(", \"{}\"", picture.description);
+            }
+        }
+    }
+
+    if !loops.is_empty() {
+        println!("Loops:");
+        for region in loops {
+            println!(
+                "  {} - {}",
+                format_duration(region.start_time(info.sample_rate())),
+                format_duration(region.end_time(info.sample_rate()))
+            );
+        }
+    }
+}
+
+fn to_json(
+    path: &Path,
+    info: &AudioInfo,
+    metadata: &Metadata,
+    loops: &[LoopRegion],
+) -> serde_json::Value {
+    // Keys can be repeated, such as several artists
+    let tags: Vec<_> = metadata
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": value }))
+        .collect();
+    let pictures: Vec<_> = metadata
+        .pictures()
+        .iter()
+        .map(|picture| {
+            json!({
+                "type": picture.picture_type,
+                "mime_type": picture.mime_type,
+                "description": picture.description,
+                "size": picture.data.len(),
+            })
+        })
+        .collect();
+    let loops: Vec<_> = loops
+        .iter()
+        .map(|region| json!({ "start": region.start, "end": region.end }))
+        .collect();
+    let replay_gain = metadata.replay_gain();
+
+    json!({
+        "path": path.display().to_string(),
+        "format": info.format().to_string(),
+        "sample_rate": info.sample_rate(),
+        "channels": info.channels(),
+        "duration": info.duration().map(|duration| duration.as_secs_f64()),
+        "tags": tags,
+        "pictures": pictures,
+        "loops": loops,
+        "replay_gain": {
+            "track_gain": replay_gain.track_gain,
+            "track_peak": replay_gain.track_peak,
+            "album_gain": replay_gain.album_gain,
+            "album_peak": replay_gain.album_peak,
+        },
+    })
+}
@@ -0,0 +1,201 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::process;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use vibe_core::decoder::Decoder;
+
+mod analyze;
+mod convert;
+mod display;
+mod info;
+mod play;
+mod verify;
+
+/// Result of a command, the error is printed before exiting with a failure code
+pub type CommandResult = Result<(), String>;
+
+pub fn main() {
+    let matches = app().get_matches();
+
+    let result = match matches.subcommand() {
+        ("play", Some(args)) => play::run(args),
+        ("info", Some(args)) => info::run(args),
+        ("convert", Some(args)) => convert::run(args),
+        ("analyze", Some(args)) => analyze::run(args),
+        ("verify", Some(args)) => verify::run(args),
+        _ => unreachable!("a subcommand is required"),
+    };
+
+    if let Err(err) = result {
+        eprintln!("vibe: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Description of the command line
+fn app() -> App<'static, 'static> {
+    let files = Arg::with_name("FILES")
+        .help("Audio files")
+        .required(true)
+        .multiple(true);
+    let json = Arg::with_name("json")
+        .long("json")
+        .help("Print the result as JSON");
+
+    App::new("vibe")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Play, inspect and convert .flac, .wav, .mp3 and .ogg files")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
+        .subcommand(
+            SubCommand::with_name("play")
+                .about("Play files one after the other, with keyboard controls in a terminal")
+                .arg(files.clone())
+                .arg(
+                    Arg::with_name("shuffle")
+                        .long("shuffle")
+                        .help("Play the files in a random order"),
+                )
+                .arg(
+                    Arg::with_name("repeat")
+                        .long("repeat")
+                        .takes_value(true)
+                        .possible_values(&["off", "one", "all"])
+                        .default_value("off")
+                        .help("Play the current file or all the files again"),
+                )
+                .arg(
+                    Arg::with_name("crossfade")
+                        .long("crossfade")
+                        .value_name("SECONDS")
+                        .help("Crossfade between consecutive files"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .value_name("FILE")
+                        .help("Render the playback to a WAV file instead of the audio device"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Print the format, the tags and the pictures of a file")
+                .arg(Arg::with_name("FILE").help("Audio file").required(true))
+                .arg(json.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("Convert a file to another format, sample rate or number of channels")
+                .arg(Arg::with_name("INPUT").help("Source file").required(true))
+                .arg(
+                    Arg::with_name("OUTPUT")
+                        .help("Converted file, the format follows its extension")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .short("f")
+                        .takes_value(true)
+                        .possible_values(convert::FORMATS)
+                        .help("Format of the output, instead of the one of its extension"),
+                )
+                .arg(
+                    Arg::with_name("sample-rate")
+                        .long("sample-rate")
+                        .short("r")
+                        .value_name("HZ")
+                        .help("Sample rate of the output"),
+                )
+                .arg(
+                    Arg::with_name("channels")
+                        .long("channels")
+                        .short("c")
+                        .value_name("COUNT")
+                        .help("Number of channels of the output"),
+                )
+                .arg(
+                    Arg::with_name("bits")
+                        .long("bits")
+                        .short("b")
+                        .takes_value(true)
+                        .possible_values(&["8", "16", "24", "32", "float"])
+                        .help("Sample format of WAV and FLAC files"),
+                )
+                .arg(
+                    Arg::with_name("compression")
+                        .long("compression")
+                        .value_name("LEVEL")
+                        .help("Compression level of FLAC files, from 0 to 8"),
+                )
+                .arg(
+                    Arg::with_name("quality")
+                        .long("quality")
+                        .short("q")
+                        .value_name("QUALITY")
+                        .help("Ogg Vorbis quality from 0 to 1, or MP3 VBR quality from 0 to 9"),
+                )
+                .arg(
+                    Arg::with_name("bitrate")
+                        .long("bitrate")
+                        .value_name("KBPS")
+                        .conflicts_with("quality")
+                        .help("Constant bitrate of MP3 files"),
+                )
+                .arg(
+                    Arg::with_name("no-dither")
+                        .long("no-dither")
+                        .help("Round the samples without dither"),
+                )
+                .arg(
+                    Arg::with_name("quiet")
+                        .long("quiet")
+                        .help("Don't print the progress in a terminal"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("analyze")
+                .about("Measure the loudness and the peaks of files")
+                .arg(files.clone())
+                .arg(
+                    Arg::with_name("album")
+                        .long("album")
+                        .help("Measure the files as one album too"),
+                )
+                .arg(
+                    Arg::with_name("write")
+                        .long("write")
+                        .help("Write the ReplayGain values in the tags of the files"),
+                )
+                .arg(json.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Decode files to their end and report the ones that are damaged")
+                .arg(files)
+                .arg(json),
+        )
+}
+
+/// Open an audio file, the error doesn't name the file
+pub fn open(path: &Path) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    Decoder::new(BufReader::new(file)).map_err(|_| "unsupported audio format".to_owned())
+}
+
+/// Parse the value of an argument, if given
+pub fn parse_arg<T>(args: &ArgMatches, name: &str) -> Result<Option<T>, String>
+where
+    T: std::str::FromStr,
+{
+    args.value_of(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("invalid value for --{}: {}", name, value))
+        })
+        .transpose()
+}
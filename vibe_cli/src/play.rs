@@ -0,0 +1,210 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::ArgMatches;
+use crossbeam::channel::Receiver;
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::queue;
+use crossterm::terminal::{self, ClearType};
+use crossterm::tty::IsTty;
+use vibe_engine::player::{Player, PlayerEvent, PlayerState, PlayerStatus, SEEK_STEP};
+use vibe_engine::queue::{RepeatMode, ShuffleMode};
+use vibe_engine::sink::WavSink;
+use vibe_engine::stream::{FadeConfig, OutputFormat};
+
+use crate::{parse_arg, CommandResult};
+
+/// Format of the files rendered with `--output`
+const RENDER_FORMAT: OutputFormat = OutputFormat {
+    sample_rate: 44100,
+    channels: 2,
+};
+
+/// Interval between two refreshes of the status line
+const REFRESH: Duration = Duration::from_millis(100);
+
+const HELP: &str = "space: play/pause  left/right: seek  n/p: next/previous  s: stop  \
+                    r: repeat  z: shuffle  q: quit";
+
+/// Play files one after the other
+///
+/// In a terminal the playback is controlled with the keyboard, otherwise the
+/// command returns at the end of the queue
+pub fn run(args: &ArgMatches) -> CommandResult {
+    let paths: Vec<PathBuf> = args
+        .values_of_os("FILES")
+        .unwrap()
+        .map(PathBuf::from)
+        .collect();
+    let output = args.value_of_os("output").map(Path::new);
+
+    let player = match output {
+        Some(path) => {
+            let sink = WavSink::create(path, RENDER_FORMAT)
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+            Player::with_sink(sink, FadeConfig::default())
+        }
+        None => Player::new(),
+    };
+
+    player.set_repeat_mode(match args.value_of("repeat") {
+        Some("one") => RepeatMode::One,
+        Some("all") => RepeatMode::All,
+        _ => RepeatMode::Off,
+    });
+    if args.is_present("shuffle") {
+        player.set_shuffle_mode(ShuffleMode::NoRepeat);
+    }
+    if let Some(secs) = parse_arg::<f64>(args, "crossfade")? {
+        if !secs.is_finite() || secs < 0.0 {
+            return Err(format!("invalid value for --crossfade: {}", secs));
+        }
+        player.set_crossfade(Duration::from_secs_f64(secs));
+    }
+
+    let events = player.events();
+    for path in paths {
+        player.enqueue(path);
+    }
+    player.play_stream();
+
+    if output.is_none() && io::stdin().is_tty() && io::stdout().is_tty() {
+        play_interactive(&player, &events)
+    } else {
+        play_to_end(&player, &events);
+        // The rendered file is complete once the output is closed
+        drop(player);
+        Ok(())
+    }
+}
+
+/// Print the tracks being played until the end of the queue
+fn play_to_end(player: &Player, events: &Receiver<PlayerEvent>) {
+    let mut status = PlayerStatus::new(player);
+    for event in events.iter() {
+        status.handle_event(&event);
+        match event {
+            PlayerEvent::TrackChanged { .. } => println!("Playing {}", title(&status)),
+            PlayerEvent::Error(err) => eprintln!("vibe: {}", err),
+            PlayerEvent::QueueEnded => break,
+            _ => {}
+        }
+    }
+}
+
+/// Play with the keyboard controls and a status line, until the end of the queue or `q`
+fn play_interactive(player: &Player, events: &Receiver<PlayerEvent>) -> CommandResult {
+    let _raw_mode = RawMode::enable()?;
+    let mut stdout = io::stdout();
+    let mut status = PlayerStatus::new(player);
+
+    print_line(&mut stdout, HELP);
+    loop {
+        for event in events.try_iter() {
+            status.handle_event(&event);
+            match event {
+                PlayerEvent::TrackChanged { index, .. } => {
+                    let count = player.queue().len();
+                    let line = format!("[{}/{}] {}", index + 1, count, title(&status));
+                    print_line(&mut stdout, &line);
+                }
+                PlayerEvent::Error(err) => print_line(&mut stdout, &format!("Error: {}", err)),
+                PlayerEvent::QueueEnded => {
+                    print_line(&mut stdout, "");
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        print_status(&mut stdout, &status);
+
+        if !event::poll(REFRESH).map_err(|err| err.to_string())? {
+            continue;
+        }
+        let key = match event::read().map_err(|err| err.to_string())? {
+            Event::Key(key) => key,
+            _ => continue,
+        };
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('c'), KeyModifiers::CONTROL)
+            | (KeyCode::Char('q'), _)
+            | (KeyCode::Esc, _) => break,
+            (KeyCode::Char(' '), _) => status.toggle_play(player),
+            (KeyCode::Left, _) => status.seek_by(player, -SEEK_STEP),
+            (KeyCode::Right, _) => status.seek_by(player, SEEK_STEP),
+            (KeyCode::Char('n'), _) => player.next_track(),
+            (KeyCode::Char('p'), _) => player.previous_track(),
+            (KeyCode::Char('s'), _) => player.stop_stream(),
+            (KeyCode::Char('r'), _) => status.cycle_repeat(player),
+            (KeyCode::Char('z'), _) => status.cycle_shuffle(player),
+            _ => {}
+        }
+    }
+
+    print_line(&mut stdout, "");
+    Ok(())
+}
+
+/// Get the artist and the title of the track being played
+fn title(status: &PlayerStatus) -> String {
+    let title = status.title().unwrap_or_default();
+    match status.metadata().artist() {
+        Some(artist) => format!("{} - {}", artist, title),
+        None => title,
+    }
+}
+
+/// Raw mode of the terminal, so the keys are read without waiting for enter
+///
+/// The terminal is restored when dropped, even on panic
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Result<Self, String> {
+        terminal::enable_raw_mode().map_err(|err| err.to_string())?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Print a line over the status line, which is drawn again below it
+fn print_line(stdout: &mut io::Stdout, text: &str) {
+    let _ = write!(stdout, "\r");
+    let _ = queue!(stdout, terminal::Clear(ClearType::CurrentLine));
+    // The raw mode doesn't move back to the first column on a new line
+    let _ = write!(stdout, "{}\r\n", text);
+    let _ = stdout.flush();
+}
+
+fn print_status(stdout: &mut io::Stdout, status: &PlayerStatus) {
+    let state = match status.state() {
+        PlayerState::Empty | PlayerState::Loading => "loading",
+        PlayerState::Playing => "playing",
+        PlayerState::Paused => "paused",
+        PlayerState::Stopped => "stopped",
+        PlayerState::Ended => "ended",
+        PlayerState::Error => "error",
+    };
+    let mut line = format!("[{}] {}", state, status.time());
+    match status.repeat() {
+        RepeatMode::Off => {}
+        RepeatMode::One => line.push_str("  repeat: one"),
+        RepeatMode::All => line.push_str("  repeat: all"),
+    }
+    match status.shuffle() {
+        ShuffleMode::Off => {}
+        ShuffleMode::Random => line.push_str("  shuffle: random"),
+        ShuffleMode::NoRepeat => line.push_str("  shuffle"),
+    }
+
+    let _ = write!(stdout, "\r");
+    let _ = queue!(stdout, terminal::Clear(ClearType::CurrentLine));
+    let _ = write!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use clap::ArgMatches;
+use serde_json::json;
+use vibe_core::decoder::{duration_to_frames, frames_to_duration};
+use vibe_core::{tags, AudioFormat};
+
+use crate::display::format_duration;
+use crate::CommandResult;
+
+/// Result of the check of a file
+struct Report {
+    sample_rate: u32,
+    /// Number of frames decoded before the end or the error
+    frames: u64,
+    error: Option<String>,
+}
+
+/// Decode files to their end and report the ones that are damaged
+pub fn run(args: &ArgMatches) -> CommandResult {
+    let paths: Vec<&Path> = args.values_of_os("FILES").unwrap().map(Path::new).collect();
+    let json = args.is_present("json");
+
+    let mut failed = 0;
+    let mut values = Vec::new();
+    for path in &paths {
+        let report = check(path);
+        if report.error.is_some() {
+            failed += 1;
+        }
+
+        let duration = frames_to_duration(report.frames, report.sample_rate);
+        if json {
+            values.push(json!({
+                "path": path.display().to_string(),
+                "ok": report.error.is_none(),
+                "frames": report.frames,
+                "duration": duration.as_secs_f64(),
+                "error": report.error,
+            }));
+        } else {
+            match &report.error {
+                None => println!("OK      {} ({})", path.display(), format_duration(duration)),
+                Some(err) => println!("FAILED  {}: {}", path.display(), err),
+            }
+        }
+    }
+    if json {
+        println!("{:#}", json!(values));
+    }
+
+    if failed > 0 {
+        Err(format!("{} of {} files are damaged", failed, paths.len()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Read the tags and decode every sample of a file
+fn check(path: &Path) -> Report {
+    let mut report = Report {
+        sample_rate: 0,
+        frames: 0,
+        error: None,
+    };
+    let decoder = match crate::open(path) {
+        Ok(decoder) => decoder,
+        Err(err) => {
+            report.error = Some(err);
+            return report;
+        }
+    };
+    let info = decoder.info();
+    report.sample_rate = info.sample_rate();
+    if info.channels() == 0 || info.sample_rate() == 0 {
+        report.error = Some("no audio stream".to_owned());
+        return report;
+    }
+
+    if let Err(err) = tags::read_metadata(path) {
+        report.error = Some(format!("tags: {}", err));
+        return report;
+    }
+
+    let mut samples = 0u64;
+    for sample in decoder {
+        match sample {
+            Ok(sample) if sample.is_finite() => samples += 1,
+            Ok(_) => {
+                report.error = Some("invalid sample".to_owned());
+                break;
+            }
+            Err(err) => {
+                report.error = Some(err.to_string());
+                break;
+            }
+        }
+    }
+    report.frames = samples / info.channels() as u64;
+
+    if report.error.is_none() && !samples.is_multiple_of(info.channels() as u64) {
+        report.error = Some("the last frame is incomplete".to_owned());
+    }
+    // The duration of WAV and FLAC files is read from their header, the others are estimated
+    if let (None, AudioFormat::Wav | AudioFormat::Flac, Some(duration)) =
+        (&report.error, info.format(), info.duration())
+    {
+        if report.frames < duration_to_frames(duration, info.sample_rate()) {
+            report.error = Some(format!(
+                "the file is truncated, its duration is {}",
+                format_duration(duration)
+            ));
+        }
+    }
+    if let Some(err) = &mut report.error {
+        let position = frames_to_duration(report.frames, report.sample_rate);
+        *err = format!("{} (at {})", err, format_duration(position));
+    }
+    report
+}
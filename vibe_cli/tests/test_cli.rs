@@ -0,0 +1,171 @@
+#[cfg(test)]
+
+mod tests_cli {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process::{Command, Output};
+
+    const SOUNDS: &str = "../vibe_core/tests/sounds";
+
+    fn vibe(args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_vibe"))
+            .args(args)
+            .output()
+            .expect("Failed to run vibe")
+    }
+
+    fn sound(name: &str) -> String {
+        format!("{}/{}", SOUNDS, name)
+    }
+
+    fn temp(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("vibe_cli_{}_{}", std::process::id(), name))
+    }
+
+    fn json(output: &Output) -> serde_json::Value {
+        assert!(output.status.success(), "{:?}", output);
+        serde_json::from_slice(&output.stdout).expect("Invalid JSON")
+    }
+
+    #[test]
+
+    fn test_cli_info() {
+        for name in &["Test1.flac", "Test1.mp3", "Test1.ogg", "Test1.wav"] {
+            let info = json(&vibe(&["info", "--json", &sound(name)]));
+            assert_eq!(info["channels"], 2);
+            assert!(info["sample_rate"] == 44100 || info["sample_rate"] == 48000);
+            let duration = info["duration"].as_f64().unwrap();
+            assert!((duration - 3.0).abs() < 0.1, "{}", duration);
+        }
+
+        let output = vibe(&["info", &sound("Test1.flac")]);
+        assert!(output.status.success());
+        let text = String::from_utf8(output.stdout).unwrap();
+        assert!(text.contains("Format:      FLAC"));
+        assert!(text.contains("Sample rate: 48000 Hz"));
+
+        let output = vibe(&["info", "missing.flac"]);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("vibe: missing.flac"));
+    }
+
+    #[test]
+
+    fn test_cli_convert() {
+        let output = temp("convert.flac");
+        let path = output.to_str().unwrap();
+        let result = vibe(&[
+            "convert",
+            &sound("Test1.wav"),
+            path,
+            "--sample-rate",
+            "44100",
+            "--channels",
+            "1",
+            "--bits",
+            "24",
+        ]);
+        assert!(result.status.success(), "{:?}", result);
+
+        let info = json(&vibe(&["info", "--json", path]));
+        assert_eq!(info["format"], "FLAC");
+        assert_eq!(info["sample_rate"], 44100);
+        assert_eq!(info["channels"], 1);
+
+        // The format can't be guessed without an extension, and MP3 has no 33 kbit/s bitrate
+        let unknown = temp("convert");
+        let result = vibe(&["convert", &sound("Test1.wav"), unknown.to_str().unwrap()]);
+        assert!(!result.status.success());
+        let mp3 = temp("convert.mp3");
+        let result = vibe(&[
+            "convert",
+            &sound("Test1.wav"),
+            mp3.to_str().unwrap(),
+            "--bitrate",
+            "33",
+        ]);
+        assert!(!result.status.success());
+        assert!(!mp3.exists());
+
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+
+    fn test_cli_analyze() {
+        let files = [sound("Test1.wav"), sound("Test1.flac")];
+        let result = json(&vibe(&[
+            "analyze", "--json", "--album", &files[0], &files[1],
+        ]));
+        let tracks = result["tracks"].as_array().unwrap();
+        assert_eq!(tracks.len(), 2);
+        for track in tracks {
+            let integrated = track["integrated"].as_f64().unwrap();
+            let gain = track["gain"].as_f64().unwrap();
+            assert!((integrated + gain + 18.0).abs() < 1e-6);
+            assert!(track["true_peak"].as_f64() >= track["sample_peak"].as_f64());
+        }
+        assert!(result["album"]["integrated"].is_number());
+
+        // The ReplayGain tags are written in a copy of the file
+        let copy = temp("analyze.flac");
+        fs::copy(&files[1], &copy).unwrap();
+        let result = vibe(&["analyze", "--write", copy.to_str().unwrap()]);
+        assert!(result.status.success(), "{:?}", result);
+        let info = json(&vibe(&["info", "--json", copy.to_str().unwrap()]));
+        let gain = info["replay_gain"]["track_gain"].as_f64().unwrap();
+        assert!((gain - tracks[1]["gain"].as_f64().unwrap()).abs() < 0.01);
+
+        fs::remove_file(copy).unwrap();
+    }
+
+    #[test]
+
+    fn test_cli_verify() {
+        let damaged = temp("damaged.wav");
+        let mut data = fs::read(sound("Test1.wav")).unwrap();
+        data.truncate(data.len() - 1000);
+        fs::write(&damaged, data).unwrap();
+
+        let output = vibe(&["verify", &sound("Test1.ogg"), &sound("Test1.mp3")]);
+        assert!(output.status.success(), "{:?}", output);
+
+        let output = vibe(&[
+            "verify",
+            "--json",
+            &sound("Test1.flac"),
+            damaged.to_str().unwrap(),
+        ]);
+        assert!(!output.status.success());
+        let reports: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(reports[0]["ok"], true);
+        assert_eq!(reports[0]["frames"], 144002);
+        assert_eq!(reports[1]["ok"], false);
+        assert!(reports[1]["error"].is_string());
+
+        fs::remove_file(damaged).unwrap();
+    }
+
+    #[test]
+
+    fn test_cli_play() {
+        // Without a terminal the files are played to the end, here rendered to a file
+        let output = temp("play.wav");
+        let result = vibe(&[
+            "play",
+            "--output",
+            output.to_str().unwrap(),
+            &sound("Test1.flac"),
+            &sound("Test1.ogg"),
+        ]);
+        assert!(result.status.success(), "{:?}", result);
+        let text = String::from_utf8(result.stdout).unwrap();
+        assert_eq!(text.lines().count(), 2);
+
+        let info = json(&vibe(&["info", "--json", output.to_str().unwrap()]));
+        let duration = info["duration"].as_f64().unwrap();
+        assert!((6.0..6.2).contains(&duration), "{}", duration);
+
+        fs::remove_file(output).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use std::env;
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::protocol::{Event, Notification, Request, Response, RpcError, Status, EVENT_METHOD};
+
+/// An error encountered while talking to the daemon.
+#[derive(Debug)]
+pub enum ClientError {
+    /// I/O error on the socket.
+    IOError(io::Error),
+    /// The daemon sent a message that isn't valid.
+    ParseError(serde_json::Error),
+    /// The daemon refused the request.
+    RpcError(RpcError),
+    /// The daemon closed the connection.
+    Closed,
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientError::IOError(err) => Some(err),
+            ClientError::ParseError(err) => Some(err),
+            ClientError::RpcError(_) | ClientError::Closed => None,
+        }
+    }
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::IOError(err) => write!(f, "IO error: {}", err),
+            ClientError::ParseError(err) => write!(f, "parse error: {}", err),
+            ClientError::RpcError(err) => write!(f, "{} ({})", err.message, err.code),
+            ClientError::Closed => write!(f, "the daemon closed the connection"),
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(err: io::Error) -> Self {
+        ClientError::IOError(err)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(err: serde_json::Error) -> Self {
+        ClientError::ParseError(err)
+    }
+}
+
+/// Connection to the daemon, each method waits for the response of the daemon
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: u64,
+}
+
+impl Client {
+    /// Connect to the daemon listening on a socket
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self, ClientError> {
+        let writer = UnixStream::connect(path)?;
+        Ok(Self {
+            reader: BufReader::new(writer.try_clone()?),
+            writer,
+            next_id: 0,
+        })
+    }
+
+    /// Call a method of the daemon, returns its result
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value, ClientError> {
+        self.next_id += 1;
+        let request = Request::new(method, params, self.next_id);
+        let mut message = serde_json::to_vec(&request)?;
+        message.push(b'\n');
+        self.writer.write_all(&message)?;
+
+        loop {
+            let line = read_line(&mut self.reader)?;
+            let message: Value = serde_json::from_str(&line)?;
+            // The events sent before the response are dropped
+            if message.get("id") != Some(&json!(self.next_id)) {
+                continue;
+            }
+            let response: Response = serde_json::from_value(message)?;
+            return match response.error {
+                Some(error) => Err(ClientError::RpcError(error)),
+                None => Ok(response.result.unwrap_or(Value::Null)),
+            };
+        }
+    }
+
+    /// Play the current track of the queue, or resume the playback
+    pub fn play(&mut self) -> Result<(), ClientError> {
+        self.call("play", Value::Null).map(drop)
+    }
+
+    /// Play a track of the queue
+    pub fn play_index(&mut self, index: usize) -> Result<(), ClientError> {
+        self.call("play", json!({ "index": index })).map(drop)
+    }
+
+    /// Pause the playback
+    pub fn pause(&mut self) -> Result<(), ClientError> {
+        self.call("pause", Value::Null).map(drop)
+    }
+
+    /// Stop the playback, playing again starts the track over
+    pub fn stop(&mut self) -> Result<(), ClientError> {
+        self.call("stop", Value::Null).map(drop)
+    }
+
+    /// Play the next track of the queue
+    pub fn next_track(&mut self) -> Result<(), ClientError> {
+        self.call("next", Value::Null).map(drop)
+    }
+
+    /// Play the previous track of the queue
+    pub fn previous_track(&mut self) -> Result<(), ClientError> {
+        self.call("previous", Value::Null).map(drop)
+    }
+
+    /// Move the playback to a position in the track
+    pub fn seek(&mut self, position: Duration) -> Result<(), ClientError> {
+        self.call("seek", json!({ "position": position.as_secs_f64() }))
+            .map(drop)
+    }
+
+    /// Add a file at the end of the queue, returns its index
+    ///
+    /// The daemon doesn't share the working directory of the client, relative paths are
+    /// made absolute first
+    pub fn enqueue<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, ClientError> {
+        let path = env::current_dir()?.join(path);
+        let result = self.call("enqueue", json!({ "path": path }))?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Remove every track of the queue
+    pub fn clear(&mut self) -> Result<(), ClientError> {
+        self.call("clear", Value::Null).map(drop)
+    }
+
+    /// Get the state of the player and the track being played
+    pub fn status(&mut self) -> Result<Status, ClientError> {
+        let result = self.call("status", Value::Null)?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Receive the events of the player, the connection is only used for them afterwards
+    pub fn subscribe(mut self) -> Result<Events, ClientError> {
+        self.call("subscribe", Value::Null)?;
+        Ok(Events {
+            reader: self.reader,
+        })
+    }
+}
+
+/// Events of the player received from the daemon, see `Client::subscribe`
+pub struct Events {
+    reader: BufReader<UnixStream>,
+}
+
+impl Events {
+    /// Wait for the next event
+    pub fn next_event(&mut self) -> Result<Event, ClientError> {
+        loop {
+            let line = read_line(&mut self.reader)?;
+            let notification: Notification = match serde_json::from_str(&line) {
+                Ok(notification) => notification,
+                // Responses have no method
+                Err(_) => continue,
+            };
+            if notification.method == EVENT_METHOD {
+                return Ok(serde_json::from_value(notification.params)?);
+            }
+        }
+    }
+
+    /// Give up waiting for an event after a timeout, `None` waits forever
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<(), ClientError> {
+        self.reader.get_ref().set_read_timeout(timeout)?;
+        Ok(())
+    }
+}
+
+impl Iterator for Events {
+    type Item = Result<Event, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_event() {
+            Err(ClientError::Closed) => None,
+            result => Some(result),
+        }
+    }
+}
+
+/// Read a message, the end of the stream is an error
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, ClientError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(ClientError::Closed);
+    }
+    Ok(line)
+}
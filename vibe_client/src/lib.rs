@@ -0,0 +1,5 @@
+mod client;
+pub mod protocol;
+
+pub use self::client::{Client, ClientError, Events};
+pub use self::protocol::{default_socket_path, Event, Repeat, Shuffle, State, Status};
@@ -0,0 +1,190 @@
+use std::env;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version of JSON-RPC spoken by the daemon
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Method of the notifications carrying the events of the player
+pub const EVENT_METHOD: &str = "event";
+
+/// The message isn't valid JSON
+pub const PARSE_ERROR: i64 = -32700;
+/// The message isn't a JSON-RPC request
+pub const INVALID_REQUEST: i64 = -32600;
+/// The daemon has no such method
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// The parameters are missing or of the wrong type
+pub const INVALID_PARAMS: i64 = -32602;
+/// The player refused the command
+pub const PLAYER_ERROR: i64 = -32000;
+
+/// Call of a method, a request without id is a notification and gets no response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+}
+
+impl Request {
+    /// Create a request expecting a response
+    pub fn new(method: &str, params: Value, id: u64) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_owned(),
+            method: method.to_owned(),
+            params,
+            id: Some(id.into()),
+        }
+    }
+}
+
+/// Result of a request, with the id of the request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: Value,
+}
+
+impl Response {
+    /// Create the response of a request that succeeded
+    pub fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_owned(),
+            result: Some(result),
+            error: None,
+            id,
+        }
+    }
+
+    /// Create the response of a request that failed
+    pub fn failure(id: Value, error: RpcError) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_owned(),
+            result: None,
+            error: Some(error),
+            id,
+        }
+    }
+}
+
+/// Error of a request, the codes are the ones of JSON-RPC
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    /// Create an error with one of the codes of this module
+    pub fn new<S: Into<String>>(code: i64, message: S) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Message sent by the daemon without a request, to the clients subscribed to the events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: Value,
+}
+
+/// Playback state of the player
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    Empty,
+    Loading,
+    Playing,
+    Paused,
+    Stopped,
+    Ended,
+    Error,
+}
+
+/// Repeat mode of the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Repeat {
+    Off,
+    One,
+    All,
+}
+
+/// Shuffle mode of the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shuffle {
+    Off,
+    NoRepeat,
+    Random,
+}
+
+/// Result of the `status` method, the times are in seconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub state: State,
+    /// Index in the queue of the track being played
+    pub index: Option<usize>,
+    pub path: Option<PathBuf>,
+    /// Title of the track, its file name if it has no title tag
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration: Option<f64>,
+    pub position: f64,
+    /// Number of tracks in the queue
+    pub queue_length: usize,
+    pub repeat: Repeat,
+    pub shuffle: Shuffle,
+}
+
+/// Event of the player, the parameters of the `event` notifications
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The player moved to another state
+    State { state: State },
+    /// Position in the track being played, in seconds
+    Position { position: f64 },
+    /// A new track of the queue is being played
+    TrackChanged { index: usize, path: PathBuf },
+    /// The track has been played until its end
+    TrackEnded { index: usize, path: PathBuf },
+    /// The last track of the queue is over
+    QueueEnded,
+    /// Tracks were added to the queue, removed from it or moved in it
+    QueueChanged,
+    /// The repeat mode of the queue changed
+    Repeat { repeat: Repeat },
+    /// The shuffle mode of the queue changed
+    Shuffle { shuffle: Shuffle },
+    /// The volume of the output changed, between 0 and 1
+    Volume { volume: f64 },
+    /// A track couldn't be opened or the output failed
+    Error { message: String },
+}
+
+/// Get the socket of the daemon, in the runtime directory of the user if there is one
+pub fn default_socket_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("vibe.sock"),
+        None => {
+            let user = env::var("USER").unwrap_or_default();
+            env::temp_dir().join(format!("vibe-{}.sock", user))
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::f64::consts::PI;
+
+use super::peak::PeakMeter;
+
+/// Loudness of the ReplayGain 2.0 reference level, in LUFS
+pub const REFERENCE_LOUDNESS: f64 = -18.0;
+
+/// Gating block of the integrated loudness, in 100 ms steps
+const BLOCK_STEPS: usize = 4;
+
+/// Window of the short-term loudness used by the loudness range, in 100 ms steps
+const SHORT_TERM_STEPS: usize = 30;
+
+/// Blocks quieter than this are ignored, in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Gate of the integrated loudness, relative to the loudness of the blocks above the absolute gate
+const INTEGRATED_RELATIVE_GATE: f64 = -10.0;
+
+/// Gate of the loudness range, relative to the loudness of the blocks above the absolute gate
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+
+/// Loudness of a track or an album, following ITU-R BS.1770-4 and EBU Tech 3342
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// Gated loudness of the whole program in LUFS, negative infinity for silence
+    pub integrated: f64,
+    /// Spread of the short-term loudness in LU
+    pub range: f64,
+    /// Highest absolute sample value
+    pub sample_peak: f64,
+    /// Highest absolute value of the signal between the samples, estimated by oversampling
+    pub true_peak: f64,
+}
+
+impl Loudness {
+    /// Get the ReplayGain 2.0 gain bringing the loudness to the reference level, in dB
+    ///
+    /// Returns None for silence
+    pub fn gain(&self) -> Option<f64> {
+        if self.integrated.is_finite() {
+            Some(REFERENCE_LOUDNESS - self.integrated)
+        } else {
+            None
+        }
+    }
+}
+
+/// Second order filter in direct form I
+#[derive(Debug, Clone, Copy)]
+struct Filter {
+    b: [f64; 3],
+    a: [f64; 2],
+}
+
+/// Delay line of a filter for one channel
+#[derive(Debug, Clone, Copy, Default)]
+struct FilterState {
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Filter {
+    #[inline]
+    fn process(&self, state: &mut FilterState, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * state.x[0] + self.b[2] * state.x[1]
+            - self.a[0] * state.y[0]
+            - self.a[1] * state.y[1];
+        state.x = [x, state.x[0]];
+        state.y = [y, state.y[0]];
+        y
+    }
+}
+
+/// Get the two stages of the K-weighting filter at the given sample rate
+///
+/// The analog prototypes of BS.1770 are matched with the bilinear transform
+/// so any sample rate gives the response specified at 48 kHz
+fn k_weighting(sample_rate: u32) -> [Filter; 2] {
+    let rate = sample_rate as f64;
+
+    // High shelf modelling the acoustic effect of the head
+    let frequency = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * frequency / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Filter {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    };
+
+    // High pass removing the lowest frequencies
+    let frequency = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * frequency / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Filter {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    };
+
+    [shelf, high_pass]
+}
+
+/// Weight of each channel in the sum of their energies
+///
+/// The surround channels of 5 and 5.1 layouts are louder, the LFE channel is ignored
+fn channel_weights(channels: usize) -> Vec<f64> {
+    match channels {
+        5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        _ => vec![1.0; channels],
+    }
+}
+
+/// Loudness meter fed with the interleaved samples of a track
+///
+/// The energy is kept for every 100 ms step, so the meters of several tracks
+/// can be combined into the loudness of an album
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: [Filter; 2],
+    states: Vec<[FilterState; 2]>,
+    weights: Vec<f64>,
+    /// Number of frames of a 100 ms step
+    step_frames: usize,
+    /// Weighted energy of the current step
+    energy: f64,
+    frames: usize,
+    /// Mean square of every completed step
+    steps: Vec<f64>,
+    peak: PeakMeter,
+}
+
+impl LoudnessMeter {
+    /// Create a meter for the given format
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            filters: k_weighting(sample_rate),
+            states: vec![[FilterState::default(); 2]; channels],
+            weights: channel_weights(channels),
+            step_frames: ((sample_rate as usize + 5) / 10).max(1),
+            energy: 0.0,
+            frames: 0,
+            steps: Vec::new(),
+            peak: PeakMeter::new(sample_rate, channels),
+        }
+    }
+
+    /// Measure interleaved samples, a trailing incomplete frame is ignored
+    pub fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            self.peak.push(frame);
+
+            for (channel, &sample) in frame.iter().enumerate() {
+                let states = &mut self.states[channel];
+                let filtered = self.filters[0].process(&mut states[0], sample as f64);
+                let filtered = self.filters[1].process(&mut states[1], filtered);
+                self.energy += self.weights[channel] * filtered * filtered;
+            }
+
+            self.frames += 1;
+            if self.frames == self.step_frames {
+                self.steps.push(self.energy / self.step_frames as f64);
+                self.energy = 0.0;
+                self.frames = 0;
+            }
+        }
+    }
+
+    /// Get the loudness of the samples measured so far
+    pub fn loudness(&self) -> Loudness {
+        Self::combine(&[self])
+    }
+
+    /// Get the loudness of several tracks played one after the other
+    pub fn album(meters: &[LoudnessMeter]) -> Loudness {
+        Self::combine(&meters.iter().collect::<Vec<_>>())
+    }
+
+    fn combine(meters: &[&LoudnessMeter]) -> Loudness {
+        let blocks: Vec<f64> = meters
+            .iter()
+            .flat_map(|meter| windows(&meter.steps, BLOCK_STEPS))
+            .collect();
+        let short_term: Vec<f64> = meters
+            .iter()
+            .flat_map(|meter| windows(&meter.steps, SHORT_TERM_STEPS))
+            .collect();
+
+        Loudness {
+            integrated: integrated(&blocks),
+            range: range(&short_term),
+            sample_peak: meters
+                .iter()
+                .map(|meter| meter.peak.sample_peak())
+                .fold(0.0, f64::max),
+            true_peak: meters
+                .iter()
+                .map(|meter| meter.peak.true_peak())
+                .fold(0.0, f64::max),
+        }
+    }
+}
+
+/// Mean squares of the overlapping windows of the given number of steps, moving by one step
+fn windows(steps: &[f64], len: usize) -> impl Iterator<Item = f64> + '_ {
+    steps
+        .windows(len)
+        .map(move |window| window.iter().sum::<f64>() / len as f64)
+}
+
+#[inline]
+fn to_lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// Mean square of the blocks louder than the absolute gate and the relative gate
+fn gated_blocks(blocks: &[f64], relative_gate: f64) -> Vec<f64> {
+    let absolute: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|&block| to_lufs(block) > ABSOLUTE_GATE)
+        .collect();
+    if absolute.is_empty() {
+        return absolute;
+    }
+
+    let gate = to_lufs(absolute.iter().sum::<f64>() / absolute.len() as f64) + relative_gate;
+    absolute
+        .into_iter()
+        .filter(|&block| to_lufs(block) > gate)
+        .collect()
+}
+
+/// Integrated loudness of the 400 ms blocks
+fn integrated(blocks: &[f64]) -> f64 {
+    let gated = gated_blocks(blocks, INTEGRATED_RELATIVE_GATE);
+    if gated.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    to_lufs(gated.iter().sum::<f64>() / gated.len() as f64)
+}
+
+/// Loudness range of the 3 s blocks, between the 10th and the 95th percentiles
+fn range(blocks: &[f64]) -> f64 {
+    let mut gated: Vec<f64> = gated_blocks(blocks, RANGE_RELATIVE_GATE)
+        .into_iter()
+        .map(to_lufs)
+        .collect();
+    if gated.is_empty() {
+        return 0.0;
+    }
+
+    gated.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let percentile = |p: f64| gated[((gated.len() - 1) as f64 * p).round() as usize];
+    percentile(0.95) - percentile(0.10)
+}
@@ -0,0 +1,57 @@
+mod loudness;
+mod peak;
+
+pub use self::loudness::{Loudness, LoudnessMeter, REFERENCE_LOUDNESS};
+
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::{decoder::Decoder, info::DecoderError, info::TagError, tags, ReplayGain};
+
+/// Number of frames measured at once
+const CHUNK_FRAMES: usize = 1024;
+
+/// Decode a track to the end and measure its loudness
+pub fn analyze<R>(decoder: Decoder<R>) -> Result<LoudnessMeter, DecoderError>
+where
+    R: Read + Seek,
+{
+    let info = decoder.info();
+    let mut meter = LoudnessMeter::new(info.sample_rate(), info.channels());
+
+    // Whole frames only, the meter ignores a trailing incomplete frame
+    let len = CHUNK_FRAMES * info.channels().max(1);
+    let mut chunk = Vec::with_capacity(len);
+    for sample in decoder {
+        chunk.push(sample?);
+        if chunk.len() == len {
+            meter.push(&chunk);
+            chunk.clear();
+        }
+    }
+    meter.push(&chunk);
+
+    Ok(meter)
+}
+
+/// Get the ReplayGain 2.0 values of a track, and of its album if given
+///
+/// The peaks are the sample peaks, as in the ReplayGain specification
+pub fn replay_gain(track: &Loudness, album: Option<&Loudness>) -> ReplayGain {
+    ReplayGain {
+        track_gain: track.gain().map(|gain| gain as f32),
+        track_peak: Some(track.sample_peak as f32),
+        album_gain: album.and_then(Loudness::gain).map(|gain| gain as f32),
+        album_peak: album.map(|album| album.sample_peak as f32),
+    }
+}
+
+/// Write the ReplayGain values in the tags of a file, the other tags are kept
+pub fn write_replay_gain<P: AsRef<Path>>(
+    path: P,
+    replay_gain: &ReplayGain,
+) -> Result<(), TagError> {
+    let mut metadata = tags::read_metadata(path.as_ref())?;
+    replay_gain.write_tags(&mut metadata);
+    tags::write_metadata(path, &metadata)
+}
@@ -0,0 +1,90 @@
+use std::f64::consts::PI;
+
+/// Taps of each phase of the interpolation filter
+const PHASE_TAPS: usize = 12;
+
+/// Sample and true peak meter
+///
+/// The true peak is measured on the signal oversampled by a windowed-sinc filter,
+/// 4 times below 96 kHz and 2 times below 192 kHz as BS.1770-4 recommends
+#[derive(Debug, Clone)]
+pub(crate) struct PeakMeter {
+    channels: usize,
+    factor: usize,
+    /// Coefficients of the interpolation filter, grouped by phase
+    phases: Vec<[f64; PHASE_TAPS]>,
+    /// Last samples of each channel, the newest first
+    history: Vec<[f64; PHASE_TAPS]>,
+    sample_peak: f64,
+    true_peak: f64,
+}
+
+impl PeakMeter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let factor = match sample_rate {
+            0..=95_999 => 4,
+            96_000..=191_999 => 2,
+            _ => 1,
+        };
+
+        // Low pass at the original Nyquist frequency, with a Blackman window
+        let len = PHASE_TAPS * factor;
+        let center = (len - 1) as f64 / 2.0;
+        let mut phases = vec![[0.0; PHASE_TAPS]; factor];
+        for n in 0..len {
+            let x = (n as f64 - center) / factor as f64;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let t = 2.0 * PI * n as f64 / (len - 1) as f64;
+            let window = 0.42 - 0.5 * t.cos() + 0.08 * (2.0 * t).cos();
+            phases[n % factor][n / factor] = sinc * window;
+        }
+
+        Self {
+            channels,
+            factor,
+            phases,
+            history: vec![[0.0; PHASE_TAPS]; channels],
+            sample_peak: 0.0,
+            true_peak: 0.0,
+        }
+    }
+
+    /// Measure one frame
+    pub fn push(&mut self, frame: &[f32]) {
+        for (channel, &sample) in frame.iter().enumerate().take(self.channels) {
+            let sample = sample as f64;
+            self.sample_peak = self.sample_peak.max(sample.abs());
+
+            if self.factor == 1 {
+                continue;
+            }
+            let history = &mut self.history[channel];
+            history.rotate_right(1);
+            history[0] = sample;
+
+            for phase in self.phases.iter() {
+                let value: f64 = phase
+                    .iter()
+                    .zip(history.iter())
+                    .map(|(coefficient, sample)| coefficient * sample)
+                    .sum();
+                self.true_peak = self.true_peak.max(value.abs());
+            }
+        }
+    }
+
+    #[inline]
+    pub fn sample_peak(&self) -> f64 {
+        self.sample_peak
+    }
+
+    /// The true peak is never below the sample peak
+    #[inline]
+    pub fn true_peak(&self) -> f64 {
+        self.true_peak.max(self.sample_peak)
+    }
+}
@@ -1,19 +1,35 @@
 use std::io::{Read, Seek, SeekFrom};
 use std::time::Duration;
 
-use claxon::FlacReader;
+use claxon::frame::FrameReader;
+use claxon::input::BufferedReader;
+use claxon::{Block, Error, FlacReader};
 
-use crate::{info::DecoderError, AudioFormat, AudioInfo, Sample};
+use super::duration_to_frames;
+use crate::tags::flac::{read_stream_blocks, SeekPoint};
+use crate::tags::vorbis::push_comment;
+use crate::{info::DecoderError, AudioFormat, AudioInfo, Metadata, Sample};
+
+/// Distance in bytes under which a seek stops bisecting and decodes up to the position
+const BISECT_BYTES: u64 = 1 << 16;
+
+/// Size of the chunks read when looking for a frame
+const SYNC_CHUNK: usize = 4096;
 
 /// Decoder for FLAC files
 pub struct FlacDecoder<R>
 where
     R: Read + Seek,
 {
-    reader: FlacReader<R>,
+    frames: Option<FrameReader<BufferedReader<R>>>,
+    first_frame: u64,
+    end: u64,
+    seek_points: Vec<SeekPoint>,
+    block_size: Option<u32>,
     sample_rate: u32,
     channels: usize,
     duration: Option<Duration>,
+    metadata: Metadata,
     current_block: Vec<i32>,
     current_block_len: usize,
     current_block_channel_len: usize,
@@ -32,6 +48,10 @@ where
             return Err(data);
         }
 
+        let start = data.stream_position().unwrap();
+        let (pictures, seek_points) = read_stream_blocks(data.by_ref());
+        let first_frame = data.stream_position().unwrap();
+        data.seek(SeekFrom::Start(start)).unwrap();
         let reader = FlacReader::new(data).unwrap();
 
         let spec = reader.streaminfo();
@@ -47,12 +67,32 @@ where
         let duration = spec
             .samples
             .map(|s| Duration::from_millis(s * 1_000 / sample_rate as u64));
+        let block_size =
+            Some(spec.max_block_size as u32).filter(|&size| size == spec.min_block_size as u32);
+
+        let mut metadata = Metadata::new();
+        for (key, value) in reader.tags() {
+            push_comment(&mut metadata, key, value);
+        }
+        for picture in pictures {
+            metadata.push_picture(picture);
+        }
+
+        // claxon reads ahead, the frames are read from the end of the metadata blocks
+        let mut data = reader.into_inner();
+        let end = data.seek(SeekFrom::End(0)).unwrap();
+        data.seek(SeekFrom::Start(first_frame)).unwrap();
 
         Ok(Self {
-            reader,
+            frames: Some(FrameReader::new(BufferedReader::new(data))),
+            first_frame,
+            end,
+            seek_points,
+            block_size,
             sample_rate,
             channels,
             duration,
+            metadata,
             current_block,
             current_block_len,
             current_block_channel_len,
@@ -77,6 +117,124 @@ where
             duration: self.duration(),
         }
     }
+
+    /// Get the Vorbis comments and the pictures of the file
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Seek to the given position
+    ///
+    /// claxon can't seek so the frame holding the position is bisected, between the closest
+    /// points of the seek table if there is one, then decoded up to the position
+    pub fn seek(&mut self, pos: Duration) -> Result<(), DecoderError> {
+        let target = duration_to_frames(pos, self.sample_rate);
+
+        let mut data = match self.frames.take() {
+            Some(frames) => frames.into_inner().into_inner(),
+            None => return Err(DecoderError::IncompleteData),
+        };
+
+        // Bounds as (offset, first sample) of the frames around the position
+        let mut low = (self.first_frame, 0);
+        let mut high = self.end;
+        for point in &self.seek_points {
+            let offset = self.first_frame + point.offset;
+            if point.sample <= target {
+                low = (offset, point.sample);
+            } else {
+                high = high.min(offset);
+                break;
+            }
+        }
+        while high.saturating_sub(low.0) > BISECT_BYTES {
+            let middle = low.0 + (high - low.0) / 2;
+            match self.find_frame(&mut data, middle, high)? {
+                Some((offset, time)) if time <= target => low = (offset, time),
+                // No frame starts between the middle and this one, the position is before
+                _ => high = middle,
+            }
+        }
+
+        data.seek(SeekFrom::Start(low.0))
+            .map_err(DecoderError::IOError)?;
+        let frames = self
+            .frames
+            .get_or_insert(FrameReader::new(BufferedReader::new(data)));
+
+        self.block_cursor = 0;
+        self.current_block_len = 0;
+
+        loop {
+            let block_buffer = std::mem::take(&mut self.current_block);
+            match frames.read_next_or_eof(block_buffer) {
+                Ok(Some(block)) => {
+                    let time = block_time(self.block_size, &block);
+                    let block_duration = block.duration() as u64;
+                    self.current_block_len = block.len() as _;
+                    self.current_block_channel_len = block_duration as usize;
+                    self.current_block = block.into_buffer();
+
+                    if time + block_duration > target {
+                        self.block_cursor = (target.saturating_sub(time) as usize) * self.channels;
+                        return Ok(());
+                    }
+                }
+                Ok(None) => {
+                    // Past the end, the next call to `next` will end the stream
+                    self.block_cursor = self.current_block_len;
+                    return Ok(());
+                }
+                Err(err) => return Err(get_error(err)),
+            }
+        }
+    }
+
+    /// Find the first frame starting between two offsets, as its offset and first sample
+    ///
+    /// A frame is found by its sync code and checked by decoding it, which also
+    /// verifies the CRC of its header and of its content
+    fn find_frame(
+        &mut self,
+        data: &mut R,
+        from: u64,
+        to: u64,
+    ) -> Result<Option<(u64, u64)>, DecoderError> {
+        let mut chunk = [0u8; SYNC_CHUNK];
+        let mut offset = from;
+        while offset < to {
+            data.seek(SeekFrom::Start(offset))
+                .map_err(DecoderError::IOError)?;
+            let len = read_chunk(data.by_ref(), &mut chunk).map_err(DecoderError::IOError)?;
+            if len < 2 {
+                return Ok(None);
+            }
+
+            for index in 0..len - 1 {
+                let candidate = offset + index as u64;
+                if candidate >= to {
+                    return Ok(None);
+                }
+                if chunk[index] != 0xff || chunk[index + 1] & 0xfe != 0xf8 {
+                    continue;
+                }
+
+                data.seek(SeekFrom::Start(candidate))
+                    .map_err(DecoderError::IOError)?;
+                let mut frames = FrameReader::new(BufferedReader::new(data.by_ref()));
+                let block_buffer = std::mem::take(&mut self.current_block);
+                if let Ok(Some(block)) = frames.read_next_or_eof(block_buffer) {
+                    let time = block_time(self.block_size, &block);
+                    self.current_block = block.into_buffer();
+                    return Ok(Some((candidate, time)));
+                }
+            }
+            // The last byte can start a sync code with the next chunk
+            offset += len as u64 - 1;
+        }
+        Ok(None)
+    }
 }
 
 impl<R> Iterator for FlacDecoder<R>
@@ -107,7 +265,8 @@ where
 
             self.block_cursor = 0;
             let block_buffer = std::mem::replace(&mut self.current_block, vec![]);
-            match self.reader.blocks().read_next_or_eof(block_buffer) {
+            let frames = self.frames.as_mut()?;
+            match frames.read_next_or_eof(block_buffer) {
                 Ok(Some(block)) => {
                     self.current_block_len = block.len() as _;
                     self.current_block_channel_len = (block.len() / block.channels()) as usize;
@@ -130,3 +289,38 @@ where
 
     return is_flac;
 }
+
+/// First sample of a block
+///
+/// The frames of a stream of fixed size blocks are numbered and claxon multiplies
+/// the number by the size of the block, which is shorter for the last one
+fn block_time(block_size: Option<u32>, block: &Block) -> u64 {
+    match block_size {
+        Some(size) if block.duration() != size && block.duration() > 0 => {
+            block.time() / block.duration() as u64 * size as u64
+        }
+        _ => block.time(),
+    }
+}
+
+/// Fill as much of a buffer as the stream allows
+fn read_chunk<R: Read>(mut data: R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match data.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(len)
+}
+
+fn get_error(error: Error) -> DecoderError {
+    match error {
+        Error::IoError(io_err) => DecoderError::IOError(io_err),
+        Error::FormatError(fmt_err) => DecoderError::FormatError(format!("flac: {}", fmt_err)),
+        Error::Unsupported(err) => DecoderError::FormatError(format!("flac: unsupported {}", err)),
+    }
+}
@@ -1,6 +1,7 @@
 use std::io::{Read, Seek};
+use std::time::Duration;
 
-use crate::{info::DecoderError, AudioInfo, Sample};
+use crate::{info::DecoderError, AudioInfo, LoopRegion, Metadata, Sample};
 
 #[cfg(feature = "flac")]
 mod flac;
@@ -37,6 +38,25 @@ where
     pub fn info(&self) -> AudioInfo {
         self.decoder.info()
     }
+
+    /// Get the tags of the file
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    /// Get the loop points stored in the file, such as the loops of a WAV `smpl` chunk
+    #[inline]
+    pub fn loops(&self) -> &[LoopRegion] {
+        self.decoder.loops()
+    }
+
+    /// Move the decoder to the given position, the next sample returned is the
+    /// first sample of the frame at this position
+    #[inline]
+    pub fn seek(&mut self, pos: Duration) -> Result<(), DecoderError> {
+        self.decoder.seek(pos)
+    }
 }
 
 impl<R> Iterator for Decoder<R>
@@ -120,6 +140,44 @@ where
             FormatDecoder::Flac(d) => d.info(),
         }
     }
+
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        match self {
+            #[cfg(feature = "wav")]
+            FormatDecoder::Wav(d) => d.metadata(),
+            #[cfg(feature = "vorbis")]
+            FormatDecoder::Vorbis(d) => d.metadata(),
+            #[cfg(feature = "mp3")]
+            FormatDecoder::Mp3(d) => d.metadata(),
+            #[cfg(feature = "flac")]
+            FormatDecoder::Flac(d) => d.metadata(),
+        }
+    }
+
+    #[inline]
+    pub fn loops(&self) -> &[LoopRegion] {
+        match self {
+            #[cfg(feature = "wav")]
+            FormatDecoder::Wav(d) => d.loops(),
+            #[allow(unreachable_patterns)]
+            _ => &[],
+        }
+    }
+
+    #[inline]
+    pub fn seek(&mut self, pos: Duration) -> Result<(), DecoderError> {
+        match self {
+            #[cfg(feature = "wav")]
+            FormatDecoder::Wav(d) => d.seek(pos),
+            #[cfg(feature = "vorbis")]
+            FormatDecoder::Vorbis(d) => d.seek(pos),
+            #[cfg(feature = "mp3")]
+            FormatDecoder::Mp3(d) => d.seek(pos),
+            #[cfg(feature = "flac")]
+            FormatDecoder::Flac(d) => d.seek(pos),
+        }
+    }
 }
 
 impl<R> Iterator for FormatDecoder<R>
@@ -142,3 +200,17 @@ where
         }
     }
 }
+
+/// Convert a position into a number of frames (samples per channel)
+#[inline]
+pub fn duration_to_frames(pos: Duration, sample_rate: u32) -> u64 {
+    (pos.as_nanos() * sample_rate as u128 / 1_000_000_000) as u64
+}
+
+/// Convert a number of frames into a position, rounded up so it converts back to the same number of frames
+#[inline]
+pub fn frames_to_duration(frames: u64, sample_rate: u32) -> Duration {
+    let rate = sample_rate.max(1) as u128;
+    let nanos = (frames as u128 * 1_000_000_000).div_ceil(rate);
+    Duration::from_nanos(nanos as u64)
+}
@@ -1,22 +1,91 @@
-use minimp3::{Decoder, Error, Frame};
-use std::io::{Read, Seek, SeekFrom};
+use minimp3::{Decoder, Error};
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::time::Duration;
 
-use crate::{info::DecoderError, AudioFormat, AudioInfo, Sample};
+use super::duration_to_frames;
+use crate::tags::id3::read_id3v2;
+use crate::{info::DecoderError, AudioFormat, AudioInfo, Metadata, ReplayGain, Sample};
+
+/// Number of bytes searched for the first frame after the ID3 tag
+const FRAME_SEARCH_LEN: usize = 8192;
+
+/// Size of the buffer used to read the headers of the frames
+const INDEX_BUFFER_LEN: usize = 1 << 16;
+
+/// Largest bit reservoir, the bytes of the previous frames a frame can use
+const RESERVOIR_LEN: u64 = 511;
+
+/// Frames decoded before a seek position once the reservoir is filled, as minimp3 does
+const PREDECODE_FRAMES: usize = 2;
 
 ///Decoder for MP3 files
+///
+/// The frames are indexed from their headers when the file is opened, which gives
+/// the duration and the frames to decode for a seek, then given one by one to minimp3
 pub struct Mp3Decoder<R>
 where
     R: Read + Seek,
 {
-    decoder: Decoder<R>,
+    decoder: Option<Decoder<FrameSource<R>>>,
+    frame_samples: usize,
     channels: usize,
     sample_rate: u32,
     duration: Option<Duration>,
-    current_frame: Frame,
+    metadata: Metadata,
+    current_frame: Vec<i16>,
     frame_cursor: usize,
 }
 
+/// Frame of the stream
+#[derive(Clone, Copy, Debug)]
+struct FrameEntry {
+    offset: u64,
+    len: usize,
+}
+
+/// Layout of a frame, read from its header
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FrameHeader {
+    version: u8,
+    layer: u8,
+    sample_rate: u32,
+    channels: usize,
+    samples: usize,
+    len: usize,
+}
+
+/// Reader giving a frame of the index at each read
+///
+/// The buffer of minimp3 then only holds the frame being decoded, so the frame
+/// of a decoded or skipped frame is known
+struct FrameSource<R> {
+    data: R,
+    position: u64,
+    frames: Vec<FrameEntry>,
+    next: usize,
+}
+
+impl<R> Read for FrameSource<R>
+where
+    R: Read + Seek,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let frame = match self.frames.get(self.next) {
+            Some(frame) if frame.len <= buf.len() => *frame,
+            Some(_) => return Err(std::io::ErrorKind::InvalidInput.into()),
+            None => return Ok(0),
+        };
+        if self.position != frame.offset {
+            self.data.seek(SeekFrom::Start(frame.offset))?;
+        }
+        self.position = u64::MAX;
+        self.data.read_exact(&mut buf[..frame.len])?;
+        self.position = frame.offset + frame.len as u64;
+        self.next += 1;
+        Ok(frame.len)
+    }
+}
+
 impl<R> Mp3Decoder<R>
 where
     R: Read + Seek,
@@ -28,23 +97,35 @@ where
             return Err(data);
         }
 
-        // TODO: Is there a better way to compute duration
-        let duration = compute_duration(data.by_ref());
-
-        let mut decoder = Decoder::new(data);
+        let (metadata, tag_len) = read_metadata(data.by_ref());
+        let start = data.stream_position().unwrap() + tag_len;
+        let (frames, header) = match index_frames(data.by_ref(), start) {
+            Some(index) => index,
+            None => return Err(data),
+        };
 
-        let current_frame = decoder.next_frame().unwrap();
-        let sample_rate = current_frame.sample_rate as u32;
-        let channels = current_frame.channels;
-        let frame_cursor = 0;
+        let sample_rate = header.sample_rate;
+        let channels = header.channels;
+        let frame_samples = header.samples;
+        let duration = Some(Duration::from_millis(
+            (frames.len() * frame_samples) as u64 * 1_000 / sample_rate as u64,
+        ));
+        let source = FrameSource {
+            data,
+            position: u64::MAX,
+            frames,
+            next: 0,
+        };
 
         Ok(Mp3Decoder {
-            decoder,
+            decoder: Some(Decoder::new(source)),
+            frame_samples,
             channels,
             sample_rate,
             duration,
-            current_frame,
-            frame_cursor,
+            metadata,
+            current_frame: Vec::new(),
+            frame_cursor: 0,
         })
     }
 
@@ -64,6 +145,74 @@ where
             duration: self.duration(),
         }
     }
+
+    /// Get the tags of the ID3v2 tag, completed by the ReplayGain values of the LAME header
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Seek to the given position
+    ///
+    /// The frame containing the position is found in the index, the frames filling the bit
+    /// reservoir and the filters before it are decoded first so that it is decoded as when
+    /// playing through
+    pub fn seek(&mut self, pos: Duration) -> Result<(), DecoderError> {
+        let target = duration_to_frames(pos, self.sample_rate);
+        let index = (target / self.frame_samples as u64) as usize;
+
+        let mut source = match self.decoder.take() {
+            Some(decoder) => decoder.into_inner(),
+            None => return Err(DecoderError::IncompleteData),
+        };
+        // The frames before the position fill the reservoir, then the filters
+        let mut frame = index.min(source.frames.len());
+        let mut reservoir = 0;
+        while frame > 0 && reservoir < RESERVOIR_LEN {
+            frame -= 1;
+            reservoir += source.frames[frame].len as u64;
+        }
+        frame = frame.saturating_sub(PREDECODE_FRAMES);
+        source.next = frame;
+        self.decoder = Some(Decoder::new(source));
+
+        self.current_frame.clear();
+        self.frame_cursor = 0;
+        while let Some(samples) = self.decode_frames()? {
+            let frames = samples.len() / (self.frame_samples * self.channels);
+            if frame + frames > index {
+                let offset = (index - frame) * self.frame_samples;
+                let offset = offset + (target % self.frame_samples as u64) as usize;
+                self.current_frame = samples;
+                self.frame_cursor = offset * self.channels;
+                return Ok(());
+            }
+            frame += frames;
+        }
+        // Past the end, the next call to `next` will end the stream
+        Ok(())
+    }
+
+    /// Decode the next frame, preceded by the frames minimp3 skipped which are silent
+    fn decode_frames(&mut self) -> Result<Option<Vec<i16>>, DecoderError> {
+        let decoder = self.decoder.as_mut().ok_or(DecoderError::IncompleteData)?;
+        let len = self.frame_samples * self.channels;
+        let next = decoder.reader().next;
+        let result = decoder.next_frame();
+        let skipped = decoder.reader().next - next;
+
+        match result {
+            Ok(frame) => {
+                let mut samples = vec![0; (skipped - 1) * len];
+                samples.extend_from_slice(&frame.data);
+                samples.resize(skipped * len, 0);
+                Ok(Some(samples))
+            }
+            Err(Error::Eof) if skipped > 0 => Ok(Some(vec![0; skipped * len])),
+            Err(Error::Eof) => Ok(None),
+            Err(e) => Err(get_error(e)),
+        }
+    }
 }
 
 impl<R> Iterator for Mp3Decoder<R>
@@ -75,44 +224,30 @@ where
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         // Read next frame in if current frame is exhausted
-        if self.frame_cursor >= self.current_frame.data.len() {
+        if self.frame_cursor >= self.current_frame.len() {
             self.frame_cursor = 0;
-            self.current_frame = loop {
-                match self.decoder.next_frame() {
-                    Ok(frame) => {
-                        // Skip empty frames
-                        if frame.data.len() == 0 {
-                            continue;
-                        }
-
-                        // Make sure the sample rates and channels match
-                        assert_eq!(frame.sample_rate as u32, self.sample_rate);
-                        assert_eq!(frame.channels, self.channels);
-
-                        break frame;
-                    }
-                    Err(Error::SkippedData) => continue,
-                    Err(Error::Eof) => return None,
-                    Err(e) => {
-                        let error = match e {
-                            Error::Io(io_err) => DecoderError::IOError(io_err),
-                            Error::InsufficientData => {
-                                DecoderError::FormatError(format!("mp3: insufficient data"))
-                            }
-                            _ => unimplemented!(),
-                        };
-                        return Some(Err(error));
-                    }
-                }
+            self.current_frame = match self.decode_frames() {
+                Ok(Some(samples)) => samples,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
             };
         }
 
-        let sample_float = self.current_frame.data[self.frame_cursor] as f32 / i16::MAX as f32;
+        let sample_float = self.current_frame[self.frame_cursor] as f32 / i16::MAX as f32;
         self.frame_cursor += 1;
         Some(Ok(sample_float))
     }
 }
 
+fn get_error(error: Error) -> DecoderError {
+    match error {
+        Error::Io(io_err) => DecoderError::IOError(io_err),
+        Error::InsufficientData => DecoderError::FormatError("mp3: insufficient data".to_owned()),
+        Error::SkippedData => DecoderError::FormatError("mp3: skipped data".to_owned()),
+        Error::Eof => DecoderError::FormatError("mp3: end of stream".to_owned()),
+    }
+}
+
 fn is_mp3<R>(mut data: R) -> bool
 where
     R: Read + Seek,
@@ -124,29 +259,285 @@ where
     return is_mp3;
 }
 
-/// Compute duration
-fn compute_duration<R>(mut data: R) -> Option<Duration>
+/// Read the ID3v2 tag and the LAME header, then resets the stream to where it was.
+///
+/// The values of the LAME header are used only if the tag doesn't have them, the size of
+/// the tag is returned with the tags
+fn read_metadata<R>(mut data: R) -> (Metadata, u64)
 where
     R: Read + Seek,
 {
-    let stream_pos = data.seek(SeekFrom::Current(0)).unwrap();
+    let stream_pos = data.stream_position().unwrap();
+
+    let (mut metadata, tag_len) = read_id3v2(data.by_ref()).unwrap_or_default();
+    let mut frame = Vec::with_capacity(FRAME_SEARCH_LEN);
+    let lame = data
+        .seek(SeekFrom::Start(stream_pos + tag_len))
+        .and_then(|_| {
+            data.by_ref()
+                .take(FRAME_SEARCH_LEN as u64)
+                .read_to_end(&mut frame)
+        })
+        .ok()
+        .and_then(|_| read_lame_header(&frame));
+    data.seek(SeekFrom::Start(stream_pos)).unwrap();
+
+    if let Some(lame) = lame {
+        let mut tags = metadata.replay_gain();
+        tags.track_gain = tags.track_gain.or(lame.track_gain);
+        tags.track_peak = tags.track_peak.or(lame.track_peak);
+        tags.album_gain = tags.album_gain.or(lame.album_gain);
+        tags.write_tags(&mut metadata);
+    }
+    (metadata, tag_len)
+}
 
-    let mut decoder = Decoder::new(data.by_ref());
-
-    let mut duration: u64 = 0;
-    loop {
-        match decoder.next_frame() {
-            Ok(Frame {
-                data,
-                sample_rate,
-                channels,
-                ..
-            }) => duration += data.len() as u64 * 1_000 / (channels as u64 * sample_rate as u64),
-            Err(Error::Eof) => break,
-            Err(e) => panic!("{:?}", e),
+/// Read the ReplayGain values of the LAME header following the Xing header of the first frame
+fn read_lame_header(data: &[u8]) -> Option<ReplayGain> {
+    let start = data
+        .windows(2)
+        .position(|sync| sync[0] == 0xff && sync[1] & 0xe0 == 0xe0)?;
+    let header = data.get(start..start + 4)?;
+
+    let xing = start + 4 + side_info_len(header)?;
+    if !is_xing(data.get(xing..xing + 4)?) {
+        return None;
+    }
+
+    // Frame count, byte count, table of contents and quality are optional
+    let flags = u32::from_be_bytes([
+        data[xing + 4],
+        data[xing + 5],
+        data[xing + 6],
+        data[xing + 7],
+    ]);
+    let mut lame = xing + 8;
+    for (flag, len) in [(0x01, 4), (0x02, 4), (0x04, 100), (0x08, 4)].iter() {
+        if flags & flag != 0 {
+            lame += len;
         }
     }
 
-    data.seek(SeekFrom::Start(stream_pos)).unwrap();
-    return Some(Duration::from_millis(duration));
+    // Encoder version, revision and lowpass come before the ReplayGain fields
+    let fields = data.get(lame + 11..lame + 19)?;
+    let peak = u32::from_be_bytes([fields[0], fields[1], fields[2], fields[3]]);
+    let mut replay_gain = ReplayGain {
+        track_peak: Some(peak as f32 / (1 << 23) as f32).filter(|_| peak != 0),
+        ..ReplayGain::default()
+    };
+    for field in [[fields[4], fields[5]], [fields[6], fields[7]]].iter() {
+        let field = u16::from_be_bytes(*field);
+        let name = field >> 13;
+        let originator = (field >> 10) & 0x07;
+        if originator == 0 {
+            continue;
+        }
+
+        let mut gain = (field & 0x01ff) as f32 / 10.0;
+        if field & 0x0200 != 0 {
+            gain = -gain;
+        }
+        match name {
+            1 => replay_gain.track_gain = Some(gain),
+            2 => replay_gain.album_gain = Some(gain),
+            _ => {}
+        }
+    }
+
+    Some(replay_gain)
+}
+
+/// Size of the side information following the header of a layer III frame
+fn side_info_len(header: &[u8]) -> Option<usize> {
+    if (header[1] >> 1) & 0x03 != 0x01 {
+        return None;
+    }
+    let mpeg1 = (header[1] >> 3) & 0x03 == 0x03;
+    let mono = header[3] >> 6 == 0x03;
+    Some(match (mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) => 17,
+        (false, false) => 17,
+        (false, true) => 9,
+    })
+}
+
+/// Returns true for the identifier of a Xing or Info frame, which holds no audio
+fn is_xing(id: &[u8]) -> bool {
+    id == b"Xing" || id == b"Info"
+}
+
+/// Read the layout of a frame from its header, free format frames aren't supported
+fn read_header(header: &[u8; 4]) -> Option<FrameHeader> {
+    const SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+    const BITRATES: [[u32; 14]; 5] = [
+        [
+            32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+        ],
+        [
+            32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+        ],
+        [
+            32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ],
+        [
+            32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+        ],
+        [8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ];
+
+    if header[0] != 0xff || header[1] & 0xe0 != 0xe0 {
+        return None;
+    }
+    // 3 for MPEG-1, 2 for MPEG-2 and 0 for MPEG-2.5
+    let version = (header[1] >> 3) & 0x03;
+    let layer = 4 - ((header[1] >> 1) & 0x03);
+    let bitrate = (header[2] >> 4) as usize;
+    let sample_rate = ((header[2] >> 2) & 0x03) as usize;
+    if version == 1 || layer == 4 || bitrate == 0 || bitrate == 15 || sample_rate == 3 {
+        return None;
+    }
+
+    let mpeg1 = version == 3;
+    let bitrate = match (mpeg1, layer) {
+        (true, layer) => BITRATES[layer as usize - 1][bitrate - 1],
+        (false, 1) => BITRATES[3][bitrate - 1],
+        (false, _) => BITRATES[4][bitrate - 1],
+    } * 1000;
+    let sample_rate = SAMPLE_RATES[sample_rate] >> (3 - version.max(1));
+    let padding = ((header[2] >> 1) & 0x01) as u32;
+    let samples = match (mpeg1, layer) {
+        (_, 1) => 384,
+        (false, 3) => 576,
+        _ => 1152,
+    };
+    let len = match layer {
+        1 => (12 * bitrate / sample_rate + padding) * 4,
+        _ => samples / 8 * bitrate / sample_rate + padding,
+    };
+
+    Some(FrameHeader {
+        version,
+        layer,
+        sample_rate,
+        channels: if header[3] >> 6 == 0x03 { 1 } else { 2 },
+        samples: samples as usize,
+        len: len as usize,
+    })
+}
+
+/// Index the frames of the stream from the given offset, without decoding them
+///
+/// The frames have the layout of the first one, which must be followed by another frame,
+/// and a Xing or Info frame is left out. The stream is left where it was
+fn index_frames<R>(mut data: R, start: u64) -> Option<(Vec<FrameEntry>, FrameHeader)>
+where
+    R: Read + Seek,
+{
+    let stream_pos = data.stream_position().ok()?;
+    let end = data.seek(SeekFrom::End(0)).ok()?;
+    let mut reader = BufReader::with_capacity(INDEX_BUFFER_LEN, data.by_ref());
+    reader.seek(SeekFrom::Start(start)).ok()?;
+
+    let mut frames = Vec::new();
+    let mut first: Option<FrameHeader> = None;
+    let mut position = start;
+    let mut header = [0u8; 4];
+    while position + 4 <= end && reader.read_exact(&mut header).is_ok() {
+        let frame = read_header(&header)
+            .filter(|frame| position + frame.len as u64 <= end)
+            .filter(|frame| match first {
+                Some(first) => {
+                    (
+                        first.version,
+                        first.layer,
+                        first.sample_rate,
+                        first.channels,
+                    ) == (
+                        frame.version,
+                        frame.layer,
+                        frame.sample_rate,
+                        frame.channels,
+                    )
+                }
+                None => is_first_frame(&mut reader, frame, position, end),
+            });
+        match frame {
+            Some(frame) => {
+                if first.is_none() {
+                    first = Some(frame);
+                    if !has_xing(&mut reader, &header) {
+                        frames.push(FrameEntry {
+                            offset: position,
+                            len: frame.len,
+                        });
+                    }
+                } else {
+                    frames.push(FrameEntry {
+                        offset: position,
+                        len: frame.len,
+                    });
+                }
+                position += frame.len as u64;
+                reader.seek_relative(frame.len as i64 - 4).ok()?;
+            }
+            None => {
+                // Look for the next sync code from the byte after this one
+                position += 1;
+                reader.seek_relative(-3).ok()?;
+            }
+        }
+    }
+
+    drop(reader);
+    data.seek(SeekFrom::Start(stream_pos)).ok()?;
+    Some((frames, first?)).filter(|(frames, _)| !frames.is_empty())
+}
+
+/// Returns true if a frame is followed by a frame of the same layout or by the end of the
+/// stream, the reader is left after the header of the frame
+fn is_first_frame<R>(reader: &mut R, frame: &FrameHeader, position: u64, end: u64) -> bool
+where
+    R: Read + Seek,
+{
+    let next = position + frame.len as u64;
+    if next == end {
+        return true;
+    }
+    let mut header = [0u8; 4];
+    let found = reader
+        .seek(SeekFrom::Start(next))
+        .and_then(|_| reader.read_exact(&mut header))
+        .is_ok();
+    if reader.seek(SeekFrom::Start(position + 4)).is_err() || !found {
+        return false;
+    }
+    read_header(&header).is_some_and(|next| {
+        (next.version, next.layer, next.sample_rate, next.channels)
+            == (
+                frame.version,
+                frame.layer,
+                frame.sample_rate,
+                frame.channels,
+            )
+    })
+}
+
+/// Returns true if the frame whose header was just read is a Xing or Info frame, the
+/// reader is left after the header
+fn has_xing<R>(reader: &mut R, header: &[u8; 4]) -> bool
+where
+    R: Read + Seek,
+{
+    let side_info = match side_info_len(header) {
+        Some(len) => len,
+        None => return false,
+    };
+    let mut content = [0u8; 36];
+    let content = &mut content[..side_info + 4];
+    let found = reader.read_exact(content).is_ok();
+    let back = reader
+        .seek(SeekFrom::Current(-(content.len() as i64)))
+        .is_ok();
+    found && back && is_xing(&content[side_info..])
 }
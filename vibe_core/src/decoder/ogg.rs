@@ -7,16 +7,20 @@ use std::time::Duration;
 
 use lewton::{inside_ogg::OggStreamReader, VorbisError};
 
-use crate::{info::DecoderError, AudioFormat, AudioInfo, Sample};
+use super::duration_to_frames;
+use crate::tags::vorbis::push_comment;
+use crate::{info::DecoderError, AudioFormat, AudioInfo, Metadata, Sample};
 
 pub struct VorbisDecoder<R>
 where
     R: Read + Seek,
 {
-    reader: OggStreamReader<R>,
+    reader: Option<OggStreamReader<R>>,
+    start: u64,
     channels: usize,
     sample_rate: u32,
     duration: Option<Duration>,
+    metadata: Metadata,
     current_packet: Option<Vec<i16>>,
     packet_cursor: usize,
 }
@@ -31,21 +35,29 @@ where
             return Err(data);
         }
 
-        // TODO: Is there a better way to compute duration
-        let duration = compute_duration(data.by_ref());
+        let granule = last_granule(data.by_ref());
 
+        let start = data.stream_position().unwrap();
         let mut reader = OggStreamReader::new(data).unwrap();
 
         let channels = reader.ident_hdr.audio_channels as usize;
         let sample_rate = reader.ident_hdr.audio_sample_rate;
+        let duration = granule
+            .map(|granule| Duration::from_millis(granule * 1_000 / sample_rate.max(1) as u64));
+        let mut metadata = Metadata::new();
+        for (key, value) in reader.comment_hdr.comment_list.iter() {
+            push_comment(&mut metadata, key, value);
+        }
         let current_packet = reader.read_dec_packet_itl().unwrap();
         let packet_cursor = 0;
 
         Ok(Self {
-            reader,
+            reader: Some(reader),
+            start,
             channels,
             sample_rate,
             duration,
+            metadata,
             current_packet,
             packet_cursor,
         })
@@ -66,6 +78,115 @@ where
             duration: self.duration(),
         }
     }
+
+    /// Get the Vorbis comments of the file
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Seek to the given position
+    ///
+    /// The page a bit before the position is bisected by lewton and decoded up to the
+    /// position, the stream is rewound if the position is too close to the start
+    pub fn seek(&mut self, pos: Duration) -> Result<(), DecoderError> {
+        let target = duration_to_frames(pos, self.sample_rate);
+
+        let mut margin = self.sample_rate as u64;
+        loop {
+            let granule = target.saturating_sub(margin);
+            if granule == 0 {
+                return self.rewind(target);
+            }
+            if self.seek_page(granule, target)? {
+                return Ok(());
+            }
+            margin *= 4;
+        }
+    }
+
+    /// Decode from the page of a granule position up to the position
+    ///
+    /// The position of the samples is only known at the end of a page, false is
+    /// returned if the end of the first page is already after the position or
+    /// if the page isn't one of audio
+    fn seek_page(&mut self, granule: u64, target: u64) -> Result<bool, DecoderError> {
+        let target = target * self.channels as u64;
+        let reader = self.reader.as_mut().ok_or(DecoderError::IncompleteData)?;
+        reader.seek_absgp_pg(granule).map_err(get_error)?;
+
+        let mut position = None;
+        loop {
+            let packet = match reader.read_dec_packet_itl() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => {
+                    // Past the end, the stream is over
+                    self.packet_cursor = 0;
+                    self.current_packet = None;
+                    return Ok(true);
+                }
+                // The page found can be the one of the headers, before the audio
+                Err(_) if position.is_none() => return Ok(false),
+                Err(err) => return Err(get_error(err)),
+            };
+            match position {
+                Some(start) => {
+                    let end = start + packet.len() as u64;
+                    if end > target {
+                        self.packet_cursor = (target - start) as usize;
+                        self.current_packet = Some(packet);
+                        return Ok(true);
+                    }
+                    position = Some(end);
+                }
+                None => {
+                    if let Some(end) = reader.get_last_absgp() {
+                        let end = end * self.channels as u64;
+                        if end > target {
+                            return Ok(false);
+                        }
+                        position = Some(end);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Rewind the stream and decode it up to the position
+    fn rewind(&mut self, target: u64) -> Result<(), DecoderError> {
+        let target = target * self.channels as u64;
+
+        let mut data = match self.reader.take() {
+            Some(reader) => reader.into_inner().into_inner(),
+            None => return Err(DecoderError::IncompleteData),
+        };
+        data.seek(SeekFrom::Start(self.start))
+            .map_err(DecoderError::IOError)?;
+        let reader = self
+            .reader
+            .get_or_insert(OggStreamReader::new(data).map_err(get_error)?);
+
+        let mut position: u64 = 0;
+        loop {
+            match reader.read_dec_packet_itl().map_err(get_error)? {
+                Some(packet) => {
+                    let packet_len = packet.len() as u64;
+                    if position + packet_len > target {
+                        self.packet_cursor = (target - position) as usize;
+                        self.current_packet = Some(packet);
+                        return Ok(());
+                    }
+                    position += packet_len;
+                }
+                None => {
+                    // Past the end, the stream is over
+                    self.packet_cursor = 0;
+                    self.current_packet = None;
+                    return Ok(());
+                }
+            }
+        }
+    }
 }
 
 impl<R> Iterator for VorbisDecoder<R>
@@ -87,22 +208,9 @@ where
                     // Get the next packet if done reading this one
                     if self.packet_cursor >= packet.len() {
                         self.packet_cursor = 0;
-                        self.current_packet = match self.reader.read_dec_packet_itl() {
+                        self.current_packet = match self.reader.as_mut()?.read_dec_packet_itl() {
                             Ok(packet) => packet,
-                            Err(e) => {
-                                let error = match e {
-                                    VorbisError::BadAudio(err) => DecoderError::FormatError(
-                                        format!("ogg: bad audio: {}", err),
-                                    ),
-                                    VorbisError::BadHeader(err) => DecoderError::FormatError(
-                                        format!("ogg: bad header: {}", err),
-                                    ),
-                                    VorbisError::OggError(err) => {
-                                        DecoderError::FormatError(format!("ogg: {}", err))
-                                    }
-                                };
-                                return Some(Err(error));
-                            }
+                            Err(e) => return Some(Err(get_error(e))),
                         };
                     }
 
@@ -112,22 +220,9 @@ where
                 }
                 None => {
                     self.packet_cursor = 0;
-                    self.current_packet = match self.reader.read_dec_packet_itl() {
+                    self.current_packet = match self.reader.as_mut()?.read_dec_packet_itl() {
                         Ok(packet) => packet,
-                        Err(e) => {
-                            let error = match e {
-                                VorbisError::BadAudio(err) => {
-                                    DecoderError::FormatError(format!("ogg: bad audio: {}", err))
-                                }
-                                VorbisError::BadHeader(err) => {
-                                    DecoderError::FormatError(format!("ogg: bad header: {}", err))
-                                }
-                                VorbisError::OggError(err) => {
-                                    DecoderError::FormatError(format!("ogg: {}", err))
-                                }
-                            };
-                            return Some(Err(error));
-                        }
+                        Err(e) => return Some(Err(get_error(e))),
                     };
                     continue;
                 }
@@ -137,6 +232,16 @@ where
     }
 }
 
+fn get_error(error: VorbisError) -> DecoderError {
+    match error {
+        VorbisError::BadAudio(err) => DecoderError::FormatError(format!("ogg: bad audio: {}", err)),
+        VorbisError::BadHeader(err) => {
+            DecoderError::FormatError(format!("ogg: bad header: {}", err))
+        }
+        VorbisError::OggError(err) => DecoderError::FormatError(format!("ogg: {}", err)),
+    }
+}
+
 fn is_ogg<R>(mut data: R) -> bool
 where
     R: Read + Seek,
@@ -148,23 +253,34 @@ where
     return is_ogg;
 }
 
-/// Compute duration
-fn compute_duration<R>(mut data: R) -> Option<Duration>
+/// Size of the end of the stream holding its last page
+const LAST_PAGE_BYTES: u64 = 65536;
+
+/// Granule position of the last page, the length of the stream in frames
+///
+/// The stream is left where it was
+fn last_granule<R>(mut data: R) -> Option<u64>
 where
     R: Read + Seek,
 {
-    let stream_pos = data.seek(SeekFrom::Current(0)).unwrap();
+    let stream_pos = data.stream_position().ok()?;
+    let end = data.seek(SeekFrom::End(0)).ok()?;
+    let from = end.saturating_sub(LAST_PAGE_BYTES).max(stream_pos);
+    let mut tail = Vec::new();
+    let read = data
+        .seek(SeekFrom::Start(from))
+        .and_then(|_| data.by_ref().take(end - from).read_to_end(&mut tail));
+    data.seek(SeekFrom::Start(stream_pos)).ok()?;
+    read.ok()?;
 
-    let mut reader = OggStreamReader::new(data.by_ref()).unwrap();
-    let channels = reader.ident_hdr.audio_channels as u64;
-    let sample_rate = reader.ident_hdr.audio_sample_rate as u64;
-    let sample_channel = channels * sample_rate;
-
-    let mut duration: u64 = 0;
-    while let Some(pck_samples) = reader.read_dec_packet_itl().unwrap() {
-        duration += pck_samples.len() as u64 * 1_000 / sample_channel;
-    }
-
-    data.seek(SeekFrom::Start(stream_pos)).unwrap();
-    return Some(Duration::from_millis(duration));
+    // The capture pattern and the version of the page header, then the granule position
+    (0..tail.len().saturating_sub(13))
+        .rev()
+        .filter(|&index| &tail[index..index + 5] == b"OggS\0")
+        .map(|index| {
+            let mut granule = [0u8; 8];
+            granule.copy_from_slice(&tail[index + 6..index + 14]);
+            u64::from_le_bytes(granule)
+        })
+        .find(|&granule| granule != u64::MAX)
 }
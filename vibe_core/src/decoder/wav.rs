@@ -3,7 +3,15 @@ use std::time::Duration;
 
 use hound::{Error, SampleFormat, WavReader, WavSpec};
 
-use crate::{info::DecoderError, AudioFormat, AudioInfo, Sample};
+use super::duration_to_frames;
+use crate::tags::{id3::parse_id3v2, riff::find_chunk};
+use crate::{info::DecoderError, AudioFormat, AudioInfo, LoopRegion, Metadata, Sample};
+
+/// Size of the header of a `smpl` chunk, before the loops
+const SAMPLER_HEADER_LEN: usize = 36;
+
+/// Size of a loop of a `smpl` chunk
+const SAMPLE_LOOP_LEN: usize = 24;
 
 /// Decoder for WAV files
 pub struct WavDecoder<R>
@@ -12,6 +20,8 @@ where
 {
     reader: WavReader<R>,
     spec: WavSpec,
+    metadata: Metadata,
+    loops: Vec<LoopRegion>,
 }
 
 impl<R> WavDecoder<R>
@@ -25,10 +35,22 @@ where
             return Err(data);
         }
 
+        let metadata = find_chunk(data.by_ref(), &[b"id3 ", b"ID3 "])
+            .and_then(|tag| parse_id3v2(&tag))
+            .unwrap_or_default();
+        let loops = find_chunk(data.by_ref(), &[b"smpl"])
+            .map(|chunk| parse_sample_loops(&chunk))
+            .unwrap_or_default();
+
         let reader = WavReader::new(data).unwrap();
         let spec = reader.spec();
 
-        Ok(Self { reader, spec })
+        Ok(Self {
+            reader,
+            spec,
+            metadata,
+            loops,
+        })
     }
 
     /// Get duration audio file
@@ -49,6 +71,28 @@ where
             duration: self.duration(),
         }
     }
+
+    /// Get the tags of the ID3 chunk
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Get the loops of the `smpl` chunk
+    #[inline]
+    pub fn loops(&self) -> &[LoopRegion] {
+        &self.loops
+    }
+
+    /// Seek to the given position, clamped to the end of the file
+    #[inline]
+    pub fn seek(&mut self, pos: Duration) -> Result<(), DecoderError> {
+        let frame =
+            duration_to_frames(pos, self.spec.sample_rate).min(self.reader.duration() as u64);
+        self.reader
+            .seek(frame as u32)
+            .map_err(DecoderError::IOError)
+    }
 }
 
 impl<R> Iterator for WavDecoder<R>
@@ -111,6 +155,24 @@ where
     return is_wav;
 }
 
+/// Read the loops of a `smpl` chunk, their end is the last frame played
+fn parse_sample_loops(chunk: &[u8]) -> Vec<LoopRegion> {
+    let read_u32 = |pos: usize| {
+        chunk
+            .get(pos..pos + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+
+    let count = read_u32(28).unwrap_or(0) as usize;
+    (0..count)
+        .map_while(|index| {
+            let pos = SAMPLER_HEADER_LEN + index * SAMPLE_LOOP_LEN;
+            Some((read_u32(pos + 8)?, read_u32(pos + 12)?))
+        })
+        .filter_map(|(start, end)| LoopRegion::new(start as u64, end as u64 + 1))
+        .collect()
+}
+
 fn get_error(error: Error) -> DecoderError {
     match error {
         Error::IoError(io_err) => DecoderError::IOError(io_err),
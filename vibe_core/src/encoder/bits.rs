@@ -0,0 +1,113 @@
+/// Writer of a stream of bits, most significant bit first
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    /// Bits not yet stored in `bytes`, in the low bits
+    acc: u64,
+    len: u32,
+}
+
+impl BitWriter {
+    #[inline]
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(bytes),
+            acc: 0,
+            len: 0,
+        }
+    }
+
+    /// Write the low `bits` bits of `value`, at most 32
+    #[inline]
+    pub fn write(&mut self, value: u32, bits: u32) {
+        debug_assert!(bits <= 32);
+        if bits == 0 {
+            return;
+        }
+
+        let mask = if bits == 32 {
+            u32::MAX
+        } else {
+            (1 << bits) - 1
+        };
+        self.acc = (self.acc << bits) | (value & mask) as u64;
+        self.len += bits;
+        while self.len >= 8 {
+            self.len -= 8;
+            self.bytes.push((self.acc >> self.len) as u8);
+        }
+    }
+
+    /// Write a signed value on `bits` bits in two's complement
+    #[cfg(feature = "encode-flac")]
+    #[inline]
+    pub fn write_signed(&mut self, value: i32, bits: u32) {
+        self.write(value as u32, bits);
+    }
+
+    /// Write `zeros` zero bits followed by a one
+    #[cfg(feature = "encode-flac")]
+    #[inline]
+    pub fn write_unary(&mut self, mut zeros: u32) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+        self.write(1, zeros + 1);
+    }
+
+    /// Pad with zero bits up to the next byte
+    #[inline]
+    pub fn align(&mut self) {
+        if self.len > 0 {
+            self.write(0, 8 - self.len);
+        }
+    }
+
+    /// Get the bytes written so far, the writer must be aligned
+    #[cfg(feature = "encode-flac")]
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        debug_assert_eq!(self.len, 0);
+        &self.bytes
+    }
+
+    #[inline]
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+/// CRC-8 of the FLAC frame headers, polynomial x^8 + x^2 + x + 1
+#[cfg(feature = "encode-flac")]
+pub(crate) fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-16 of the FLAC frames, polynomial x^16 + x^15 + x^2 + 1
+#[cfg(feature = "encode-flac")]
+pub(crate) fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
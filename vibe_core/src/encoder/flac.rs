@@ -0,0 +1,829 @@
+use std::io::{Seek, SeekFrom, Write};
+
+use super::bits::{crc16, crc8, BitWriter};
+use super::md5::Md5;
+use super::quantize;
+use crate::tags::picture::build_picture;
+use crate::tags::vorbis::{build_comments, VENDOR};
+use crate::{info::EncoderError, Metadata, Sample};
+
+/// Highest compression level, the slowest to encode
+pub const MAX_COMPRESSION_LEVEL: u8 = 8;
+
+/// Length of the STREAMINFO block
+const STREAMINFO_LEN: usize = 34;
+
+/// Type of the metadata block holding the Vorbis comments
+const VORBIS_COMMENT: u8 = 4;
+
+/// Type of the metadata blocks holding a picture
+const PICTURE: u8 = 6;
+
+/// Flag of the header of the last metadata block
+const LAST_BLOCK: u8 = 0x80;
+
+/// Highest order of the LPC subframes
+const MAX_LPC_ORDER: usize = 32;
+
+/// Highest order of the fixed predictors
+const MAX_FIXED_ORDER: usize = 4;
+
+/// Highest precision of the quantized LPC coefficients, 15 bits is the escape code
+const MAX_QLP_PRECISION: u32 = 15;
+
+/// Settings of the FLAC encoder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlacConfig {
+    /// Number of bits of the samples stored, 8, 16 or 24.
+    pub bits_per_sample: u32,
+    /// Trade-off between the encoding speed and the size of the file, from 0 to `MAX_COMPRESSION_LEVEL`.
+    pub compression_level: u8,
+}
+
+impl Default for FlacConfig {
+    fn default() -> Self {
+        Self {
+            bits_per_sample: 16,
+            compression_level: 5,
+        }
+    }
+}
+
+/// Search settings of a compression level, following the levels of the reference encoder
+#[derive(Debug, Clone, Copy)]
+struct LevelParams {
+    block_size: usize,
+    max_lpc_order: usize,
+    max_partition_order: u32,
+    /// Try to store the difference between the channels of stereo frames
+    stereo_decorrelation: bool,
+    /// Encode every LPC order instead of the one with the lowest estimated size
+    search_lpc_order: bool,
+}
+
+impl LevelParams {
+    fn new(level: u8) -> Self {
+        let (block_size, max_lpc_order, max_partition_order, stereo_decorrelation) = match level {
+            0 => (1152, 0, 3, false),
+            1 | 2 => (1152, 0, 3, true),
+            3 => (4096, 6, 4, false),
+            4 => (4096, 8, 4, true),
+            5 => (4096, 8, 5, true),
+            6 | 7 => (4096, 8, 6, true),
+            _ => (4096, 12, 6, true),
+        };
+
+        Self {
+            block_size,
+            max_lpc_order,
+            max_partition_order,
+            stereo_decorrelation,
+            search_lpc_order: level >= 7,
+        }
+    }
+}
+
+/// Encoder writing a FLAC stream, the STREAMINFO block is filled by `finish`
+pub(crate) struct FlacEncoder<W>
+where
+    W: Write + Seek,
+{
+    writer: W,
+    params: LevelParams,
+    bits_per_sample: u32,
+    sample_rate: u32,
+    channels: usize,
+    /// Position of the STREAMINFO block
+    streaminfo_offset: u64,
+    /// Interleaved samples of the next frame
+    buffer: Vec<i32>,
+    md5: Md5,
+    frame_number: u64,
+    total_frames: u64,
+    min_frame_len: u32,
+    max_frame_len: u32,
+}
+
+impl<W> FlacEncoder<W>
+where
+    W: Write + Seek,
+{
+    pub fn new(
+        mut writer: W,
+        sample_rate: u32,
+        channels: usize,
+        config: FlacConfig,
+        metadata: &Metadata,
+    ) -> Result<Self, EncoderError> {
+        if channels == 0 || channels > 8 || sample_rate == 0 || sample_rate >= 1 << 20 {
+            return Err(EncoderError::Unsupported(format!(
+                "FLAC with {} channels at {} Hz",
+                channels, sample_rate
+            )));
+        }
+        if sample_size_code(config.bits_per_sample).is_none() {
+            return Err(EncoderError::Unsupported(format!(
+                "FLAC with {} bits per sample",
+                config.bits_per_sample
+            )));
+        }
+        if config.compression_level > MAX_COMPRESSION_LEVEL {
+            return Err(EncoderError::Unsupported(format!(
+                "FLAC compression level {}",
+                config.compression_level
+            )));
+        }
+
+        // The Vorbis comments follow STREAMINFO if there are tags, then the pictures
+        let mut blocks = Vec::new();
+        if !metadata.is_empty() {
+            blocks.push((VORBIS_COMMENT, build_comments(VENDOR, metadata, &[])));
+        }
+        for picture in metadata.pictures() {
+            blocks.push((PICTURE, build_picture(picture)));
+        }
+        if blocks.iter().any(|(_, block)| block.len() >= 1 << 24) {
+            return Err(EncoderError::Unsupported(
+                "FLAC metadata block larger than 16 MiB".to_owned(),
+            ));
+        }
+
+        let streaminfo_offset = writer
+            .stream_position()
+            .and_then(|offset| {
+                writer.write_all(b"fLaC")?;
+                let last = if blocks.is_empty() { LAST_BLOCK } else { 0 };
+                writer.write_all(&[last, 0, 0, STREAMINFO_LEN as u8])?;
+                writer.write_all(&[0; STREAMINFO_LEN])?;
+                for (index, (kind, block)) in blocks.iter().enumerate() {
+                    let last = if index + 1 == blocks.len() {
+                        LAST_BLOCK
+                    } else {
+                        0
+                    };
+                    writer.write_all(&[kind | last])?;
+                    writer.write_all(&(block.len() as u32).to_be_bytes()[1..])?;
+                    writer.write_all(block)?;
+                }
+                Ok(offset + 8)
+            })
+            .map_err(EncoderError::IOError)?;
+        let params = LevelParams::new(config.compression_level);
+
+        Ok(Self {
+            writer,
+            params,
+            bits_per_sample: config.bits_per_sample,
+            sample_rate,
+            channels,
+            streaminfo_offset,
+            buffer: Vec::with_capacity(params.block_size * channels),
+            md5: Md5::new(),
+            frame_number: 0,
+            total_frames: 0,
+            min_frame_len: u32::MAX,
+            max_frame_len: 0,
+        })
+    }
+
+    /// Write interleaved samples, the frames are encoded once a block is full
+    pub fn write(&mut self, samples: &[Sample]) -> Result<(), EncoderError> {
+        let block_len = self.params.block_size * self.channels;
+        let bytes = self.bits_per_sample as usize / 8;
+        for &sample in samples {
+            let sample = quantize(sample, self.bits_per_sample);
+            // The digest is computed on the little-endian samples
+            self.md5.update(&sample.to_le_bytes()[..bytes]);
+            self.buffer.push(sample);
+
+            if self.buffer.len() == block_len {
+                self.write_frame()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode the last block and fill the STREAMINFO block
+    pub fn finish(mut self) -> Result<W, EncoderError> {
+        if !self.buffer.is_empty() {
+            self.write_frame()?;
+        }
+
+        let streaminfo = self.streaminfo();
+        self.finish_stream(&streaminfo)
+            .map_err(EncoderError::IOError)?;
+        Ok(self.writer)
+    }
+
+    fn finish_stream(&mut self, streaminfo: &[u8]) -> std::io::Result<()> {
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.streaminfo_offset))?;
+        self.writer.write_all(streaminfo)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    fn streaminfo(&mut self) -> Vec<u8> {
+        let md5 = std::mem::take(&mut self.md5).finish();
+        // A zero frame length or number of samples means unknown
+        let (min_frame_len, max_frame_len) = if self.max_frame_len > 0 {
+            (self.min_frame_len, self.max_frame_len)
+        } else {
+            (0, 0)
+        };
+        let total_frames = if self.total_frames < 1 << 36 {
+            self.total_frames
+        } else {
+            0
+        };
+
+        let mut streaminfo = BitWriter::with_capacity(STREAMINFO_LEN);
+        streaminfo.write(self.params.block_size as u32, 16);
+        streaminfo.write(self.params.block_size as u32, 16);
+        streaminfo.write(min_frame_len, 24);
+        streaminfo.write(max_frame_len, 24);
+        streaminfo.write(self.sample_rate, 20);
+        streaminfo.write(self.channels as u32 - 1, 3);
+        streaminfo.write(self.bits_per_sample - 1, 5);
+        streaminfo.write((total_frames >> 32) as u32, 4);
+        streaminfo.write(total_frames as u32, 32);
+        let mut streaminfo = streaminfo.into_bytes();
+        streaminfo.extend_from_slice(&md5);
+        streaminfo
+    }
+
+    /// Encode the samples of the buffer as a frame
+    fn write_frame(&mut self) -> Result<(), EncoderError> {
+        // The frame number is coded on 31 bits at most
+        if self.frame_number >= 1 << 31 {
+            return Err(EncoderError::Unsupported(
+                "FLAC streams are limited to 2^31 frames".to_owned(),
+            ));
+        }
+
+        let block_size = self.buffer.len() / self.channels;
+        let channels: Vec<Vec<i32>> = (0..self.channels)
+            .map(|channel| {
+                self.buffer
+                    .iter()
+                    .skip(channel)
+                    .step_by(self.channels)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        let frame = encode_frame(
+            &channels,
+            self.bits_per_sample,
+            self.frame_number,
+            &self.params,
+        );
+
+        self.writer
+            .write_all(&frame)
+            .map_err(EncoderError::IOError)?;
+        self.min_frame_len = self.min_frame_len.min(frame.len() as u32);
+        self.max_frame_len = self.max_frame_len.max(frame.len() as u32);
+        self.frame_number += 1;
+        self.total_frames += block_size as u64;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+/// Get the code of the sample size in the frame headers
+#[inline]
+fn sample_size_code(bits_per_sample: u32) -> Option<u32> {
+    match bits_per_sample {
+        8 => Some(1),
+        16 => Some(4),
+        24 => Some(6),
+        _ => None,
+    }
+}
+
+/// Get the code of the block size in the frame headers, and the value stored after the header
+#[inline]
+fn block_size_code(block_size: usize) -> (u32, Option<(u32, u32)>) {
+    match block_size {
+        192 => (1, None),
+        576 | 1152 | 2304 | 4608 => (block_size.trailing_zeros() - 4, None),
+        256 | 512 | 1024 | 2048 | 4096 | 8192 | 16384 | 32768 => {
+            (block_size.trailing_zeros(), None)
+        }
+        _ if block_size <= 256 => (6, Some((block_size as u32 - 1, 8))),
+        _ => (7, Some((block_size as u32 - 1, 16))),
+    }
+}
+
+/// Write a number with the variable length coding of UTF-8, extended to 36 bits
+fn write_utf8(writer: &mut BitWriter, value: u64) {
+    if value < 0x80 {
+        writer.write(value as u32, 8);
+        return;
+    }
+
+    let len = match value {
+        _ if value < 0x800 => 2,
+        _ if value < 0x10000 => 3,
+        _ if value < 0x200000 => 4,
+        _ if value < 0x4000000 => 5,
+        _ if value < 0x80000000 => 6,
+        _ => 7,
+    };
+    let prefix = (0xff00u32 >> len) & 0xff;
+    writer.write(prefix | (value >> (6 * (len - 1))) as u32, 8);
+    for index in (0..len - 1).rev() {
+        writer.write(0x80 | ((value >> (6 * index)) & 0x3f) as u32, 8);
+    }
+}
+
+/// Encode a frame, choosing the cheapest channel assignment and subframes
+fn encode_frame(
+    channels: &[Vec<i32>],
+    bits_per_sample: u32,
+    frame_number: u64,
+    params: &LevelParams,
+) -> Vec<u8> {
+    let block_size = channels[0].len();
+    let encoded: Vec<Subframe> = channels
+        .iter()
+        .map(|samples| Subframe::encode(samples, bits_per_sample, params))
+        .collect();
+
+    let (assignment, subframes) = if channels.len() == 2 && params.stereo_decorrelation {
+        let (left, right) = (&channels[0], &channels[1]);
+        let mid: Vec<i32> = left
+            .iter()
+            .zip(right)
+            .map(|(&l, &r)| (l + r) >> 1)
+            .collect();
+        let side: Vec<i32> = left.iter().zip(right).map(|(&l, &r)| l - r).collect();
+        let mid = Subframe::encode(&mid, bits_per_sample, params);
+        let side = Subframe::encode(&side, bits_per_sample + 1, params);
+        let (left, right) = (encoded[0].clone(), encoded[1].clone());
+
+        vec![
+            (1, vec![left.clone(), right.clone()]),
+            (8, vec![left, side.clone()]),
+            (9, vec![side.clone(), right]),
+            (10, vec![mid, side]),
+        ]
+        .into_iter()
+        .min_by_key(|(_, subframes)| subframes.iter().map(|s| s.bits).sum::<u64>())
+        .unwrap()
+    } else {
+        (channels.len() as u32 - 1, encoded)
+    };
+
+    let len = subframes.iter().map(|s| s.bits).sum::<u64>() / 8 + 32;
+    let mut frame = BitWriter::with_capacity(len as usize);
+    let (size_code, size_value) = block_size_code(block_size);
+    frame.write(0xfff8, 16);
+    frame.write(size_code, 4);
+    // The sample rate is the one of the STREAMINFO block
+    frame.write(0, 4);
+    frame.write(assignment, 4);
+    frame.write(sample_size_code(bits_per_sample).unwrap_or(0), 3);
+    frame.write(0, 1);
+    write_utf8(&mut frame, frame_number);
+    if let Some((value, bits)) = size_value {
+        frame.write(value, bits);
+    }
+    let crc = crc8(frame.bytes());
+    frame.write(crc as u32, 8);
+
+    for subframe in &subframes {
+        subframe.write(&mut frame);
+    }
+    frame.align();
+    let crc = crc16(frame.bytes());
+    frame.write(crc as u32, 16);
+    frame.into_bytes()
+}
+
+/// Prediction of the samples of a subframe
+#[derive(Debug, Clone)]
+enum Prediction {
+    Constant,
+    Verbatim,
+    Fixed(Residual),
+    Lpc {
+        precision: u32,
+        shift: u32,
+        coefficients: Vec<i32>,
+        residual: Residual,
+    },
+}
+
+/// Samples of a channel with the cheapest prediction found
+#[derive(Debug, Clone)]
+struct Subframe {
+    samples: Vec<i32>,
+    bits_per_sample: u32,
+    prediction: Prediction,
+    /// Size of the encoded subframe
+    bits: u64,
+}
+
+impl Subframe {
+    fn encode(samples: &[i32], bits_per_sample: u32, params: &LevelParams) -> Self {
+        let subframe = |prediction, bits| Self {
+            samples: samples.to_vec(),
+            bits_per_sample,
+            prediction,
+            bits,
+        };
+        if samples.iter().all(|&sample| sample == samples[0]) {
+            return subframe(Prediction::Constant, 8 + bits_per_sample as u64);
+        }
+
+        let mut best = (
+            Prediction::Verbatim,
+            8 + samples.len() as u64 * bits_per_sample as u64,
+        );
+        let mut candidates = Vec::new();
+        if let Some(residual) = encode_fixed(samples, params) {
+            let bits = 8 + residual.order as u64 * bits_per_sample as u64 + residual.bits;
+            candidates.push((Prediction::Fixed(residual), bits));
+        }
+        candidates.extend(encode_lpc(samples, bits_per_sample, params));
+        for candidate in candidates {
+            if candidate.1 < best.1 {
+                best = candidate;
+            }
+        }
+
+        subframe(best.0, best.1)
+    }
+
+    fn write(&self, writer: &mut BitWriter) {
+        // The zero padding bit, the type and the wasted bits flag
+        let write_header = |writer: &mut BitWriter, kind: u32| writer.write(kind << 1, 8);
+        let write_warmup = |writer: &mut BitWriter, order: usize| {
+            for &sample in &self.samples[..order] {
+                writer.write_signed(sample, self.bits_per_sample);
+            }
+        };
+
+        match &self.prediction {
+            Prediction::Constant => {
+                write_header(writer, 0);
+                writer.write_signed(self.samples[0], self.bits_per_sample);
+            }
+            Prediction::Verbatim => {
+                write_header(writer, 1);
+                write_warmup(writer, self.samples.len());
+            }
+            Prediction::Fixed(residual) => {
+                write_header(writer, 0x08 | residual.order as u32);
+                write_warmup(writer, residual.order);
+                residual.write(writer);
+            }
+            Prediction::Lpc {
+                precision,
+                shift,
+                coefficients,
+                residual,
+            } => {
+                write_header(writer, 0x20 | (residual.order as u32 - 1));
+                write_warmup(writer, residual.order);
+                writer.write(precision - 1, 4);
+                writer.write(*shift, 5);
+                for &coefficient in coefficients {
+                    writer.write_signed(coefficient, *precision);
+                }
+                residual.write(writer);
+            }
+        }
+    }
+}
+
+/// Residual of a prediction, coded with Rice codes in partitions
+#[derive(Debug, Clone)]
+struct Residual {
+    /// Order of the predictor, the number of warm-up samples before the residual
+    order: usize,
+    values: Vec<i32>,
+    partition_order: u32,
+    /// Rice parameter of each partition
+    parameters: Vec<u32>,
+    /// Number of bits of the parameters, 4 or 5
+    parameter_bits: u32,
+    /// Size of the coded residual
+    bits: u64,
+}
+
+impl Residual {
+    /// Choose the partition order and the parameters giving the smallest residual
+    fn new(values: Vec<i32>, order: usize, max_partition_order: u32) -> Self {
+        let block_size = values.len() + order;
+        let folded: Vec<u64> = values.iter().map(|&value| fold(value)).collect();
+
+        let mut best: Option<Self> = None;
+        for partition_order in 0..=max_partition_order {
+            let partitions = 1 << partition_order;
+            // The first partition holds the warm-up samples, it mustn't be empty
+            if !block_size.is_multiple_of(partitions) || block_size / partitions <= order {
+                break;
+            }
+
+            let len = block_size / partitions;
+            let mut parameters = Vec::with_capacity(partitions);
+            let mut bits = 0;
+            for partition in 0..partitions {
+                let start = (partition * len).saturating_sub(order);
+                let end = (partition + 1) * len - order;
+                let (parameter, partition_bits) = rice_parameter(&folded[start..end]);
+                parameters.push(parameter);
+                bits += partition_bits;
+            }
+            let parameter_bits = if parameters.iter().any(|&p| p > 14) {
+                5
+            } else {
+                4
+            };
+            bits += 6 + partitions as u64 * parameter_bits as u64;
+
+            if best.as_ref().is_none_or(|best| bits < best.bits) {
+                best = Some(Self {
+                    order,
+                    values: Vec::new(),
+                    partition_order,
+                    parameters,
+                    parameter_bits,
+                    bits,
+                });
+            }
+        }
+
+        // The first order is always valid since the block is longer than the order
+        let mut best = best.unwrap();
+        best.values = values;
+        best
+    }
+
+    fn write(&self, writer: &mut BitWriter) {
+        let block_size = self.values.len() + self.order;
+        let len = block_size >> self.partition_order;
+        writer.write(self.parameter_bits - 4, 2);
+        writer.write(self.partition_order, 4);
+
+        for (partition, &parameter) in self.parameters.iter().enumerate() {
+            let start = (partition * len).saturating_sub(self.order);
+            let end = (partition + 1) * len - self.order;
+            writer.write(parameter, self.parameter_bits);
+            for &value in &self.values[start..end] {
+                let value = fold(value);
+                writer.write_unary((value >> parameter) as u32);
+                writer.write((value & ((1 << parameter) - 1)) as u32, parameter);
+            }
+        }
+    }
+}
+
+/// Map the signed values to unsigned ones, alternating the signs
+#[inline]
+fn fold(value: i32) -> u64 {
+    if value >= 0 {
+        (value as u64) << 1
+    } else {
+        ((-(value as i64) as u64) << 1) - 1
+    }
+}
+
+/// Find the cheapest Rice parameter of a partition, and its size
+fn rice_parameter(folded: &[u64]) -> (u32, u64) {
+    let len = folded.len() as u64;
+    let cost = |parameter: u32| {
+        folded.iter().map(|&value| value >> parameter).sum::<u64>() + len * (parameter as u64 + 1)
+    };
+    if len == 0 {
+        return (0, 0);
+    }
+
+    // The best parameter is close to the logarithm of the mean
+    let mean = folded.iter().sum::<u64>() / len;
+    let estimate = (64 - mean.leading_zeros()).min(30);
+    (estimate.saturating_sub(1)..=(estimate + 1).min(30))
+        .map(|parameter| (parameter, cost(parameter)))
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap()
+}
+
+/// Compute the residual of a prediction, `None` if it doesn't fit in 32 bits
+fn predict<F>(samples: &[i32], order: usize, prediction: F) -> Option<Vec<i32>>
+where
+    F: Fn(&[i32]) -> i64,
+{
+    samples
+        .windows(order + 1)
+        .map(|window| {
+            let residual = window[order] as i64 - prediction(&window[..order]);
+            if residual >= i32::MIN as i64 && residual <= i32::MAX as i64 {
+                Some(residual as i32)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Encode the residual of the fixed predictor with the smallest residual
+fn encode_fixed(samples: &[i32], params: &LevelParams) -> Option<Residual> {
+    let max_order = MAX_FIXED_ORDER.min(samples.len() - 1);
+    (0..=max_order)
+        .filter_map(|order| {
+            let residual = predict(samples, order, |w| {
+                let w = |index: usize| w[index] as i64;
+                match order {
+                    0 => 0,
+                    1 => w(0),
+                    2 => 2 * w(1) - w(0),
+                    3 => 3 * w(2) - 3 * w(1) + w(0),
+                    _ => 4 * w(3) - 6 * w(2) + 4 * w(1) - w(0),
+                }
+            })?;
+            let sum: u64 = residual.iter().map(|&value| fold(value)).sum();
+            Some((order, residual, sum))
+        })
+        .min_by_key(|(_, _, sum)| *sum)
+        .map(|(order, residual, _)| Residual::new(residual, order, params.max_partition_order))
+}
+
+/// Encode the residual of the linear predictions, either the order estimated best or every order
+fn encode_lpc(
+    samples: &[i32],
+    bits_per_sample: u32,
+    params: &LevelParams,
+) -> Vec<(Prediction, u64)> {
+    let max_order = params
+        .max_lpc_order
+        .min(MAX_LPC_ORDER)
+        .min(samples.len() - 1);
+    if max_order == 0 {
+        return Vec::new();
+    }
+
+    let autocorrelation = autocorrelation(&window(samples), max_order);
+    if autocorrelation[0] == 0.0 {
+        return Vec::new();
+    }
+    let (coefficients, errors) = levinson_durbin(&autocorrelation);
+    let precision = qlp_precision(samples.len());
+
+    let orders: Vec<usize> = if params.search_lpc_order {
+        (1..=coefficients.len()).collect()
+    } else {
+        // Estimate the size of the residual from the prediction error
+        let scale = 0.5 / samples.len() as f64;
+        (1..=coefficients.len())
+            .map(|order| {
+                let error = errors[order - 1] * scale;
+                let bits = if error > 0.0 {
+                    (0.5 * error.log2()).max(0.0)
+                } else {
+                    0.0
+                };
+                let header = order as f64 * (precision + bits_per_sample) as f64;
+                (order, header + bits * (samples.len() - order) as f64)
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(order, _)| order)
+            .into_iter()
+            .collect()
+    };
+
+    orders
+        .into_iter()
+        .filter_map(|order| {
+            let (quantized, shift) = quantize_coefficients(&coefficients[order - 1], precision)?;
+            let values = predict(samples, order, |w| {
+                let prediction: i64 = quantized
+                    .iter()
+                    .zip(w.iter().rev())
+                    .map(|(&c, &sample)| c as i64 * sample as i64)
+                    .sum();
+                prediction >> shift
+            })?;
+            let residual = Residual::new(values, order, params.max_partition_order);
+            let bits = 8 + order as u64 * (bits_per_sample + precision) as u64 + 9 + residual.bits;
+
+            Some((
+                Prediction::Lpc {
+                    precision,
+                    shift,
+                    coefficients: quantized,
+                    residual,
+                },
+                bits,
+            ))
+        })
+        .collect()
+}
+
+/// Apply a Tukey window, tapering a quarter of the block on each side
+fn window(samples: &[i32]) -> Vec<f64> {
+    let len = samples.len();
+    let taper = len / 4;
+    samples
+        .iter()
+        .enumerate()
+        .map(|(index, &sample)| {
+            let distance = index.min(len - 1 - index);
+            let weight = if distance < taper {
+                0.5 - 0.5 * (std::f64::consts::PI * distance as f64 / taper as f64).cos()
+            } else {
+                1.0
+            };
+            sample as f64 * weight
+        })
+        .collect()
+}
+
+fn autocorrelation(samples: &[f64], max_lag: usize) -> Vec<f64> {
+    (0..=max_lag)
+        .map(|lag| samples[lag..].iter().zip(samples).map(|(a, b)| a * b).sum())
+        .collect()
+}
+
+/// Compute the predictor coefficients of every order with the Levinson-Durbin recursion
+///
+/// The coefficients of each order start with the one of the previous sample, the errors are the
+/// remaining energy of each order
+fn levinson_durbin(autocorrelation: &[f64]) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let mut error = autocorrelation[0];
+    let mut predictor: Vec<f64> = Vec::new();
+    let mut coefficients = Vec::new();
+    let mut errors = Vec::new();
+
+    for order in 1..autocorrelation.len() {
+        let correlation = autocorrelation[order]
+            - predictor
+                .iter()
+                .enumerate()
+                .map(|(index, c)| c * autocorrelation[order - 1 - index])
+                .sum::<f64>();
+        let reflection = correlation / error;
+
+        let previous = predictor.clone();
+        for (index, c) in predictor.iter_mut().enumerate() {
+            *c -= reflection * previous[order - 2 - index];
+        }
+        predictor.push(reflection);
+        error *= 1.0 - reflection * reflection;
+
+        coefficients.push(predictor.clone());
+        errors.push(error);
+        if error <= 0.0 {
+            break;
+        }
+    }
+
+    (coefficients, errors)
+}
+
+/// Get the precision of the quantized coefficients, longer blocks are worth more precise ones
+#[inline]
+fn qlp_precision(block_size: usize) -> u32 {
+    match block_size {
+        0..=192 => 7,
+        193..=384 => 8,
+        385..=576 => 9,
+        577..=1152 => 10,
+        1153..=2304 => 11,
+        2305..=4608 => 12,
+        _ => 13,
+    }
+    .min(MAX_QLP_PRECISION)
+}
+
+/// Quantize the coefficients on `precision` bits, carrying the rounding errors over
+///
+/// Returns the coefficients and their shift, `None` if they can't be stored
+fn quantize_coefficients(coefficients: &[f64], precision: u32) -> Option<(Vec<i32>, u32)> {
+    let max = coefficients.iter().fold(0.0f64, |max, c| max.max(c.abs()));
+    if max <= 0.0 || !max.is_finite() {
+        return None;
+    }
+
+    // The largest coefficient uses all the bits but the sign
+    let shift = precision as i32 - 2 - max.log2().floor() as i32;
+    if shift < 0 {
+        return None;
+    }
+    let shift = shift.min(15) as u32;
+
+    let limit = 1i64 << (precision - 1);
+    let mut error = 0.0;
+    let quantized = coefficients
+        .iter()
+        .map(|c| {
+            let value = c * (1u32 << shift) as f64 + error;
+            let rounded = (value.round() as i64).clamp(-limit, limit - 1);
+            error = value - rounded as f64;
+            rounded as i32
+        })
+        .collect();
+    Some((quantized, shift))
+}
@@ -0,0 +1,230 @@
+use std::io::{Seek, Write};
+
+use crate::{info::EncoderError, Metadata, Sample};
+
+#[cfg(any(feature = "encode-flac", feature = "encode-mp3"))]
+mod bits;
+#[cfg(feature = "encode-flac")]
+mod flac;
+#[cfg(feature = "encode-flac")]
+mod md5;
+#[cfg(feature = "encode-mp3")]
+mod mp3;
+#[cfg(feature = "encode-vorbis")]
+mod vorbis;
+#[cfg(feature = "encode-wav")]
+mod wav;
+
+#[cfg(feature = "encode-flac")]
+pub use self::flac::{FlacConfig, MAX_COMPRESSION_LEVEL};
+#[cfg(feature = "encode-flac")]
+pub use self::md5::Md5;
+#[cfg(feature = "encode-mp3")]
+pub use self::mp3::{Mp3Bitrate, MAX_VBR_QUALITY, MP3_BITRATES};
+#[cfg(feature = "encode-vorbis")]
+pub use self::vorbis::VorbisConfig;
+#[cfg(feature = "encode-wav")]
+pub use self::wav::WavSampleFormat;
+
+/// Format of the file written by an `Encoder`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncoderFormat {
+    #[cfg(feature = "encode-wav")]
+    Wav(WavSampleFormat),
+    #[cfg(feature = "encode-flac")]
+    Flac(FlacConfig),
+    #[cfg(feature = "encode-vorbis")]
+    Vorbis(VorbisConfig),
+    #[cfg(feature = "encode-mp3")]
+    Mp3(Mp3Bitrate),
+}
+
+impl EncoderFormat {
+    /// Get the number of bits of the integer samples stored, None for the samples stored as floats
+    /// and for the lossy formats
+    pub fn bits_per_sample(&self) -> Option<u32> {
+        match *self {
+            #[cfg(feature = "encode-wav")]
+            EncoderFormat::Wav(WavSampleFormat::Float32) => None,
+            #[cfg(feature = "encode-wav")]
+            EncoderFormat::Wav(format) => Some(format.bits_per_sample()),
+            #[cfg(feature = "encode-flac")]
+            EncoderFormat::Flac(config) => Some(config.bits_per_sample),
+            #[cfg(feature = "encode-vorbis")]
+            EncoderFormat::Vorbis(_) => None,
+            #[cfg(feature = "encode-mp3")]
+            EncoderFormat::Mp3(_) => None,
+        }
+    }
+}
+
+/// Description of the stream written by an `Encoder`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncoderSpec {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub format: EncoderFormat,
+}
+
+/// Audio encoder
+///
+/// Support WAV, FLAC, Ogg Vorbis and MP3, the samples are interleaved like the ones of a `Decoder`
+pub struct Encoder<W>
+where
+    W: Write + Seek,
+{
+    encoder: FormatEncoder<W>,
+    spec: EncoderSpec,
+    samples: u64,
+}
+
+impl<W> Encoder<W>
+where
+    W: Write + Seek,
+{
+    /// Write the header of the file, the fields depending on the length are filled by `finish`
+    #[inline]
+    pub fn new(writer: W, spec: EncoderSpec) -> Result<Self, EncoderError> {
+        Self::with_metadata(writer, spec, &Metadata::new())
+    }
+
+    /// Write the header of the file with the tags of the metadata, like the ones of the source file
+    #[inline]
+    pub fn with_metadata(
+        writer: W,
+        spec: EncoderSpec,
+        metadata: &Metadata,
+    ) -> Result<Self, EncoderError> {
+        Ok(Self {
+            encoder: FormatEncoder::new(writer, &spec, metadata)?,
+            spec,
+            samples: 0,
+        })
+    }
+
+    #[inline]
+    pub fn spec(&self) -> EncoderSpec {
+        self.spec
+    }
+
+    /// Encode interleaved samples, a frame may be split across calls
+    #[inline]
+    pub fn write(&mut self, samples: &[Sample]) -> Result<(), EncoderError> {
+        self.encoder.write(samples)?;
+        self.samples += samples.len() as u64;
+        Ok(())
+    }
+
+    /// Get the number of frames (samples per channel) written so far
+    #[inline]
+    pub fn frames(&self) -> u64 {
+        self.samples / self.spec.channels as u64
+    }
+
+    /// Write the end of the file and give the writer back
+    #[inline]
+    pub fn finish(self) -> Result<W, EncoderError> {
+        if !self.samples.is_multiple_of(self.spec.channels as u64) {
+            return Err(EncoderError::IncompleteData);
+        }
+        self.encoder.finish()
+    }
+}
+
+/// Choose the right encoder
+pub(crate) enum FormatEncoder<W>
+where
+    W: Write + Seek,
+{
+    #[cfg(feature = "encode-wav")]
+    Wav(self::wav::WavEncoder<W>),
+    #[cfg(feature = "encode-flac")]
+    Flac(self::flac::FlacEncoder<W>),
+    #[cfg(feature = "encode-vorbis")]
+    Vorbis(self::vorbis::VorbisEncoder<W>),
+    #[cfg(feature = "encode-mp3")]
+    Mp3(self::mp3::Mp3Encoder<W>),
+}
+
+impl<W> FormatEncoder<W>
+where
+    W: Write + Seek,
+{
+    #[inline]
+    pub fn new(writer: W, spec: &EncoderSpec, metadata: &Metadata) -> Result<Self, EncoderError> {
+        match spec.format {
+            #[cfg(feature = "encode-wav")]
+            EncoderFormat::Wav(format) => Ok(FormatEncoder::Wav(self::wav::WavEncoder::new(
+                writer,
+                spec.sample_rate,
+                spec.channels,
+                format,
+                metadata,
+            )?)),
+            #[cfg(feature = "encode-flac")]
+            EncoderFormat::Flac(config) => Ok(FormatEncoder::Flac(self::flac::FlacEncoder::new(
+                writer,
+                spec.sample_rate,
+                spec.channels,
+                config,
+                metadata,
+            )?)),
+            #[cfg(feature = "encode-vorbis")]
+            EncoderFormat::Vorbis(config) => {
+                Ok(FormatEncoder::Vorbis(self::vorbis::VorbisEncoder::new(
+                    writer,
+                    spec.sample_rate,
+                    spec.channels,
+                    config,
+                    metadata,
+                )?))
+            }
+            #[cfg(feature = "encode-mp3")]
+            EncoderFormat::Mp3(bitrate) => Ok(FormatEncoder::Mp3(self::mp3::Mp3Encoder::new(
+                writer,
+                spec.sample_rate,
+                spec.channels,
+                bitrate,
+                metadata,
+            )?)),
+        }
+    }
+
+    #[inline]
+    pub fn write(&mut self, samples: &[Sample]) -> Result<(), EncoderError> {
+        match self {
+            #[cfg(feature = "encode-wav")]
+            FormatEncoder::Wav(e) => e.write(samples),
+            #[cfg(feature = "encode-flac")]
+            FormatEncoder::Flac(e) => e.write(samples),
+            #[cfg(feature = "encode-vorbis")]
+            FormatEncoder::Vorbis(e) => e.write(samples),
+            #[cfg(feature = "encode-mp3")]
+            FormatEncoder::Mp3(e) => e.write(samples),
+        }
+    }
+
+    #[inline]
+    pub fn finish(self) -> Result<W, EncoderError> {
+        match self {
+            #[cfg(feature = "encode-wav")]
+            FormatEncoder::Wav(e) => e.finish(),
+            #[cfg(feature = "encode-flac")]
+            FormatEncoder::Flac(e) => e.finish(),
+            #[cfg(feature = "encode-vorbis")]
+            FormatEncoder::Vorbis(e) => e.finish(),
+            #[cfg(feature = "encode-mp3")]
+            FormatEncoder::Mp3(e) => e.finish(),
+        }
+    }
+}
+
+/// Convert a sample into an integer of the given number of bits
+///
+/// This is the inverse of the scaling of the decoders, so decoded integer samples are encoded back untouched
+#[cfg(any(feature = "encode-wav", feature = "encode-flac"))]
+#[inline]
+pub(crate) fn quantize(sample: Sample, bits: u32) -> i32 {
+    let max = (i32::MAX >> (32 - bits)) as f64;
+    (sample as f64 * max).round().clamp(-max - 1.0, max) as i32
+}
@@ -0,0 +1,144 @@
+use std::f64::consts::PI;
+
+use super::tables::ANALYSIS_WINDOW;
+
+/// Number of subbands of the polyphase filter bank
+const SUBBANDS: usize = 32;
+
+/// Number of samples of each subband in a granule
+const SLOTS: usize = 18;
+
+/// Number of samples of a granule
+pub(super) const GRANULE_LEN: usize = SUBBANDS * SLOTS;
+
+/// Length of the analysis window
+const WINDOW_LEN: usize = 512;
+
+/// Coefficients of the butterflies reducing the aliasing between subbands
+const ALIAS_COEFFICIENTS: [f64; 8] = [
+    -0.6, -0.535, -0.33, -0.185, -0.095, -0.041, -0.0142, -0.0037,
+];
+
+/// Transforms shared by the channels
+pub(super) struct Transforms {
+    /// Cosine modulation of the polyphase filter bank, 64 terms per subband
+    analysis: Vec<f32>,
+    /// Windowed MDCT, 36 terms per line
+    mdct: Vec<f32>,
+    alias: [(f32, f32); 8],
+}
+
+impl Transforms {
+    pub fn new() -> Self {
+        let analysis = (0..SUBBANDS)
+            .flat_map(|k| {
+                (0..64).map(move |i| {
+                    ((2 * k + 1) as f64 * (i as f64 - 16.0) * PI / 64.0).cos() as f32
+                        / (1 << 22) as f32
+                })
+            })
+            .collect();
+
+        // Long blocks only, with the sine window. The scale makes the decoders give the input back
+        let mdct = (0..SLOTS)
+            .flat_map(|k| {
+                (0..2 * SLOTS).map(move |i| {
+                    let window = (PI / 36.0 * (i as f64 + 0.5)).sin();
+                    let basis = (PI / 72.0 * (2 * i + 1 + SLOTS) as f64 * (2 * k + 1) as f64).cos();
+                    (window * basis / 4.5) as f32
+                })
+            })
+            .collect();
+
+        let mut alias = [(0.0, 0.0); 8];
+        for (pair, &c) in alias.iter_mut().zip(ALIAS_COEFFICIENTS.iter()) {
+            let norm = (1.0 + c * c).sqrt();
+            *pair = ((1.0 / norm) as f32, (c / norm) as f32);
+        }
+
+        Self {
+            analysis,
+            mdct,
+            alias,
+        }
+    }
+}
+
+/// Filter bank of a channel, giving the 576 frequency lines of each granule
+///
+/// The decoders give the samples back 1057 samples later: 481 for the polyphase filter bank and
+/// a granule for the overlap of the MDCT
+pub(super) struct FilterBank {
+    /// Last input samples, the newest at the end
+    history: Vec<f32>,
+    /// Subband samples of the previous granule, the first half of the MDCT blocks
+    previous: [[f32; SLOTS]; SUBBANDS],
+}
+
+impl FilterBank {
+    pub fn new() -> Self {
+        Self {
+            history: vec![0.0; WINDOW_LEN],
+            previous: [[0.0; SLOTS]; SUBBANDS],
+        }
+    }
+
+    /// Transform the samples of a granule of the channel
+    pub fn granule(&mut self, transforms: &Transforms, samples: &[f32], lines: &mut [f32]) {
+        debug_assert_eq!(samples.len(), GRANULE_LEN);
+        let mut current = [[0.0; SLOTS]; SUBBANDS];
+
+        for (slot, input) in samples.chunks_exact(SUBBANDS).enumerate() {
+            self.history.copy_within(SUBBANDS.., 0);
+            self.history[WINDOW_LEN - SUBBANDS..].copy_from_slice(input);
+
+            // The window is folded on 64 terms, the cosines having a period of 128
+            let mut folded = [0.0; 64];
+            for (n, &coefficient) in ANALYSIS_WINDOW.iter().enumerate() {
+                let sign = if (n / 64).is_multiple_of(2) {
+                    1.0
+                } else {
+                    -1.0
+                };
+                folded[n % 64] += sign * coefficient as f32 * self.history[WINDOW_LEN - 1 - n];
+            }
+
+            for (subband, cosines) in transforms.analysis.chunks_exact(64).enumerate() {
+                let value: f32 = cosines.iter().zip(&folded).map(|(c, y)| c * y).sum();
+                // The decoders invert every other sample of the odd subbands
+                current[subband][slot] = if subband % 2 == 1 && slot % 2 == 1 {
+                    -value
+                } else {
+                    value
+                };
+            }
+        }
+
+        for subband in 0..SUBBANDS {
+            let block: Vec<f32> = self.previous[subband]
+                .iter()
+                .chain(&current[subband])
+                .copied()
+                .collect();
+            let output = &mut lines[subband * SLOTS..(subband + 1) * SLOTS];
+            for (line, basis) in output
+                .iter_mut()
+                .zip(transforms.mdct.chunks_exact(2 * SLOTS))
+            {
+                *line = basis.iter().zip(&block).map(|(b, x)| b * x).sum();
+            }
+        }
+        self.previous = current;
+
+        // Inverse of the butterflies of the decoders
+        for subband in 1..SUBBANDS {
+            for (i, &(cs, ca)) in transforms.alias.iter().enumerate() {
+                let upper = subband * SLOTS - 1 - i;
+                let lower = subband * SLOTS + i;
+                let (up, down) = (lines[upper], lines[lower]);
+                lines[upper] = up * cs + down * ca;
+                lines[lower] = down * cs - up * ca;
+            }
+        }
+    }
+}
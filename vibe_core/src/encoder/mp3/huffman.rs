@@ -0,0 +1,242 @@
+use super::filterbank::GRANULE_LEN;
+use super::tables::{BIG_VALUE_TABLES, COUNT1_CODES, COUNT1_LENGTHS};
+use crate::encoder::bits::BitWriter;
+
+/// Huffman table of the pairs of big values
+pub(super) struct HuffmanTable {
+    pub codes: &'static [u32],
+    pub lengths: &'static [u8],
+    /// Number of values of `x` and `y`, the last one is an escape if the table has linbits
+    pub xlen: usize,
+    pub linbits: u32,
+}
+
+impl HuffmanTable {
+    /// Get the number of bits of a pair, with its signs
+    #[inline]
+    fn pair_bits(&self, x: u32, y: u32) -> u32 {
+        let (index, extra) = self.index(x, y);
+        self.lengths[index] as u32 + extra + (x != 0) as u32 + (y != 0) as u32
+    }
+
+    /// Get the index of the code of a pair and the number of linbits following it
+    #[inline]
+    fn index(&self, x: u32, y: u32) -> (usize, u32) {
+        if self.linbits == 0 {
+            return (x as usize * self.xlen + y as usize, 0);
+        }
+        let escapes = (x >= 15) as u32 + (y >= 15) as u32;
+        (
+            x.min(15) as usize * self.xlen + y.min(15) as usize,
+            escapes * self.linbits,
+        )
+    }
+
+    fn write_pair(&self, writer: &mut BitWriter, x: i32, y: i32) {
+        let (ax, ay) = (x.unsigned_abs(), y.unsigned_abs());
+        let (index, _) = self.index(ax, ay);
+        writer.write(self.codes[index], self.lengths[index] as u32);
+        for (value, abs) in [(x, ax), (y, ay)].iter() {
+            if self.linbits > 0 && *abs >= 15 {
+                writer.write(abs - 15, self.linbits);
+            }
+            if *abs != 0 {
+                writer.write((*value < 0) as u32, 1);
+            }
+        }
+    }
+}
+
+/// Number of scale factor bands of the first two regions of the big values, indexed by the
+/// number of bands they cover
+const REGION_COUNTS: [(usize, usize); 23] = [
+    (0, 0),
+    (0, 0),
+    (0, 0),
+    (0, 0),
+    (0, 0),
+    (0, 1),
+    (1, 1),
+    (1, 1),
+    (1, 2),
+    (2, 2),
+    (2, 3),
+    (2, 3),
+    (3, 4),
+    (3, 4),
+    (3, 4),
+    (4, 5),
+    (4, 5),
+    (4, 6),
+    (5, 6),
+    (5, 6),
+    (5, 7),
+    (6, 7),
+    (6, 7),
+];
+
+/// Quantized lines of a granule with the way they are coded
+pub(super) struct Granule {
+    pub values: Vec<i32>,
+    /// Number of pairs coded with the tables of the big values
+    pub big_values: usize,
+    /// End of the quadruples of values up to one, the following values are zero
+    pub count1_end: usize,
+    pub table_select: [usize; 3],
+    pub region0_count: usize,
+    pub region1_count: usize,
+    /// True if the quadruples use the table B
+    pub count1_table: bool,
+    /// Number of bits of the Huffman data
+    pub bits: u32,
+}
+
+impl Granule {
+    /// Split the values in regions and choose the cheapest tables
+    pub fn new(values: Vec<i32>, bands: &[usize; 23]) -> Self {
+        debug_assert_eq!(values.len(), GRANULE_LEN);
+
+        let mut count1_end = GRANULE_LEN;
+        while count1_end >= 2 && values[count1_end - 1] == 0 && values[count1_end - 2] == 0 {
+            count1_end -= 2;
+        }
+        let mut big_end = count1_end;
+        while big_end >= 4 && values[big_end - 4..big_end].iter().all(|v| v.abs() <= 1) {
+            big_end -= 4;
+        }
+
+        // The regions end on scale factor bands
+        let band_count = bands
+            .iter()
+            .position(|&bound| bound >= big_end)
+            .unwrap_or(22);
+        let (mut region0_count, mut region1_count) = REGION_COUNTS[band_count];
+        while region0_count > 0 && bands[region0_count + 1] > big_end {
+            region0_count -= 1;
+        }
+        while region1_count > 0 && bands[region0_count + region1_count + 2] > big_end {
+            region1_count -= 1;
+        }
+        let region0_end = bands[region0_count + 1].min(big_end);
+        let region1_end = bands[region0_count + region1_count + 2].min(big_end);
+
+        let mut bits = 0;
+        let mut table_select = [0; 3];
+        for (select, (start, end)) in table_select.iter_mut().zip(
+            [
+                (0, region0_end),
+                (region0_end, region1_end),
+                (region1_end, big_end),
+            ]
+            .iter(),
+        ) {
+            let (table, region_bits) = choose_table(&values[*start..*end]);
+            *select = table;
+            bits += region_bits;
+        }
+
+        let quadruples = &values[big_end..count1_end];
+        let (bits_a, bits_b) = count1_bits(quadruples);
+
+        Self {
+            values,
+            big_values: big_end / 2,
+            count1_end,
+            table_select,
+            region0_count,
+            region1_count,
+            count1_table: bits_b < bits_a,
+            bits: bits + bits_a.min(bits_b),
+        }
+    }
+
+    /// Write the Huffman data
+    pub fn write(&self, writer: &mut BitWriter, bands: &[usize; 23]) {
+        let big_end = self.big_values * 2;
+        let region0_end = bands[self.region0_count + 1].min(big_end);
+        let region1_end = bands[self.region0_count + self.region1_count + 2].min(big_end);
+
+        for (pair, index) in self.values[..big_end].chunks_exact(2).zip((0..).step_by(2)) {
+            let region = if index < region0_end {
+                0
+            } else if index < region1_end {
+                1
+            } else {
+                2
+            };
+            let table = &BIG_VALUE_TABLES[self.table_select[region]];
+            if table.xlen > 1 {
+                table.write_pair(writer, pair[0], pair[1]);
+            }
+        }
+
+        for quadruple in self.values[big_end..self.count1_end].chunks_exact(4) {
+            let index = count1_index(quadruple);
+            if self.count1_table {
+                writer.write(15 - index as u32, 4);
+            } else {
+                writer.write(COUNT1_CODES[index], COUNT1_LENGTHS[index] as u32);
+            }
+            for &value in quadruple.iter().filter(|&&value| value != 0) {
+                writer.write((value < 0) as u32, 1);
+            }
+        }
+    }
+}
+
+/// Choose the table coding the pairs with the fewest bits, returns it with the number of bits
+fn choose_table(values: &[i32]) -> (usize, u32) {
+    let max = values
+        .iter()
+        .map(|value| value.unsigned_abs())
+        .max()
+        .unwrap_or(0);
+    if max == 0 {
+        return (0, 0);
+    }
+
+    let candidates: Vec<usize> = if max < 16 {
+        (1..16)
+            .filter(|&index| BIG_VALUE_TABLES[index].xlen > max as usize)
+            .collect()
+    } else {
+        // The first table of each set with enough linbits, more of them only cost bits
+        [16..24, 24..32]
+            .iter()
+            .filter_map(|range| {
+                range
+                    .clone()
+                    .find(|&index| max - 15 < 1 << BIG_VALUE_TABLES[index].linbits)
+            })
+            .collect()
+    };
+
+    candidates
+        .into_iter()
+        .map(|index| {
+            let table = &BIG_VALUE_TABLES[index];
+            let bits = values
+                .chunks_exact(2)
+                .map(|pair| table.pair_bits(pair[0].unsigned_abs(), pair[1].unsigned_abs()))
+                .sum();
+            (index, bits)
+        })
+        .min_by_key(|&(_, bits)| bits)
+        .unwrap()
+}
+
+/// Get the number of bits of the quadruples with the tables A and B
+fn count1_bits(values: &[i32]) -> (u32, u32) {
+    values.chunks_exact(4).fold((0, 0), |(a, b), quadruple| {
+        let signs = quadruple.iter().filter(|&&value| value != 0).count() as u32;
+        let index = count1_index(quadruple);
+        (a + COUNT1_LENGTHS[index] as u32 + signs, b + 4 + signs)
+    })
+}
+
+#[inline]
+fn count1_index(quadruple: &[i32]) -> usize {
+    quadruple
+        .iter()
+        .fold(0, |index, &value| (index << 1) | (value != 0) as usize)
+}
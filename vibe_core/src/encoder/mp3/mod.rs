@@ -0,0 +1,638 @@
+mod filterbank;
+mod huffman;
+mod tables;
+
+use std::io::{Seek, SeekFrom, Write};
+
+use self::filterbank::{FilterBank, Transforms, GRANULE_LEN};
+use self::huffman::Granule;
+use self::tables::SCALEFACTOR_BANDS;
+use super::bits::BitWriter;
+use crate::tags::id3::build_id3v2;
+use crate::{info::EncoderError, Metadata, Sample};
+
+/// Bitrates of MPEG-1 Layer III in kbit/s
+pub const MP3_BITRATES: [u32; 14] = [
+    32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+
+/// Lowest quality of the variable bitrate, 0 is the highest
+pub const MAX_VBR_QUALITY: u8 = 9;
+
+/// Number of samples of a frame on each channel
+const FRAME_LEN: usize = 2 * GRANULE_LEN;
+
+/// Delay of the samples given back by the decoders
+const DELAY: u64 = 1057;
+
+/// Delay of the decoders themselves, the LAME extension gives the rest of `DELAY`
+const DECODER_DELAY: u64 = 529;
+
+/// Length of the Xing header with all its fields: frames, bytes, table of contents and quality
+const XING_LEN: usize = 120;
+
+/// Length of the LAME extension following the Xing header
+const LAME_LEN: usize = 36;
+
+/// Name of the encoder in the LAME extension, the decoders only trust the delay and the padding
+/// after a LAME or a Lavf/Lavc version
+const ENCODER_NAME: &[u8; 9] = b"LAME3.100";
+
+/// Largest quantized value, the last one of the tables with 13 linbits
+const MAX_QUANTIZED: f32 = 8206.0;
+
+/// Largest number of Huffman bits of a granule and channel
+const MAX_PART2_3_LENGTH: u32 = 4095;
+
+/// Noise allowed in the variable bitrate whatever the level of the signal, about the one of
+/// 16-bit samples
+const NOISE_FLOOR: f32 = 1.0 / (1u64 << 32) as f32;
+
+/// Bitrate of an MP3 file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mp3Bitrate {
+    /// Constant bitrate in kbit/s, one of `MP3_BITRATES`.
+    Constant(u32),
+    /// Variable bitrate keeping the noise under a level, from 0 to `MAX_VBR_QUALITY` like `-V` of LAME.
+    Variable(u8),
+}
+
+impl Default for Mp3Bitrate {
+    fn default() -> Self {
+        Mp3Bitrate::Constant(128)
+    }
+}
+
+impl Mp3Bitrate {
+    /// Get the highest frequency kept, the bits are spent on the audible part of the spectrum
+    fn lowpass(&self, channels: usize) -> f32 {
+        match *self {
+            Mp3Bitrate::Constant(bitrate) => match bitrate / channels as u32 {
+                0..=23 => 5500.0,
+                24..=31 => 8000.0,
+                32..=47 => 11000.0,
+                48..=63 => 15000.0,
+                64..=79 => 17000.0,
+                80..=95 => 18500.0,
+                96..=127 => 19500.0,
+                _ => 20000.0,
+            },
+            Mp3Bitrate::Variable(quality) => 19500.0 - 900.0 * quality as f32,
+        }
+    }
+}
+
+/// Encoder writing MPEG-1 Layer III frames, after an ID3v2 tag holding the metadata
+///
+/// The frames use long blocks only and no bit reservoir. The frames are padded with silence at
+/// the end, and start with the delay of the filter banks. A Xing or Info frame comes first, it
+/// gives the number of frames, the seek table and the delay and padding of the LAME extension
+pub(crate) struct Mp3Encoder<W>
+where
+    W: Write + Seek,
+{
+    writer: W,
+    channels: usize,
+    bitrate: Mp3Bitrate,
+    /// Index of the sample rate in the frame headers and the tables
+    sample_rate_index: usize,
+    sample_rate: u32,
+    /// Number of frequency lines kept in each granule
+    lines: usize,
+    transforms: Transforms,
+    filter_banks: Vec<FilterBank>,
+    /// Interleaved samples of the next frame
+    buffer: Vec<Sample>,
+    /// Remainder of the length of the constant bitrate frames, adding a padding byte once it reaches the sample rate
+    padding: u32,
+    samples: u64,
+    frames: u64,
+    /// Position of the Xing or Info frame, filled once the audio frames are written
+    info_offset: u64,
+    info_bitrate: u32,
+    /// Number of bytes of the audio frames at the end of each frame
+    frame_ends: Vec<u64>,
+    /// CRC of the audio frames, in the LAME extension
+    crc: u16,
+}
+
+impl<W> Mp3Encoder<W>
+where
+    W: Write + Seek,
+{
+    pub fn new(
+        mut writer: W,
+        sample_rate: u32,
+        channels: usize,
+        bitrate: Mp3Bitrate,
+        metadata: &Metadata,
+    ) -> Result<Self, EncoderError> {
+        let sample_rate_index = match sample_rate {
+            44100 => Some(0),
+            48000 => Some(1),
+            32000 => Some(2),
+            _ => None,
+        };
+        let sample_rate_index = match sample_rate_index {
+            Some(index) if channels == 1 || channels == 2 => index,
+            _ => {
+                return Err(EncoderError::Unsupported(format!(
+                    "MP3 with {} channels at {} Hz",
+                    channels, sample_rate
+                )))
+            }
+        };
+        match bitrate {
+            Mp3Bitrate::Constant(bitrate) if !MP3_BITRATES.contains(&bitrate) => {
+                return Err(EncoderError::Unsupported(format!(
+                    "MP3 bitrate of {} kbit/s",
+                    bitrate
+                )))
+            }
+            Mp3Bitrate::Variable(quality) if quality > MAX_VBR_QUALITY => {
+                return Err(EncoderError::Unsupported(format!(
+                    "MP3 variable bitrate quality {}",
+                    quality
+                )))
+            }
+            _ => {}
+        }
+
+        if !metadata.is_empty() {
+            writer
+                .write_all(&build_id3v2(None, metadata))
+                .map_err(EncoderError::IOError)?;
+        }
+
+        let lowpass = bitrate.lowpass(channels).min(sample_rate as f32 / 2.0);
+        let mut encoder = Self {
+            writer,
+            channels,
+            bitrate,
+            sample_rate_index,
+            sample_rate,
+            lines: (lowpass / (sample_rate as f32 / 2.0) * GRANULE_LEN as f32) as usize,
+            transforms: Transforms::new(),
+            filter_banks: (0..channels).map(|_| FilterBank::new()).collect(),
+            buffer: Vec::with_capacity(FRAME_LEN * channels),
+            padding: 0,
+            samples: 0,
+            frames: 0,
+            info_offset: 0,
+            info_bitrate: 0,
+            frame_ends: Vec::new(),
+            crc: 0,
+        };
+
+        // The Info frame is written again with the numbers of frames and bytes at the end
+        encoder.info_bitrate = encoder.info_bitrate();
+        let frame = encoder.info_frame();
+        encoder.info_offset = encoder
+            .writer
+            .stream_position()
+            .and_then(|offset| encoder.writer.write_all(&frame).map(|_| offset))
+            .map_err(EncoderError::IOError)?;
+        Ok(encoder)
+    }
+
+    /// Write interleaved samples, the frames are encoded once they are full
+    pub fn write(&mut self, samples: &[Sample]) -> Result<(), EncoderError> {
+        let frame_len = FRAME_LEN * self.channels;
+        for chunk in samples.chunks(frame_len) {
+            let len = chunk.len().min(frame_len - self.buffer.len());
+            self.buffer.extend_from_slice(&chunk[..len]);
+            if self.buffer.len() == frame_len {
+                self.write_frame()?;
+            }
+            self.buffer.extend_from_slice(&chunk[len..]);
+        }
+        self.samples += samples.len() as u64;
+        Ok(())
+    }
+
+    /// Encode the delayed samples followed by silence, and fill the Info frame
+    pub fn finish(mut self) -> Result<W, EncoderError> {
+        let end = self.samples / self.channels as u64 + DELAY;
+        while self.frames * (FRAME_LEN as u64) < end {
+            self.buffer.resize(FRAME_LEN * self.channels, 0.0);
+            self.write_frame()?;
+        }
+
+        let frame = self.info_frame();
+        self.finish_stream(&frame).map_err(EncoderError::IOError)?;
+        Ok(self.writer)
+    }
+
+    fn finish_stream(&mut self, info_frame: &[u8]) -> std::io::Result<()> {
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.info_offset))?;
+        self.writer.write_all(info_frame)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+
+    /// Get the bitrate of the Info frame, the one of the stream if the frame can hold the headers
+    fn info_bitrate(&self) -> u32 {
+        let len = 4 + self.side_info_len() + XING_LEN + LAME_LEN;
+        let fits = |bitrate: u32| (144_000 * bitrate / self.sample_rate) as usize >= len;
+        match self.bitrate {
+            Mp3Bitrate::Constant(bitrate) if fits(bitrate) => bitrate,
+            _ => *MP3_BITRATES.iter().find(|&&bitrate| fits(bitrate)).unwrap(),
+        }
+    }
+
+    /// Build the frame holding the Xing header and the LAME extension, which holds no audio
+    ///
+    /// The variable bitrate has a `Xing` header and the constant one an `Info` header
+    fn info_frame(&self) -> Vec<u8> {
+        let len = (144_000 * self.info_bitrate / self.sample_rate) as usize;
+        let mut writer = BitWriter::with_capacity(len);
+        self.write_header(&mut writer, self.info_bitrate, false, false);
+        for _ in 0..self.side_info_len() {
+            writer.write(0, 8);
+        }
+        let mut frame = writer.into_bytes();
+
+        let bytes = len as u64 + self.frame_ends.last().copied().unwrap_or(0);
+        let (id, quality) = match self.bitrate {
+            Mp3Bitrate::Constant(_) => (b"Info", 0),
+            Mp3Bitrate::Variable(quality) => (b"Xing", 100 - 10 * quality as u32),
+        };
+        frame.extend_from_slice(id);
+        // Every field is present
+        frame.extend_from_slice(&0x0fu32.to_be_bytes());
+        frame.extend_from_slice(&(self.frames as u32).to_be_bytes());
+        frame.extend_from_slice(&(bytes as u32).to_be_bytes());
+        // Position of each percent of the duration, in 256ths of the stream
+        for percent in 0..100 {
+            let index = (percent * self.frames / 100) as usize;
+            let offset = len as u64 + index.checked_sub(1).map_or(0, |i| self.frame_ends[i]);
+            frame.push((offset * 256 / bytes).min(255) as u8);
+        }
+        frame.extend_from_slice(&quality.to_be_bytes());
+
+        // LAME extension, without the ReplayGain values
+        let (method, min_bitrate) = match self.bitrate {
+            Mp3Bitrate::Constant(bitrate) => (1, bitrate),
+            Mp3Bitrate::Variable(_) => (3, MP3_BITRATES[0]),
+        };
+        let lowpass = self.lines as u32 * self.sample_rate / (2 * GRANULE_LEN as u32);
+        frame.extend_from_slice(ENCODER_NAME);
+        frame.push(method);
+        frame.push((lowpass / 100).min(255) as u8);
+        frame.extend_from_slice(&[0; 9]);
+        frame.push(min_bitrate.min(255) as u8);
+        // The decoders trim the delay and the padding, the silence added around the samples
+        let delay = DELAY - DECODER_DELAY;
+        let padding = (self.frames * FRAME_LEN as u64)
+            .saturating_sub(delay + self.samples / self.channels as u64);
+        frame.extend_from_slice(&(((delay << 12) | padding.min(0xfff)) as u32).to_be_bytes()[1..]);
+        // Mono or joint stereo, and the sample rate
+        let mode = if self.channels == 1 { 0 } else { 3 };
+        let source_rate = match self.sample_rate {
+            44100 => 1,
+            48000 => 2,
+            _ => 0,
+        };
+        frame.push(source_rate << 6 | mode << 2);
+        frame.extend_from_slice(&[0; 3]);
+        frame.extend_from_slice(&(bytes as u32).to_be_bytes());
+        frame.extend_from_slice(&self.crc.to_be_bytes());
+        let crc = lame_crc(0, &frame);
+        frame.extend_from_slice(&crc.to_be_bytes());
+
+        debug_assert!(frame.len() <= len);
+        frame.resize(len, 0);
+        frame
+    }
+
+    fn write_frame(&mut self) -> Result<(), EncoderError> {
+        // Frequency lines of each granule and channel
+        let mut lines = vec![vec![0.0; GRANULE_LEN]; 2 * self.channels];
+        let mut input = vec![0.0; GRANULE_LEN];
+        for (granule, samples) in self
+            .buffer
+            .chunks_exact(GRANULE_LEN * self.channels)
+            .enumerate()
+        {
+            for channel in 0..self.channels {
+                for (value, frame) in input.iter_mut().zip(samples.chunks_exact(self.channels)) {
+                    *value = frame[channel];
+                }
+                let output = &mut lines[granule * self.channels + channel];
+                self.filter_banks[channel].granule(&self.transforms, &input, output);
+                output[self.lines..].iter_mut().for_each(|line| *line = 0.0);
+            }
+        }
+        self.buffer.clear();
+
+        let mid_side = self.channels == 2 && use_mid_side(&lines);
+        if mid_side {
+            for granule in lines.chunks_exact_mut(2) {
+                let (left, right) = granule.split_at_mut(1);
+                for (l, r) in left[0].iter_mut().zip(right[0].iter_mut()) {
+                    let (mid, side) = ((*l + *r) / 2f32.sqrt(), (*l - *r) / 2f32.sqrt());
+                    *l = mid;
+                    *r = side;
+                }
+            }
+        }
+
+        let quantizer = Quantizer::new(&lines);
+        let (bitrate, gain, granules) = match self.bitrate {
+            Mp3Bitrate::Constant(bitrate) => {
+                let (gain, granules) = self.fit(&quantizer, bitrate);
+                (bitrate, gain, granules)
+            }
+            Mp3Bitrate::Variable(quality) => self.variable(&quantizer, quality),
+        };
+
+        let (len, padding) = self.frame_len(bitrate);
+        let mut writer = BitWriter::with_capacity(len);
+        self.write_header(&mut writer, bitrate, padding, mid_side);
+        self.write_side_info(&mut writer, gain, &granules);
+        let bands = &SCALEFACTOR_BANDS[self.sample_rate_index];
+        for granule in &granules {
+            granule.write(&mut writer, bands);
+        }
+
+        let mut bytes = writer.into_bytes();
+        debug_assert!(bytes.len() <= len);
+        bytes.resize(len, 0);
+        self.writer
+            .write_all(&bytes)
+            .map_err(EncoderError::IOError)?;
+        self.crc = lame_crc(self.crc, &bytes);
+        let end = self.frame_ends.last().copied().unwrap_or(0);
+        self.frame_ends.push(end + len as u64);
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Get the length of a frame in bytes and its padding, the constant bitrate spreads the
+    /// fraction of bytes over the frames
+    fn frame_len(&mut self, bitrate: u32) -> (usize, bool) {
+        let bytes = 144_000 * bitrate;
+        let padding = match self.bitrate {
+            Mp3Bitrate::Constant(_) => {
+                self.padding += bytes % self.sample_rate;
+                if self.padding >= self.sample_rate {
+                    self.padding -= self.sample_rate;
+                    true
+                } else {
+                    false
+                }
+            }
+            Mp3Bitrate::Variable(_) => false,
+        };
+        (
+            (bytes / self.sample_rate) as usize + padding as usize,
+            padding,
+        )
+    }
+
+    /// Get the number of bits of the Huffman data of a frame
+    fn budget(&self, bitrate: u32) -> u32 {
+        let bytes = 144_000 * bitrate / self.sample_rate;
+        bytes * 8 - 32 - self.side_info_len() as u32 * 8
+    }
+
+    /// Find the finest quantization fitting in a frame of the bitrate
+    fn fit(&self, quantizer: &Quantizer, bitrate: u32) -> (u32, Vec<Granule>) {
+        let budget = self.budget(bitrate);
+        let bands = &SCALEFACTOR_BANDS[self.sample_rate_index];
+        let fits = |granules: &[Granule]| {
+            granules.iter().map(|granule| granule.bits).sum::<u32>() <= budget
+                && granules
+                    .iter()
+                    .all(|granule| granule.bits <= MAX_PART2_3_LENGTH)
+        };
+
+        // The number of bits decreases with the gain, the highest one gives only zeros
+        let (mut low, mut high) = (quantizer.min_gain(), 255);
+        while low < high {
+            let gain = (low + high) / 2;
+            if fits(&quantizer.granules(gain, bands)) {
+                high = gain;
+            } else {
+                low = gain + 1;
+            }
+        }
+        (high, quantizer.granules(high, bands))
+    }
+
+    /// Find the coarsest quantization keeping the noise under the level of the quality, then the
+    /// lowest bitrate fitting it
+    fn variable(&self, quantizer: &Quantizer, quality: u8) -> (u32, u32, Vec<Granule>) {
+        let bands = &SCALEFACTOR_BANDS[self.sample_rate_index];
+        let snr = 10f32.powf((42.0 - 3.0 * quality as f32) / 10.0);
+        let allowed = (quantizer.energy / snr).max(NOISE_FLOOR * quantizer.len() as f32);
+
+        let (mut low, mut high) = (quantizer.min_gain(), 255);
+        while low < high {
+            let gain = (low + high).div_ceil(2);
+            if quantizer.noise(gain) <= allowed {
+                low = gain;
+            } else {
+                high = gain - 1;
+            }
+        }
+
+        let granules = quantizer.granules(low, bands);
+        let bits: u32 = granules.iter().map(|granule| granule.bits).sum();
+        if granules
+            .iter()
+            .all(|granule| granule.bits <= MAX_PART2_3_LENGTH)
+        {
+            if let Some(&bitrate) = MP3_BITRATES
+                .iter()
+                .find(|&&bitrate| bits <= self.budget(bitrate))
+            {
+                return (bitrate, low, granules);
+            }
+        }
+
+        let bitrate = MP3_BITRATES[MP3_BITRATES.len() - 1];
+        let (gain, granules) = self.fit(quantizer, bitrate);
+        (bitrate, gain, granules)
+    }
+
+    #[inline]
+    fn side_info_len(&self) -> usize {
+        if self.channels == 1 {
+            17
+        } else {
+            32
+        }
+    }
+
+    fn write_header(&self, writer: &mut BitWriter, bitrate: u32, padding: bool, mid_side: bool) {
+        // Sync word, MPEG-1, Layer III, no CRC
+        writer.write(0x7ff, 11);
+        writer.write(0b11, 2);
+        writer.write(0b01, 2);
+        writer.write(1, 1);
+        let bitrate_index = MP3_BITRATES.iter().position(|&b| b == bitrate).unwrap() + 1;
+        writer.write(bitrate_index as u32, 4);
+        writer.write(self.sample_rate_index as u32, 2);
+        writer.write(padding as u32, 1);
+        writer.write(0, 1);
+        // Mono or joint stereo, the mode extension tells if the frame is in mid/side
+        let (mode, extension) = match (self.channels, mid_side) {
+            (1, _) => (0b11, 0),
+            (_, true) => (0b01, 0b10),
+            _ => (0b01, 0),
+        };
+        writer.write(mode, 2);
+        writer.write(extension, 2);
+        // Not copyrighted, original, no emphasis
+        writer.write(0, 1);
+        writer.write(1, 1);
+        writer.write(0, 2);
+    }
+
+    fn write_side_info(&self, writer: &mut BitWriter, gain: u32, granules: &[Granule]) {
+        // No bit reservoir, private bits and scale factors shared between the granules
+        writer.write(0, 9);
+        writer.write(0, if self.channels == 1 { 5 } else { 3 });
+        writer.write(0, 4 * self.channels as u32);
+
+        for granule in granules {
+            writer.write(granule.bits, 12);
+            writer.write(granule.big_values as u32, 9);
+            writer.write(gain, 8);
+            // No scale factors and long blocks only
+            writer.write(0, 4);
+            writer.write(0, 1);
+            for &table in &granule.table_select {
+                writer.write(table as u32, 5);
+            }
+            writer.write(granule.region0_count as u32, 4);
+            writer.write(granule.region1_count as u32, 3);
+            writer.write(0, 1);
+            writer.write(0, 1);
+            writer.write(granule.count1_table as u32, 1);
+        }
+    }
+}
+
+/// Update a CRC-16 of the LAME extension, polynomial x^16 + x^15 + x^2 + 1 with the bits reversed
+fn lame_crc(mut crc: u16, data: &[u8]) -> u16 {
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Returns true if the channels are close enough to code their sum and difference
+fn use_mid_side(lines: &[Vec<f32>]) -> bool {
+    let (mut mid, mut side) = (0.0, 0.0);
+    for granule in lines.chunks_exact(2) {
+        for (l, r) in granule[0].iter().zip(&granule[1]) {
+            mid += (l + r) * (l + r);
+            side += (l - r) * (l - r);
+        }
+    }
+    side < 0.25 * mid
+}
+
+/// Quantization of the lines of a frame with a global gain
+///
+/// The decoders multiply the values by `2^((gain - 210) / 4)` and raise them to the power of 4/3
+struct Quantizer<'a> {
+    lines: &'a [Vec<f32>],
+    /// Absolute values of the lines raised to the power of 3/4
+    powers: Vec<Vec<f32>>,
+    energy: f32,
+}
+
+impl<'a> Quantizer<'a> {
+    fn new(lines: &'a [Vec<f32>]) -> Self {
+        let powers = lines
+            .iter()
+            .map(|lines| lines.iter().map(|line| line.abs().powf(0.75)).collect())
+            .collect();
+        let energy = lines.iter().flatten().map(|line| line * line).sum();
+        Self {
+            lines,
+            powers,
+            energy,
+        }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.lines.len() * GRANULE_LEN
+    }
+
+    /// Get the lowest gain keeping the values in the range of the tables
+    fn min_gain(&self) -> u32 {
+        let max = self
+            .powers
+            .iter()
+            .flatten()
+            .fold(0f32, |max, &p| max.max(p));
+        (0..255)
+            .find(|&gain| max * step(gain) + 0.4054 < MAX_QUANTIZED + 1.0)
+            .unwrap_or(255)
+    }
+
+    fn quantize(&self, gain: u32) -> impl Iterator<Item = Vec<i32>> + '_ {
+        let scale = step(gain);
+        self.lines
+            .iter()
+            .zip(&self.powers)
+            .map(move |(lines, powers)| {
+                lines
+                    .iter()
+                    .zip(powers)
+                    .map(|(line, power)| {
+                        let value = (power * scale + 0.4054) as i32;
+                        if *line < 0.0 {
+                            -value
+                        } else {
+                            value
+                        }
+                    })
+                    .collect()
+            })
+    }
+
+    fn granules(&self, gain: u32, bands: &[usize; 23]) -> Vec<Granule> {
+        self.quantize(gain)
+            .map(|values| Granule::new(values, bands))
+            .collect()
+    }
+
+    /// Get the energy of the difference between the lines and their quantized values
+    fn noise(&self, gain: u32) -> f32 {
+        let scale = 2f32.powf((gain as f32 - 210.0) / 4.0);
+        self.quantize(gain)
+            .zip(self.lines)
+            .map(|(values, lines)| {
+                values
+                    .iter()
+                    .zip(lines)
+                    .map(|(&value, line)| {
+                        let dequantized = (value.unsigned_abs() as f32).powf(4.0 / 3.0) * scale;
+                        let error = line.abs() - dequantized;
+                        error * error
+                    })
+                    .sum::<f32>()
+            })
+            .sum()
+    }
+}
+
+/// Get the factor of the values raised to the power of 3/4
+#[inline]
+fn step(gain: u32) -> f32 {
+    2f32.powf(-0.1875 * (gain as f32 - 210.0))
+}
@@ -0,0 +1,71 @@
+/// Writer of the packets of Vorbis, least significant bit first
+pub(super) struct BitPacker {
+    bytes: Vec<u8>,
+    /// Bits not yet stored in `bytes`, in the low bits
+    acc: u64,
+    len: u32,
+}
+
+impl BitPacker {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            acc: 0,
+            len: 0,
+        }
+    }
+
+    /// Write the low `bits` bits of `value`, at most 32
+    #[inline]
+    pub fn write(&mut self, value: u32, bits: u32) {
+        debug_assert!(bits <= 32);
+        if bits == 0 {
+            return;
+        }
+
+        let mask = if bits == 32 {
+            u32::MAX
+        } else {
+            (1 << bits) - 1
+        };
+        self.acc |= ((value & mask) as u64) << self.len;
+        self.len += bits;
+        while self.len >= 8 {
+            self.bytes.push(self.acc as u8);
+            self.acc >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    #[inline]
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write(byte as u32, 8);
+        }
+    }
+
+    /// Write a float in the format of the codebooks, the value must be an integer below 2^21
+    #[inline]
+    pub fn write_float(&mut self, value: i32) {
+        debug_assert!(value.unsigned_abs() < 1 << 21);
+        // The exponent is biased by 788, so 788 makes the mantissa the value itself
+        let sign = if value < 0 { 1 << 31 } else { 0 };
+        self.write(sign | (788 << 21) | value.unsigned_abs(), 32);
+    }
+
+    /// Get the bytes, the last one padded with zero bits
+    #[inline]
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Get the number of bits needed to store a value
+#[inline]
+pub(super) fn ilog(value: u32) -> u32 {
+    32 - value.leading_zeros()
+}
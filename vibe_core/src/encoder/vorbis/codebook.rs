@@ -0,0 +1,198 @@
+use super::bitpack::{ilog, BitPacker};
+
+/// Sync pattern starting each codebook
+const SYNC: u32 = 0x56_4342;
+
+/// Longest codeword allowed, longer ones are avoided by flattening the weights
+const MAX_LENGTH: u8 = 24;
+
+/// Values of the vectors of a codebook, `minimum + delta * multiplicand` on each dimension
+struct Lookup {
+    minimum: i32,
+    delta: i32,
+    /// Number of multiplicands, from 0 to `values - 1`
+    values: u32,
+}
+
+/// Huffman codebook of Vorbis, with lookup table of type 1 for the vector codebooks
+pub(super) struct Codebook {
+    dimensions: usize,
+    lengths: Vec<u8>,
+    /// Codewords with their bits reversed, the packets being written least significant bit first
+    codewords: Vec<u32>,
+    lookup: Option<Lookup>,
+}
+
+impl Codebook {
+    /// Codebook of scalars, the weights give the probabilities of the entries
+    pub fn scalar(dimensions: usize, weights: &[f64]) -> Self {
+        let lengths = huffman_lengths(weights);
+        Self {
+            dimensions,
+            codewords: codewords(&lengths),
+            lengths,
+            lookup: None,
+        }
+    }
+
+    /// Codebook of the vectors of a lattice, the probability of a vector is the product of the
+    /// weights of its values
+    pub fn vector<F>(dimensions: usize, minimum: i32, delta: i32, values: u32, weight: F) -> Self
+    where
+        F: Fn(i32) -> f64,
+    {
+        let entries = values.pow(dimensions as u32) as usize;
+        let weights: Vec<f64> = (0..entries)
+            .map(|entry| {
+                let mut entry = entry as u32;
+                let mut product = 1.0;
+                for _ in 0..dimensions {
+                    product *= weight(minimum + delta * (entry % values) as i32);
+                    entry /= values;
+                }
+                product
+            })
+            .collect();
+
+        let lengths = huffman_lengths(&weights);
+        Self {
+            dimensions,
+            codewords: codewords(&lengths),
+            lengths,
+            lookup: Some(Lookup {
+                minimum,
+                delta,
+                values,
+            }),
+        }
+    }
+
+    #[inline]
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Get the entry of a vector, the values are rounded to the closest ones of the lattice
+    pub fn entry(&self, vector: &[i32]) -> usize {
+        let lookup = self.lookup.as_ref().expect("scalar codebook");
+        vector.iter().rev().fold(0, |entry, &value| {
+            let multiplicand = ((value - lookup.minimum) as f64 / lookup.delta as f64)
+                .round()
+                .clamp(0.0, (lookup.values - 1) as f64);
+            entry * lookup.values as usize + multiplicand as usize
+        })
+    }
+
+    /// Get the value of the vector of an entry on a dimension
+    #[inline]
+    pub fn value(&self, entry: usize, dimension: usize) -> i32 {
+        let lookup = self.lookup.as_ref().expect("scalar codebook");
+        let multiplicand = entry as u32 / lookup.values.pow(dimension as u32) % lookup.values;
+        lookup.minimum + lookup.delta * multiplicand as i32
+    }
+
+    #[inline]
+    pub fn write_entry(&self, packer: &mut BitPacker, entry: usize) {
+        packer.write(self.codewords[entry], self.lengths[entry] as u32);
+    }
+
+    /// Write the codebook in the setup header
+    pub fn write_header(&self, packer: &mut BitPacker) {
+        packer.write(SYNC, 24);
+        packer.write(self.dimensions as u32, 16);
+        packer.write(self.lengths.len() as u32, 24);
+        // Not ordered and not sparse, every entry has a codeword
+        packer.write(0, 1);
+        packer.write(0, 1);
+        for &length in &self.lengths {
+            packer.write(length as u32 - 1, 5);
+        }
+
+        match &self.lookup {
+            None => packer.write(0, 4),
+            Some(lookup) => {
+                let bits = ilog(lookup.values - 1).max(1);
+                packer.write(1, 4);
+                packer.write_float(lookup.minimum);
+                packer.write_float(lookup.delta);
+                packer.write(bits - 1, 4);
+                // Not a sequence
+                packer.write(0, 1);
+                for multiplicand in 0..lookup.values {
+                    packer.write(multiplicand, bits);
+                }
+            }
+        }
+    }
+}
+
+/// Get the lengths of the codewords of a Huffman code for the weights
+fn huffman_lengths(weights: &[f64]) -> Vec<u8> {
+    let mut weights: Vec<f64> = weights.iter().map(|&weight| weight.max(1e-30)).collect();
+    loop {
+        // Active nodes with their weights, the leaves first then the merged nodes
+        let mut nodes: Vec<(f64, usize)> = weights.iter().copied().zip(0..).collect();
+        let mut parents: Vec<usize> = vec![usize::MAX; weights.len()];
+        while nodes.len() > 1 {
+            nodes.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+            let (first, second) = (nodes.pop().unwrap(), nodes.pop().unwrap());
+            let parent = parents.len();
+            parents.push(usize::MAX);
+            parents[first.1] = parent;
+            parents[second.1] = parent;
+            nodes.push((first.0 + second.0, parent));
+        }
+
+        let lengths: Vec<u8> = (0..weights.len())
+            .map(|mut node| {
+                let mut length = 0;
+                while parents[node] != usize::MAX {
+                    node = parents[node];
+                    length += 1;
+                }
+                length.max(1)
+            })
+            .collect();
+        if lengths.iter().all(|&length| length <= MAX_LENGTH) {
+            return lengths;
+        }
+        weights
+            .iter_mut()
+            .for_each(|weight| *weight = weight.sqrt());
+    }
+}
+
+/// Assign the codewords in the order of the entries, like the decoders do
+fn codewords(lengths: &[u8]) -> Vec<u32> {
+    // Next free codeword of each length
+    let mut marker = [0u32; 33];
+    lengths
+        .iter()
+        .map(|&length| {
+            let length = length as usize;
+            let mut entry = marker[length];
+            let codeword = entry;
+
+            for j in (1..=length).rev() {
+                if marker[j] & 1 != 0 {
+                    marker[j] = if j == 1 {
+                        marker[1] + 1
+                    } else {
+                        marker[j - 1] << 1
+                    };
+                    break;
+                }
+                marker[j] += 1;
+            }
+            for j in length + 1..33 {
+                if marker[j] >> 1 != entry {
+                    break;
+                }
+                entry = marker[j];
+                marker[j] = marker[j - 1] << 1;
+            }
+
+            codeword.reverse_bits() >> (32 - length)
+        })
+        .collect()
+}
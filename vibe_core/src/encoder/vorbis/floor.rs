@@ -0,0 +1,244 @@
+use super::bitpack::{ilog, BitPacker};
+use super::codebook::Codebook;
+
+/// Multiplier of the values of the floor, they step by about 1.1 dB
+const MULTIPLIER: i32 = 2;
+
+/// Number of values of the posts with the multiplier
+const RANGE: i32 = 128;
+
+/// Number of bits of the positions of the posts, the last one is at the end of the long blocks
+const RANGE_BITS: u32 = 10;
+
+/// Number of posts of each partition
+const PARTITION_LEN: usize = 4;
+
+/// Positions of the posts following the first and last ones, closer in the low frequencies
+const POSTS: [i32; 28] = [
+    2, 4, 6, 8, 11, 14, 18, 22, 27, 33, 40, 48, 57, 68, 80, 95, 112, 132, 156, 184, 216, 256, 300,
+    350, 410, 480, 600, 800,
+];
+
+/// Ratio between two steps of the curve of the floor, which goes from about -140 dB to 0 dB
+const DB_STEP: f64 = 1.064_986_3;
+
+/// Floor of type 1 with a fixed set of posts, the curve is made of lines between them
+pub(super) struct Floor {
+    /// Positions of the posts in the order of the packets
+    positions: Vec<i32>,
+    /// Posts with the closest lower and higher positions among the previous ones
+    neighbors: Vec<(usize, usize)>,
+    /// Posts in the order of the positions
+    order: Vec<usize>,
+    /// Number of frequency lines of the blocks
+    len: usize,
+}
+
+/// Values of the posts of a channel, with the curve the decoders compute from them
+pub(super) struct FloorCurve {
+    /// The first two values are the levels of the ends, the others are coded from the prediction
+    values: Vec<u32>,
+    pub curve: Vec<f32>,
+}
+
+impl Floor {
+    pub fn new() -> Self {
+        let positions: Vec<i32> = [0, 1 << RANGE_BITS]
+            .iter()
+            .chain(POSTS.iter())
+            .copied()
+            .collect();
+        let neighbors = (0..positions.len())
+            .map(|post| {
+                let previous = &positions[..post.max(2)];
+                let low = (0..previous.len())
+                    .filter(|&index| previous[index] < positions[post])
+                    .max_by_key(|&index| previous[index])
+                    .unwrap_or(0);
+                let high = (0..previous.len())
+                    .filter(|&index| previous[index] > positions[post])
+                    .min_by_key(|&index| previous[index])
+                    .unwrap_or(1);
+                (low, high)
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..positions.len()).collect();
+        order.sort_by_key(|&post| positions[post]);
+
+        Self {
+            positions,
+            neighbors,
+            order,
+            len: 1 << RANGE_BITS,
+        }
+    }
+
+    /// Write the configuration in the setup header, the values are coded with the codebook
+    pub fn write_header(&self, packer: &mut BitPacker, book: usize) {
+        // Partitions of a single class, without subclasses
+        let partitions = POSTS.len() / PARTITION_LEN;
+        packer.write(partitions as u32, 5);
+        for _ in 0..partitions {
+            packer.write(0, 4);
+        }
+        packer.write(PARTITION_LEN as u32 - 1, 3);
+        packer.write(0, 2);
+        packer.write(book as u32 + 1, 8);
+
+        packer.write(MULTIPLIER as u32 - 1, 2);
+        packer.write(RANGE_BITS, 4);
+        for &position in &POSTS {
+            packer.write(position as u32, RANGE_BITS);
+        }
+    }
+
+    /// Choose the values of the posts following the levels of the spectrum around them
+    ///
+    /// The levels are in units of the steps of the curve, from 0 to `RANGE * MULTIPLIER`
+    pub fn fit(&self, spectrum: &[f32], level: impl Fn(f64) -> f64) -> FloorCurve {
+        let targets: Vec<i32> = (0..self.positions.len())
+            .map(|post| {
+                let (start, end) = self.region(post);
+                let energy: f64 = spectrum[start..end]
+                    .iter()
+                    .map(|&line| line as f64 * line as f64)
+                    .sum();
+                let amplitude = level((energy / (end - start) as f64).sqrt());
+                let step = amplitude.max(1e-30).ln() / DB_STEP.ln() + 255.0;
+                (step / MULTIPLIER as f64)
+                    .round()
+                    .clamp(0.0, (RANGE - 1) as f64) as i32
+            })
+            .collect();
+
+        let mut values = vec![0; self.positions.len()];
+        let mut levels = vec![0; self.positions.len()];
+        let mut used = vec![false; self.positions.len()];
+        for post in 0..2 {
+            values[post] = targets[post] as u32;
+            levels[post] = targets[post];
+            used[post] = true;
+        }
+
+        for post in 2..self.positions.len() {
+            let (low, high) = self.neighbors[post];
+            let predicted = render_point(
+                self.positions[low],
+                levels[low],
+                self.positions[high],
+                levels[high],
+                self.positions[post],
+            );
+
+            // A post close to the line of its neighbors is left unused
+            let (value, level) = if (targets[post] - predicted).abs() <= 1 {
+                (0, predicted)
+            } else {
+                (0..RANGE as u32)
+                    .map(|value| (value, decode_level(value, predicted)))
+                    .min_by_key(|&(_, level)| (level - targets[post]).abs())
+                    .unwrap()
+            };
+            values[post] = value;
+            levels[post] = level;
+            if value != 0 {
+                used[low] = true;
+                used[high] = true;
+                used[post] = true;
+            }
+        }
+
+        // Lines between the used posts
+        let mut curve = vec![0.0; self.len];
+        let (mut x0, mut y0) = (0, levels[0] * MULTIPLIER);
+        for &post in &self.order[1..] {
+            if used[post] {
+                let (x1, y1) = (self.positions[post], levels[post] * MULTIPLIER);
+                render_line(x0, y0, x1, y1, &mut curve);
+                x0 = x1;
+                y0 = y1;
+            }
+        }
+
+        FloorCurve { values, curve }
+    }
+
+    /// Write the values of a channel
+    pub fn write(&self, packer: &mut BitPacker, curve: &FloorCurve, book: &Codebook) {
+        packer.write(1, 1);
+        let bits = ilog(RANGE as u32 - 1);
+        packer.write(curve.values[0], bits);
+        packer.write(curve.values[1], bits);
+        for &value in &curve.values[2..] {
+            book.write_entry(packer, value as usize);
+        }
+    }
+
+    /// Get the lines around a post, up to the middle of the neighbor posts
+    fn region(&self, post: usize) -> (usize, usize) {
+        let index = self.order.iter().position(|&p| p == post).unwrap();
+        let middle = |a: usize, b: usize| ((self.positions[a] + self.positions[b]) / 2) as usize;
+        let start = if index == 0 {
+            0
+        } else {
+            middle(self.order[index - 1], post)
+        };
+        let end = match self.order.get(index + 1) {
+            Some(&next) => middle(post, next),
+            None => self.len,
+        };
+        (start.min(self.len - 1), end.clamp(start + 1, self.len))
+    }
+}
+
+/// Get the level of a post from its value and its prediction, like the decoders
+fn decode_level(value: u32, predicted: i32) -> i32 {
+    let value = value as i32;
+    let (high_room, low_room) = (RANGE - predicted, predicted);
+    let room = high_room.min(low_room) * 2;
+    if value >= room {
+        if high_room > low_room {
+            value - low_room + predicted
+        } else {
+            predicted - value + high_room - 1
+        }
+    } else if value % 2 == 1 {
+        predicted - (value + 1) / 2
+    } else {
+        predicted + value / 2
+    }
+}
+
+/// Get the level of the line between two posts at a position
+fn render_point(x0: i32, y0: i32, x1: i32, y1: i32, x: i32) -> i32 {
+    let dy = y1 - y0;
+    let offset = dy.abs() * (x - x0) / (x1 - x0);
+    if dy < 0 {
+        y0 - offset
+    } else {
+        y0 + offset
+    }
+}
+
+/// Draw the line between two posts with the integer steps of the decoders
+fn render_line(x0: i32, y0: i32, x1: i32, y1: i32, curve: &mut [f32]) {
+    let (dy, adx) = (y1 - y0, x1 - x0);
+    let base = dy / adx;
+    let step = if dy < 0 { base - 1 } else { base + 1 };
+    let ady = dy.abs() - base.abs() * adx;
+
+    let mut y = y0;
+    let mut error = 0;
+    for x in x0..x1.min(curve.len() as i32) {
+        if x > x0 {
+            error += ady;
+            if error >= adx {
+                error -= adx;
+                y += step;
+            } else {
+                y += base;
+            }
+        }
+        curve[x as usize] = DB_STEP.powi(y - 255) as f32;
+    }
+}
@@ -0,0 +1,102 @@
+use std::f64::consts::PI;
+
+/// Forward MDCT of a block of `n` samples giving `n / 2` coefficients, computed with a complex
+/// FFT of `n / 4` points
+pub(super) struct Mdct {
+    n: usize,
+    /// Twiddle factors applied before and after the FFT
+    pre: Vec<(f64, f64)>,
+    post: Vec<(f64, f64)>,
+    /// Roots of unity of the FFT
+    roots: Vec<(f64, f64)>,
+    /// Window of the block, the one of Vorbis
+    window: Vec<f64>,
+}
+
+impl Mdct {
+    pub fn new(n: usize) -> Self {
+        let m = n / 2;
+        let twiddle = |angle: f64| (angle.cos(), -angle.sin());
+        Self {
+            n,
+            pre: (0..m / 2)
+                .map(|i| twiddle(PI * (i as f64 + 0.25) / m as f64))
+                .collect(),
+            post: (0..m / 2)
+                .map(|k| twiddle(PI * k as f64 / m as f64))
+                .collect(),
+            roots: (0..m / 4)
+                .map(|k| twiddle(2.0 * PI * k as f64 / (m / 2) as f64))
+                .collect(),
+            window: (0..n)
+                .map(|i| {
+                    let x = ((i as f64 + 0.5) / n as f64 * PI).sin();
+                    (PI / 2.0 * x * x).sin()
+                })
+                .collect(),
+        }
+    }
+
+    /// Window and transform a block
+    ///
+    /// The coefficients are scaled so the decoders give the samples back
+    pub fn forward(&self, samples: &[f32], output: &mut [f32]) {
+        let (n, m) = (self.n, self.n / 2);
+        debug_assert_eq!(samples.len(), n);
+        let x = |i: usize| samples[i] as f64 * self.window[i];
+
+        // Fold the block in a DCT-IV of n / 2 terms
+        let folded: Vec<f64> = (0..m)
+            .map(|i| {
+                if i < m / 2 {
+                    -x(3 * m / 2 + i) - x(3 * m / 2 - 1 - i)
+                } else {
+                    x(i - m / 2) - x(3 * m / 2 - 1 - i)
+                }
+            })
+            .collect();
+
+        let mut data: Vec<(f64, f64)> = (0..m / 2)
+            .map(|i| multiply((folded[2 * i], folded[m - 1 - 2 * i]), self.pre[i]))
+            .collect();
+        self.fft(&mut data);
+
+        let scale = 2.0 / m as f64;
+        for (k, &value) in data.iter().enumerate() {
+            let (re, im) = multiply(value, self.post[k]);
+            output[2 * k] = (re * scale) as f32;
+            output[m - 1 - 2 * k] = (-im * scale) as f32;
+        }
+    }
+
+    /// Radix-2 FFT in place
+    fn fft(&self, data: &mut [(f64, f64)]) {
+        let len = data.len();
+        let bits = len.trailing_zeros();
+        for i in 0..len {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+
+        let mut size = 2;
+        while size <= len {
+            let stride = len / size;
+            for start in (0..len).step_by(size) {
+                for k in 0..size / 2 {
+                    let even = data[start + k];
+                    let odd = multiply(data[start + k + size / 2], self.roots[k * stride]);
+                    data[start + k] = (even.0 + odd.0, even.1 + odd.1);
+                    data[start + k + size / 2] = (even.0 - odd.0, even.1 - odd.1);
+                }
+            }
+            size *= 2;
+        }
+    }
+}
+
+#[inline]
+fn multiply(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
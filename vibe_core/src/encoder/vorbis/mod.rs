@@ -0,0 +1,404 @@
+mod bitpack;
+mod codebook;
+mod floor;
+mod mdct;
+mod residue;
+
+use std::io::{Seek, Write};
+
+use self::bitpack::BitPacker;
+use self::codebook::Codebook;
+use self::floor::Floor;
+use self::mdct::Mdct;
+use self::residue::Residue;
+use crate::tags::ogg::{write_packets, Page, BEGIN_OF_STREAM, CONTINUED, END_OF_STREAM};
+use crate::tags::vorbis::{build_comments, VENDOR};
+use crate::{info::EncoderError, Metadata, Sample};
+
+/// Length of the blocks, the short blocks of the header are not used
+const BLOCK_LEN: usize = 2048;
+
+/// Length of the short blocks declared in the identification header
+const SHORT_BLOCK_LEN: usize = 256;
+
+/// Number of samples given by each block after the first one
+const HOP_LEN: usize = BLOCK_LEN / 2;
+
+/// Serial number of the logical stream
+const SERIAL: u32 = 0x7669_6265;
+
+/// Number of bytes of audio data after which a page is written
+const PAGE_LEN: usize = 4096;
+
+/// Settings of the Ogg Vorbis encoder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VorbisConfig {
+    /// Quality of the variable bitrate, from 0.0 to 1.0 like the base quality of libvorbis.
+    pub quality: f32,
+}
+
+impl Default for VorbisConfig {
+    fn default() -> Self {
+        Self { quality: 0.5 }
+    }
+}
+
+/// Codebooks, floor and residue of the stream, written in the setup header
+struct Setup {
+    codebooks: Vec<Codebook>,
+    floor: Floor,
+    residue: Residue,
+}
+
+impl Setup {
+    fn new() -> Self {
+        // The weights follow the values most often found in the packets
+        let laplace = |scale: f64| move |value: i32| (-(value.abs() as f64) / scale).exp();
+        let class_weights = [0.3, 0.3, 0.2, 0.1, 0.07, 0.03];
+        let class_pairs: Vec<f64> = (0..class_weights.len() * class_weights.len())
+            .map(|entry| {
+                class_weights[entry / class_weights.len()]
+                    * class_weights[entry % class_weights.len()]
+            })
+            .collect();
+        let floor_values: Vec<f64> = (0..128).map(|value| (-value as f64 / 4.0).exp()).collect();
+
+        let codebooks = vec![
+            Codebook::scalar(1, &floor_values),
+            Codebook::scalar(2, &class_pairs),
+            Codebook::vector(4, -1, 1, 3, laplace(1.0)),
+            Codebook::vector(2, -2, 1, 5, laplace(1.5)),
+            Codebook::vector(2, -4, 1, 9, laplace(2.5)),
+            Codebook::vector(2, -8, 1, 17, laplace(4.0)),
+            Codebook::vector(2, -8 * 17, 17, 17, laplace(4.0 * 17.0)),
+            Codebook::vector(2, -8 * 289, 289, 17, laplace(2.0 * 289.0)),
+            Codebook::vector(2, -8 * 4913, 4913, 17, laplace(4913.0)),
+        ];
+
+        Self {
+            codebooks,
+            floor: Floor::new(),
+            residue: Residue::new(HOP_LEN, 1, [2, 3, 4, 5, 6, 7, 8]),
+        }
+    }
+
+    /// Build the setup header: a single mode of long blocks with one floor and one residue
+    fn header(&self) -> Vec<u8> {
+        let mut packer = BitPacker::new();
+        packer.write_bytes(b"\x05vorbis");
+
+        packer.write(self.codebooks.len() as u32 - 1, 8);
+        for codebook in &self.codebooks {
+            codebook.write_header(&mut packer);
+        }
+
+        // Placeholders of the time domain transforms
+        packer.write(0, 6);
+        packer.write(0, 16);
+
+        packer.write(0, 6);
+        packer.write(1, 16);
+        self.floor.write_header(&mut packer, 0);
+
+        packer.write(0, 6);
+        packer.write(1, 16);
+        self.residue.write_header(&mut packer);
+
+        // Mapping of type 0 with one submap and no coupling
+        packer.write(0, 6);
+        packer.write(0, 16);
+        packer.write(0, 1);
+        packer.write(0, 1);
+        packer.write(0, 2);
+        packer.write(0, 8);
+        packer.write(0, 8);
+        packer.write(0, 8);
+
+        // Mode of long blocks
+        packer.write(0, 6);
+        packer.write(1, 1);
+        packer.write(0, 16);
+        packer.write(0, 16);
+        packer.write(0, 8);
+
+        // Framing bit
+        packer.write(1, 1);
+        packer.into_bytes()
+    }
+}
+
+/// Encoder writing an Ogg Vorbis stream, the comment header holds the metadata
+///
+/// Every block is long. The floor follows the level of the spectrum, the quality setting how far
+/// under it the noise of the residue stays
+pub(crate) struct VorbisEncoder<W>
+where
+    W: Write + Seek,
+{
+    writer: W,
+    channels: usize,
+    setup: Setup,
+    mdct: Mdct,
+    /// Level of the floor under the level of the spectrum
+    ratio: f64,
+    /// Lowest level of the floor, the lines under it are silent
+    min_level: f64,
+    /// Number of frequency lines kept in each block
+    lines: usize,
+    /// Samples of each channel from the start of the next block
+    buffers: Vec<Vec<f32>>,
+    samples: u64,
+    blocks: u64,
+    page: PageBuffer,
+}
+
+impl<W> VorbisEncoder<W>
+where
+    W: Write + Seek,
+{
+    pub fn new(
+        mut writer: W,
+        sample_rate: u32,
+        channels: usize,
+        config: VorbisConfig,
+        metadata: &Metadata,
+    ) -> Result<Self, EncoderError> {
+        if channels == 0 || channels > u8::MAX as usize || sample_rate == 0 {
+            return Err(EncoderError::Unsupported(format!(
+                "Vorbis with {} channels at {} Hz",
+                channels, sample_rate
+            )));
+        }
+        if !(0.0..=1.0).contains(&config.quality) {
+            return Err(EncoderError::Unsupported(format!(
+                "Vorbis quality {}",
+                config.quality
+            )));
+        }
+
+        let setup = Setup::new();
+        let mut output = Vec::new();
+        Page {
+            header_type: BEGIN_OF_STREAM,
+            granule: 0,
+            serial: SERIAL,
+            sequence: 0,
+            segments: &[30],
+            data: &identification_header(sample_rate, channels),
+        }
+        .write(&mut output);
+
+        let mut comment = b"\x03vorbis".to_vec();
+        comment.extend(build_comments(VENDOR, metadata, metadata.pictures()));
+        comment.push(1);
+        let pages = write_packets(&mut output, SERIAL, 1, &[&comment, &setup.header()]);
+        writer.write_all(&output).map_err(EncoderError::IOError)?;
+
+        let quality = config.quality as f64;
+        let cutoff = 12000.0 + 8000.0 * quality;
+        Ok(Self {
+            writer,
+            channels,
+            setup,
+            mdct: Mdct::new(BLOCK_LEN),
+            ratio: 2f64.powf(-1.0 - 4.0 * quality),
+            min_level: 10f64.powf(-(4.5 + 1.5 * quality)),
+            lines: ((cutoff / (sample_rate as f64 / 2.0) * HOP_LEN as f64) as usize).min(HOP_LEN),
+            // The first block starts before the samples, it gives no sample
+            buffers: vec![vec![0.0; HOP_LEN]; channels],
+            samples: 0,
+            blocks: 0,
+            page: PageBuffer::new(1 + pages),
+        })
+    }
+
+    /// Write interleaved samples, the blocks are encoded once they are full
+    pub fn write(&mut self, samples: &[Sample]) -> Result<(), EncoderError> {
+        for &sample in samples {
+            let channel = (self.samples % self.channels as u64) as usize;
+            self.buffers[channel].push(sample);
+            self.samples += 1;
+            if channel == self.channels - 1 && self.buffers[channel].len() == BLOCK_LEN {
+                self.write_block()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode the last blocks, padded with silence, and end the stream
+    pub fn finish(mut self) -> Result<W, EncoderError> {
+        let frames = self.samples / self.channels as u64;
+        // Each block gives the samples of the first half of its window
+        let blocks = 1 + frames.div_ceil(HOP_LEN as u64);
+        while self.blocks < blocks {
+            // The decoders only cut the samples past the end on a page of its own
+            if self.blocks + 1 == blocks && !self.page.segments.is_empty() {
+                self.page
+                    .write(&mut self.writer, false)
+                    .map_err(EncoderError::IOError)?;
+            }
+            for buffer in &mut self.buffers {
+                buffer.resize(BLOCK_LEN, 0.0);
+            }
+            self.write_block()?;
+        }
+
+        self.page
+            .finish(&mut self.writer, frames)
+            .map_err(EncoderError::IOError)?;
+        self.writer.flush().map_err(EncoderError::IOError)?;
+        Ok(self.writer)
+    }
+
+    fn write_block(&mut self) -> Result<(), EncoderError> {
+        let mut packer = BitPacker::new();
+        // Audio packet of the only mode, the previous and next blocks are long
+        packer.write(0, 1);
+        packer.write(1, 1);
+        packer.write(1, 1);
+
+        let mut spectrum = vec![0.0; HOP_LEN];
+        let mut residues = Vec::with_capacity(self.channels);
+        for buffer in &mut self.buffers {
+            self.mdct.forward(&buffer[..BLOCK_LEN], &mut spectrum);
+            buffer.drain(..HOP_LEN);
+            spectrum[self.lines..]
+                .iter_mut()
+                .for_each(|line| *line = 0.0);
+
+            let (ratio, min_level) = (self.ratio, self.min_level);
+            let floor = self
+                .setup
+                .floor
+                .fit(&spectrum, |level| (level * ratio).max(min_level));
+            let max = self.setup.residue.max();
+            let residue: Vec<i32> = spectrum
+                .iter()
+                .zip(&floor.curve)
+                .map(|(&line, &level)| ((line / level).round() as i32).clamp(-max, max))
+                .collect();
+
+            // A silent channel has no floor and no residue
+            if residue.iter().all(|&value| value == 0) {
+                packer.write(0, 1);
+            } else {
+                self.setup
+                    .floor
+                    .write(&mut packer, &floor, &self.setup.codebooks[0]);
+                residues.push(residue);
+            }
+        }
+
+        let residues: Vec<&[i32]> = residues.iter().map(|residue| &residue[..]).collect();
+        self.setup
+            .residue
+            .write(&mut packer, &residues, &self.setup.codebooks);
+
+        // The first block only primes the overlap
+        let granule = self.blocks * HOP_LEN as u64;
+        self.blocks += 1;
+        self.page
+            .push(&mut self.writer, &packer.into_bytes(), granule)
+            .map_err(EncoderError::IOError)
+    }
+}
+
+/// Build the identification header
+fn identification_header(sample_rate: u32, channels: usize) -> Vec<u8> {
+    let mut header = b"\x01vorbis".to_vec();
+    header.extend_from_slice(&0u32.to_le_bytes());
+    header.push(channels as u8);
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    // No maximum, nominal nor minimum bitrate
+    header.extend_from_slice(&[0; 12]);
+    let log2 = |len: usize| len.trailing_zeros() as u8;
+    header.push(log2(BLOCK_LEN) << 4 | log2(SHORT_BLOCK_LEN));
+    header.push(1);
+    header
+}
+
+/// Audio packets of the next page
+struct PageBuffer {
+    sequence: u32,
+    segments: Vec<u8>,
+    data: Vec<u8>,
+    /// Position after the last packet ending in the page
+    granule: Option<u64>,
+    /// True if the page starts with the end of a packet
+    continued: bool,
+}
+
+impl PageBuffer {
+    fn new(sequence: u32) -> Self {
+        Self {
+            sequence,
+            segments: Vec::with_capacity(255),
+            data: Vec::with_capacity(PAGE_LEN),
+            granule: None,
+            continued: false,
+        }
+    }
+
+    /// Add a packet giving the samples up to the granule position
+    ///
+    /// The page is written once full, when the next packet comes
+    fn push<W: Write>(
+        &mut self,
+        writer: &mut W,
+        packet: &[u8],
+        granule: u64,
+    ) -> std::io::Result<()> {
+        if self.data.len() >= PAGE_LEN {
+            self.write(writer, false)?;
+        }
+
+        // The last lacing value is under 255, zero if the length is a multiple of it
+        let mut chunks: Vec<&[u8]> = packet.chunks(255).collect();
+        if packet.len().is_multiple_of(255) {
+            chunks.push(&[]);
+        }
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            if self.segments.len() == 255 {
+                self.write(writer, false)?;
+                self.continued = index > 0;
+            }
+            self.segments.push(chunk.len() as u8);
+            self.data.extend_from_slice(chunk);
+        }
+        self.granule = Some(granule);
+        Ok(())
+    }
+
+    /// Write the last page, its granule position is the number of samples of the stream
+    fn finish<W: Write>(&mut self, writer: &mut W, samples: u64) -> std::io::Result<()> {
+        self.granule = Some(samples);
+        self.write(writer, true)
+    }
+
+    fn write<W: Write>(&mut self, writer: &mut W, last: bool) -> std::io::Result<()> {
+        let mut header_type = if self.continued { CONTINUED } else { 0 };
+        if last {
+            header_type |= END_OF_STREAM;
+        }
+
+        let mut output = Vec::with_capacity(27 + self.segments.len() + self.data.len());
+        Page {
+            header_type,
+            // No packet ends in the page
+            granule: self.granule.unwrap_or(u64::MAX),
+            serial: SERIAL,
+            sequence: self.sequence,
+            segments: &self.segments,
+            data: &self.data,
+        }
+        .write(&mut output);
+        writer.write_all(&output)?;
+
+        self.sequence += 1;
+        self.segments.clear();
+        self.data.clear();
+        self.granule = None;
+        self.continued = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use super::bitpack::BitPacker;
+use super::codebook::Codebook;
+
+/// Number of lines of a partition
+const PARTITION_LEN: usize = 32;
+
+/// Number of passes of the decoders, each class may add a vector at each pass
+const PASSES: usize = 8;
+
+/// Residue of type 1 over the lines of the long blocks, each partition of a channel is coded with
+/// the books of its class
+///
+/// The classes are chosen by the largest value of the partition: zero, then vectors up to 1, 2,
+/// 4 and 8, and a cascade of books with steps of 17^3, 17^2, 17 and 1 for the larger values
+pub(super) struct Residue {
+    len: usize,
+    classbook: usize,
+    /// Codebook of each pass of the classes
+    books: Vec<Vec<Option<usize>>>,
+    /// Largest value of each class
+    limits: Vec<i32>,
+}
+
+impl Residue {
+    /// Create the residue, `books` are the codebooks of the values up to 1, 2, 4 and 8 followed
+    /// by the steps of 17, 17^2 and 17^3
+    pub fn new(len: usize, classbook: usize, books: [usize; 7]) -> Self {
+        let single = |book| {
+            let mut passes = vec![None; PASSES];
+            passes[0] = Some(book);
+            passes
+        };
+        let cascade = vec![
+            Some(books[6]),
+            Some(books[5]),
+            Some(books[4]),
+            Some(books[3]),
+            None,
+            None,
+            None,
+            None,
+        ];
+        let max = 8 * (1 + 17 + 17 * 17 + 17 * 17 * 17);
+
+        Self {
+            len,
+            classbook,
+            books: vec![
+                vec![None; PASSES],
+                single(books[0]),
+                single(books[1]),
+                single(books[2]),
+                single(books[3]),
+                cascade,
+            ],
+            limits: vec![0, 1, 2, 4, 8, max],
+        }
+    }
+
+    /// Write the configuration in the setup header
+    pub fn write_header(&self, packer: &mut BitPacker) {
+        packer.write(0, 24);
+        packer.write(self.len as u32, 24);
+        packer.write(PARTITION_LEN as u32 - 1, 24);
+        packer.write(self.books.len() as u32 - 1, 6);
+        packer.write(self.classbook as u32, 8);
+
+        for passes in &self.books {
+            let cascade = passes
+                .iter()
+                .enumerate()
+                .filter(|(_, book)| book.is_some())
+                .fold(0, |cascade, (pass, _)| cascade | 1 << pass);
+            packer.write(cascade & 0x07, 3);
+            packer.write((cascade > 0x07) as u32, 1);
+            if cascade > 0x07 {
+                packer.write(cascade >> 3, 5);
+            }
+        }
+        for book in self.books.iter().flatten().flatten() {
+            packer.write(*book as u32, 8);
+        }
+    }
+
+    /// Get the largest value coded, the values must be clamped to it
+    #[inline]
+    pub fn max(&self) -> i32 {
+        self.limits[self.limits.len() - 1]
+    }
+
+    /// Write the values of the channels with a floor, in the order of the decoders
+    pub fn write(&self, packer: &mut BitPacker, channels: &[&[i32]], codebooks: &[Codebook]) {
+        let partitions = self.len / PARTITION_LEN;
+        // Class of each partition with the entries of its passes
+        let coded: Vec<Vec<(usize, Vec<Vec<usize>>)>> = channels
+            .iter()
+            .map(|values| {
+                values[..partitions * PARTITION_LEN]
+                    .chunks(PARTITION_LEN)
+                    .map(|partition| self.encode_partition(partition, codebooks))
+                    .collect()
+            })
+            .collect();
+
+        let classbook = &codebooks[self.classbook];
+        let classwords = classbook.dimensions();
+        for pass in 0..PASSES {
+            for first in (0..partitions).step_by(classwords) {
+                if pass == 0 {
+                    // The class of the partitions past the end is zero
+                    for partitions in &coded {
+                        let entry = (first..first + classwords).fold(0, |entry, partition| {
+                            let class = partitions.get(partition).map_or(0, |(class, _)| *class);
+                            entry * self.books.len() + class
+                        });
+                        classbook.write_entry(packer, entry);
+                    }
+                }
+
+                for partition in first..(first + classwords).min(partitions) {
+                    for partitions in &coded {
+                        let (class, entries) = &partitions[partition];
+                        if let Some(book) = self.books[*class][pass] {
+                            for &entry in &entries[pass] {
+                                codebooks[book].write_entry(packer, entry);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Choose the class of a partition and the entries of its passes, each pass codes what
+    /// remains of the values after the previous ones
+    fn encode_partition(&self, values: &[i32], codebooks: &[Codebook]) -> (usize, Vec<Vec<usize>>) {
+        let max = values.iter().map(|value| value.abs()).max().unwrap_or(0);
+        let class = self
+            .limits
+            .iter()
+            .position(|&limit| max <= limit)
+            .unwrap_or(self.limits.len() - 1);
+
+        let mut remainder = values.to_vec();
+        let entries = self.books[class]
+            .iter()
+            .map(|book| match book {
+                Some(book) => {
+                    let book = &codebooks[*book];
+                    remainder
+                        .chunks_mut(book.dimensions())
+                        .map(|vector| {
+                            let entry = book.entry(vector);
+                            for (dimension, value) in vector.iter_mut().enumerate() {
+                                *value -= book.value(entry, dimension);
+                            }
+                            entry
+                        })
+                        .collect()
+                }
+                None => Vec::new(),
+            })
+            .collect();
+        (class, entries)
+    }
+}
@@ -0,0 +1,250 @@
+use std::io::{Seek, SeekFrom, Write};
+
+use super::quantize;
+use crate::tags::id3::build_id3v2;
+use crate::{info::EncoderError, Metadata, Sample};
+
+/// `WAVE_FORMAT_PCM` format tag
+const FORMAT_PCM: u16 = 1;
+
+/// `WAVE_FORMAT_IEEE_FLOAT` format tag
+const FORMAT_FLOAT: u16 = 3;
+
+/// `WAVE_FORMAT_EXTENSIBLE` format tag, needed to describe more than two channels
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// End of the GUIDs of the extensible sub-formats, after their format tag
+const SUBFORMAT_GUID: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+
+/// Position of the length of the RIFF chunk
+const RIFF_LEN_OFFSET: u64 = 4;
+
+/// Sample encoding of a WAV file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WavSampleFormat {
+    /// 16-bit integers.
+    Pcm16,
+    /// 24-bit integers.
+    Pcm24,
+    /// 32-bit integers.
+    Pcm32,
+    /// 32-bit floats, the samples are stored untouched.
+    Float32,
+}
+
+impl WavSampleFormat {
+    /// Get the number of bits of a sample.
+    #[inline]
+    pub fn bits_per_sample(&self) -> u32 {
+        match self {
+            WavSampleFormat::Pcm16 => 16,
+            WavSampleFormat::Pcm24 => 24,
+            WavSampleFormat::Pcm32 | WavSampleFormat::Float32 => 32,
+        }
+    }
+
+    #[inline]
+    fn tag(&self) -> u16 {
+        match self {
+            WavSampleFormat::Float32 => FORMAT_FLOAT,
+            _ => FORMAT_PCM,
+        }
+    }
+}
+
+/// Encoder writing the samples after a WAVE header, the lengths are filled by `finish`
+pub(crate) struct WavEncoder<W>
+where
+    W: Write + Seek,
+{
+    writer: W,
+    format: WavSampleFormat,
+    channels: usize,
+    /// Position of the number of frames of the `fact` chunk, for the float format
+    fact_offset: Option<u64>,
+    data_offset: u64,
+    data_len: u64,
+    buffer: Vec<u8>,
+    /// ID3v2 tag of the metadata, written in a chunk after the samples
+    tag: Vec<u8>,
+}
+
+impl<W> WavEncoder<W>
+where
+    W: Write + Seek,
+{
+    pub fn new(
+        mut writer: W,
+        sample_rate: u32,
+        channels: usize,
+        format: WavSampleFormat,
+        metadata: &Metadata,
+    ) -> Result<Self, EncoderError> {
+        // The size of a frame is stored on 16 bits
+        if channels == 0 || channels > (u16::MAX / 4) as usize || sample_rate == 0 {
+            return Err(EncoderError::Unsupported(format!(
+                "WAV with {} channels at {} Hz",
+                channels, sample_rate
+            )));
+        }
+
+        let header = build_header(sample_rate, channels as u16, format);
+        writer.write_all(&header).map_err(EncoderError::IOError)?;
+        let fact_offset = match format {
+            WavSampleFormat::Float32 => Some(header.len() as u64 - 12),
+            _ => None,
+        };
+
+        Ok(Self {
+            writer,
+            format,
+            channels,
+            fact_offset,
+            data_offset: header.len() as u64,
+            data_len: 0,
+            buffer: Vec::new(),
+            tag: if metadata.is_empty() {
+                Vec::new()
+            } else {
+                build_id3v2(None, metadata)
+            },
+        })
+    }
+
+    /// Write interleaved samples
+    pub fn write(&mut self, samples: &[Sample]) -> Result<(), EncoderError> {
+        self.buffer.clear();
+        for &sample in samples {
+            match self.format {
+                WavSampleFormat::Pcm16 => self
+                    .buffer
+                    .extend_from_slice(&(quantize(sample, 16) as i16).to_le_bytes()),
+                WavSampleFormat::Pcm24 => self
+                    .buffer
+                    .extend_from_slice(&quantize(sample, 24).to_le_bytes()[..3]),
+                WavSampleFormat::Pcm32 => self
+                    .buffer
+                    .extend_from_slice(&quantize(sample, 32).to_le_bytes()),
+                WavSampleFormat::Float32 => self.buffer.extend_from_slice(&sample.to_le_bytes()),
+            }
+        }
+
+        // The lengths of the chunks are stored on 32 bits
+        let len = self.data_len + self.buffer.len() as u64;
+        if self.data_offset + len + 1 + self.tag_chunk_len() > u32::MAX as u64 {
+            return Err(EncoderError::Unsupported(
+                "WAV files are limited to 4 GiB".to_owned(),
+            ));
+        }
+
+        self.writer
+            .write_all(&self.buffer)
+            .map_err(EncoderError::IOError)?;
+        self.data_len = len;
+        Ok(())
+    }
+
+    /// Fill the lengths of the header and give the writer back
+    pub fn finish(mut self) -> Result<W, EncoderError> {
+        let frame_len = (self.format.bits_per_sample() / 8) as u64 * self.channels as u64;
+        let riff_len =
+            self.data_offset - 8 + self.data_len + self.data_len % 2 + self.tag_chunk_len();
+        let mut fields = vec![
+            (RIFF_LEN_OFFSET, riff_len as u32),
+            (self.data_offset - 4, self.data_len as u32),
+        ];
+        if let Some(offset) = self.fact_offset {
+            fields.push((offset, (self.data_len / frame_len) as u32));
+        }
+
+        self.finish_chunks(&fields).map_err(EncoderError::IOError)?;
+        Ok(self.writer)
+    }
+
+    /// Get the length of the chunk of the tag with its header and padding
+    #[inline]
+    fn tag_chunk_len(&self) -> u64 {
+        match self.tag.len() as u64 {
+            0 => 0,
+            len => 8 + len + len % 2,
+        }
+    }
+
+    fn finish_chunks(&mut self, fields: &[(u64, u32)]) -> std::io::Result<()> {
+        // Chunks are aligned on two bytes
+        if !self.data_len.is_multiple_of(2) {
+            self.writer.write_all(&[0])?;
+        }
+        if !self.tag.is_empty() {
+            self.writer.write_all(b"id3 ")?;
+            self.writer
+                .write_all(&(self.tag.len() as u32).to_le_bytes())?;
+            self.writer.write_all(&self.tag)?;
+            if !self.tag.len().is_multiple_of(2) {
+                self.writer.write_all(&[0])?;
+            }
+        }
+        let end = self.writer.stream_position()?;
+
+        for (offset, value) in fields {
+            self.writer.seek(SeekFrom::Start(*offset))?;
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+}
+
+/// Build the header up to the length of the `data` chunk
+///
+/// The lengths are zero until `finish`, the float format adds a `fact` chunk before `data`
+fn build_header(sample_rate: u32, channels: u16, format: WavSampleFormat) -> Vec<u8> {
+    let bits = format.bits_per_sample() as u16;
+    let block_align = channels as u32 * bits as u32 / 8;
+    let extensible = channels > 2;
+
+    let mut fmt = Vec::with_capacity(40);
+    fmt.extend_from_slice(
+        &if extensible {
+            FORMAT_EXTENSIBLE
+        } else {
+            format.tag()
+        }
+        .to_le_bytes(),
+    );
+    fmt.extend_from_slice(&channels.to_le_bytes());
+    fmt.extend_from_slice(&sample_rate.to_le_bytes());
+    fmt.extend_from_slice(&sample_rate.saturating_mul(block_align).to_le_bytes());
+    fmt.extend_from_slice(&(block_align as u16).to_le_bytes());
+    fmt.extend_from_slice(&bits.to_le_bytes());
+    if extensible {
+        // The channels follow the order of the speaker positions
+        let mask = if channels <= 18 {
+            (1u32 << channels) - 1
+        } else {
+            0
+        };
+        fmt.extend_from_slice(&22u16.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+        fmt.extend_from_slice(&mask.to_le_bytes());
+        fmt.extend_from_slice(&format.tag().to_le_bytes());
+        fmt.extend_from_slice(&SUBFORMAT_GUID);
+    } else if format == WavSampleFormat::Float32 {
+        // Formats other than PCM have an empty extension
+        fmt.extend_from_slice(&0u16.to_le_bytes());
+    }
+
+    let mut header = b"RIFF\0\0\0\0WAVE".to_vec();
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    header.extend_from_slice(&fmt);
+    if format == WavSampleFormat::Float32 {
+        header.extend_from_slice(b"fact");
+        header.extend_from_slice(&4u32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+    }
+    header.extend_from_slice(b"data\0\0\0\0");
+    header
+}
@@ -30,3 +30,91 @@ impl Display for DecoderError {
         }
     }
 }
+
+/// An error encountered while reading or writing the tags of an audio file.
+#[derive(Debug)]
+pub enum TagError {
+    /// I/O error.
+    IOError(std::io::Error),
+    /// The file is not valid for its format.
+    FormatError(String),
+    /// The format or a feature of the file is not supported.
+    Unsupported(String),
+}
+
+impl Error for TagError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+impl Display for TagError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagError::IOError(err) => write!(f, "IO error: {}", err),
+            TagError::FormatError(err) => write!(f, "format error: {}", err),
+            TagError::Unsupported(err) => write!(f, "unsupported: {}", err),
+        }
+    }
+}
+
+/// An error encountered while encoding an audio file.
+#[derive(Debug)]
+pub enum EncoderError {
+    /// I/O error.
+    IOError(std::io::Error),
+    /// The settings can't be stored in the format.
+    Unsupported(String),
+    /// The samples written don't fill a whole frame.
+    IncompleteData,
+}
+
+impl Error for EncoderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EncoderError::IOError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Display for EncoderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncoderError::IOError(err) => write!(f, "IO error: {}", err),
+            EncoderError::Unsupported(err) => write!(f, "unsupported: {}", err),
+            EncoderError::IncompleteData => write!(f, "incomplete data"),
+        }
+    }
+}
+
+/// An error encountered while transcoding an audio file.
+#[derive(Debug)]
+pub enum TranscodeError {
+    /// Error while decoding the source.
+    Decoder(DecoderError),
+    /// Error while encoding the output.
+    Encoder(EncoderError),
+    /// The transcoding was cancelled by the progress callback.
+    Cancelled,
+}
+
+impl Error for TranscodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TranscodeError::Decoder(err) => Some(err),
+            TranscodeError::Encoder(err) => Some(err),
+            TranscodeError::Cancelled => None,
+        }
+    }
+}
+
+impl Display for TranscodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranscodeError::Decoder(err) => write!(f, "decoding error: {}", err),
+            TranscodeError::Encoder(err) => write!(f, "encoding error: {}", err),
+            TranscodeError::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use crate::decoder::{duration_to_frames, frames_to_duration};
+
+/// Region of a track played in a loop, in frames (samples per channel)
+///
+/// The start is the first frame of the loop and the end the first frame after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopRegion {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl LoopRegion {
+    /// Create a region, returns None if it is empty
+    #[inline]
+    pub fn new(start: u64, end: u64) -> Option<Self> {
+        if start < end {
+            Some(Self { start, end })
+        } else {
+            None
+        }
+    }
+
+    /// Create a region between two positions of a track at the given sample rate
+    #[inline]
+    pub fn from_duration(start: Duration, end: Duration, sample_rate: u32) -> Option<Self> {
+        Self::new(
+            duration_to_frames(start, sample_rate),
+            duration_to_frames(end, sample_rate),
+        )
+    }
+
+    /// Get the number of frames of the loop
+    #[inline]
+    pub fn frames(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Get the position of the start of the loop
+    #[inline]
+    pub fn start_time(&self, sample_rate: u32) -> Duration {
+        frames_to_duration(self.start, sample_rate)
+    }
+
+    /// Get the position of the end of the loop
+    #[inline]
+    pub fn end_time(&self, sample_rate: u32) -> Duration {
+        frames_to_duration(self.end, sample_rate)
+    }
+}
@@ -0,0 +1,199 @@
+/// Tags read from an audio file.
+///
+/// The keys follow the Vorbis comment names (`TITLE`, `ARTIST`, `REPLAYGAIN_TRACK_GAIN`...)
+/// whatever the format of the file, they are stored uppercase.
+/// The pictures, such as the cover art, are kept apart from the tags.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    tags: Vec<(String, String)>,
+    pictures: Vec<Picture>,
+}
+
+/// Picture stored in the tags of a file, like the cover of the album.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Picture {
+    /// Type of the picture as in ID3v2 and FLAC, such as `Picture::FRONT_COVER`.
+    pub picture_type: u8,
+    /// MIME type of the data, such as `image/jpeg`.
+    pub mime_type: String,
+    pub description: String,
+    pub data: Vec<u8>,
+}
+
+impl Picture {
+    /// Type of the picture on the front cover of the album.
+    pub const FRONT_COVER: u8 = 3;
+}
+
+impl Metadata {
+    /// Create empty metadata.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a tag, a key can have several values.
+    pub fn push(&mut self, key: &str, value: &str) {
+        self.tags.push((key.to_uppercase(), value.to_owned()));
+    }
+
+    /// Replace every value of a tag.
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.remove(key);
+        self.push(key, value);
+    }
+
+    /// Remove every value of a tag.
+    pub fn remove(&mut self, key: &str) {
+        let key = key.to_uppercase();
+        self.tags.retain(|(k, _)| *k != key);
+    }
+
+    /// Get the first value of a tag, the key is case insensitive.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.get_all(key).next()
+    }
+
+    /// Get every value of a tag, the key is case insensitive.
+    pub fn get_all<'a>(&'a self, key: &str) -> impl Iterator<Item = &'a str> + 'a {
+        let key = key.to_uppercase();
+        self.tags
+            .iter()
+            .filter(move |(k, _)| *k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Iterate over the tags in the order they were read.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tags.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Get the number of tags, the pictures are not counted.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Returns true if there is no tag and no picture.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.pictures.is_empty()
+    }
+
+    /// Get the pictures in the order they were read.
+    #[inline]
+    pub fn pictures(&self) -> &[Picture] {
+        &self.pictures
+    }
+
+    /// Add a picture.
+    #[inline]
+    pub fn push_picture(&mut self, picture: Picture) {
+        self.pictures.push(picture);
+    }
+
+    /// Remove every picture.
+    #[inline]
+    pub fn remove_pictures(&mut self) {
+        self.pictures.clear();
+    }
+
+    /// Get the front cover, or the first picture if none is marked as such.
+    pub fn cover(&self) -> Option<&Picture> {
+        self.pictures
+            .iter()
+            .find(|picture| picture.picture_type == Picture::FRONT_COVER)
+            .or_else(|| self.pictures.first())
+    }
+
+    /// Get the title of the track.
+    #[inline]
+    pub fn title(&self) -> Option<&str> {
+        self.get("TITLE")
+    }
+
+    /// Get the artist of the track.
+    #[inline]
+    pub fn artist(&self) -> Option<&str> {
+        self.get("ARTIST")
+    }
+
+    /// Get the album of the track.
+    #[inline]
+    pub fn album(&self) -> Option<&str> {
+        self.get("ALBUM")
+    }
+
+    /// Get the ReplayGain values of the tags.
+    #[inline]
+    pub fn replay_gain(&self) -> ReplayGain {
+        ReplayGain::from_metadata(self)
+    }
+}
+
+/// ReplayGain values of a track, the gains are in dB and the peaks are linear.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Read the `REPLAYGAIN_*` tags, values that can't be parsed are ignored.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let value = |key| metadata.get(key).and_then(parse_value);
+        Self {
+            track_gain: value("REPLAYGAIN_TRACK_GAIN"),
+            track_peak: value("REPLAYGAIN_TRACK_PEAK"),
+            album_gain: value("REPLAYGAIN_ALBUM_GAIN"),
+            album_peak: value("REPLAYGAIN_ALBUM_PEAK"),
+        }
+    }
+
+    /// Returns true if no value is known.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Write the known values as `REPLAYGAIN_*` tags.
+    pub fn write_tags(&self, metadata: &mut Metadata) {
+        let gains = [
+            ("REPLAYGAIN_TRACK_GAIN", self.track_gain),
+            ("REPLAYGAIN_ALBUM_GAIN", self.album_gain),
+        ];
+        for (key, gain) in gains.iter() {
+            if let Some(gain) = gain {
+                metadata.set(key, &format!("{:.2} dB", gain));
+            }
+        }
+
+        let peaks = [
+            ("REPLAYGAIN_TRACK_PEAK", self.track_peak),
+            ("REPLAYGAIN_ALBUM_PEAK", self.album_peak),
+        ];
+        for (key, peak) in peaks.iter() {
+            if let Some(peak) = peak {
+                metadata.set(key, &format!("{:.6}", peak));
+            }
+        }
+    }
+}
+
+/// Parse values such as `-6.48 dB` or `0.988553`
+fn parse_value(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .or_else(|| value.strip_suffix("DB"))
+        .unwrap_or(value);
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+}
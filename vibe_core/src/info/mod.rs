@@ -1,6 +1,10 @@
 mod errors;
 mod info;
+mod loops;
+mod metadata;
 
-pub use self::errors::DecoderError;
+pub use self::errors::{DecoderError, EncoderError, TagError, TranscodeError};
 pub use self::info::AudioFormat;
 pub use self::info::AudioInfo;
+pub use self::loops::LoopRegion;
+pub use self::metadata::{Metadata, Picture, ReplayGain};
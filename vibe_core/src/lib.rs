@@ -1,7 +1,21 @@
+pub mod analysis;
 pub mod decoder;
+#[cfg(any(
+    feature = "encode-wav",
+    feature = "encode-flac",
+    feature = "encode-vorbis",
+    feature = "encode-mp3"
+))]
+pub mod encoder;
 mod info;
+pub mod library;
+pub mod tags;
+pub mod transcode;
 
 pub type Sample = f32;
 
 pub use crate::info::AudioFormat;
 pub use crate::info::AudioInfo;
+pub use crate::info::LoopRegion;
+pub use crate::info::{DecoderError, EncoderError, TagError, TranscodeError};
+pub use crate::info::{Metadata, Picture, ReplayGain};
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Entry of a directory of the library, the paths are relative to its root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibraryEntry {
+    Directory(PathBuf),
+    Track(PathBuf),
+}
+
+impl LibraryEntry {
+    /// Get the path of the entry, relative to the root of the library
+    #[inline]
+    pub fn path(&self) -> &Path {
+        match self {
+            LibraryEntry::Directory(path) | LibraryEntry::Track(path) => path,
+        }
+    }
+}
+
+/// Music directory browsed by the front-ends
+///
+/// The paths given and returned are relative to the root, the paths going out of it are refused
+#[derive(Debug, Clone)]
+pub struct Library {
+    root: PathBuf,
+}
+
+impl Library {
+    /// Create a library of the audio files under a directory
+    #[inline]
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Get the directory of the library
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the path of an entry of the library on the file system
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let path = path.as_ref();
+        // Absolute paths and parent directories could leave the library
+        if path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            Ok(self.root.join(path))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is outside of the library", path.display()),
+            ))
+        }
+    }
+
+    /// List the subdirectories and the audio files of a directory
+    ///
+    /// The directories come first, each group is sorted by name, hidden files are skipped
+    pub fn list<P: AsRef<Path>>(&self, dir: P) -> io::Result<Vec<LibraryEntry>> {
+        let dir = dir.as_ref();
+        let mut directories = Vec::new();
+        let mut tracks = Vec::new();
+        for entry in fs::read_dir(self.resolve(dir)?)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            // Links are followed, like the other readers of the files
+            let path = entry.path();
+            if path.is_dir() {
+                directories.push(dir.join(name));
+            } else if is_audio_file(&path) {
+                tracks.push(dir.join(name));
+            }
+        }
+        directories.sort();
+        tracks.sort();
+
+        Ok(directories
+            .into_iter()
+            .map(LibraryEntry::Directory)
+            .chain(tracks.into_iter().map(LibraryEntry::Track))
+            .collect())
+    }
+
+    /// Get every audio file under a directory, in the order of `list`
+    ///
+    /// A directory reached again through a link is skipped, so the links can't make a loop
+    pub fn tracks<P: AsRef<Path>>(&self, dir: P) -> io::Result<Vec<PathBuf>> {
+        let mut tracks = Vec::new();
+        self.add_tracks(dir.as_ref(), &mut HashSet::new(), &mut tracks)?;
+        Ok(tracks)
+    }
+
+    fn add_tracks(
+        &self,
+        dir: &Path,
+        visited: &mut HashSet<PathBuf>,
+        tracks: &mut Vec<PathBuf>,
+    ) -> io::Result<()> {
+        if !visited.insert(fs::canonicalize(self.resolve(dir)?)?) {
+            return Ok(());
+        }
+        for entry in self.list(dir)? {
+            match entry {
+                LibraryEntry::Directory(path) => self.add_tracks(&path, visited, tracks)?,
+                LibraryEntry::Track(path) => tracks.push(path),
+            }
+        }
+        Ok(())
+    }
+
+    /// Get an audio file of the library on the file system
+    pub fn find_song<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        let file = self.resolve(&path)?;
+        if file.is_file() && is_audio_file(&file) {
+            Ok(file)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not an audio file", path.as_ref().display()),
+            ))
+        }
+    }
+
+    /// Get an audio file, or every audio file under a directory, on the file system
+    pub fn find_songs<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<PathBuf>> {
+        if self.resolve(&path)?.is_dir() {
+            self.tracks(path)?
+                .into_iter()
+                .map(|track| self.resolve(track))
+                .collect()
+        } else {
+            self.find_song(path).map(|file| vec![file])
+        }
+    }
+}
+
+/// Extensions of the audio files, and whether the decoder of their format is built
+const EXTENSIONS: &[(&str, bool)] = &[
+    ("flac", cfg!(feature = "flac")),
+    ("mp3", cfg!(feature = "mp3")),
+    ("ogg", cfg!(feature = "vorbis")),
+    ("oga", cfg!(feature = "vorbis")),
+    ("wav", cfg!(feature = "wav")),
+    ("wave", cfg!(feature = "wav")),
+];
+
+/// Returns true if the extension of the file is one of a format the decoders can read
+pub fn is_audio_file<P: AsRef<Path>>(path: P) -> bool {
+    let extension = match path
+        .as_ref()
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some(extension) => extension.to_lowercase(),
+        None => return false,
+    };
+    EXTENSIONS
+        .iter()
+        .any(|&(known, enabled)| enabled && extension == known)
+}
@@ -0,0 +1,183 @@
+#[cfg(feature = "flac")]
+use std::io::Read;
+
+use super::picture::{build_picture, parse_picture};
+use super::vorbis::{build_comments, parse_comments, VENDOR};
+#[cfg(feature = "flac")]
+use crate::Picture;
+use crate::{info::TagError, Metadata};
+
+/// Type of the metadata block holding the Vorbis comments
+const VORBIS_COMMENT: u8 = 4;
+
+/// Type of the metadata blocks holding a picture
+const PICTURE: u8 = 6;
+
+/// Type of the metadata block holding the seek points
+#[cfg(feature = "flac")]
+const SEEKTABLE: u8 = 3;
+
+/// Point of a SEEKTABLE
+#[cfg(feature = "flac")]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SeekPoint {
+    /// First sample of the frame
+    pub sample: u64,
+    /// Offset of the frame from the first frame
+    pub offset: u64,
+}
+
+/// Metadata block of a FLAC file
+struct Block<'a> {
+    kind: u8,
+    data: &'a [u8],
+}
+
+/// Read the Vorbis comments and the pictures
+pub(crate) fn read(file: &[u8]) -> Result<Metadata, TagError> {
+    let (blocks, _) = parse_blocks(file)?;
+    let mut metadata = blocks
+        .iter()
+        .find(|block| block.kind == VORBIS_COMMENT)
+        .and_then(|block| parse_comments(block.data))
+        .map(|(_, metadata)| metadata)
+        .unwrap_or_default();
+    for block in blocks.iter().filter(|block| block.kind == PICTURE) {
+        if let Some(picture) = parse_picture(block.data) {
+            metadata.push_picture(picture);
+        }
+    }
+    Ok(metadata)
+}
+
+/// Read the pictures and the seek points of the metadata blocks at the start of a stream
+///
+/// The stream is left after the blocks, on the first frame, claxon giving the Vorbis comments
+/// but neither the pictures nor the seek table
+#[cfg(feature = "flac")]
+pub(crate) fn read_stream_blocks<R: Read>(mut data: R) -> (Vec<Picture>, Vec<SeekPoint>) {
+    let mut pictures = Vec::new();
+    let mut seek_points = Vec::new();
+    let mut marker = [0u8; 4];
+    if data.read_exact(&mut marker).is_err() || &marker != b"fLaC" {
+        return (pictures, seek_points);
+    }
+
+    let mut header = [0u8; 4];
+    while data.read_exact(&mut header).is_ok() {
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let mut block = vec![0u8; len];
+        if data.read_exact(&mut block).is_err() {
+            break;
+        }
+        match header[0] & 0x7f {
+            PICTURE => pictures.extend(parse_picture(&block)),
+            SEEKTABLE => seek_points.extend(parse_seek_points(&block)),
+            _ => {}
+        }
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+    (pictures, seek_points)
+}
+
+/// Read the seek points of a SEEKTABLE, without the placeholders
+#[cfg(feature = "flac")]
+fn parse_seek_points(block: &[u8]) -> impl Iterator<Item = SeekPoint> + '_ {
+    block
+        .chunks_exact(18)
+        .map(|point| {
+            let read = |bytes: &[u8]| {
+                let mut value = [0u8; 8];
+                value.copy_from_slice(bytes);
+                u64::from_be_bytes(value)
+            };
+            SeekPoint {
+                sample: read(&point[..8]),
+                offset: read(&point[8..16]),
+            }
+        })
+        .filter(|point| point.sample != u64::MAX)
+}
+
+/// Replace the Vorbis comments, the other metadata blocks are kept
+///
+/// The pictures of the metadata replace the ones of the file, which are kept if there is none
+pub(crate) fn write(file: &[u8], metadata: &Metadata) -> Result<Vec<u8>, TagError> {
+    let (blocks, frames) = parse_blocks(file)?;
+    let vendor = blocks
+        .iter()
+        .find(|block| block.kind == VORBIS_COMMENT)
+        .and_then(|block| parse_comments(block.data))
+        .map(|(vendor, _)| vendor)
+        .unwrap_or_else(|| VENDOR.to_owned());
+    let comments = build_comments(&vendor, metadata, &[]);
+    let pictures: Vec<Vec<u8>> = metadata.pictures().iter().map(build_picture).collect();
+
+    // The comments take the place of the previous ones, or follow STREAMINFO, with the pictures
+    let mut blocks: Vec<Block<'_>> = blocks
+        .into_iter()
+        .filter(|block| block.kind != VORBIS_COMMENT)
+        .filter(|block| block.kind != PICTURE || pictures.is_empty())
+        .collect();
+    let position = 1.min(blocks.len());
+    blocks.splice(
+        position..position,
+        Some(Block {
+            kind: VORBIS_COMMENT,
+            data: &comments,
+        })
+        .into_iter()
+        .chain(pictures.iter().map(|picture| Block {
+            kind: PICTURE,
+            data: picture,
+        })),
+    );
+
+    let mut output = b"fLaC".to_vec();
+    for (index, block) in blocks.iter().enumerate() {
+        if block.data.len() >= 1 << 24 {
+            return Err(format_error("metadata block too large"));
+        }
+        let last = if index + 1 == blocks.len() { 0x80 } else { 0 };
+        output.push(block.kind | last);
+        output.extend_from_slice(&(block.data.len() as u32).to_be_bytes()[1..]);
+        output.extend_from_slice(block.data);
+    }
+    output.extend_from_slice(frames);
+    Ok(output)
+}
+
+/// Split a FLAC file in metadata blocks and audio frames
+fn parse_blocks(file: &[u8]) -> Result<(Vec<Block<'_>>, &[u8]), TagError> {
+    if file.get(..4) != Some(b"fLaC") {
+        return Err(format_error("not a FLAC file"));
+    }
+
+    let mut blocks = Vec::new();
+    let mut pos = 4;
+    loop {
+        let header = file
+            .get(pos..pos + 4)
+            .ok_or_else(|| format_error("truncated metadata"))?;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let data = file
+            .get(pos + 4..pos + 4 + len)
+            .ok_or_else(|| format_error("truncated metadata"))?;
+        blocks.push(Block {
+            kind: header[0] & 0x7f,
+            data,
+        });
+        pos += 4 + len;
+
+        if header[0] & 0x80 != 0 {
+            return Ok((blocks, &file[pos..]));
+        }
+    }
+}
+
+#[inline]
+fn format_error(err: &str) -> TagError {
+    TagError::FormatError(format!("flac: {}", err))
+}
@@ -0,0 +1,532 @@
+#[cfg(feature = "mp3")]
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{Metadata, Picture};
+
+/// Size of the header and of the footer of an ID3v2 tag
+const HEADER_LEN: usize = 10;
+
+/// Padding written after the frames
+const PADDING_LEN: usize = 1024;
+
+/// Text frames and the Vorbis comment names they are stored under, ID3v2.3/2.4 then ID3v2.2
+const TEXT_FRAMES: [(&str, &str, &str); 10] = [
+    ("TIT2", "TT2", "TITLE"),
+    ("TPE1", "TP1", "ARTIST"),
+    ("TPE2", "TP2", "ALBUMARTIST"),
+    ("TALB", "TAL", "ALBUM"),
+    ("TRCK", "TRK", "TRACKNUMBER"),
+    ("TPOS", "TPA", "DISCNUMBER"),
+    ("TCON", "TCO", "GENRE"),
+    ("TCOM", "TCM", "COMPOSER"),
+    ("TDRC", "TYE", "DATE"),
+    ("TYER", "TYE", "DATE"),
+];
+
+/// Read the ID3v2 tag at the current position of the stream, then resets it to where it was.
+///
+/// Returns the tags and the size of the tag in the stream
+#[cfg(feature = "mp3")]
+pub(crate) fn read_id3v2<R>(mut data: R) -> Option<(Metadata, u64)>
+where
+    R: Read + Seek,
+{
+    let stream_pos = data.stream_position().ok()?;
+    let tag = read_tag(data.by_ref());
+    data.seek(SeekFrom::Start(stream_pos)).ok()?;
+    tag
+}
+
+#[cfg(feature = "mp3")]
+fn read_tag<R: Read>(mut data: R) -> Option<(Metadata, u64)> {
+    let mut header = [0u8; HEADER_LEN];
+    data.read_exact(&mut header).ok()?;
+    let size = tag_size(&header)?;
+
+    let mut tag = vec![0u8; HEADER_LEN + size];
+    tag[..HEADER_LEN].copy_from_slice(&header);
+    data.read_exact(&mut tag[HEADER_LEN..]).ok()?;
+
+    let metadata = parse_id3v2(&tag)?;
+    Some((metadata, tag_len(&header)? as u64))
+}
+
+/// Get the size of the tag in the file, header and footer included
+pub(crate) fn tag_len(header: &[u8]) -> Option<usize> {
+    let size = tag_size(header)?;
+    // Footer of ID3v2.4 tags
+    if header[3] == 4 && header[5] & 0x10 != 0 {
+        Some(2 * HEADER_LEN + size)
+    } else {
+        Some(HEADER_LEN + size)
+    }
+}
+
+/// Get the size of the tag following the header, None if this is not an ID3v2 header
+fn tag_size(header: &[u8]) -> Option<usize> {
+    if header.len() < HEADER_LEN || &header[..3] != b"ID3" || !(2..=4).contains(&header[3]) {
+        return None;
+    }
+    syncsafe(&header[6..10])
+}
+
+/// Frame of an ID3v2 tag as stored in the file
+struct Frame {
+    id: String,
+    flags: u16,
+    data: Vec<u8>,
+}
+
+/// Parse an ID3v2.2, 2.3 or 2.4 tag, the header included
+///
+/// Text frames are stored under their Vorbis comment names and TXXX frames under their description
+pub(crate) fn parse_id3v2(tag: &[u8]) -> Option<Metadata> {
+    let (major, frames) = parse_frames(tag)?;
+
+    let mut metadata = Metadata::new();
+    for frame in frames {
+        if let Some(data) = frame_data(major, frame.flags, &frame.data) {
+            read_frame(&frame.id, &data, &mut metadata);
+        }
+    }
+    Some(metadata)
+}
+
+/// Split a tag in frames, returns the major version of the tag
+fn parse_frames(tag: &[u8]) -> Option<(u8, Vec<Frame>)> {
+    let size = tag_size(tag)?;
+    let major = tag[3];
+    let flags = tag[5];
+    let body = tag.get(HEADER_LEN..HEADER_LEN + size)?;
+
+    // Compressed ID3v2.2 tags have no defined compression scheme
+    if major == 2 && flags & 0x40 != 0 {
+        return None;
+    }
+
+    // ID3v2.4 unsynchronises each frame, older versions the whole tag
+    let body = if major < 4 && flags & 0x80 != 0 {
+        resync(body)
+    } else {
+        body.to_vec()
+    };
+
+    let mut pos = 0;
+    if major > 2 && flags & 0x40 != 0 {
+        pos = match major {
+            3 => 4 + read_u32(body.get(..4)?) as usize,
+            _ => syncsafe(body.get(..4)?)?,
+        };
+    }
+
+    let (id_len, header_len) = if major == 2 { (3, 6) } else { (4, 10) };
+    let mut frames = Vec::new();
+    while pos + header_len <= body.len() {
+        let header = &body[pos..pos + header_len];
+        // Padding
+        if header[0] == 0 {
+            break;
+        }
+
+        let id = std::str::from_utf8(&header[..id_len]).ok()?;
+        let (size, flags) = match major {
+            2 => (read_u24(&header[3..6]) as usize, 0),
+            3 => (read_u32(&header[4..8]) as usize, read_u16(&header[8..10])),
+            _ => (syncsafe(&header[4..8])?, read_u16(&header[8..10])),
+        };
+
+        let start = pos + header_len;
+        let end = start.checked_add(size)?;
+        if end > body.len() {
+            break;
+        }
+        pos = end;
+
+        frames.push(Frame {
+            id: id.to_owned(),
+            flags,
+            data: body[start..end].to_vec(),
+        });
+    }
+
+    Some((major, frames))
+}
+
+/// Write an ID3v2 tag holding the metadata
+///
+/// The frames of the previous tag that don't hold metadata, such as pictures, are kept.
+/// The tag is written in the version of the previous tag, ID3v2.4 if there was none
+pub(crate) fn build_id3v2(previous: Option<&[u8]>, metadata: &Metadata) -> Vec<u8> {
+    let (major, mut frames) = match previous.and_then(parse_frames) {
+        Some((major, frames)) if major > 2 => (major, frames),
+        _ => (4, Vec::new()),
+    };
+    frames.retain(|frame| !holds_metadata(frame));
+    // The pictures of the metadata replace the ones of the previous tag
+    if !metadata.pictures().is_empty() {
+        frames.retain(|frame| frame.id != "APIC");
+    }
+
+    let mut keys: Vec<&str> = Vec::new();
+    for (key, _) in metadata.iter() {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    for key in keys {
+        let values: Vec<&str> = metadata.get_all(key).collect();
+        frames.push(text_frame(major, key, &values));
+    }
+    frames.extend(
+        metadata
+            .pictures()
+            .iter()
+            .map(|picture| picture_frame(major, picture)),
+    );
+
+    let mut body = Vec::new();
+    for frame in frames {
+        body.extend_from_slice(frame.id.as_bytes());
+        if major == 4 {
+            body.extend_from_slice(&to_syncsafe(frame.data.len()));
+        } else {
+            body.extend_from_slice(&(frame.data.len() as u32).to_be_bytes());
+        }
+        body.extend_from_slice(&frame.flags.to_be_bytes());
+        body.extend_from_slice(&frame.data);
+    }
+    // Room for later edits
+    body.resize(body.len() + PADDING_LEN, 0);
+
+    let mut tag = vec![b'I', b'D', b'3', major, 0, 0];
+    tag.extend_from_slice(&to_syncsafe(body.len()));
+    tag.extend(body);
+    tag
+}
+
+/// Returns true if the frame is rewritten from the metadata
+fn holds_metadata(frame: &Frame) -> bool {
+    match frame.id.as_str() {
+        "TXXX" => true,
+        // Only the comments without description are read
+        "COMM" => frame
+            .data
+            .split_first()
+            .map(|(&encoding, text)| {
+                split_terminated(encoding, text.get(3..).unwrap_or(&[]))
+                    .0
+                    .is_empty()
+            })
+            .unwrap_or(true),
+        id => TEXT_FRAMES.iter().any(|(id3, _, _)| *id3 == id),
+    }
+}
+
+/// Build the frame storing the values of a tag
+fn text_frame(major: u8, key: &str, values: &[&str]) -> Frame {
+    // ID3v2.3 has a single value per frame
+    let encoding = text_encoding(major, values);
+    let text = if major == 4 {
+        values.join("\0")
+    } else {
+        values.join("/")
+    };
+
+    let date = if major == 4 { "TDRC" } else { "TYER" };
+    let id = match key {
+        "DATE" => Some(date),
+        _ => TEXT_FRAMES
+            .iter()
+            .find(|(_, _, name)| *name == key)
+            .map(|(id3, _, _)| *id3),
+    };
+
+    let mut data = vec![encoding];
+    let id = match (id, key) {
+        (Some(id), _) => id,
+        (None, "COMMENT") => {
+            data.extend_from_slice(b"eng");
+            data.extend(encode_text(encoding, ""));
+            data.extend(terminator(encoding));
+            "COMM"
+        }
+        (None, _) => {
+            data.extend(encode_text(encoding, key));
+            data.extend(terminator(encoding));
+            "TXXX"
+        }
+    };
+    data.extend(encode_text(encoding, &text));
+
+    Frame {
+        id: id.to_owned(),
+        flags: 0,
+        data,
+    }
+}
+
+/// Build the APIC frame of a picture
+fn picture_frame(major: u8, picture: &Picture) -> Frame {
+    let encoding = text_encoding(major, &[&picture.description]);
+    // The MIME type is always in ISO-8859-1
+    let mut data = vec![encoding];
+    data.extend(encode_text(0, &picture.mime_type));
+    data.push(0);
+    data.push(picture.picture_type);
+    data.extend(encode_text(encoding, &picture.description));
+    data.extend(terminator(encoding));
+    data.extend_from_slice(&picture.data);
+
+    Frame {
+        id: "APIC".to_owned(),
+        flags: 0,
+        data,
+    }
+}
+
+/// Choose the encoding of texts, ID3v2.3 has no UTF-8
+fn text_encoding(major: u8, texts: &[&str]) -> u8 {
+    if major == 4 {
+        3
+    } else if texts.iter().all(|text| text.chars().all(|c| (c as u32) < 0x100)) {
+        0
+    } else {
+        1
+    }
+}
+
+/// Encode a text without terminator
+fn encode_text(encoding: u8, text: &str) -> Vec<u8> {
+    match encoding {
+        0 => text.chars().map(|c| c as u8).collect(),
+        1 => {
+            let mut bytes = vec![0xff, 0xfe];
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&unit.to_le_bytes());
+            }
+            bytes
+        }
+        _ => text.as_bytes().to_vec(),
+    }
+}
+
+#[inline]
+fn terminator(encoding: u8) -> &'static [u8] {
+    if encoding == 1 || encoding == 2 {
+        &[0, 0]
+    } else {
+        &[0]
+    }
+}
+
+/// Get the content of a frame, None if it is compressed or encrypted
+fn frame_data(major: u8, flags: u16, data: &[u8]) -> Option<Vec<u8>> {
+    let mut data = data;
+    match major {
+        3 => {
+            if flags & 0x00c0 != 0 {
+                return None;
+            }
+            // Group identifier
+            if flags & 0x0020 != 0 {
+                data = data.get(1..)?;
+            }
+            Some(data.to_vec())
+        }
+        4 => {
+            if flags & 0x000c != 0 {
+                return None;
+            }
+            if flags & 0x0040 != 0 {
+                data = data.get(1..)?;
+            }
+            // Data length indicator
+            if flags & 0x0001 != 0 {
+                data = data.get(4..)?;
+            }
+            if flags & 0x0002 != 0 {
+                Some(resync(data))
+            } else {
+                Some(data.to_vec())
+            }
+        }
+        _ => Some(data.to_vec()),
+    }
+}
+
+/// Store the values of the frames known as tags
+fn read_frame(id: &str, data: &[u8], metadata: &mut Metadata) {
+    let (&encoding, text) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    match id {
+        "TXXX" | "TXX" => {
+            let (description, value) = split_terminated(encoding, text);
+            let key = decode_text(encoding, description);
+            if !key.is_empty() {
+                for value in decode_values(encoding, value) {
+                    metadata.push(&key, &value);
+                }
+            }
+        }
+        "APIC" | "PIC" => {
+            if let Some(picture) = parse_picture(id, encoding, text) {
+                metadata.push_picture(picture);
+            }
+        }
+        "COMM" | "COM" => {
+            // Language, then description
+            let (description, value) = split_terminated(encoding, text.get(3..).unwrap_or(&[]));
+            if description.is_empty() {
+                for value in decode_values(encoding, value) {
+                    metadata.push("COMMENT", &value);
+                }
+            }
+        }
+        _ => {
+            let key = TEXT_FRAMES
+                .iter()
+                .find(|(id3, id22, _)| *id3 == id || *id22 == id)
+                .map(|(_, _, key)| key);
+            if let Some(key) = key {
+                for value in decode_values(encoding, text) {
+                    metadata.push(key, &value);
+                }
+            }
+        }
+    }
+}
+
+/// Parse an APIC frame, or the PIC frame of ID3v2.2 giving the format instead of the MIME type
+fn parse_picture(id: &str, encoding: u8, data: &[u8]) -> Option<Picture> {
+    let (mime_type, data) = if id == "PIC" {
+        let format = decode_text(0, data.get(..3)?);
+        let mime_type = match format.to_ascii_uppercase().as_str() {
+            "JPG" => "image/jpeg".to_owned(),
+            "-->" => "-->".to_owned(),
+            format => format!("image/{}", format.to_ascii_lowercase()),
+        };
+        (mime_type, &data[3..])
+    } else {
+        let (mime_type, data) = split_terminated(0, data);
+        (decode_text(0, mime_type), data)
+    };
+    let (&picture_type, data) = data.split_first()?;
+    let (description, data) = split_terminated(encoding, data);
+
+    Some(Picture {
+        picture_type,
+        mime_type,
+        description: decode_text(encoding, description),
+        data: data.to_vec(),
+    })
+}
+
+/// Split a text at the first terminator of its encoding
+fn split_terminated(encoding: u8, text: &[u8]) -> (&[u8], &[u8]) {
+    if encoding == 1 || encoding == 2 {
+        let mut i = 0;
+        while i + 1 < text.len() {
+            if text[i] == 0 && text[i + 1] == 0 {
+                return (&text[..i], &text[i + 2..]);
+            }
+            i += 2;
+        }
+    } else if let Some(i) = text.iter().position(|&byte| byte == 0) {
+        return (&text[..i], &text[i + 1..]);
+    }
+    (text, &[])
+}
+
+/// Decode the values of a text frame, ID3v2.4 separates them with terminators
+fn decode_values(encoding: u8, mut text: &[u8]) -> Vec<String> {
+    let mut values = Vec::new();
+    while !text.is_empty() {
+        let (value, rest) = split_terminated(encoding, text);
+        let value = decode_text(encoding, value);
+        if !value.is_empty() {
+            values.push(value);
+        }
+        text = rest;
+    }
+    values
+}
+
+/// Decode a text without terminator
+fn decode_text(encoding: u8, text: &[u8]) -> String {
+    match encoding {
+        // ISO-8859-1 maps directly on the first code points
+        0 => text.iter().map(|&byte| byte as char).collect(),
+        1 | 2 => {
+            let (big_endian, text) = match text {
+                [0xfe, 0xff, rest @ ..] => (true, rest),
+                [0xff, 0xfe, rest @ ..] => (false, rest),
+                _ => (encoding == 2, text),
+            };
+            let units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|unit| {
+                    if big_endian {
+                        u16::from_be_bytes([unit[0], unit[1]])
+                    } else {
+                        u16::from_le_bytes([unit[0], unit[1]])
+                    }
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(text).into_owned(),
+    }
+}
+
+/// Undo the unsynchronisation scheme, a zero byte is inserted after each 0xFF
+fn resync(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut previous = 0;
+    for &byte in data {
+        if !(previous == 0xff && byte == 0) {
+            output.push(byte);
+        }
+        previous = byte;
+    }
+    output
+}
+
+/// Write a 28 bits integer on the low 7 bits of 4 bytes
+fn to_syncsafe(size: usize) -> [u8; 4] {
+    [
+        (size >> 21) as u8 & 0x7f,
+        (size >> 14) as u8 & 0x7f,
+        (size >> 7) as u8 & 0x7f,
+        size as u8 & 0x7f,
+    ]
+}
+
+/// Read a 28 bits integer stored on the low 7 bits of 4 bytes
+fn syncsafe(bytes: &[u8]) -> Option<usize> {
+    if bytes.iter().any(|byte| byte & 0x80 != 0) {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0, |size, &byte| (size << 7) | byte as usize),
+    )
+}
+
+#[inline]
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+#[inline]
+fn read_u24(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]])
+}
+
+#[inline]
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
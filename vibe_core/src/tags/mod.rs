@@ -0,0 +1,77 @@
+pub(crate) mod flac;
+pub(crate) mod id3;
+mod mp3;
+pub(crate) mod ogg;
+pub(crate) mod picture;
+pub(crate) mod riff;
+pub(crate) mod vorbis;
+mod wav;
+
+use std::fs;
+use std::path::Path;
+
+use crate::{info::TagError, Metadata};
+
+/// Container of the tags of a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TagFormat {
+    Flac,
+    Ogg,
+    Wav,
+    Mp3,
+}
+
+impl TagFormat {
+    /// Find the format from the first bytes of the file
+    fn detect(file: &[u8]) -> Result<Self, TagError> {
+        if file.starts_with(b"fLaC") {
+            Ok(TagFormat::Flac)
+        } else if file.starts_with(b"OggS") {
+            Ok(TagFormat::Ogg)
+        } else if riff::is_wave(file) {
+            Ok(TagFormat::Wav)
+        } else if mp3::is_mp3(file) {
+            Ok(TagFormat::Mp3)
+        } else {
+            Err(TagError::Unsupported("unknown format".to_owned()))
+        }
+    }
+}
+
+/// Read the tags and the pictures of an audio file
+///
+/// Vorbis comments for FLAC and Ogg Vorbis, ID3v2 for MP3 and WAV
+pub fn read_metadata<P: AsRef<Path>>(path: P) -> Result<Metadata, TagError> {
+    let file = fs::read(path).map_err(TagError::IOError)?;
+    match TagFormat::detect(&file)? {
+        TagFormat::Flac => flac::read(&file),
+        TagFormat::Ogg => ogg::read(&file),
+        TagFormat::Wav => wav::read(&file),
+        TagFormat::Mp3 => mp3::read(&file),
+    }
+}
+
+/// Replace the tags of an audio file, the audio data is left untouched
+///
+/// The file is written next to the original then renamed over it.
+/// The pictures of the metadata replace the ones of the file, which are kept if there is none.
+/// ID3v2 frames that are not tags are kept
+pub fn write_metadata<P: AsRef<Path>>(path: P, metadata: &Metadata) -> Result<(), TagError> {
+    let path = path.as_ref();
+    let file = fs::read(path).map_err(TagError::IOError)?;
+    let output = match TagFormat::detect(&file)? {
+        TagFormat::Flac => flac::write(&file, metadata)?,
+        TagFormat::Ogg => ogg::write(&file, metadata)?,
+        TagFormat::Wav => wav::write(&file, metadata)?,
+        TagFormat::Mp3 => mp3::write(&file, metadata)?,
+    };
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".vibe-tmp");
+    fs::write(&temp, output)
+        .and_then(|_| fs::rename(&temp, path))
+        .map_err(|err| {
+            let _ = fs::remove_file(&temp);
+            TagError::IOError(err)
+        })
+}
@@ -0,0 +1,31 @@
+use super::id3::{build_id3v2, parse_id3v2, tag_len};
+use crate::{info::TagError, Metadata};
+
+/// Read the tags of the ID3v2 tag at the start of the file
+pub(crate) fn read(file: &[u8]) -> Result<Metadata, TagError> {
+    Ok(split_tag(file)
+        .0
+        .and_then(parse_id3v2)
+        .unwrap_or_default())
+}
+
+/// Replace the ID3v2 tag at the start of the file
+pub(crate) fn write(file: &[u8], metadata: &Metadata) -> Result<Vec<u8>, TagError> {
+    let (previous, audio) = split_tag(file);
+    let mut output = build_id3v2(previous, metadata);
+    output.extend_from_slice(audio);
+    Ok(output)
+}
+
+/// Returns true if the file starts with an ID3v2 tag or a MPEG audio frame
+pub(crate) fn is_mp3(file: &[u8]) -> bool {
+    file.starts_with(b"ID3") || (file.len() >= 2 && file[0] == 0xff && file[1] & 0xe0 == 0xe0)
+}
+
+/// Split the file in its ID3v2 tag and its audio frames
+fn split_tag(file: &[u8]) -> (Option<&[u8]>, &[u8]) {
+    match tag_len(file) {
+        Some(len) if len <= file.len() => (Some(&file[..len]), &file[len..]),
+        _ => (None, file),
+    }
+}
@@ -0,0 +1,253 @@
+use std::convert::TryInto;
+
+use super::vorbis::{build_comments, parse_comments, VENDOR};
+use crate::{info::TagError, Metadata};
+
+/// Size of a page header without its segment table
+const PAGE_HEADER_LEN: usize = 27;
+
+/// Header type flag of a page starting with the rest of a packet
+pub(crate) const CONTINUED: u8 = 0x01;
+
+/// Header type flag of the first page of a stream
+#[cfg(feature = "encode-vorbis")]
+pub(crate) const BEGIN_OF_STREAM: u8 = 0x02;
+
+/// Header type flag of the last page of a stream
+#[cfg(feature = "encode-vorbis")]
+pub(crate) const END_OF_STREAM: u8 = 0x04;
+
+/// Start of the comment header packet of a Vorbis stream
+const COMMENT_HEADER: &[u8; 7] = b"\x03vorbis";
+
+/// Page of an Ogg stream
+pub(crate) struct Page<'a> {
+    pub header_type: u8,
+    pub granule: u64,
+    pub serial: u32,
+    pub sequence: u32,
+    pub segments: &'a [u8],
+    pub data: &'a [u8],
+}
+
+impl<'a> Page<'a> {
+    /// Parse the page at the start of the bytes, returns it with its size
+    pub fn parse(bytes: &'a [u8]) -> Option<(Self, usize)> {
+        let header = bytes.get(..PAGE_HEADER_LEN)?;
+        if &header[..4] != b"OggS" || header[4] != 0 {
+            return None;
+        }
+
+        let count = header[26] as usize;
+        let segments = bytes.get(PAGE_HEADER_LEN..PAGE_HEADER_LEN + count)?;
+        let start = PAGE_HEADER_LEN + count;
+        let len: usize = segments.iter().map(|&len| len as usize).sum();
+        let data = bytes.get(start..start + len)?;
+
+        let page = Page {
+            header_type: header[5],
+            granule: u64::from_le_bytes(header[6..14].try_into().ok()?),
+            serial: u32::from_le_bytes(header[14..18].try_into().ok()?),
+            sequence: u32::from_le_bytes(header[18..22].try_into().ok()?),
+            segments,
+            data,
+        };
+        Some((page, start + len))
+    }
+
+    /// Write the page with its checksum
+    pub fn write(&self, output: &mut Vec<u8>) {
+        let start = output.len();
+        output.extend_from_slice(b"OggS\0");
+        output.push(self.header_type);
+        output.extend_from_slice(&self.granule.to_le_bytes());
+        output.extend_from_slice(&self.serial.to_le_bytes());
+        output.extend_from_slice(&self.sequence.to_le_bytes());
+        output.extend_from_slice(&[0; 4]);
+        output.push(self.segments.len() as u8);
+        output.extend_from_slice(self.segments);
+        output.extend_from_slice(self.data);
+
+        let crc = crc32(&output[start..]);
+        output[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+    }
+}
+
+/// Read the comments of the first Vorbis stream
+pub(crate) fn read(file: &[u8]) -> Result<Metadata, TagError> {
+    let headers = parse_headers(file)?;
+    Ok(parse_comment_packet(&headers.comment)
+        .map(|(_, metadata)| metadata)
+        .unwrap_or_default())
+}
+
+/// Replace the comments of the first Vorbis stream
+///
+/// The pictures of the metadata replace the ones of the file, which are kept if there is none.
+/// The pages following the comments are renumbered if the comments take more or less pages
+pub(crate) fn write(file: &[u8], metadata: &Metadata) -> Result<Vec<u8>, TagError> {
+    let headers = parse_headers(file)?;
+    let (vendor, previous) = parse_comment_packet(&headers.comment)
+        .unwrap_or_else(|| (VENDOR.to_owned(), Metadata::new()));
+    let pictures = if metadata.pictures().is_empty() {
+        previous.pictures()
+    } else {
+        metadata.pictures()
+    };
+
+    let mut comment = COMMENT_HEADER.to_vec();
+    comment.extend(build_comments(&vendor, metadata, pictures));
+    // Framing bit
+    comment.push(1);
+
+    let mut output = file[..headers.start].to_vec();
+    let pages = write_packets(
+        &mut output,
+        headers.serial,
+        1,
+        &[&comment, &headers.setup],
+    );
+
+    // The sequence numbers of the following pages move with the number of header pages
+    let mut pos = headers.end;
+    while pos < file.len() {
+        let (mut page, len) =
+            Page::parse(&file[pos..]).ok_or_else(|| format_error("invalid page"))?;
+        if page.serial == headers.serial {
+            page.sequence = page.sequence.wrapping_add(pages).wrapping_sub(headers.pages);
+        }
+        page.write(&mut output);
+        pos += len;
+    }
+    Ok(output)
+}
+
+/// Write packets on new pages starting with the given sequence number, returns the number of pages
+pub(crate) fn write_packets(
+    output: &mut Vec<u8>,
+    serial: u32,
+    sequence: u32,
+    packets: &[&[u8]],
+) -> u32 {
+    // Lacing values of the packets, then their data
+    let mut lacing = Vec::new();
+    let mut data = Vec::new();
+    for packet in packets {
+        let mut len = packet.len();
+        while len >= 255 {
+            lacing.push(255u8);
+            len -= 255;
+        }
+        lacing.push(len as u8);
+        data.extend_from_slice(packet);
+    }
+
+    let mut pages = 0;
+    let mut offset = 0;
+    let mut continued = false;
+    for segments in lacing.chunks(255) {
+        let len: usize = segments.iter().map(|&len| len as usize).sum();
+        let page = Page {
+            header_type: if continued { CONTINUED } else { 0 },
+            granule: 0,
+            serial,
+            sequence: sequence + pages,
+            segments,
+            data: &data[offset..offset + len],
+        };
+        page.write(output);
+
+        continued = segments.last() == Some(&255);
+        offset += len;
+        pages += 1;
+    }
+    pages
+}
+
+/// Comment and setup headers of the first stream of the file
+struct Headers {
+    serial: u32,
+    comment: Vec<u8>,
+    setup: Vec<u8>,
+    /// Position of the first page of the comment header
+    start: usize,
+    /// Position of the page following the setup header
+    end: usize,
+    /// Number of pages used by the comment and setup headers
+    pages: u32,
+}
+
+/// Read the packets following the identification header
+fn parse_headers(file: &[u8]) -> Result<Headers, TagError> {
+    let (first, start) = Page::parse(file).ok_or_else(|| format_error("not an Ogg file"))?;
+
+    let mut packets: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut pos = start;
+    let mut pages = 0;
+    while packets.len() < 3 {
+        let (page, len) = Page::parse(&file[pos..]).ok_or_else(|| format_error("invalid page"))?;
+        if page.serial != first.serial {
+            return Err(TagError::Unsupported("ogg: multiplexed streams".to_owned()));
+        }
+
+        let mut offset = 0;
+        for &segment in page.segments {
+            let segment = segment as usize;
+            packets
+                .last_mut()
+                .unwrap()
+                .extend_from_slice(&page.data[offset..offset + segment]);
+            offset += segment;
+            if segment < 255 {
+                packets.push(Vec::new());
+            }
+        }
+        pos += len;
+        pages += 1;
+    }
+
+    // The audio packets start on a new page
+    if packets.len() > 3 || !packets[2].is_empty() {
+        return Err(format_error("audio data on a header page"));
+    }
+    packets.truncate(2);
+    let setup = packets.pop().unwrap();
+    let comment = packets.pop().unwrap();
+    if !comment.starts_with(COMMENT_HEADER) {
+        return Err(TagError::Unsupported("ogg: not a Vorbis stream".to_owned()));
+    }
+
+    Ok(Headers {
+        serial: first.serial,
+        comment,
+        setup,
+        start,
+        end: pos,
+        pages,
+    })
+}
+
+/// Parse the comment header packet
+fn parse_comment_packet(packet: &[u8]) -> Option<(String, Metadata)> {
+    parse_comments(packet.get(COMMENT_HEADER.len()..)?)
+}
+
+/// Checksum of the Ogg pages, CRC-32 with the 0x04c11db7 polynomial and no reflection
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    data.iter().fold(0u32, |crc, &byte| {
+        let mut crc = crc ^ ((byte as u32) << 24);
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+#[inline]
+fn format_error(err: &str) -> TagError {
+    TagError::FormatError(format!("ogg: {}", err))
+}
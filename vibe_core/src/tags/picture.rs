@@ -0,0 +1,99 @@
+use std::convert::TryInto;
+
+use crate::Picture;
+
+/// Name of the Vorbis comment holding a picture block encoded in base64
+pub(crate) const PICTURE_COMMENT: &str = "METADATA_BLOCK_PICTURE";
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Parse a picture as stored in the PICTURE block of FLAC files
+pub(crate) fn parse_picture(block: &[u8]) -> Option<Picture> {
+    let mut reader = block;
+    let picture_type = read_u32(&mut reader)?;
+    let mime_type = String::from_utf8_lossy(read_field(&mut reader)?).into_owned();
+    let description = String::from_utf8_lossy(read_field(&mut reader)?).into_owned();
+    // Width, height, color depth and number of colors of indexed pictures
+    reader = reader.get(16..)?;
+    let data = read_field(&mut reader)?.to_vec();
+
+    Some(Picture {
+        picture_type: picture_type.try_into().ok()?,
+        mime_type,
+        description,
+        data,
+    })
+}
+
+/// Write a picture as stored in the PICTURE block of FLAC files
+///
+/// The size and the colors of the picture are left to zero, the readers find them in the data
+pub(crate) fn build_picture(picture: &Picture) -> Vec<u8> {
+    let mut block = Vec::with_capacity(32 + picture.mime_type.len() + picture.data.len());
+    block.extend_from_slice(&(picture.picture_type as u32).to_be_bytes());
+    write_field(&mut block, picture.mime_type.as_bytes());
+    write_field(&mut block, picture.description.as_bytes());
+    block.extend_from_slice(&[0; 16]);
+    write_field(&mut block, &picture.data);
+    block
+}
+
+/// Encode bytes in base64 with padding
+pub(crate) fn encode_base64(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for index in 0..4 {
+            if index <= chunk.len() {
+                text.push(BASE64[(group >> (18 - 6 * index)) as usize & 0x3f] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Decode base64, the padding and the whitespaces are ignored
+pub(crate) fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(text.len() / 4 * 3);
+    let mut group = 0u32;
+    let mut bits = 0;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'=' | b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => BASE64.iter().position(|&c| c == byte)? as u32,
+        };
+        group = (group << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((group >> bits) as u8);
+        }
+    }
+    Some(data)
+}
+
+fn read_u32(reader: &mut &[u8]) -> Option<u32> {
+    let bytes = reader.get(..4)?;
+    let value = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    *reader = &reader[4..];
+    Some(value)
+}
+
+fn read_field<'a>(reader: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = read_u32(reader)? as usize;
+    let field = reader.get(..len)?;
+    *reader = &reader[len..];
+    Some(field)
+}
+
+fn write_field(data: &mut Vec<u8>, field: &[u8]) {
+    data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+    data.extend_from_slice(field);
+}
@@ -0,0 +1,90 @@
+#[cfg(feature = "wav")]
+use std::io::{Read, Seek, SeekFrom};
+
+/// Chunk of a RIFF file
+pub(crate) struct Chunk<'a> {
+    pub id: [u8; 4],
+    pub data: &'a [u8],
+}
+
+/// Get the content of the first chunk of a WAVE file with one of the given ids, then resets the stream to where it was.
+#[cfg(feature = "wav")]
+pub(crate) fn find_chunk<R>(mut data: R, ids: &[&[u8; 4]]) -> Option<Vec<u8>>
+where
+    R: Read + Seek,
+{
+    let stream_pos = data.stream_position().ok()?;
+    let chunk = read_chunk(data.by_ref(), ids);
+    data.seek(SeekFrom::Start(stream_pos)).ok()?;
+    chunk
+}
+
+#[cfg(feature = "wav")]
+fn read_chunk<R>(mut data: R, ids: &[&[u8; 4]]) -> Option<Vec<u8>>
+where
+    R: Read + Seek,
+{
+    let mut header = [0u8; 12];
+    data.read_exact(&mut header).ok()?;
+    if !is_wave(&header) {
+        return None;
+    }
+
+    let mut chunk = [0u8; 8];
+    while data.read_exact(&mut chunk).is_ok() {
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+        if ids.iter().any(|id| chunk[..4] == id[..]) {
+            let mut content = Vec::with_capacity(size as usize);
+            data.by_ref().take(size).read_to_end(&mut content).ok()?;
+            return Some(content);
+        }
+
+        // Chunks are padded to an even size
+        data.seek(SeekFrom::Current((size + size % 2) as i64)).ok()?;
+    }
+    None
+}
+
+/// Returns true if the bytes start with the header of a WAVE file
+#[inline]
+pub(crate) fn is_wave(file: &[u8]) -> bool {
+    file.len() >= 12 && &file[..4] == b"RIFF" && &file[8..12] == b"WAVE"
+}
+
+/// Split a WAVE file in chunks, a chunk going past the end of the file is truncated
+pub(crate) fn parse_chunks(file: &[u8]) -> Option<Vec<Chunk<'_>>> {
+    if !is_wave(file) {
+        return None;
+    }
+
+    let mut chunks = Vec::new();
+    let mut pos = 12;
+    while pos + 8 <= file.len() {
+        let header = &file[pos..pos + 8];
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let start = pos + 8;
+        let end = start.saturating_add(size).min(file.len());
+        chunks.push(Chunk {
+            id: [header[0], header[1], header[2], header[3]],
+            data: &file[start..end],
+        });
+        pos = end + size % 2;
+    }
+    Some(chunks)
+}
+
+/// Write a WAVE file from its chunks
+pub(crate) fn build_wave(chunks: &[Chunk<'_>]) -> Vec<u8> {
+    let mut file = b"RIFF\0\0\0\0WAVE".to_vec();
+    for chunk in chunks {
+        file.extend_from_slice(&chunk.id);
+        file.extend_from_slice(&(chunk.data.len() as u32).to_le_bytes());
+        file.extend_from_slice(chunk.data);
+        if chunk.data.len() % 2 != 0 {
+            file.push(0);
+        }
+    }
+    let size = (file.len() - 8) as u32;
+    file[4..8].copy_from_slice(&size.to_le_bytes());
+    file
+}
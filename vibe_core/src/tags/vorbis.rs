@@ -0,0 +1,68 @@
+use super::picture::{build_picture, decode_base64, encode_base64, parse_picture, PICTURE_COMMENT};
+use crate::{Metadata, Picture};
+
+/// Vendor string written when the file has none
+pub(crate) const VENDOR: &str = "vibe";
+
+/// Parse a Vorbis comment block, returns the vendor string and the comments
+pub(crate) fn parse_comments(data: &[u8]) -> Option<(String, Metadata)> {
+    let mut reader = data;
+    let vendor = String::from_utf8_lossy(read_field(&mut reader)?).into_owned();
+    let count = read_u32(&mut reader)?;
+
+    let mut metadata = Metadata::new();
+    for _ in 0..count {
+        let comment = String::from_utf8_lossy(read_field(&mut reader)?).into_owned();
+        if let Some((key, value)) = comment.split_once('=') {
+            push_comment(&mut metadata, key, value);
+        }
+    }
+    Some((vendor, metadata))
+}
+
+/// Add a comment to the metadata, the pictures encoded in base64 are decoded
+pub(crate) fn push_comment(metadata: &mut Metadata, key: &str, value: &str) {
+    if key.eq_ignore_ascii_case(PICTURE_COMMENT) {
+        if let Some(picture) = decode_base64(value).and_then(|block| parse_picture(&block)) {
+            metadata.push_picture(picture);
+        }
+    } else {
+        metadata.push(key, value);
+    }
+}
+
+/// Write a Vorbis comment block, without the framing bit of Ogg Vorbis
+///
+/// The pictures are written as comments, FLAC files store them in blocks of their own instead
+pub(crate) fn build_comments(vendor: &str, metadata: &Metadata, pictures: &[Picture]) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_field(&mut data, vendor.as_bytes());
+    data.extend_from_slice(&((metadata.len() + pictures.len()) as u32).to_le_bytes());
+    for (key, value) in metadata.iter() {
+        write_field(&mut data, format!("{}={}", key, value).as_bytes());
+    }
+    for picture in pictures {
+        let value = encode_base64(&build_picture(picture));
+        write_field(&mut data, format!("{}={}", PICTURE_COMMENT, value).as_bytes());
+    }
+    data
+}
+
+fn read_u32(reader: &mut &[u8]) -> Option<u32> {
+    let bytes = reader.get(..4)?;
+    let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    *reader = &reader[4..];
+    Some(value)
+}
+
+fn read_field<'a>(reader: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = read_u32(reader)? as usize;
+    let field = reader.get(..len)?;
+    *reader = &reader[len..];
+    Some(field)
+}
+
+fn write_field(data: &mut Vec<u8>, field: &[u8]) {
+    data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+    data.extend_from_slice(field);
+}
@@ -0,0 +1,43 @@
+use super::{
+    id3::{build_id3v2, parse_id3v2},
+    riff::{build_wave, parse_chunks, Chunk},
+};
+use crate::{info::TagError, Metadata};
+
+/// Ids of the chunk holding an ID3v2 tag
+const ID3_CHUNKS: [&[u8; 4]; 2] = [b"id3 ", b"ID3 "];
+
+/// Read the tags of the ID3 chunk
+pub(crate) fn read(file: &[u8]) -> Result<Metadata, TagError> {
+    let chunks = parse_chunks(file).ok_or_else(|| format_error("not a WAVE file"))?;
+    Ok(chunks
+        .iter()
+        .find(|chunk| ID3_CHUNKS.contains(&&chunk.id))
+        .and_then(|chunk| parse_id3v2(chunk.data))
+        .unwrap_or_default())
+}
+
+/// Replace the ID3 chunk, it is written at the end of the file
+pub(crate) fn write(file: &[u8], metadata: &Metadata) -> Result<Vec<u8>, TagError> {
+    let chunks = parse_chunks(file).ok_or_else(|| format_error("not a WAVE file"))?;
+    let previous = chunks
+        .iter()
+        .find(|chunk| ID3_CHUNKS.contains(&&chunk.id))
+        .map(|chunk| chunk.data);
+    let tag = build_id3v2(previous, metadata);
+
+    let mut chunks: Vec<Chunk<'_>> = chunks
+        .into_iter()
+        .filter(|chunk| !ID3_CHUNKS.contains(&&chunk.id))
+        .collect();
+    chunks.push(Chunk {
+        id: *b"id3 ",
+        data: &tag,
+    });
+    Ok(build_wave(&chunks))
+}
+
+#[inline]
+fn format_error(err: &str) -> TagError {
+    TagError::FormatError(format!("wav: {}", err))
+}
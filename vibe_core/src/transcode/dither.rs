@@ -0,0 +1,44 @@
+use crate::Sample;
+
+/// Distance to the integers under which a sample is taken as already rounded
+const ROUNDED: f64 = 1e-3;
+
+/// TPDF dither, the samples are rounded to the integers of the encoder after adding the
+/// difference of two uniform noises of one step each
+///
+/// The samples already on the integers, like the ones of a source with as many bits or digital
+/// silence, are kept untouched
+pub(super) struct Dither {
+    /// Value of the largest integer, as in the quantization of the encoders
+    scale: f64,
+    state: u32,
+}
+
+impl Dither {
+    pub fn new(bits_per_sample: u32) -> Self {
+        Self {
+            scale: (i32::MAX >> (32 - bits_per_sample)) as f64,
+            state: 0x2545_f491,
+        }
+    }
+
+    pub fn apply(&mut self, samples: &mut [Sample]) {
+        for sample in samples {
+            let value = *sample as f64 * self.scale;
+            if (value - value.round()).abs() < ROUNDED {
+                continue;
+            }
+            let noise = self.uniform() - self.uniform();
+            *sample = ((value + noise).round() / self.scale) as Sample;
+        }
+    }
+
+    /// Get a uniform value from 0 to 1 with a xorshift generator
+    #[inline]
+    fn uniform(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f64 / (1 << 24) as f64
+    }
+}
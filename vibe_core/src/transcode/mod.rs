@@ -0,0 +1,104 @@
+#[cfg(any(
+    feature = "encode-wav",
+    feature = "encode-flac",
+    feature = "encode-vorbis",
+    feature = "encode-mp3"
+))]
+mod dither;
+#[cfg(any(
+    feature = "encode-wav",
+    feature = "encode-flac",
+    feature = "encode-vorbis",
+    feature = "encode-mp3"
+))]
+mod resample;
+#[cfg(any(
+    feature = "encode-wav",
+    feature = "encode-flac",
+    feature = "encode-vorbis",
+    feature = "encode-mp3"
+))]
+mod transcoder;
+
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use crate::Sample;
+
+#[cfg(any(
+    feature = "encode-wav",
+    feature = "encode-flac",
+    feature = "encode-vorbis",
+    feature = "encode-mp3"
+))]
+pub use self::transcoder::{transcode, transcode_file, Progress, TranscodeConfig};
+
+/// Map a frame on the channels of the output
+///
+/// Mono is copied on every channel, surround layouts are downmixed with the
+/// ITU-R BS.775 coefficients into stereo and mono outputs, everything else keeps
+/// its first channels, the missing ones are silent
+pub fn map_channels(input: &[Sample], output: &mut [Sample]) {
+    if input.len() == output.len() {
+        output.copy_from_slice(input);
+    } else if input.len() == 1 {
+        for sample in output.iter_mut() {
+            *sample = input[0];
+        }
+    } else if output.len() <= 2 && input.len() > 2 {
+        let (left, right) = downmix(input);
+        if output.len() == 1 {
+            output[0] = (left + right) / 2.0;
+        } else {
+            output[0] = left;
+            output[1] = right;
+        }
+    } else if output.len() == 1 {
+        output[0] = input.iter().sum::<Sample>() / input.len() as Sample;
+    } else {
+        for (channel, sample) in output.iter_mut().enumerate() {
+            *sample = input.get(channel).copied().unwrap_or(0.0);
+        }
+    }
+}
+
+/// Downmix a surround frame into stereo
+///
+/// The channels are in the WAVE order, the LFE is dropped and the gains are
+/// normalized so that the downmix cannot clip
+fn downmix(input: &[Sample]) -> (Sample, Sample) {
+    let (mut left, mut right) = (0.0, 0.0);
+    let (mut left_gain, mut right_gain) = (0.0, 0.0);
+    for (channel, &sample) in input.iter().enumerate() {
+        let (to_left, to_right) = downmix_gains(input.len(), channel);
+        left += sample * to_left;
+        right += sample * to_right;
+        left_gain += to_left;
+        right_gain += to_right;
+    }
+    (left / left_gain, right / right_gain)
+}
+
+/// Gains of a channel of a surround layout into the left and right outputs
+fn downmix_gains(channels: usize, channel: usize) -> (Sample, Sample) {
+    const FRONT: (Sample, Sample) = (1.0, 0.0);
+    const CENTER: (Sample, Sample) = (FRAC_1_SQRT_2, FRAC_1_SQRT_2);
+    const LFE: (Sample, Sample) = (0.0, 0.0);
+    const LEFT: (Sample, Sample) = (FRAC_1_SQRT_2, 0.0);
+    const RIGHT: (Sample, Sample) = (0.0, FRAC_1_SQRT_2);
+    const BACK: (Sample, Sample) = (0.5, 0.5);
+
+    match (channels, channel) {
+        (_, 0) => FRONT,
+        (_, 1) => (FRONT.1, FRONT.0),
+        (3, 2) | (5..=8, 2) => CENTER,
+        (4, 2) | (5, 3) => LEFT,
+        (4, 3) | (5, 4) => RIGHT,
+        (6..=8, 3) => LFE,
+        (6, 4) | (8, 4) | (8, 6) => LEFT,
+        (6, 5) | (8, 5) | (8, 7) => RIGHT,
+        (7, 4) => BACK,
+        (7, 5) => LEFT,
+        (7, 6) => RIGHT,
+        _ => BACK,
+    }
+}
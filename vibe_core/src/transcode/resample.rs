@@ -0,0 +1,140 @@
+use std::f64::consts::PI;
+
+use crate::Sample;
+
+/// Zero crossings of the sinc on each side of the kernel
+const ZERO_CROSSINGS: f64 = 24.0;
+
+/// Phases of the kernel between two input frames, the weights are interpolated between them
+const PHASES: usize = 256;
+
+/// Part of the band kept under the Nyquist frequency of the lower rate
+const ROLLOFF: f64 = 0.95;
+
+/// Sample rate converter with a windowed sinc
+///
+/// The output frames are at exact positions of the input, computed from the ratio of the rates,
+/// so long files don't drift
+pub(super) struct Resampler {
+    channels: usize,
+    input_rate: u64,
+    output_rate: u64,
+    /// Weights of the input frames around an output frame, `width` for each phase
+    kernel: Vec<f32>,
+    width: usize,
+    /// Interleaved input frames, the first one is `dropped - width / 2` of the input
+    buffer: Vec<Sample>,
+    dropped: u64,
+    input_frames: u64,
+    output_frames: u64,
+    /// Weights of the current output frame
+    weights: Vec<f32>,
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, output_rate: u32, channels: usize) -> Self {
+        // The cutoff follows the lower rate, in cycles per input frame times two
+        let cutoff = ROLLOFF * (output_rate as f64 / input_rate as f64).min(1.0);
+        let half = (ZERO_CROSSINGS / cutoff).ceil() as usize;
+        let width = 2 * half;
+
+        let mut kernel = Vec::with_capacity((PHASES + 1) * width);
+        for phase in 0..=PHASES {
+            let fraction = phase as f64 / PHASES as f64;
+            let start = kernel.len();
+            kernel.extend((0..width).map(|tap| {
+                let x = fraction + (half - 1) as f64 - tap as f64;
+                (cutoff * sinc(cutoff * x) * blackman(x / half as f64)) as f32
+            }));
+            // No gain at the lowest frequencies whatever the phase
+            let sum: f32 = kernel[start..].iter().sum();
+            kernel[start..].iter_mut().for_each(|weight| *weight /= sum);
+        }
+
+        Self {
+            channels,
+            input_rate: input_rate as u64,
+            output_rate: output_rate as u64,
+            kernel,
+            width,
+            // The first output frames take the silence before the input
+            buffer: vec![0.0; half * channels],
+            dropped: 0,
+            input_frames: 0,
+            output_frames: 0,
+            weights: vec![0.0; width],
+        }
+    }
+
+    /// Resample interleaved frames, the output frames are added once their input is known
+    pub fn process(&mut self, input: &[Sample], output: &mut Vec<Sample>) {
+        self.buffer.extend_from_slice(input);
+        self.input_frames += (input.len() / self.channels) as u64;
+        self.produce(output, u64::MAX);
+    }
+
+    /// Add the last frames, the input is followed by silence
+    pub fn flush(&mut self, output: &mut Vec<Sample>) {
+        let total = (self.input_frames * self.output_rate).div_ceil(self.input_rate);
+        self.buffer
+            .resize(self.buffer.len() + (self.width + 1) * self.channels, 0.0);
+        self.produce(output, total);
+    }
+
+    fn produce(&mut self, output: &mut Vec<Sample>, limit: u64) {
+        let frames = (self.buffer.len() / self.channels) as u64;
+        let half = (self.width / 2) as u64;
+        while self.output_frames < limit {
+            let time = self.output_frames as u128 * self.input_rate as u128;
+            let index = (time / self.output_rate as u128) as u64;
+            // The last weight is on the frame `index + half`
+            if index + 2 * half >= frames + self.dropped {
+                break;
+            }
+
+            let fraction = (time % self.output_rate as u128) as f64 / self.output_rate as f64;
+            let phase = fraction * PHASES as f64;
+            let (phase, position) = (phase as usize, (phase - phase.floor()) as f32);
+            let current = &self.kernel[phase * self.width..(phase + 1) * self.width];
+            let next = &self.kernel[(phase + 1) * self.width..(phase + 2) * self.width];
+            for ((weight, current), next) in self.weights.iter_mut().zip(current).zip(next) {
+                *weight = current + (next - current) * position;
+            }
+
+            let first = (index + 1 - self.dropped) as usize * self.channels;
+            let frames = &self.buffer[first..first + self.width * self.channels];
+            for channel in 0..self.channels {
+                let sample: f32 = frames[channel..]
+                    .iter()
+                    .step_by(self.channels)
+                    .zip(&self.weights)
+                    .map(|(sample, weight)| sample * weight)
+                    .sum();
+                output.push(sample);
+            }
+            self.output_frames += 1;
+        }
+
+        // Drop the frames before the first weight of the next output frame
+        let index = (self.output_frames as u128 * self.input_rate as u128
+            / self.output_rate as u128) as u64;
+        let unused = (index + 1 - self.dropped).min(frames);
+        self.buffer.drain(..unused as usize * self.channels);
+        self.dropped += unused;
+    }
+}
+
+#[inline]
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman window over -1 to 1
+#[inline]
+fn blackman(x: f64) -> f64 {
+    0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+}
@@ -0,0 +1,206 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+use super::dither::Dither;
+use super::map_channels;
+use super::resample::Resampler;
+use crate::decoder::{duration_to_frames, Decoder};
+use crate::encoder::{Encoder, EncoderFormat, EncoderSpec};
+use crate::info::{DecoderError, EncoderError, TranscodeError};
+
+/// Number of frames decoded at once, the progress is given after each of them
+const CHUNK_FRAMES: usize = 4096;
+
+/// Settings of a transcoding
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TranscodeConfig {
+    pub format: EncoderFormat,
+    /// Sample rate of the output, the one of the source if None.
+    pub sample_rate: Option<u32>,
+    /// Number of channels of the output, the one of the source if None.
+    pub channels: Option<usize>,
+    /// Add TPDF dither to the samples rounded to the integers of the format.
+    pub dither: bool,
+}
+
+impl TranscodeConfig {
+    /// Keep the sample rate and the channels of the source, with dither
+    #[inline]
+    pub fn new(format: EncoderFormat) -> Self {
+        Self {
+            format,
+            sample_rate: None,
+            channels: None,
+            dither: true,
+        }
+    }
+}
+
+/// Progress of a transcoding, in frames of the source
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Number of frames decoded so far.
+    pub frames: u64,
+    /// Number of frames of the source, if its duration is known.
+    pub total_frames: Option<u64>,
+}
+
+impl Progress {
+    /// Get the part of the source already transcoded, from 0.0 to 1.0
+    #[inline]
+    pub fn fraction(&self) -> Option<f64> {
+        self.total_frames
+            .filter(|&total| total > 0)
+            .map(|total| (self.frames as f64 / total as f64).min(1.0))
+    }
+}
+
+/// Decode a file and encode it in another format
+///
+/// The samples are resampled, then mapped on the channels of the output, then dithered before
+/// being rounded to the integers of the format. The tags and the pictures of the source are
+/// written in the output.
+///
+/// The callback gets the progress after each chunk of frames, the transcoding is cancelled if it
+/// returns false and the output is left incomplete.
+pub fn transcode<R, W, F>(
+    mut decoder: Decoder<R>,
+    writer: W,
+    config: &TranscodeConfig,
+    mut progress: F,
+) -> Result<W, TranscodeError>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+    F: FnMut(Progress) -> bool,
+{
+    let info = decoder.info();
+    let input_channels = info.channels();
+    let spec = EncoderSpec {
+        sample_rate: config.sample_rate.unwrap_or_else(|| info.sample_rate()),
+        channels: config.channels.unwrap_or(input_channels),
+        format: config.format,
+    };
+    if input_channels == 0 || info.sample_rate() == 0 {
+        return Err(TranscodeError::Decoder(DecoderError::FormatError(
+            "no audio stream".to_owned(),
+        )));
+    }
+    let mut encoder = Encoder::with_metadata(writer, spec, decoder.metadata())
+        .map_err(TranscodeError::Encoder)?;
+
+    let mut resampler = Some(Resampler::new(
+        info.sample_rate(),
+        spec.sample_rate,
+        input_channels,
+    ))
+    .filter(|_| spec.sample_rate != info.sample_rate());
+    // Past 24 bits the steps are under the precision of the samples
+    let mut dither = config
+        .format
+        .bits_per_sample()
+        .filter(|&bits| config.dither && bits <= 24)
+        .map(Dither::new);
+
+    let mut state = Progress {
+        frames: 0,
+        total_frames: info
+            .duration()
+            .map(|duration| duration_to_frames(duration, info.sample_rate())),
+    };
+    if !progress(state) {
+        return Err(TranscodeError::Cancelled);
+    }
+
+    let len = CHUNK_FRAMES * input_channels;
+    let mut input = Vec::with_capacity(len);
+    let mut resampled = Vec::new();
+    let mut output = Vec::new();
+    loop {
+        input.clear();
+        for sample in decoder.by_ref().take(len) {
+            input.push(sample.map_err(TranscodeError::Decoder)?);
+        }
+        let end = input.len() < len;
+        // A trailing incomplete frame is dropped
+        input.truncate(input.len() - input.len() % input_channels);
+
+        let samples = match &mut resampler {
+            Some(resampler) => {
+                resampled.clear();
+                resampler.process(&input, &mut resampled);
+                if end {
+                    resampler.flush(&mut resampled);
+                }
+                &resampled
+            }
+            None => &input,
+        };
+
+        output.clear();
+        for frame in samples.chunks(input_channels) {
+            let start = output.len();
+            output.resize(start + spec.channels, 0.0);
+            map_channels(frame, &mut output[start..]);
+        }
+        if let Some(dither) = &mut dither {
+            dither.apply(&mut output);
+        }
+        encoder.write(&output).map_err(TranscodeError::Encoder)?;
+
+        state.frames += (input.len() / input_channels) as u64;
+        if !progress(state) {
+            return Err(TranscodeError::Cancelled);
+        }
+        if end {
+            break;
+        }
+    }
+
+    encoder.finish().map_err(TranscodeError::Encoder)
+}
+
+/// Transcode a file into another one, like `transcode`
+///
+/// The output is removed if the transcoding fails or is cancelled
+pub fn transcode_file<P, Q, F>(
+    input: P,
+    output: Q,
+    config: &TranscodeConfig,
+    progress: F,
+) -> Result<(), TranscodeError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: FnMut(Progress) -> bool,
+{
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let file =
+        File::open(input).map_err(|err| TranscodeError::Decoder(DecoderError::IOError(err)))?;
+    // Creating the output would erase the source before it is read
+    if fs::canonicalize(output).ok() == Some(fs::canonicalize(input).unwrap_or_default()) {
+        return Err(TranscodeError::Encoder(EncoderError::Unsupported(
+            "the output is the source file".to_owned(),
+        )));
+    }
+    let decoder = Decoder::new(BufReader::new(file)).map_err(|_| {
+        TranscodeError::Decoder(DecoderError::FormatError(
+            "unsupported audio format".to_owned(),
+        ))
+    })?;
+
+    let writer = File::create(output)
+        .map(BufWriter::new)
+        .map_err(|err| TranscodeError::Encoder(EncoderError::IOError(err)))?;
+    let result = transcode(decoder, writer, config, progress).and_then(|writer| {
+        writer
+            .into_inner()
+            .map(drop)
+            .map_err(|err| TranscodeError::Encoder(EncoderError::IOError(err.into_error())))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(output);
+    }
+    result
+}
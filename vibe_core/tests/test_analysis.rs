@@ -0,0 +1,130 @@
+#[cfg(test)]
+
+mod tests_analysis {
+    use std::f64::consts::PI;
+    use std::{fs::File, io::BufReader};
+    use vibe_core::analysis::{self, LoudnessMeter, REFERENCE_LOUDNESS};
+    use vibe_core::decoder::Decoder;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// Stereo sine of the given level in dBFS, as the EBU test signals
+    fn sine(meter: &mut LoudnessMeter, frequency: f64, level: f64, seconds: f64) {
+        let amplitude = 10f64.powf(level / 20.0);
+        let frames = (seconds * SAMPLE_RATE as f64) as usize;
+        let samples: Vec<f32> = (0..frames)
+            .flat_map(|i| {
+                let value =
+                    amplitude * (2.0 * PI * frequency * i as f64 / SAMPLE_RATE as f64).sin();
+                vec![value as f32; 2]
+            })
+            .collect();
+        meter.push(&samples);
+    }
+
+    fn assert_close(value: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (value - expected).abs() <= tolerance,
+            "{} instead of {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+
+    fn test_analysis_integrated() {
+        // EBU Tech 3341, case 1
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        sine(&mut meter, 1000.0, -23.0, 20.0);
+        let loudness = meter.loudness();
+        assert_close(loudness.integrated, -23.0, 0.1);
+        assert_close(loudness.gain().unwrap(), REFERENCE_LOUDNESS + 23.0, 0.1);
+
+        // EBU Tech 3341, case 3, the quiet parts are under the relative gate
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        sine(&mut meter, 1000.0, -36.0, 10.0);
+        sine(&mut meter, 1000.0, -23.0, 60.0);
+        sine(&mut meter, 1000.0, -36.0, 10.0);
+        assert_close(meter.loudness().integrated, -23.0, 0.1);
+
+        // EBU Tech 3341, case 5, the parts under the absolute gate are ignored
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        sine(&mut meter, 1000.0, -26.0, 20.0);
+        sine(&mut meter, 1000.0, -20.0, 20.1);
+        sine(&mut meter, 1000.0, -26.0, 20.0);
+        assert_close(meter.loudness().integrated, -23.0, 0.1);
+
+        let mut meter = LoudnessMeter::new(44100, 2);
+        meter.push(&vec![0.0; 44100 * 2]);
+        let silence = meter.loudness();
+        assert_eq!(silence.integrated, f64::NEG_INFINITY);
+        assert_eq!(silence.gain(), None);
+    }
+
+    #[test]
+
+    fn test_analysis_range() {
+        // EBU Tech 3342, cases 1 and 2
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        sine(&mut meter, 1000.0, -20.0, 20.0);
+        sine(&mut meter, 1000.0, -30.0, 20.0);
+        assert_close(meter.loudness().range, 10.0, 1.0);
+
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        sine(&mut meter, 1000.0, -20.0, 20.0);
+        sine(&mut meter, 1000.0, -15.0, 20.0);
+        assert_close(meter.loudness().range, 5.0, 1.0);
+    }
+
+    #[test]
+
+    fn test_analysis_peaks() {
+        // A quarter of the sample rate shifted by 45° falls between the peaks
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 1);
+        let samples: Vec<f32> = (0..SAMPLE_RATE)
+            .map(|i| 0.5 * (PI / 2.0 * i as f64 + PI / 4.0).sin() as f32)
+            .collect();
+        meter.push(&samples);
+
+        let loudness = meter.loudness();
+        assert_close(loudness.sample_peak, 0.5 / 2f64.sqrt(), 1e-6);
+        assert_close(
+            20.0 * loudness.true_peak.log10(),
+            20.0 * 0.5f64.log10(),
+            0.2,
+        );
+    }
+
+    #[test]
+
+    fn test_analysis_album() {
+        let mut loud = LoudnessMeter::new(SAMPLE_RATE, 2);
+        sine(&mut loud, 1000.0, -20.0, 10.0);
+        let mut quiet = LoudnessMeter::new(SAMPLE_RATE, 2);
+        sine(&mut quiet, 1000.0, -26.0, 10.0);
+
+        let album = LoudnessMeter::album(&[loud.clone(), quiet.clone()]);
+        let expected = -20.0 + 10.0 * ((1.0 + 10f64.powf(-0.6)) / 2.0).log10();
+        assert_close(album.integrated, expected, 0.1);
+        assert_close(album.sample_peak, 0.1, 1e-3);
+
+        let values = analysis::replay_gain(&quiet.loudness(), Some(&album));
+        assert_close(values.track_gain.unwrap() as f64, 8.0, 0.1);
+        assert_close(values.album_gain.unwrap() as f64, -18.0 - expected, 0.1);
+        assert_eq!(values.album_peak, Some(album.sample_peak as f32));
+    }
+
+    #[test]
+
+    fn test_analysis_decoder() {
+        let file = File::open("tests/sounds/Test1.wav").unwrap();
+        let decoder = Decoder::new(BufReader::new(file)).unwrap();
+        let loudness = analysis::analyze(decoder).unwrap().loudness();
+
+        assert!(loudness.integrated.is_finite() && loudness.integrated < 0.0);
+        assert!(loudness.sample_peak > 0.0 && loudness.sample_peak <= 1.0);
+        assert!(loudness.true_peak >= loudness.sample_peak);
+        assert!(loudness.range >= 0.0);
+    }
+}
@@ -2,7 +2,7 @@
 
 mod tests_decoder {
     use std::{io::BufReader, time::Duration};
-    use vibe_core::decoder::Decoder;
+    use vibe_core::decoder::{duration_to_frames, Decoder};
 
     #[test]
 
@@ -16,7 +16,7 @@ mod tests_decoder {
         assert_eq!("MP3", format!("{}", info.format()));
         assert_eq!(info.sample_rate(), 44100); // Sample rate is just
         assert_eq!(info.channels(), 2); // Number of channels is just
-        assert_eq!(info.duration(), Some(Duration::from_millis(3042))); //Approximate duration of audio file
+        assert_eq!(info.duration(), Some(Duration::from_millis(3030))); // 116 frames of 1152 samples after the Xing frame
     }
 
     #[test]
@@ -58,6 +58,42 @@ mod tests_decoder {
         assert_eq!("OGG", format!("{}", info.format()));
         assert_eq!(info.sample_rate(), 44100); // Sample rate is just
         assert_eq!(info.channels(), 2); // Number of channels is just
-        assert_eq!(info.duration(), Some(Duration::from_millis(2963))); //Approximate duration of audio file
+        assert_eq!(info.duration(), Some(Duration::from_millis(3000))); // Granule position of the last page: 132301 samples
+    }
+
+    /// Check that seeking gives the same samples as decoding from the beginning
+    fn check_seek(path: &str) {
+        let file = std::fs::File::open(path).unwrap();
+        let decoder = Decoder::new(BufReader::new(file)).unwrap();
+        let info = decoder.info();
+        let samples: Vec<f32> = decoder.map(|s| s.unwrap()).collect();
+
+        let file = std::fs::File::open(path).unwrap();
+        let mut decoder = Decoder::new(BufReader::new(file)).unwrap();
+        // Back and forth, near the start and the end of the stream
+        for &pos in &[1500, 100, 2900, 0, 2000, 1990] {
+            decoder.seek(Duration::from_millis(pos)).unwrap();
+
+            let offset = duration_to_frames(Duration::from_millis(pos), info.sample_rate())
+                as usize
+                * info.channels();
+            let len = 1024.min(samples.len() - offset);
+            let seeked: Vec<f32> = decoder.by_ref().take(len).map(|s| s.unwrap()).collect();
+            assert_eq!(
+                &samples[offset..offset + len],
+                seeked.as_slice(),
+                "{} at {}",
+                path,
+                pos
+            );
+        }
+    }
+
+    #[test]
+    fn test_seeking() {
+        check_seek("tests/sounds/Test1.wav");
+        check_seek("tests/sounds/Test1.flac");
+        check_seek("tests/sounds/Test1.ogg");
+        check_seek("tests/sounds/Test1.mp3");
     }
 }
@@ -0,0 +1,632 @@
+#[cfg(test)]
+
+mod tests_encoder {
+    use std::io::Cursor;
+    use std::time::Duration;
+    use symphonia::core::audio::SampleBuffer;
+    use symphonia::core::codecs::{CodecParameters, DecoderOptions};
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+    use vibe_core::decoder::Decoder;
+    use vibe_core::encoder::{
+        Encoder, EncoderFormat, EncoderSpec, FlacConfig, Md5, Mp3Bitrate, VorbisConfig,
+        WavSampleFormat,
+    };
+    use vibe_core::{AudioInfo, EncoderError, Metadata, Sample};
+
+    /// Frames of the MP3 encoder before the first samples of the file
+    const MP3_DELAY: usize = 1057;
+
+    /// Decode a file, giving its information and its samples
+    fn decode(data: Vec<u8>) -> (AudioInfo, Vec<Sample>) {
+        let decoder = Decoder::new(Cursor::new(data)).expect("Decoding error");
+        let info = decoder.info();
+        let samples = decoder.map(|sample| sample.unwrap()).collect();
+        (info, samples)
+    }
+
+    fn encode(
+        samples: &[Sample],
+        sample_rate: u32,
+        channels: usize,
+        format: EncoderFormat,
+    ) -> Vec<u8> {
+        let spec = EncoderSpec {
+            sample_rate,
+            channels,
+            format,
+        };
+        let mut encoder = Encoder::new(Cursor::new(Vec::new()), spec).unwrap();
+        // The frames may be split across the writes
+        for chunk in samples.chunks(1001) {
+            encoder.write(chunk).unwrap();
+        }
+        assert_eq!(encoder.frames(), (samples.len() / channels) as u64);
+        encoder.finish().unwrap().into_inner()
+    }
+
+    fn test_file() -> (AudioInfo, Vec<Sample>) {
+        decode(std::fs::read("tests/sounds/Test1.wav").unwrap())
+    }
+
+    /// Integer samples of 16 bits on each channel, a chord with some noise
+    fn signal(frames: usize, channels: usize) -> Vec<Sample> {
+        let mut noise = 1u32;
+        (0..frames * channels)
+            .map(|index| {
+                let (frame, channel) = ((index / channels) as f32, (index % channels) as f32);
+                noise = noise.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                let value = 8000.0 * (frame * 0.01 * (channel + 1.0)).sin()
+                    + 4000.0 * (frame * 0.037).sin()
+                    + (noise >> 24) as f32;
+                value.round() / i16::MAX as f32
+            })
+            .collect()
+    }
+
+    fn assert_close(values: &[Sample], expected: &[Sample], precision: f32) {
+        assert_eq!(values.len(), expected.len());
+        for (value, expected) in values.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() <= precision,
+                "{} instead of {}",
+                value,
+                expected
+            );
+        }
+    }
+
+    /// Signal to noise ratio in dB of the decoded samples of a lossy format
+    fn snr(decoded: &[Sample], samples: &[Sample]) -> f64 {
+        let (signal, noise) =
+            samples
+                .iter()
+                .zip(decoded)
+                .fold((0.0, 0.0), |(signal, noise), (&x, &y)| {
+                    let (x, y) = (x as f64, y as f64);
+                    (signal + x * x, noise + (x - y) * (x - y))
+                });
+        10.0 * (signal / noise).log10()
+    }
+
+    /// Get the bitrate of each frame of an MP3 file without tags, the frames must be valid
+    /// and fill the file
+    fn mp3_bitrates(data: &[u8], sample_rate: u32) -> Vec<u32> {
+        const BITRATES: [u32; 14] = [
+            32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ];
+        let rate_index = match sample_rate {
+            44100 => 0,
+            48000 => 1,
+            _ => 2,
+        };
+
+        let mut bitrates = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let header = &data[offset..offset + 4];
+            // Sync word, MPEG-1 Layer III without CRC
+            assert_eq!((header[0], header[1]), (0xff, 0xfb), "frame at {}", offset);
+            let index = (header[2] >> 4) as usize;
+            assert!(
+                (1..=14).contains(&index),
+                "bitrate of the frame at {}",
+                offset
+            );
+            assert_eq!((header[2] >> 2) & 0x03, rate_index);
+            let bitrate = BITRATES[index - 1];
+            let padding = ((header[2] >> 1) & 0x01) as usize;
+            offset += (144_000 * bitrate / sample_rate) as usize + padding;
+            bitrates.push(bitrate);
+        }
+        assert_eq!(offset, data.len());
+        bitrates
+    }
+
+    #[test]
+
+    fn test_encoder_wav() {
+        let (info, samples) = test_file();
+
+        for &format in &[
+            WavSampleFormat::Pcm16,
+            WavSampleFormat::Pcm24,
+            WavSampleFormat::Pcm32,
+            WavSampleFormat::Float32,
+        ] {
+            let data = encode(&samples, 48000, 2, EncoderFormat::Wav(format));
+            let (decoded_info, decoded) = decode(data);
+
+            assert_eq!("WAV", format!("{}", decoded_info.format()));
+            assert_eq!(decoded_info.sample_rate(), info.sample_rate());
+            assert_eq!(decoded_info.channels(), info.channels());
+            assert_eq!(decoded_info.duration(), info.duration());
+            match format {
+                // The samples of the file are 16-bit integers
+                WavSampleFormat::Pcm16 | WavSampleFormat::Float32 => assert_eq!(decoded, samples),
+                _ => assert_close(&decoded, &samples, 1e-6),
+            }
+        }
+
+        // More than two channels
+        let samples = signal(1000, 6);
+        let (info, decoded) = decode(encode(
+            &samples,
+            96000,
+            6,
+            EncoderFormat::Wav(WavSampleFormat::Pcm24),
+        ));
+        assert_eq!(info.sample_rate(), 96000);
+        assert_eq!(info.channels(), 6);
+        assert_close(&decoded, &samples, 1e-6);
+    }
+
+    #[test]
+
+    fn test_encoder_flac() {
+        let (info, samples) = test_file();
+        let wav = std::fs::read("tests/sounds/Test1.wav").unwrap();
+
+        let mut sizes = Vec::new();
+        for &level in &[0, 3, 5, 8] {
+            let config = FlacConfig {
+                bits_per_sample: 16,
+                compression_level: level,
+            };
+            let data = encode(&samples, 48000, 2, EncoderFormat::Flac(config));
+            sizes.push(data.len());
+            let (decoded_info, decoded) = decode(data);
+
+            assert_eq!("FLAC", format!("{}", decoded_info.format()));
+            assert_eq!(decoded_info.sample_rate(), info.sample_rate());
+            assert_eq!(decoded_info.channels(), info.channels());
+            assert_eq!(decoded_info.duration(), info.duration());
+            // The compression is lossless
+            assert_eq!(decoded, samples);
+        }
+
+        // The higher levels compress better
+        assert!(sizes[0] < wav.len());
+        assert!(sizes.windows(2).all(|sizes| sizes[1] <= sizes[0]));
+
+        let config = FlacConfig {
+            bits_per_sample: 24,
+            ..FlacConfig::default()
+        };
+        let (_, decoded) = decode(encode(&samples, 48000, 2, EncoderFormat::Flac(config)));
+        assert_close(&decoded, &samples, 1e-6);
+    }
+
+    /// Get the MD5 signature of the samples stored in the STREAMINFO block of a FLAC file
+    fn flac_md5(data: Vec<u8>) -> [u8; 16] {
+        claxon::FlacReader::new(Cursor::new(data))
+            .unwrap()
+            .streaminfo()
+            .md5sum
+    }
+
+    #[test]
+
+    fn test_encoder_md5() {
+        // Test suite of RFC 1321
+        let vectors = [
+            ("", "d41d8cd98f00b204e9800998ecf8427e"),
+            ("a", "0cc175b9c0f1b6a831c399e269772661"),
+            ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+            ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+            (
+                "abcdefghijklmnopqrstuvwxyz",
+                "c3fcd3d76192e4007dfb496cca67e13b",
+            ),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "d174ab98d277d9f5a5611c2c9f419d9f",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "57edf4a22be3c955ac49da2e2107b67a",
+            ),
+        ];
+        for (message, expected) in vectors.iter() {
+            let mut md5 = Md5::new();
+            md5.update(message.as_bytes());
+            let digest: String = md5
+                .finish()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            assert_eq!(&digest, expected, "MD5 of {:?}", message);
+
+            // The data may be split across the updates
+            let mut md5 = Md5::new();
+            for chunk in message.as_bytes().chunks(7) {
+                md5.update(chunk);
+            }
+            let split: String = md5
+                .finish()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            assert_eq!(split, digest);
+        }
+
+        // The signature of the samples is the one of the reference encoder
+        let reference = std::fs::read("tests/sounds/Test1.flac").unwrap();
+        let (info, samples) = decode(reference.clone());
+        let config = FlacConfig {
+            bits_per_sample: 24,
+            ..FlacConfig::default()
+        };
+        let encoded = encode(
+            &samples,
+            info.sample_rate(),
+            info.channels(),
+            EncoderFormat::Flac(config),
+        );
+        assert_ne!(flac_md5(reference.clone()), [0; 16]);
+        assert_eq!(flac_md5(encoded), flac_md5(reference));
+
+        // On 16 bits the signature is the one of the PCM data of the WAV file
+        let wav = std::fs::read("tests/sounds/Test1.wav").unwrap();
+        let pcm = hound::WavReader::new(Cursor::new(wav))
+            .unwrap()
+            .into_samples::<i16>()
+            .flat_map(|sample| sample.unwrap().to_le_bytes().to_vec())
+            .collect::<Vec<u8>>();
+        let mut md5 = Md5::new();
+        md5.update(&pcm);
+        let (_, samples) = test_file();
+        let encoded = encode(
+            &samples,
+            48000,
+            2,
+            EncoderFormat::Flac(FlacConfig::default()),
+        );
+        assert_eq!(flac_md5(encoded), md5.finish());
+    }
+
+    #[test]
+
+    fn test_encoder_flac_channels() {
+        // Mono, several channels and blocks shorter than the block size of the level
+        for &(channels, frames) in &[(1, 10000), (6, 5000), (2, 100), (3, 1)] {
+            for &level in &[1, 5, 8] {
+                let samples = signal(frames, channels);
+                let config = FlacConfig {
+                    compression_level: level,
+                    ..FlacConfig::default()
+                };
+                let data = encode(&samples, 44100, channels, EncoderFormat::Flac(config));
+                let (info, decoded) = decode(data);
+
+                assert_eq!(info.sample_rate(), 44100);
+                assert_eq!(info.channels(), channels);
+                assert_eq!(decoded, samples);
+            }
+        }
+
+        // Silence and full scale samples
+        let mut samples = vec![0.0; 8192];
+        samples.extend((0..8192).map(|index| if index % 3 == 0 { 1.0 } else { -1.0 }));
+        let data = encode(
+            &samples,
+            44100,
+            2,
+            EncoderFormat::Flac(FlacConfig::default()),
+        );
+        let (_, decoded) = decode(data);
+        assert_close(&decoded, &samples, 1e-4);
+    }
+
+    #[test]
+
+    fn test_encoder_vorbis() {
+        let (info, samples) = test_file();
+
+        let mut sizes = Vec::new();
+        for &quality in &[0.0, 0.5, 1.0] {
+            let data = encode(
+                &samples,
+                48000,
+                2,
+                EncoderFormat::Vorbis(VorbisConfig { quality }),
+            );
+            sizes.push(data.len());
+            let (decoded_info, decoded) = decode(data);
+
+            assert_eq!("OGG", format!("{}", decoded_info.format()));
+            assert_eq!(decoded_info.sample_rate(), info.sample_rate());
+            assert_eq!(decoded_info.channels(), info.channels());
+            // The decoded samples are aligned with the ones of the file
+            assert_eq!(decoded.len(), samples.len());
+            assert!(snr(&decoded, &samples) > 20.0 + 20.0 * quality as f64);
+        }
+
+        // The higher qualities take more space
+        assert!(sizes.windows(2).all(|sizes| sizes[1] > sizes[0]));
+
+        // Mono, silence and files shorter than a block
+        for &(channels, frames) in &[(1, 20000), (3, 5000), (2, 100), (1, 1)] {
+            let samples = signal(frames, channels);
+            let format = EncoderFormat::Vorbis(VorbisConfig::default());
+            let (info, decoded) = decode(encode(&samples, 44100, channels, format));
+
+            assert_eq!(info.sample_rate(), 44100);
+            assert_eq!(info.channels(), channels);
+            assert_eq!(decoded.len(), samples.len());
+        }
+        let samples = vec![0.0; 10000];
+        let format = EncoderFormat::Vorbis(VorbisConfig::default());
+        let (_, decoded) = decode(encode(&samples, 44100, 2, format));
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+
+    fn test_encoder_mp3() {
+        let (info, samples) = test_file();
+        let seconds = samples.len() as f64 / 2.0 / 48000.0;
+
+        // Lowest signal to noise ratio of each bitrate, the variable one keeps the noise
+        // 42 - 3 * quality dB under the signal
+        let mut sizes = Vec::new();
+        for &(bitrate, min_snr) in &[
+            (Mp3Bitrate::Constant(96), 50.0),
+            (Mp3Bitrate::Constant(192), 55.0),
+            (Mp3Bitrate::Variable(4), 28.0),
+            (Mp3Bitrate::Variable(0), 40.0),
+        ] {
+            let data = encode(&samples, 48000, 2, EncoderFormat::Mp3(bitrate));
+            let bitrates = mp3_bitrates(&data, 48000);
+            sizes.push(data.len());
+            let (decoded_info, decoded) = decode(data);
+
+            assert_eq!("MP3", format!("{}", decoded_info.format()));
+            assert_eq!(decoded_info.sample_rate(), info.sample_rate());
+            assert_eq!(decoded_info.channels(), info.channels());
+            // The frames after the Info frame are padded to give every sample after the delay
+            let frames = (bitrates.len() - 1) * 1152;
+            assert_eq!(decoded.len(), frames * 2);
+            assert!(frames >= samples.len() / 2 + MP3_DELAY);
+            assert!(frames < samples.len() / 2 + MP3_DELAY + 1152);
+            let duration = decoded_info.duration().unwrap().as_secs_f64();
+            assert!((duration - frames as f64 / 48000.0).abs() < 0.001);
+
+            let delay = MP3_DELAY * 2;
+            let ratio = snr(&decoded[delay..delay + samples.len()], &samples);
+            assert!(ratio > min_snr, "{:?}: {} dB", bitrate, ratio);
+
+            match bitrate {
+                // The constant bitrates give the size of the file
+                Mp3Bitrate::Constant(kbps) => {
+                    assert!(bitrates.iter().all(|&bitrate| bitrate == kbps));
+                    let size = *sizes.last().unwrap() as f64;
+                    let bitrate = size * 8.0 / seconds / 1000.0;
+                    let kbps = kbps as f64;
+                    assert!((bitrate - kbps).abs() < kbps * 0.05, "{} kbps", bitrate);
+                }
+                // The variable bitrate follows the signal
+                Mp3Bitrate::Variable(_) => {
+                    let mut used = bitrates[1..].to_vec();
+                    used.sort_unstable();
+                    used.dedup();
+                    assert!(used.len() > 1, "{:?}", used);
+                }
+            }
+        }
+        assert!(sizes[1] > sizes[0]);
+        assert!(sizes[3] > sizes[2]);
+
+        // Mono and the other sample rates
+        for &(sample_rate, channels) in &[(44100, 1), (32000, 2)] {
+            let samples = signal(10000, channels);
+            let format = EncoderFormat::Mp3(Mp3Bitrate::default());
+            let data = encode(&samples, sample_rate, channels, format);
+            let frames = (mp3_bitrates(&data, sample_rate).len() - 1) * 1152;
+            let (info, decoded) = decode(data);
+
+            assert_eq!(info.sample_rate(), sample_rate);
+            assert_eq!(info.channels(), channels);
+            assert_eq!(decoded.len(), frames * channels);
+            let duration = info.duration().unwrap().as_secs_f64();
+            assert!((duration - frames as f64 / sample_rate as f64).abs() < 0.001);
+            let delay = MP3_DELAY * channels;
+            let ratio = snr(&decoded[delay..delay + samples.len()], &samples);
+            assert!(ratio > 40.0, "{} Hz: {} dB", sample_rate, ratio);
+        }
+    }
+
+    #[test]
+
+    fn test_encoder_mp3_info() {
+        let samples = signal(10000, 2);
+        for &(bitrate, id) in &[
+            (Mp3Bitrate::Constant(128), b"Info"),
+            (Mp3Bitrate::Variable(4), b"Xing"),
+        ] {
+            let data = encode(&samples, 44100, 2, EncoderFormat::Mp3(bitrate));
+            let field = |offset: usize| {
+                u32::from_be_bytes([
+                    data[offset],
+                    data[offset + 1],
+                    data[offset + 2],
+                    data[offset + 3],
+                ])
+            };
+
+            // Header, side information, then the Xing header with all its fields
+            assert_eq!(&data[36..40], id);
+            assert_eq!(field(40), 0x0f);
+            let frames = field(44) as usize;
+            assert_eq!(field(48) as usize, data.len());
+            let toc = &data[52..152];
+            assert!(toc.windows(2).all(|pair| pair[0] <= pair[1]));
+
+            // The LAME extension gives the delay and the padding around the samples
+            let lame = &data[156..192];
+            assert_eq!(&lame[..9], b"LAME3.100");
+            let gapless = field(156 + 20) & 0xff_ffff;
+            let (delay, padding) = ((gapless >> 12) as usize, (gapless & 0xfff) as usize);
+            assert_eq!(delay + 529, MP3_DELAY);
+            assert_eq!(frames * 1152 - delay - padding, 10000);
+            assert_eq!(field(156 + 28) as usize, data.len());
+            let crc = data[..190].iter().fold(0u16, |mut crc, &byte| {
+                crc ^= byte as u16;
+                for _ in 0..8 {
+                    crc = if crc & 1 != 0 {
+                        (crc >> 1) ^ 0xa001
+                    } else {
+                        crc >> 1
+                    };
+                }
+                crc
+            });
+            assert_eq!(u16::from_be_bytes([data[190], data[191]]), crc);
+
+            // The Info frame holds no audio
+            let (info, decoded) = decode(data);
+            assert_eq!(decoded.len(), frames * 1152 * 2);
+            assert_eq!(
+                info.duration(),
+                Some(Duration::from_millis(frames as u64 * 1152 * 1000 / 44100))
+            );
+        }
+    }
+
+    /// Decode a file with symphonia, which trims the delay and the padding of the LAME extension
+    fn decode_gapless(data: Vec<u8>) -> (CodecParameters, Vec<Sample>) {
+        let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let options = FormatOptions {
+            enable_gapless: true,
+            ..Default::default()
+        };
+        let mut reader = symphonia::default::get_probe()
+            .format(&Hint::new(), source, &options, &MetadataOptions::default())
+            .unwrap()
+            .format;
+        let params = reader.default_track().unwrap().codec_params.clone();
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&params, &DecoderOptions::default())
+            .unwrap();
+        let mut samples = Vec::new();
+        while let Ok(packet) = reader.next_packet() {
+            let decoded = decoder.decode(&packet).unwrap();
+            let mut buffer =
+                SampleBuffer::<Sample>::new(decoded.capacity() as u64, *decoded.spec());
+            buffer.copy_interleaved_ref(decoded);
+            samples.extend_from_slice(buffer.samples());
+        }
+        (params, samples)
+    }
+
+    #[test]
+
+    fn test_encoder_mp3_gapless() {
+        for &(sample_rate, channels) in &[(44100, 2), (48000, 1)] {
+            for &bitrate in &[Mp3Bitrate::Constant(192), Mp3Bitrate::Variable(2)] {
+                let samples = signal(10000, channels);
+                let data = encode(&samples, sample_rate, channels, EncoderFormat::Mp3(bitrate));
+                let (params, decoded) = decode_gapless(data);
+
+                assert_eq!(params.delay, Some(MP3_DELAY as u32));
+                assert_eq!(params.n_frames, Some(10000));
+                assert_eq!(decoded.len(), samples.len());
+                let ratio = snr(&decoded, &samples);
+                assert!(ratio > 30.0, "{:?}: {} dB", bitrate, ratio);
+            }
+        }
+    }
+
+    #[test]
+
+    fn test_encoder_metadata() {
+        let mut metadata = Metadata::new();
+        metadata.push("TITLE", "Tïtle");
+        metadata.push("ARTIST", "Artist");
+        metadata.push("ALBUM", "Album");
+
+        let samples = signal(5000, 2);
+        for &format in &[
+            EncoderFormat::Wav(WavSampleFormat::Pcm16),
+            EncoderFormat::Flac(FlacConfig::default()),
+            EncoderFormat::Vorbis(VorbisConfig::default()),
+            EncoderFormat::Mp3(Mp3Bitrate::default()),
+        ] {
+            let spec = EncoderSpec {
+                sample_rate: 44100,
+                channels: 2,
+                format,
+            };
+            let mut encoder =
+                Encoder::with_metadata(Cursor::new(Vec::new()), spec, &metadata).unwrap();
+            encoder.write(&samples).unwrap();
+            let data = encoder.finish().unwrap().into_inner();
+
+            let decoder = Decoder::new(Cursor::new(data)).expect("Decoding error");
+            let written = decoder.metadata();
+            assert_eq!(written.title(), Some("Tïtle"));
+            assert_eq!(written.artist(), Some("Artist"));
+            assert_eq!(written.album(), Some("Album"));
+            // The samples follow the tags
+            assert!(decoder.count() >= samples.len());
+        }
+    }
+
+    #[test]
+
+    fn test_encoder_errors() {
+        let spec = |channels, format| EncoderSpec {
+            sample_rate: 44100,
+            channels,
+            format,
+        };
+        let wav = EncoderFormat::Wav(WavSampleFormat::Pcm16);
+        let flac = |bits_per_sample, compression_level| {
+            EncoderFormat::Flac(FlacConfig {
+                bits_per_sample,
+                compression_level,
+            })
+        };
+
+        for spec in &[
+            spec(0, wav),
+            spec(0, flac(16, 5)),
+            spec(9, flac(16, 5)),
+            spec(2, flac(20, 5)),
+            spec(2, flac(16, 9)),
+            spec(0, EncoderFormat::Vorbis(VorbisConfig::default())),
+            spec(2, EncoderFormat::Vorbis(VorbisConfig { quality: 1.5 })),
+            spec(3, EncoderFormat::Mp3(Mp3Bitrate::default())),
+            spec(2, EncoderFormat::Mp3(Mp3Bitrate::Constant(100))),
+            spec(2, EncoderFormat::Mp3(Mp3Bitrate::Variable(10))),
+            EncoderSpec {
+                sample_rate: 22050,
+                channels: 2,
+                format: EncoderFormat::Mp3(Mp3Bitrate::default()),
+            },
+        ] {
+            assert!(matches!(
+                Encoder::new(Cursor::new(Vec::new()), *spec),
+                Err(EncoderError::Unsupported(_))
+            ));
+        }
+
+        // The last frame must be complete
+        for &format in &[
+            wav,
+            flac(16, 5),
+            EncoderFormat::Vorbis(VorbisConfig::default()),
+            EncoderFormat::Mp3(Mp3Bitrate::default()),
+        ] {
+            let mut encoder = Encoder::new(Cursor::new(Vec::new()), spec(2, format)).unwrap();
+            encoder.write(&[0.0, 0.5, 1.0]).unwrap();
+            assert_eq!(encoder.frames(), 1);
+            assert!(matches!(
+                encoder.finish(),
+                Err(EncoderError::IncompleteData)
+            ));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+#[cfg(test)]
+
+mod tests_library {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use vibe_core::library::{is_audio_file, Library, LibraryEntry};
+
+    /// Create a music directory with nested albums and files that aren't tracks
+    fn music_dir(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("vibe_library_{}_{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("B/CD1")).unwrap();
+        fs::create_dir_all(root.join("A")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        for file in &[
+            "b.mp3",
+            "a.FLAC",
+            "cover.jpg",
+            ".a.wav",
+            "A/1.ogg",
+            "B/CD1/1.wav",
+            "B/2.wav",
+        ] {
+            fs::write(root.join(file), b"").unwrap();
+        }
+        root
+    }
+
+    #[test]
+
+    fn test_library_list() {
+        let root = music_dir("list");
+        let library = Library::new(&root);
+        assert_eq!(library.root(), root.as_path());
+
+        assert_eq!(
+            library.list("").unwrap(),
+            vec![
+                LibraryEntry::Directory(PathBuf::from("A")),
+                LibraryEntry::Directory(PathBuf::from("B")),
+                LibraryEntry::Track(PathBuf::from("a.FLAC")),
+                LibraryEntry::Track(PathBuf::from("b.mp3")),
+            ]
+        );
+        assert_eq!(
+            library.list("B").unwrap()[0].path(),
+            Path::new("B").join("CD1")
+        );
+        assert_eq!(
+            library.tracks("").unwrap(),
+            vec![
+                PathBuf::from("A/1.ogg"),
+                PathBuf::from("B/CD1/1.wav"),
+                PathBuf::from("B/2.wav"),
+                PathBuf::from("a.FLAC"),
+                PathBuf::from("b.mp3"),
+            ]
+        );
+        assert!(library.list("missing").is_err());
+
+        assert_eq!(
+            library.find_songs("B").unwrap(),
+            vec![root.join("B/CD1/1.wav"), root.join("B/2.wav")]
+        );
+        assert_eq!(
+            library.find_songs("b.mp3").unwrap(),
+            vec![root.join("b.mp3")]
+        );
+        assert_eq!(library.find_song("A/1.ogg").unwrap(), root.join("A/1.ogg"));
+        assert!(library.find_song("A").is_err());
+        assert!(library.find_songs("cover.jpg").is_err());
+        assert!(library.find_songs("missing.wav").is_err());
+        assert!(library.find_songs("../b.mp3").is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+
+    fn test_library_links() {
+        let root = music_dir("links");
+        // A link to a parent directory makes a loop, a link to an album lists it twice
+        std::os::unix::fs::symlink(&root, root.join("B/CD1/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("A"), root.join("B/A")).unwrap();
+        let library = Library::new(&root);
+
+        assert_eq!(
+            library.list("B/CD1").unwrap()[0],
+            LibraryEntry::Directory(PathBuf::from("B/CD1/loop"))
+        );
+        assert_eq!(
+            library.tracks("").unwrap(),
+            vec![
+                PathBuf::from("A/1.ogg"),
+                PathBuf::from("B/CD1/1.wav"),
+                PathBuf::from("B/2.wav"),
+                PathBuf::from("a.FLAC"),
+                PathBuf::from("b.mp3"),
+            ]
+        );
+        assert_eq!(
+            library.tracks("B").unwrap(),
+            vec![
+                PathBuf::from("B/A/1.ogg"),
+                PathBuf::from("B/CD1/loop/a.FLAC"),
+                PathBuf::from("B/CD1/loop/b.mp3"),
+                PathBuf::from("B/CD1/1.wav"),
+                PathBuf::from("B/2.wav"),
+            ]
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+
+    fn test_library_resolve() {
+        let library = Library::new("/music");
+        assert_eq!(
+            library.resolve("./A/1.ogg").unwrap(),
+            PathBuf::from("/music/A/1.ogg")
+        );
+        assert!(library.resolve("../etc").is_err());
+        assert!(library.resolve("A/../../etc").is_err());
+        assert!(library.resolve("/etc").is_err());
+        assert!(library.list("..").is_err());
+
+        assert!(is_audio_file("song.Wav"));
+        assert!(is_audio_file("dir/song.oga"));
+        assert!(!is_audio_file("cover.png"));
+        assert!(!is_audio_file("flac"));
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+
+mod tests_loops {
+    use std::io::Cursor;
+    use std::time::Duration;
+    use vibe_core::decoder::{duration_to_frames, frames_to_duration, Decoder};
+    use vibe_core::LoopRegion;
+
+    /// Build a `smpl` chunk with the given loops, their end is the last frame played
+    fn smpl_chunk(loops: &[(u32, u32)]) -> Vec<u8> {
+        let mut data = vec![0u8; 28];
+        data.extend_from_slice(&(loops.len() as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        for (index, (start, end)) in loops.iter().enumerate() {
+            data.extend_from_slice(&(index as u32).to_le_bytes());
+            data.extend_from_slice(&[0; 4]);
+            data.extend_from_slice(&start.to_le_bytes());
+            data.extend_from_slice(&end.to_le_bytes());
+            data.extend_from_slice(&[0; 8]);
+        }
+
+        let mut chunk = b"smpl".to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend(data);
+        chunk
+    }
+
+    #[test]
+
+    fn test_loops_region() {
+        assert_eq!(LoopRegion::new(10, 10), None);
+        let region = LoopRegion::new(44100, 88200).unwrap();
+        assert_eq!(region.frames(), 44100);
+        assert_eq!(region.start_time(44100), Duration::from_secs(1));
+        assert_eq!(
+            LoopRegion::from_duration(Duration::from_secs(1), Duration::from_secs(2), 44100),
+            Some(region)
+        );
+
+        // Every frame converts back to itself, even when its position isn't a whole number of nanoseconds
+        for &rate in [44100, 48000, 22050, 96000].iter() {
+            for frames in (0..200_000).step_by(997) {
+                let duration = frames_to_duration(frames, rate);
+                assert_eq!(duration_to_frames(duration, rate), frames);
+            }
+        }
+    }
+
+    #[test]
+
+    fn test_loops_smpl() {
+        let mut wav = std::fs::read("tests/sounds/Test1.wav").expect("File not found");
+        let decoder = Decoder::new(Cursor::new(wav.clone())).expect("Decoding error");
+        assert!(decoder.loops().is_empty());
+
+        // The empty loop is ignored
+        let chunk = smpl_chunk(&[(1000, 4999), (20, 10)]);
+        let riff_len = u32::from_le_bytes([wav[4], wav[5], wav[6], wav[7]]) + chunk.len() as u32;
+        wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+        wav.splice(36..36, chunk);
+
+        let decoder = Decoder::new(Cursor::new(wav)).expect("Decoding error");
+        assert_eq!(decoder.loops(), &[LoopRegion::new(1000, 5000).unwrap()]);
+        assert_eq!(decoder.info().channels(), 2);
+    }
+}
@@ -0,0 +1,241 @@
+#[cfg(test)]
+
+mod tests_metadata {
+    use std::io::Cursor;
+    use vibe_core::{decoder::Decoder, Metadata, ReplayGain};
+
+    /// Offset of the ReplayGain fields of the LAME header in the MP3 test file
+    const LAME_REPLAY_GAIN: usize = 0xa7;
+
+    fn read(path: &str) -> Vec<u8> {
+        std::fs::read(path).expect("File not found")
+    }
+
+    fn read_metadata(bytes: Vec<u8>) -> Metadata {
+        let decoder = Decoder::new(Cursor::new(bytes)).expect("Decoding error");
+        decoder.metadata().clone()
+    }
+
+    fn syncsafe(size: usize) -> [u8; 4] {
+        [
+            (size >> 21) as u8 & 0x7f,
+            (size >> 14) as u8 & 0x7f,
+            (size >> 7) as u8 & 0x7f,
+            size as u8 & 0x7f,
+        ]
+    }
+
+    /// Build an ID3v2 tag, frame sizes are syncsafe in ID3v2.4 only
+    fn id3_tag(major: u8, frames: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (id, data) in frames {
+            body.extend_from_slice(id.as_bytes());
+            if major == 4 {
+                body.extend_from_slice(&syncsafe(data.len()));
+            } else {
+                body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            }
+            body.extend_from_slice(&[0, 0]);
+            body.extend_from_slice(data);
+        }
+        // Padding
+        body.extend_from_slice(&[0; 16]);
+
+        let mut tag = vec![b'I', b'D', b'3', major, 0, 0];
+        tag.extend_from_slice(&syncsafe(body.len()));
+        tag.extend(body);
+        tag
+    }
+
+    /// Text in UTF-16 with a byte order mark, without terminator
+    fn utf16(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xfe];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn txxx(encoding: u8, description: &[u8], value: &[u8]) -> Vec<u8> {
+        let terminator: &[u8] = if encoding == 1 { &[0, 0] } else { &[0] };
+        [&[encoding], description, terminator, value].concat()
+    }
+
+    #[test]
+
+    fn test_metadata_tags() {
+        let mut metadata = Metadata::new();
+        metadata.push("artist", "First");
+        metadata.push("ARTIST", "Second");
+        metadata.push("Title", "Song");
+        assert_eq!(metadata.len(), 3);
+        assert_eq!(metadata.artist(), Some("First"));
+        assert_eq!(
+            metadata.get_all("Artist").collect::<Vec<_>>(),
+            vec!["First", "Second"]
+        );
+        assert_eq!(metadata.title(), Some("Song"));
+        assert_eq!(metadata.album(), None);
+
+        metadata.set("artist", "Third");
+        assert_eq!(
+            metadata.get_all("ARTIST").collect::<Vec<_>>(),
+            vec!["Third"]
+        );
+        metadata.remove("title");
+        assert_eq!(metadata.title(), None);
+    }
+
+    #[test]
+
+    fn test_metadata_replay_gain() {
+        let mut metadata = Metadata::new();
+        assert!(metadata.replay_gain().is_empty());
+
+        metadata.push("replaygain_track_gain", "-6.48 dB");
+        metadata.push("REPLAYGAIN_TRACK_PEAK", "0.988553");
+        metadata.push("REPLAYGAIN_ALBUM_GAIN", "+1.5dB");
+        metadata.push("REPLAYGAIN_ALBUM_PEAK", "not a number");
+        let replay_gain = metadata.replay_gain();
+        assert_eq!(
+            replay_gain,
+            ReplayGain {
+                track_gain: Some(-6.48),
+                track_peak: Some(0.988553),
+                album_gain: Some(1.5),
+                album_peak: None,
+            }
+        );
+
+        // The values written back are read the same
+        let mut written = Metadata::new();
+        replay_gain.write_tags(&mut written);
+        assert_eq!(written.get("REPLAYGAIN_TRACK_GAIN"), Some("-6.48 dB"));
+        assert_eq!(written.replay_gain(), replay_gain);
+    }
+
+    #[test]
+
+    fn test_metadata_id3() {
+        let tag = id3_tag(
+            4,
+            &[
+                ("TIT2", [&[3u8][..], "Tïtle".as_bytes()].concat()),
+                ("TPE1", [&[0u8][..], b"One\0Two"].concat()),
+                (
+                    "TXXX",
+                    txxx(1, &utf16("replaygain_track_gain"), &utf16("-7.25 dB")),
+                ),
+                ("TXXX", txxx(0, b"REPLAYGAIN_TRACK_PEAK", b"0.750000")),
+            ],
+        );
+        let metadata = read_metadata([tag, read("tests/sounds/Test1.mp3")].concat());
+
+        assert_eq!(metadata.title(), Some("Tïtle"));
+        assert_eq!(
+            metadata.get_all("ARTIST").collect::<Vec<_>>(),
+            vec!["One", "Two"]
+        );
+        let replay_gain = metadata.replay_gain();
+        assert_eq!(replay_gain.track_gain, Some(-7.25));
+        assert_eq!(replay_gain.track_peak, Some(0.75));
+        assert_eq!(replay_gain.album_gain, None);
+
+        // ID3v2.3 sizes are plain integers
+        let tag = id3_tag(
+            3,
+            &[
+                ("TALB", [&[0u8][..], b"Album"].concat()),
+                ("TXXX", txxx(0, b"REPLAYGAIN_ALBUM_GAIN", b"2.00 dB")),
+            ],
+        );
+        let metadata = read_metadata([tag, read("tests/sounds/Test1.mp3")].concat());
+        assert_eq!(metadata.album(), Some("Album"));
+        assert_eq!(metadata.replay_gain().album_gain, Some(2.0));
+    }
+
+    #[test]
+
+    fn test_metadata_lame() {
+        let mut mp3 = read("tests/sounds/Test1.mp3");
+        assert!(read_metadata(mp3.clone()).replay_gain().is_empty());
+
+        // Peak of 0.5, radio gain of -5.0 dB set automatically, audiophile gain of +1.2 dB set by the user
+        let fields = &mut mp3[LAME_REPLAY_GAIN..LAME_REPLAY_GAIN + 8];
+        fields[..4].copy_from_slice(&(1u32 << 22).to_be_bytes());
+        fields[4..6].copy_from_slice(&(1u16 << 13 | 3 << 10 | 1 << 9 | 50).to_be_bytes());
+        fields[6..].copy_from_slice(&(2u16 << 13 | 2 << 10 | 12).to_be_bytes());
+        assert_eq!(
+            read_metadata(mp3.clone()).replay_gain(),
+            ReplayGain {
+                track_gain: Some(-5.0),
+                track_peak: Some(0.5),
+                album_gain: Some(1.2),
+                album_peak: None,
+            }
+        );
+
+        // The tags take precedence over the LAME header
+        let tag = id3_tag(3, &[("TXXX", txxx(0, b"REPLAYGAIN_TRACK_GAIN", b"-3 dB"))]);
+        let replay_gain = read_metadata([tag, mp3].concat()).replay_gain();
+        assert_eq!(replay_gain.track_gain, Some(-3.0));
+        assert_eq!(replay_gain.track_peak, Some(0.5));
+    }
+
+    #[test]
+
+    fn test_metadata_vorbis_comments() {
+        let metadata = read_metadata(read("tests/sounds/Test1.flac"));
+        assert!(metadata.get("ENCODER").is_some());
+
+        // Replace the comment block following STREAMINFO
+        let mut flac = read("tests/sounds/Test1.flac");
+        let start = 4 + 4 + 34;
+        assert_eq!(flac[start] & 0x7f, 4);
+        let len = u32::from_be_bytes([0, flac[start + 1], flac[start + 2], flac[start + 3]]);
+
+        let mut comments = Vec::new();
+        comments.extend_from_slice(&4u32.to_le_bytes());
+        comments.extend_from_slice(b"vibe");
+        let tags = [
+            "TITLE=Flac",
+            "REPLAYGAIN_ALBUM_GAIN=-4.10 dB",
+            "REPLAYGAIN_ALBUM_PEAK=1.05",
+        ];
+        comments.extend_from_slice(&(tags.len() as u32).to_le_bytes());
+        for tag in tags.iter() {
+            comments.extend_from_slice(&(tag.len() as u32).to_le_bytes());
+            comments.extend_from_slice(tag.as_bytes());
+        }
+        let mut block = vec![flac[start]];
+        block.extend_from_slice(&(comments.len() as u32).to_be_bytes()[1..]);
+        block.extend(comments);
+        flac.splice(start..start + 4 + len as usize, block);
+
+        let metadata = read_metadata(flac);
+        assert_eq!(metadata.title(), Some("Flac"));
+        assert_eq!(metadata.replay_gain().album_gain, Some(-4.1));
+        assert_eq!(metadata.replay_gain().album_peak, Some(1.05));
+    }
+
+    #[test]
+
+    fn test_metadata_wav() {
+        let mut wav = read("tests/sounds/Test1.wav");
+        assert!(read_metadata(wav.clone()).is_empty());
+
+        // ID3 chunk between the format and the data chunks, of even size as hound ignores padding
+        let tag = id3_tag(3, &[("TIT2", [&[0u8][..], b"Waves"].concat())]);
+        assert_eq!(tag.len() % 2, 0);
+        let mut chunk = b"id3 ".to_vec();
+        chunk.extend_from_slice(&(tag.len() as u32).to_le_bytes());
+        chunk.extend(tag);
+        let riff_len = u32::from_le_bytes([wav[4], wav[5], wav[6], wav[7]]) + chunk.len() as u32;
+        wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+        wav.splice(36..36, chunk);
+
+        let decoder = Decoder::new(Cursor::new(wav)).expect("Decoding error");
+        assert_eq!(decoder.metadata().title(), Some("Waves"));
+        assert_eq!(decoder.info().channels(), 2);
+    }
+}
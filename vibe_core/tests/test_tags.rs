@@ -0,0 +1,154 @@
+#[cfg(test)]
+
+mod tests_tags {
+    use std::path::PathBuf;
+    use std::{fs, fs::File, io::BufReader};
+    use vibe_core::{analysis, decoder::Decoder, tags, Metadata, Picture, ReplayGain, TagError};
+
+    const REPLAY_GAIN: ReplayGain = ReplayGain {
+        track_gain: Some(-6.5),
+        track_peak: Some(0.875),
+        album_gain: Some(-7.25),
+        album_peak: Some(0.96875),
+    };
+
+    /// Copy a test file to the temporary directory so it can be rewritten
+    fn copy(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("vibe_tags_{}", name));
+        fs::copy(format!("tests/sounds/{}", name), &path).expect("File not found");
+        path
+    }
+
+    fn decode(path: &PathBuf) -> (Metadata, Vec<f32>) {
+        let file = File::open(path).expect("File not found");
+        let decoder = Decoder::new(BufReader::new(file)).expect("Decoding error");
+        let metadata = decoder.metadata().clone();
+        let samples = decoder.map(|sample| sample.unwrap()).collect();
+        (metadata, samples)
+    }
+
+    /// Write the ReplayGain values and check they are read back without changing the audio
+    fn round_trip(name: &str) -> Metadata {
+        let path = copy(name);
+        let (before, samples) = decode(&path);
+
+        analysis::write_replay_gain(&path, &REPLAY_GAIN).unwrap();
+        let (after, written) = decode(&path);
+        assert_eq!(after.replay_gain(), REPLAY_GAIN);
+        assert_eq!(tags::read_metadata(&path).unwrap(), after);
+        assert_eq!(written, samples);
+        for (key, value) in before.iter() {
+            assert!(after.get_all(key).any(|other| other == value));
+        }
+
+        // Writing again replaces the values
+        let mut metadata = after.clone();
+        metadata.set("REPLAYGAIN_TRACK_GAIN", "+1.00 dB");
+        metadata.set("TITLE", "Tïtle");
+        tags::write_metadata(&path, &metadata).unwrap();
+        let (after, written) = decode(&path);
+        assert_eq!(after.replay_gain().track_gain, Some(1.0));
+        assert_eq!(after.title(), Some("Tïtle"));
+        assert_eq!(written, samples);
+
+        fs::remove_file(path).unwrap();
+        after
+    }
+
+    #[test]
+
+    fn test_tags_flac() {
+        let metadata = round_trip("Test1.flac");
+        assert!(metadata.get("ENCODER").is_some());
+    }
+
+    #[test]
+
+    fn test_tags_ogg() {
+        round_trip("Test1.ogg");
+    }
+
+    #[test]
+
+    fn test_tags_wav() {
+        round_trip("Test1.wav");
+    }
+
+    #[test]
+
+    fn test_tags_mp3() {
+        round_trip("Test1.mp3");
+    }
+
+    #[test]
+
+    fn test_tags_pictures() {
+        let cover = Picture {
+            picture_type: Picture::FRONT_COVER,
+            mime_type: "image/png".to_owned(),
+            description: "Cövers".to_owned(),
+            data: (0..=255).cycle().take(3000).collect(),
+        };
+        let back = Picture {
+            picture_type: 4,
+            mime_type: "image/jpeg".to_owned(),
+            description: String::new(),
+            data: vec![0xff, 0xd8, 0xff, 0xe0, 0, 0, 0xff, 0xd9],
+        };
+
+        for name in &["Test1.flac", "Test1.ogg", "Test1.wav", "Test1.mp3"] {
+            let path = copy(name);
+            let (before, samples) = decode(&path);
+
+            let mut metadata = before.clone();
+            metadata.push_picture(back.clone());
+            metadata.push_picture(cover.clone());
+            tags::write_metadata(&path, &metadata).unwrap();
+            let (after, written) = decode(&path);
+            assert_eq!(
+                after.pictures(),
+                &[back.clone(), cover.clone()][..],
+                "{}",
+                name
+            );
+            assert_eq!(after.cover(), Some(&cover));
+            assert_eq!(tags::read_metadata(&path).unwrap(), after);
+            assert_eq!(written, samples);
+
+            // The pictures are kept when the metadata has none
+            let mut metadata = before.clone();
+            metadata.set("TITLE", "Tïtle");
+            tags::write_metadata(&path, &metadata).unwrap();
+            let after = tags::read_metadata(&path).unwrap();
+            assert_eq!(after.title(), Some("Tïtle"));
+            assert_eq!(after.pictures().len(), 2);
+
+            // And replaced by the ones of the metadata
+            metadata.push_picture(back.clone());
+            tags::write_metadata(&path, &metadata).unwrap();
+            let (after, written) = decode(&path);
+            assert_eq!(after.pictures(), &[back.clone()][..]);
+            assert_eq!(after.cover(), Some(&back));
+            assert_eq!(written, samples);
+
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+
+    fn test_tags_errors() {
+        let path = std::env::temp_dir().join("vibe_tags_unknown");
+        fs::write(&path, b"not an audio file").unwrap();
+        assert!(matches!(
+            tags::read_metadata(&path),
+            Err(TagError::Unsupported(_))
+        ));
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            tags::read_metadata("tests/sounds/missing.flac"),
+            Err(TagError::IOError(_))
+        ));
+    }
+}
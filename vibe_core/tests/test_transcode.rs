@@ -0,0 +1,300 @@
+#[cfg(test)]
+
+mod tests_transcode {
+    use std::f64::consts::PI;
+    use std::fs;
+    use std::io::Cursor;
+    use vibe_core::decoder::Decoder;
+    use vibe_core::encoder::{
+        Encoder, EncoderFormat, EncoderSpec, FlacConfig, Mp3Bitrate, VorbisConfig, WavSampleFormat,
+    };
+    use vibe_core::transcode::{
+        map_channels, transcode, transcode_file, Progress, TranscodeConfig,
+    };
+    use vibe_core::{AudioInfo, Metadata, Picture, Sample, TranscodeError};
+
+    const WAV_FLOAT: EncoderFormat = EncoderFormat::Wav(WavSampleFormat::Float32);
+
+    fn decoder(data: Vec<u8>) -> Decoder<Cursor<Vec<u8>>> {
+        Decoder::new(Cursor::new(data)).expect("Decoding error")
+    }
+
+    fn decode(data: Vec<u8>) -> (AudioInfo, Vec<Sample>) {
+        let decoder = decoder(data);
+        let info = decoder.info();
+        (info, decoder.map(|sample| sample.unwrap()).collect())
+    }
+
+    fn encode(
+        samples: &[Sample],
+        sample_rate: u32,
+        channels: usize,
+        metadata: &Metadata,
+    ) -> Vec<u8> {
+        let spec = EncoderSpec {
+            sample_rate,
+            channels,
+            format: WAV_FLOAT,
+        };
+        let mut encoder = Encoder::with_metadata(Cursor::new(Vec::new()), spec, metadata).unwrap();
+        encoder.write(samples).unwrap();
+        encoder.finish().unwrap().into_inner()
+    }
+
+    fn run(data: Vec<u8>, config: &TranscodeConfig) -> Vec<u8> {
+        transcode(decoder(data), Cursor::new(Vec::new()), config, |_| true)
+            .unwrap()
+            .into_inner()
+    }
+
+    /// Sine of the given frequency on each channel
+    fn sine(frequency: f64, sample_rate: u32, frames: usize, channels: usize) -> Vec<Sample> {
+        (0..frames * channels)
+            .map(|index| {
+                let time = (index / channels) as f64 / sample_rate as f64;
+                0.5 * (2.0 * PI * frequency * time).sin() as Sample
+            })
+            .collect()
+    }
+
+    #[test]
+
+    fn test_transcode_resample() {
+        for &(from, to) in &[
+            (44100, 48000),
+            (48000, 44100),
+            (96000, 32000),
+            (8000, 48000),
+        ] {
+            let frames = from as usize;
+            let data = encode(&sine(1000.0, from, frames, 2), from, 2, &Metadata::new());
+            let config = TranscodeConfig {
+                sample_rate: Some(to),
+                ..TranscodeConfig::new(WAV_FLOAT)
+            };
+            let (info, decoded) = decode(run(data, &config));
+
+            assert_eq!(info.sample_rate(), to);
+            assert_eq!(info.channels(), 2);
+            assert_eq!(decoded.len(), to as usize * 2);
+
+            // Away from the edges, the sine is the same at the new rate
+            let expected = sine(1000.0, to, to as usize, 2);
+            let range = to as usize / 10..to as usize * 19 / 10;
+            let noise: f32 = range
+                .clone()
+                .map(|index| (decoded[index] - expected[index]).powi(2))
+                .sum();
+            let signal: f32 = range.map(|index| expected[index].powi(2)).sum();
+            let snr = 10.0 * (signal / noise).log10();
+            assert!(snr > 60.0, "{} Hz to {} Hz: {} dB", from, to, snr);
+        }
+
+        // The frequencies over the Nyquist frequency of the output are removed
+        let data = encode(&sine(20000.0, 48000, 48000, 1), 48000, 1, &Metadata::new());
+        let config = TranscodeConfig {
+            sample_rate: Some(32000),
+            ..TranscodeConfig::new(WAV_FLOAT)
+        };
+        let (_, decoded) = decode(run(data, &config));
+        let peak = decoded[3200..28800]
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!(peak < 0.001, "{}", peak);
+    }
+
+    #[test]
+
+    fn test_transcode_channels() {
+        let stereo: Vec<Sample> = (0..2000)
+            .map(|index| if index % 2 == 0 { 0.5 } else { -0.25 })
+            .collect();
+        let data = encode(&stereo, 44100, 2, &Metadata::new());
+
+        let config = |channels| TranscodeConfig {
+            channels: Some(channels),
+            ..TranscodeConfig::new(WAV_FLOAT)
+        };
+        let (info, mono) = decode(run(data.clone(), &config(1)));
+        assert_eq!(info.channels(), 1);
+        assert_eq!(mono, vec![0.125; 1000]);
+
+        let (info, surround) = decode(run(data, &config(4)));
+        assert_eq!(info.channels(), 4);
+        assert_eq!(surround[..4], [0.5, -0.25, 0.0, 0.0]);
+
+        let (info, stereo) = decode(run(encode(&mono, 44100, 1, &Metadata::new()), &config(2)));
+        assert_eq!(info.channels(), 2);
+        assert_eq!(stereo, vec![0.125; 2000]);
+    }
+
+    #[test]
+
+    fn test_transcode_downmix() {
+        let close = |output: &[Sample], expected: &[Sample]| {
+            output
+                .iter()
+                .zip(expected)
+                .all(|(output, expected)| (output - expected).abs() < 1e-6)
+        };
+        let gain = std::f32::consts::FRAC_1_SQRT_2;
+        let norm = 1.0 + 2.0 * gain;
+
+        // 5.1 in the WAVE order: L R C LFE BL BR
+        let mut stereo = [0.0; 2];
+        map_channels(&[0.0, 0.0, 0.0, 0.0, 0.5, 0.0], &mut stereo);
+        assert!(close(&stereo, &[0.5 * gain / norm, 0.0]), "{:?}", stereo);
+        map_channels(&[0.0, 0.0, 0.6, 1.0, 0.0, 0.0], &mut stereo);
+        assert!(close(&stereo, &[0.6 * gain / norm; 2]), "{:?}", stereo);
+        map_channels(&[1.0; 6], &mut stereo);
+        assert!(close(&stereo, &[1.0; 2]), "{:?}", stereo);
+
+        let mut mono = [0.0];
+        map_channels(&[0.4, 0.2, 0.0, 0.0, 0.0, 0.0], &mut mono);
+        assert!(close(&mono, &[0.3 / norm]), "{:?}", mono);
+
+        // Quadraphonic: L R BL BR
+        map_channels(&[0.0, 0.2, 0.4, 0.0], &mut stereo);
+        let norm = 1.0 + gain;
+        assert!(
+            close(&stereo, &[0.4 * gain / norm, 0.2 / norm]),
+            "{:?}",
+            stereo
+        );
+    }
+
+    #[test]
+
+    fn test_transcode_dither() {
+        let flac = EncoderFormat::Flac(FlacConfig::default());
+
+        // The samples of a 16-bit source are copied untouched
+        let wav = fs::read("tests/sounds/Test1.wav").unwrap();
+        let (_, samples) = decode(wav.clone());
+        let (_, decoded) = decode(run(wav, &TranscodeConfig::new(flac)));
+        assert_eq!(decoded, samples);
+
+        // The others are rounded with less than two steps of error, without bias
+        let samples: Vec<Sample> = (0..20000)
+            .map(|index| 0.001 * (index as f32 * 0.01).sin())
+            .collect();
+        let data = encode(&samples, 44100, 1, &Metadata::new());
+        let step = 1.0 / i16::MAX as f32;
+        for &dither in &[true, false] {
+            let config = TranscodeConfig {
+                dither,
+                ..TranscodeConfig::new(flac)
+            };
+            let (_, decoded) = decode(run(data.clone(), &config));
+            let errors: Vec<f32> = decoded
+                .iter()
+                .zip(&samples)
+                .map(|(decoded, sample)| (decoded - sample) / step)
+                .collect();
+            let mean = errors.iter().sum::<f32>() / errors.len() as f32;
+            let power = errors.iter().map(|error| error * error).sum::<f32>() / errors.len() as f32;
+
+            assert!(mean.abs() < 0.05);
+            if dither {
+                assert!(errors.iter().all(|error| error.abs() < 1.5));
+                // Rounding error and twice the variance of an uniform noise
+                assert!((power - 0.25).abs() < 0.05, "{}", power);
+            } else {
+                assert!(errors.iter().all(|error| error.abs() <= 0.5));
+                assert!((power - 1.0 / 12.0).abs() < 0.02, "{}", power);
+            }
+        }
+    }
+
+    #[test]
+
+    fn test_transcode_metadata() {
+        let mut metadata = Metadata::new();
+        metadata.push("TITLE", "Tïtle");
+        metadata.push("ARTIST", "Artist");
+        metadata.push_picture(Picture {
+            picture_type: Picture::FRONT_COVER,
+            mime_type: "image/png".to_owned(),
+            description: "Cover".to_owned(),
+            data: (0..=255).collect(),
+        });
+        let data = encode(&sine(440.0, 44100, 20000, 2), 44100, 2, &metadata);
+
+        for &format in &[
+            EncoderFormat::Wav(WavSampleFormat::Pcm16),
+            EncoderFormat::Flac(FlacConfig::default()),
+            EncoderFormat::Vorbis(VorbisConfig::default()),
+            EncoderFormat::Mp3(Mp3Bitrate::default()),
+        ] {
+            let config = TranscodeConfig {
+                sample_rate: Some(48000),
+                channels: Some(1),
+                ..TranscodeConfig::new(format)
+            };
+            let output = decoder(run(data.clone(), &config));
+            assert_eq!(output.info().sample_rate(), 48000);
+            assert_eq!(output.info().channels(), 1);
+            assert_eq!(output.metadata(), &metadata);
+        }
+    }
+
+    #[test]
+
+    fn test_transcode_progress() {
+        let data = encode(&sine(440.0, 44100, 30000, 2), 44100, 2, &Metadata::new());
+
+        let mut calls: Vec<Progress> = Vec::new();
+        let config = TranscodeConfig::new(EncoderFormat::Flac(FlacConfig::default()));
+        transcode(
+            decoder(data.clone()),
+            Cursor::new(Vec::new()),
+            &config,
+            |progress| {
+                calls.push(progress);
+                true
+            },
+        )
+        .unwrap();
+        assert_eq!(calls[0].frames, 0);
+        assert_eq!(calls.last().unwrap().frames, 30000);
+        assert_eq!(calls.last().unwrap().fraction(), Some(1.0));
+        assert!(calls
+            .windows(2)
+            .all(|calls| calls[0].frames < calls[1].frames));
+        // The duration of the file is rounded down to the millisecond
+        let total = calls[0].total_frames.unwrap();
+        assert!((30000 - 44..=30000).contains(&total));
+        assert!(calls
+            .iter()
+            .all(|progress| progress.total_frames == Some(total)));
+
+        // Cancelled after the first chunk
+        let result = transcode(
+            decoder(data.clone()),
+            Cursor::new(Vec::new()),
+            &config,
+            |progress| progress.frames == 0,
+        );
+        assert!(matches!(result, Err(TranscodeError::Cancelled)));
+
+        // The output file is removed
+        let input = std::env::temp_dir().join("vibe_transcode_input.wav");
+        let output = std::env::temp_dir().join("vibe_transcode_output.flac");
+        fs::write(&input, data).unwrap();
+        let result = transcode_file(&input, &output, &config, |progress| progress.frames == 0);
+        assert!(matches!(result, Err(TranscodeError::Cancelled)));
+        assert!(!output.exists());
+
+        transcode_file(&input, &output, &config, |_| true).unwrap();
+        let (info, _) = decode(fs::read(&output).unwrap());
+        assert_eq!(info.sample_rate(), 44100);
+
+        // The source is never overwritten
+        let result = transcode_file(&input, &input, &config, |_| true);
+        assert!(matches!(result, Err(TranscodeError::Encoder(_))));
+        assert!(input.exists());
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+}
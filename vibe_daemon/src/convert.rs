@@ -0,0 +1,93 @@
+use vibe_client::{Event, Repeat, Shuffle, State, Status};
+use vibe_engine::player::{Player, PlayerEvent, PlayerState, PlayerStatus};
+use vibe_engine::queue::{RepeatMode, ShuffleMode};
+
+/// Get the state sent to the clients
+pub fn state(state: PlayerState) -> State {
+    match state {
+        PlayerState::Empty => State::Empty,
+        PlayerState::Loading => State::Loading,
+        PlayerState::Playing => State::Playing,
+        PlayerState::Paused => State::Paused,
+        PlayerState::Stopped => State::Stopped,
+        PlayerState::Ended => State::Ended,
+        PlayerState::Error => State::Error,
+    }
+}
+
+/// Get the repeat mode sent to the clients
+pub fn repeat(repeat: RepeatMode) -> Repeat {
+    match repeat {
+        RepeatMode::Off => Repeat::Off,
+        RepeatMode::One => Repeat::One,
+        RepeatMode::All => Repeat::All,
+    }
+}
+
+/// Get the shuffle mode sent to the clients
+pub fn shuffle(shuffle: ShuffleMode) -> Shuffle {
+    match shuffle {
+        ShuffleMode::Off => Shuffle::Off,
+        ShuffleMode::NoRepeat => Shuffle::NoRepeat,
+        ShuffleMode::Random => Shuffle::Random,
+    }
+}
+
+/// Get the event sent to the clients
+pub fn event(event: &PlayerEvent) -> Event {
+    match event {
+        PlayerEvent::StateChanged(value) => Event::State {
+            state: state(*value),
+        },
+        PlayerEvent::Position(position) => Event::Position {
+            position: position.as_secs_f64(),
+        },
+        PlayerEvent::TrackChanged { index, track } => Event::TrackChanged {
+            index: *index,
+            path: track.path().to_owned(),
+        },
+        PlayerEvent::TrackEnded { index, track } => Event::TrackEnded {
+            index: *index,
+            path: track.path().to_owned(),
+        },
+        PlayerEvent::QueueEnded => Event::QueueEnded,
+        PlayerEvent::QueueChanged => Event::QueueChanged,
+        PlayerEvent::RepeatChanged(value) => Event::Repeat {
+            repeat: repeat(*value),
+        },
+        PlayerEvent::ShuffleChanged(value) => Event::Shuffle {
+            shuffle: shuffle(*value),
+        },
+        PlayerEvent::VolumeChanged(volume) => Event::Volume {
+            volume: *volume as f64,
+        },
+        PlayerEvent::Error(message) => Event::Error {
+            message: message.clone(),
+        },
+    }
+}
+
+/// Get the status sent to the clients
+///
+/// The position is read from the player, the one of the events lags behind
+pub fn status(status: &PlayerStatus, player: &Player) -> Status {
+    let metadata = status.metadata();
+    let position = if status.state().is_active() {
+        player.position()
+    } else {
+        status.position()
+    };
+    Status {
+        state: state(status.state()),
+        index: status.index(),
+        path: status.track().map(|track| track.path().to_owned()),
+        title: status.title(),
+        artist: metadata.artist().map(str::to_owned),
+        album: metadata.album().map(str::to_owned),
+        duration: status.duration().map(|duration| duration.as_secs_f64()),
+        position: position.as_secs_f64(),
+        queue_length: player.queue().len(),
+        repeat: repeat(player.repeat_mode()),
+        shuffle: shuffle(player.shuffle_mode()),
+    }
+}
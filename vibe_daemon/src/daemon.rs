@@ -0,0 +1,295 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::{bounded, select, Receiver, Select, TryRecvError};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use vibe_client::protocol::{
+    Notification, Request, Response, RpcError, EVENT_METHOD, INVALID_PARAMS, INVALID_REQUEST,
+    JSONRPC_VERSION, METHOD_NOT_FOUND, PARSE_ERROR, PLAYER_ERROR,
+};
+use vibe_engine::player::{Player, PlayerEvent, PlayerStatus};
+
+use crate::convert;
+
+/// Writing half of a connection, shared with the thread sending the events
+type Writer = Arc<Mutex<UnixStream>>;
+
+#[derive(Deserialize)]
+struct PlayParams {
+    #[serde(default)]
+    index: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SeekParams {
+    /// Position in seconds
+    position: f64,
+}
+
+#[derive(Deserialize)]
+struct EnqueueParams {
+    path: PathBuf,
+}
+
+/// Status of the player and the events not applied to it yet
+struct Tracker {
+    status: PlayerStatus,
+    events: Receiver<PlayerEvent>,
+}
+
+impl Tracker {
+    /// Apply the pending events, returns false once the player is gone
+    fn update(&mut self) -> bool {
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => self.status.handle_event(&event),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+}
+
+/// Player shared by the clients of the daemon
+#[derive(Clone)]
+pub struct Daemon {
+    player: Player,
+    tracker: Arc<Mutex<Tracker>>,
+}
+
+impl Daemon {
+    /// Serve a player, its status is kept up to date on a thread of its own
+    pub fn new(player: Player) -> Self {
+        let events = player.events();
+        let tracker = Arc::new(Mutex::new(Tracker {
+            status: PlayerStatus::new(&player),
+            events: events.clone(),
+        }));
+
+        // The events are only taken with the lock, the requests see the ones sent before them
+        let shared = Arc::clone(&tracker);
+        thread::spawn(move || loop {
+            let mut select = Select::new();
+            select.recv(&events);
+            select.ready();
+            if !shared.lock().unwrap().update() {
+                break;
+            }
+        });
+
+        Self { player, tracker }
+    }
+
+    #[inline]
+    /// Get the player served
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    #[inline]
+    /// Get the status of the player, with the events sent until now
+    pub fn status(&self) -> PlayerStatus {
+        let mut tracker = self.tracker.lock().unwrap();
+        tracker.update();
+        tracker.status.clone()
+    }
+
+    /// Accept the clients of a socket, each one on its own thread
+    pub fn serve(&self, listener: UnixListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let daemon = self.clone();
+            thread::spawn(move || {
+                // A client leaving without closing the connection is not an error of the daemon
+                let _ = daemon.serve_client(stream);
+            });
+        }
+        Ok(())
+    }
+
+    /// Answer the requests of a client until it closes the connection
+    fn serve_client(&self, stream: UnixStream) -> io::Result<()> {
+        let writer: Writer = Arc::new(Mutex::new(stream.try_clone()?));
+        // Dropped when the client leaves, which stops the events
+        let (_closed, closed) = bounded::<()>(0);
+        // The events are forwarded once, whatever the number of subscriptions
+        let mut subscribed = false;
+
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let request = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => serde_json::from_value::<Request>(message)
+                    .map_err(|err| RpcError::new(INVALID_REQUEST, err.to_string())),
+                Err(err) => Err(RpcError::new(PARSE_ERROR, err.to_string())),
+            };
+            let request = match request {
+                Ok(request) => request,
+                Err(error) => {
+                    send(&writer, &Response::failure(Value::Null, error))?;
+                    continue;
+                }
+            };
+
+            // The events are received from before the response, and sent after it
+            let (result, events) = if request.method == "subscribe" {
+                let events = if subscribed {
+                    None
+                } else {
+                    subscribed = true;
+                    Some(self.player.events())
+                };
+                (Ok(Value::Null), events)
+            } else {
+                (self.handle(&request.method, &request.params), None)
+            };
+            if let Some(id) = request.id {
+                let response = match result {
+                    Ok(result) => Response::success(id, result),
+                    Err(error) => Response::failure(id, error),
+                };
+                send(&writer, &response)?;
+            }
+            if let Some(events) = events {
+                let writer = Arc::clone(&writer);
+                let closed = closed.clone();
+                thread::spawn(move || forward_events(events, closed, writer));
+            }
+        }
+        Ok(())
+    }
+
+    /// Call a method on the player, returns its result
+    ///
+    /// `subscribe` depends on the connection and is answered by the connection
+    pub fn handle(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        let player = &self.player;
+        match method {
+            "play" => {
+                let params: PlayParams = parse_params(params)?;
+                match params.index {
+                    Some(index) if !player.play_index(index) => {
+                        return Err(RpcError::new(
+                            PLAYER_ERROR,
+                            format!("no track at index {}", index),
+                        ))
+                    }
+                    Some(_) => {}
+                    None => player.play_stream(),
+                }
+            }
+            "pause" => player.pause_stream(),
+            "stop" => player.stop_stream(),
+            "next" => player.next_track(),
+            "previous" => player.previous_track(),
+            "seek" => {
+                let params: SeekParams = parse_params(params)?;
+                if !params.position.is_finite() || params.position < 0.0 {
+                    return Err(RpcError::new(INVALID_PARAMS, "invalid position"));
+                }
+                if !player.state().is_active() {
+                    return Err(RpcError::new(PLAYER_ERROR, "no track is being played"));
+                }
+                player.seek_stream(Duration::from_secs_f64(params.position));
+            }
+            "enqueue" => {
+                let params: EnqueueParams = parse_params(params)?;
+                // The daemon doesn't share the working directory of its clients
+                if !params.path.is_absolute() {
+                    return Err(RpcError::new(INVALID_PARAMS, "the path must be absolute"));
+                }
+                if !params.path.is_file() {
+                    return Err(RpcError::new(
+                        PLAYER_ERROR,
+                        format!("{} is not a file", params.path.display()),
+                    ));
+                }
+                // Other clients may change the queue in the meantime, the track is found by its id
+                let id = player.enqueue(params.path);
+                return match player.queue().index_of(id) {
+                    Some(index) => Ok(json!(index)),
+                    None => Err(RpcError::new(
+                        PLAYER_ERROR,
+                        "the track was removed from the queue",
+                    )),
+                };
+            }
+            "clear" => player.clear_queue(),
+            "status" => {
+                let status = convert::status(&self.status(), player);
+                return Ok(serde_json::to_value(status).unwrap_or_default());
+            }
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("unknown method {}", method),
+                ))
+            }
+        }
+        Ok(Value::Null)
+    }
+}
+
+/// Listen on a socket, replacing the socket of a daemon that is gone
+pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<UnixListener> {
+    let path = path.as_ref();
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another daemon is listening on the socket",
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+}
+
+/// Read the parameters of a method, missing parameters are an empty object
+fn parse_params<T: DeserializeOwned>(params: &Value) -> Result<T, RpcError> {
+    let params = if params.is_null() {
+        json!({})
+    } else {
+        params.clone()
+    };
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+/// Write a message on a line
+fn send<T: serde::Serialize>(writer: &Writer, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.lock().unwrap().write_all(&line)
+}
+
+/// Send the events of the player to a client, until it leaves
+fn forward_events(events: Receiver<PlayerEvent>, closed: Receiver<()>, writer: Writer) {
+    loop {
+        select! {
+            recv(events) -> event => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(_) => return,
+                };
+                let notification = Notification {
+                    jsonrpc: JSONRPC_VERSION.to_owned(),
+                    method: EVENT_METHOD.to_owned(),
+                    params: serde_json::to_value(convert::event(&event)).unwrap_or_default(),
+                };
+                if send(&writer, &notification).is_err() {
+                    return;
+                }
+            }
+            recv(closed) -> _ => return,
+        }
+    }
+}
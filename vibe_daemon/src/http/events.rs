@@ -0,0 +1,70 @@
+use std::io;
+use std::time::Duration;
+
+use crossbeam::channel::{select, Receiver};
+use serde_json::json;
+use tiny_http::{Header, Request, Response};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+use vibe_engine::player::{Player, PlayerEvent};
+
+use super::json_response;
+use crate::convert;
+
+/// Interval of the pings finding the clients that left while the player is idle
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Send the events of the player over a WebSocket, until the client leaves
+///
+/// The events are the JSON objects of the `event` notifications of the daemon, the messages of
+/// the client are not read
+pub(super) fn stream(player: &Player, request: Request) -> io::Result<()> {
+    let key = header(&request, "Sec-WebSocket-Key");
+    let upgrade = header(&request, "Upgrade");
+    let key = match (key, upgrade) {
+        (Some(key), Some(upgrade)) if upgrade.eq_ignore_ascii_case("websocket") => key,
+        _ => {
+            let error = json!({ "error": "expected a WebSocket handshake" });
+            return request.respond(json_response(400, &error));
+        }
+    };
+
+    // The events are received from before the handshake
+    let events = player.events();
+    let accept =
+        Header::from_bytes("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes())).unwrap();
+    let stream = request.upgrade("websocket", Response::empty(101).with_header(accept));
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    forward_events(&mut socket, events);
+    Ok(())
+}
+
+fn forward_events<S: io::Read + io::Write>(
+    socket: &mut WebSocket<S>,
+    events: Receiver<PlayerEvent>,
+) {
+    loop {
+        let message = select! {
+            recv(events) -> event => match event {
+                Ok(event) => {
+                    let event = serde_json::to_string(&convert::event(&event)).unwrap_or_default();
+                    Message::Text(event)
+                }
+                Err(_) => return,
+            },
+            default(PING_INTERVAL) => Message::Ping(Vec::new()),
+        };
+        if socket.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+fn header(request: &Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.to_string())
+}
@@ -0,0 +1,385 @@
+mod events;
+mod web;
+
+use std::io::{self, Read};
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+use vibe_client::protocol::{RpcError, INVALID_PARAMS, METHOD_NOT_FOUND, PLAYER_ERROR};
+use vibe_core::library::{Library, LibraryEntry};
+
+use crate::Daemon;
+
+/// Number of threads answering the requests
+const WORKERS: usize = 8;
+
+/// Number of WebSockets sending the events at once, each one on a thread of its own
+const MAX_EVENT_STREAMS: usize = 32;
+
+/// Largest body accepted, the commands only take small JSON objects
+const MAX_BODY: usize = 64 * 1024;
+
+/// Song of the queue, with its tags
+#[derive(Serialize)]
+struct Song {
+    index: usize,
+    /// Path relative to the library, absolute for the files out of it
+    path: String,
+    title: String,
+    artist: Option<String>,
+    album: Option<String>,
+    /// Duration in seconds
+    duration: Option<f64>,
+}
+
+/// Entry of a directory of the library
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    Directory { path: String, name: String },
+    Track { path: String, name: String },
+}
+
+#[derive(Deserialize)]
+struct AddParams {
+    /// Song or directory of the library
+    path: String,
+}
+
+/// Error of a request, sent as `{"error": message}`
+#[derive(Debug)]
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new<S: Into<String>>(status: u16, message: S) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Self::new(404, "not found")
+    }
+}
+
+impl From<RpcError> for HttpError {
+    fn from(error: RpcError) -> Self {
+        let status = match error.code {
+            INVALID_PARAMS => 400,
+            METHOD_NOT_FOUND => 404,
+            PLAYER_ERROR => 409,
+            _ => 500,
+        };
+        Self::new(status, error.message)
+    }
+}
+
+/// Server of the state, the queue and the library of a daemon as JSON, for the browsers
+///
+/// The routes are under `/api`, the transport commands take the parameters of the JSON-RPC methods
+/// as a JSON body, and `/api/events` is a WebSocket pushing the events of the player. The other
+/// paths serve a web client driving the player with these routes
+#[derive(Clone)]
+pub struct HttpServer {
+    daemon: Daemon,
+    library: Library,
+    /// Number of WebSockets open
+    event_streams: Arc<AtomicUsize>,
+}
+
+impl HttpServer {
+    /// Serve the player of a daemon, with the songs of a library
+    pub fn new(daemon: Daemon, library: Library) -> Self {
+        Self {
+            daemon,
+            library,
+            event_streams: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Answer the requests of a socket on a fixed number of threads
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        let server = Server::from_listener(listener, None).map_err(io::Error::other)?;
+        let server = Arc::new(server);
+        let workers: Vec<_> = (0..WORKERS)
+            .map(|_| {
+                let server = Arc::clone(&server);
+                let http = self.clone();
+                thread::spawn(move || {
+                    for request in server.incoming_requests() {
+                        // A client leaving before the response is not an error of the server
+                        let _ = http.respond(request);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            let _ = worker.join();
+        }
+        Ok(())
+    }
+
+    /// Send the events over a WebSocket on a thread of its own, the workers stay free
+    fn stream_events(&self, request: Request) -> io::Result<()> {
+        let streams = Arc::clone(&self.event_streams);
+        if streams.fetch_add(1, Ordering::SeqCst) >= MAX_EVENT_STREAMS {
+            streams.fetch_sub(1, Ordering::SeqCst);
+            let error = json!({ "error": "too many event streams" });
+            return request.respond(json_response(503, &error));
+        }
+
+        let player = self.daemon.player().clone();
+        thread::spawn(move || {
+            let _ = events::stream(&player, request);
+            streams.fetch_sub(1, Ordering::SeqCst);
+        });
+        Ok(())
+    }
+
+    fn respond(&self, mut request: Request) -> io::Result<()> {
+        let url = request.url().to_owned();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        if *request.method() == Method::Get {
+            if path == "/api/events" {
+                return self.stream_events(request);
+            }
+            if path == "/api/cover" {
+                let response = self.cover();
+                return request.respond(response);
+            }
+            if let Some(asset) = web::asset(path) {
+                let response =
+                    Response::from_data(asset.content).with_header(content_type(asset.mime));
+                return request.respond(response);
+            }
+        }
+
+        // The bodies without length are cut after the limit
+        if request.body_length().unwrap_or(0) > MAX_BODY {
+            return request.respond(body_too_large());
+        }
+        let mut body = Vec::new();
+        let limit = MAX_BODY as u64 + 1;
+        request.as_reader().take(limit).read_to_end(&mut body)?;
+        if body.len() > MAX_BODY {
+            return request.respond(body_too_large());
+        }
+        let result = parse_body(&body).and_then(|body| {
+            let method = request.method().clone();
+            self.route(&method, path, query, &body)
+        });
+        let response = match result {
+            Ok(Some(value)) => json_response(200, &value),
+            Ok(None) => Response::from_data(Vec::new()).with_status_code(204),
+            Err(error) => json_response(error.status, &json!({ "error": error.message })),
+        };
+        request.respond(response)
+    }
+
+    /// Answer a request, with a JSON value or no content
+    fn route(
+        &self,
+        method: &Method,
+        path: &str,
+        query: &str,
+        body: &Value,
+    ) -> Result<Option<Value>, HttpError> {
+        let player = self.daemon.player();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            (Method::Get, ["api", "status"]) => Ok(Some(self.daemon.handle("status", body)?)),
+            (
+                Method::Post,
+                ["api", command @ ("play" | "pause" | "stop" | "next" | "previous" | "seek")],
+            ) => {
+                self.daemon.handle(command, body)?;
+                Ok(None)
+            }
+            (Method::Get, ["api", "queue"]) => Ok(Some(json!(self.queue()))),
+            (Method::Post, ["api", "queue"]) => {
+                let params: AddParams = serde_json::from_value(body.clone())
+                    .map_err(|err| HttpError::new(400, err.to_string()))?;
+                let index = player.queue().len();
+                let songs = self
+                    .library
+                    .find_songs(params.path.trim_matches('/'))
+                    .map_err(|_| HttpError::new(404, "no such song"))?;
+                for path in songs {
+                    player.enqueue(path);
+                }
+                Ok(Some(json!({ "index": index })))
+            }
+            (Method::Delete, ["api", "queue"]) => {
+                player.clear_queue();
+                Ok(None)
+            }
+            (Method::Delete, ["api", "queue", index]) => {
+                let index = index.parse().map_err(|_| HttpError::not_found())?;
+                match player.remove_track(index) {
+                    Some(_) => Ok(None),
+                    None => Err(HttpError::not_found()),
+                }
+            }
+            (Method::Get, ["api", "library"]) => {
+                let dir = query_param(query, "path").unwrap_or_default();
+                Ok(Some(json!(self.list(dir.trim_matches('/'))?)))
+            }
+            (_, ["api", ..]) if route_exists(&segments) => {
+                Err(HttpError::new(405, "method not allowed"))
+            }
+            _ => Err(HttpError::not_found()),
+        }
+    }
+
+    /// Get the cover of the track being played
+    fn cover(&self) -> Response<io::Cursor<Vec<u8>>> {
+        let status = self.daemon.status();
+        match status.metadata().cover() {
+            Some(picture) if status.state().is_active() => {
+                // The MIME type is read from the file
+                let mime = Header::from_bytes("Content-Type", picture.mime_type.as_bytes())
+                    .unwrap_or_else(|_| content_type("application/octet-stream"));
+                Response::from_data(picture.data.clone())
+                    .with_header(mime)
+                    .with_header(Header::from_bytes("Cache-Control", "no-cache").unwrap())
+            }
+            _ => json_response(404, &json!({ "error": "no cover" })),
+        }
+    }
+
+    /// Get the songs of the queue
+    fn queue(&self) -> Vec<Song> {
+        let queue = self.daemon.player().queue();
+        queue
+            .tracks()
+            .iter()
+            .enumerate()
+            .map(|(index, track)| {
+                let metadata = track.metadata();
+                Song {
+                    index,
+                    path: self.uri(track.path()),
+                    title: metadata
+                        .title()
+                        .map(str::to_owned)
+                        .unwrap_or_else(|| file_name(track.path())),
+                    artist: metadata.artist().map(str::to_owned),
+                    album: metadata.album().map(str::to_owned),
+                    duration: track.duration().map(|duration| duration.as_secs_f64()),
+                }
+            })
+            .collect()
+    }
+
+    /// List a directory of the library
+    fn list(&self, dir: &str) -> Result<Vec<Entry>, HttpError> {
+        let entries = self
+            .library
+            .list(dir)
+            .map_err(|_| HttpError::new(404, "no such directory"))?;
+        Ok(entries
+            .iter()
+            .map(|entry| {
+                let path = entry.path().to_string_lossy().into_owned();
+                let name = file_name(entry.path());
+                match entry {
+                    LibraryEntry::Directory(_) => Entry::Directory { path, name },
+                    LibraryEntry::Track(_) => Entry::Track { path, name },
+                }
+            })
+            .collect())
+    }
+
+    /// Get the path of a file relative to the library if it is in it
+    fn uri(&self, path: &Path) -> String {
+        path.strip_prefix(self.library.root())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Returns true if the path is a route for another method
+fn route_exists(segments: &[&str]) -> bool {
+    matches!(
+        segments,
+        [
+            "api",
+            "status" | "play" | "pause" | "stop" | "next" | "previous" | "seek" | "events"
+        ] | ["api", "cover"]
+            | ["api", "queue"]
+            | ["api", "queue", _]
+            | ["api", "library"]
+    )
+}
+
+/// Read the JSON body of a request, an empty body is `null`
+fn parse_body(body: &[u8]) -> Result<Value, HttpError> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Value::Null);
+    }
+    serde_json::from_slice(body).map_err(|err| HttpError::new(400, err.to_string()))
+}
+
+fn body_too_large() -> Response<io::Cursor<Vec<u8>>> {
+    json_response(413, &json!({ "error": "request body too large" }))
+}
+
+fn json_response(status: u16, value: &Value) -> Response<io::Cursor<Vec<u8>>> {
+    Response::from_data(value.to_string())
+        .with_status_code(status)
+        .with_header(content_type("application/json"))
+}
+
+fn content_type(mime: &str) -> Header {
+    Header::from_bytes("Content-Type", mime).unwrap()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Get a parameter of a query string, with its percent escapes decoded
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+            (b'+', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
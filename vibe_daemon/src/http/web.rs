@@ -0,0 +1,19 @@
+/// File of the web client, built into the binary
+pub(super) struct Asset {
+    pub mime: &'static str,
+    pub content: &'static str,
+}
+
+/// Get the file of the web client served at a path
+pub(super) fn asset(path: &str) -> Option<Asset> {
+    let (mime, content) = match path {
+        "/" | "/index.html" => ("text/html; charset=utf-8", include_str!("web/index.html")),
+        "/app.js" => (
+            "application/javascript; charset=utf-8",
+            include_str!("web/app.js"),
+        ),
+        "/style.css" => ("text/css; charset=utf-8", include_str!("web/style.css")),
+        _ => return None,
+    };
+    Some(Asset { mime, content })
+}
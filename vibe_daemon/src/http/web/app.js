@@ -0,0 +1,221 @@
+"use strict";
+
+const $ = (id) => document.getElementById(id);
+
+let status = { state: "empty" };
+let seeking = false;
+let libraryPath = "";
+
+async function api(method, path, body) {
+  const options = { method };
+  if (body !== undefined) {
+    options.headers = { "Content-Type": "application/json" };
+    options.body = JSON.stringify(body);
+  }
+  const response = await fetch("/api/" + path, options);
+  if (response.status === 204) {
+    return null;
+  }
+  const value = await response.json();
+  if (!response.ok) {
+    throw new Error(value.error);
+  }
+  return value;
+}
+
+function command(name, body) {
+  return api("POST", name, body).catch((error) => console.warn(name, error.message));
+}
+
+function formatTime(seconds) {
+  seconds = Math.floor(seconds || 0);
+  const minutes = Math.floor(seconds / 60);
+  return minutes + ":" + String(seconds % 60).padStart(2, "0");
+}
+
+function element(tag, className, text) {
+  const node = document.createElement(tag);
+  if (className) {
+    node.className = className;
+  }
+  if (text !== undefined) {
+    node.textContent = text;
+  }
+  return node;
+}
+
+function button(text, title, onClick) {
+  const node = element("button", "small", text);
+  node.title = title;
+  node.addEventListener("click", onClick);
+  return node;
+}
+
+// Now playing and transport
+
+function isActive(state) {
+  return state === "loading" || state === "playing" || state === "paused";
+}
+
+function renderStatus() {
+  const playing = status.index !== null && status.index !== undefined && isActive(status.state);
+  $("title").textContent = playing ? status.title : "Nothing is playing";
+  $("artist").textContent = playing ? status.artist || "" : "";
+  $("album").textContent = playing ? status.album || "" : "";
+  document.title = playing ? status.title + " - vibe" : "vibe";
+
+  const cover = $("cover");
+  // The query changes with the track, so the browser doesn't show the cached cover
+  const source = playing ? "/api/cover?track=" + status.index + "-" + encodeURIComponent(status.path) : "";
+  if (cover.dataset.source !== source) {
+    cover.dataset.source = source;
+    // Hidden until the image is loaded, the track may have no cover
+    cover.classList.add("missing");
+    if (playing) {
+      cover.src = source;
+    } else {
+      cover.removeAttribute("src");
+    }
+  }
+
+  const play = $("play-pause");
+  play.textContent = status.state === "playing" ? "⏸" : "▶";
+  play.title = status.state === "playing" ? "Pause" : "Play";
+
+  const bar = $("seek-bar");
+  bar.disabled = !playing || !status.duration;
+  bar.max = status.duration || 0;
+  $("duration").textContent = formatTime(status.duration);
+  renderPosition(playing ? status.position : 0);
+  highlightQueue();
+}
+
+function renderPosition(position) {
+  status.position = position;
+  if (!seeking) {
+    $("seek-bar").value = position;
+    $("position").textContent = formatTime(position);
+  }
+}
+
+async function refreshStatus() {
+  status = await api("GET", "status");
+  renderStatus();
+}
+
+// Queue
+
+function highlightQueue() {
+  for (const item of $("queue").children) {
+    item.classList.toggle("current", Number(item.dataset.index) === status.index);
+  }
+}
+
+async function refreshQueue() {
+  const queue = await api("GET", "queue");
+  const list = $("queue");
+  list.replaceChildren(
+    ...queue.map((song) => {
+      const item = element("li");
+      item.dataset.index = song.index;
+      const name = element("span", "name", song.title);
+      name.title = song.path;
+      name.addEventListener("click", () => command("play", { index: song.index }));
+      const details = [song.artist, song.duration ? formatTime(song.duration) : null];
+      item.append(
+        name,
+        element("span", "details", details.filter(Boolean).join(" · ")),
+        // The queue is refreshed by the `queue_changed` event
+        button("✕", "Remove", () => api("DELETE", "queue/" + song.index))
+      );
+      return item;
+    })
+  );
+  highlightQueue();
+}
+
+// Library
+
+async function browse(path) {
+  const entries = await api("GET", "library?path=" + encodeURIComponent(path));
+  libraryPath = path;
+  $("library-path").textContent = "/" + path;
+
+  const items = entries.map((entry) => {
+    const item = element("li");
+    const name = element("span", "name", entry.type === "directory" ? entry.name + "/" : entry.name);
+    if (entry.type === "directory") {
+      name.addEventListener("click", () => browse(entry.path));
+    }
+    item.append(
+      name,
+      button("+", "Add to the queue", () => api("POST", "queue", { path: entry.path }))
+    );
+    return item;
+  });
+  if (path !== "") {
+    const parent = element("li");
+    const name = element("span", "name", "..");
+    name.addEventListener("click", () => browse(libraryPath.split("/").slice(0, -1).join("/")));
+    parent.append(name);
+    items.unshift(parent);
+  }
+  $("library").replaceChildren(...items);
+}
+
+// Events of the player
+
+function connect() {
+  const scheme = location.protocol === "https:" ? "wss://" : "ws://";
+  const socket = new WebSocket(scheme + location.host + "/api/events");
+  // The queue may have changed while the socket was closed
+  socket.addEventListener("open", () => {
+    refreshStatus();
+    refreshQueue();
+  });
+  socket.addEventListener("message", (message) => {
+    const event = JSON.parse(message.data);
+    switch (event.type) {
+      case "position":
+        renderPosition(event.position);
+        break;
+      case "track_changed":
+        refreshStatus();
+        refreshQueue();
+        break;
+      case "queue_changed":
+        refreshQueue();
+        break;
+      default:
+        refreshStatus();
+    }
+  });
+  // The daemon may restart, try again until it is back
+  socket.addEventListener("close", () => setTimeout(connect, 2000));
+}
+
+$("previous").addEventListener("click", () => command("previous"));
+$("next").addEventListener("click", () => command("next"));
+$("stop").addEventListener("click", () => command("stop"));
+$("play-pause").addEventListener("click", () =>
+  command(status.state === "playing" ? "pause" : "play")
+);
+$("clear").addEventListener("click", () => api("DELETE", "queue"));
+
+const bar = $("seek-bar");
+bar.addEventListener("input", () => {
+  seeking = true;
+  $("position").textContent = formatTime(bar.value);
+});
+bar.addEventListener("change", async () => {
+  await command("seek", { position: Number(bar.value) });
+  seeking = false;
+});
+
+$("cover").addEventListener("error", () => $("cover").classList.add("missing"));
+$("cover").addEventListener("load", () => $("cover").classList.remove("missing"));
+
+refreshStatus();
+refreshQueue();
+browse("");
+connect();
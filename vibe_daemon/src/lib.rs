@@ -0,0 +1,12 @@
+pub mod convert;
+mod daemon;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod mpd;
+#[cfg(feature = "mpris")]
+pub mod mpris;
+
+pub use self::daemon::{bind, Daemon};
+#[cfg(feature = "http")]
+pub use self::http::HttpServer;
+pub use self::mpd::MpdServer;
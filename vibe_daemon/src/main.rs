@@ -0,0 +1,107 @@
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+
+use clap::{App, Arg};
+use vibe_client::default_socket_path;
+use vibe_core::library::Library;
+use vibe_daemon::{bind, Daemon, MpdServer};
+use vibe_engine::player::Player;
+
+pub fn main() {
+    let app = App::new("vibed")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Play music in the background, controlled over a Unix socket")
+        .arg(
+            Arg::with_name("socket")
+                .long("socket")
+                .short("s")
+                .value_name("PATH")
+                .help("Socket of the JSON-RPC control, in the runtime directory by default"),
+        )
+        .arg(
+            Arg::with_name("mpd")
+                .long("mpd")
+                .value_name("ADDRESS")
+                .help("Also serve the MPD protocol on an address, like 127.0.0.1:6600"),
+        )
+        .arg(
+            Arg::with_name("music-dir")
+                .long("music-dir")
+                .value_name("DIR")
+                .default_value(".")
+                .help("Music directory browsed by the MPD and HTTP clients"),
+        );
+    #[cfg(feature = "http")]
+    let app = app.arg(
+        Arg::with_name("http")
+            .long("http")
+            .value_name("ADDRESS")
+            .help("Also serve the JSON API on an address, like 0.0.0.0:8080"),
+    );
+    let matches = app.get_matches();
+    let socket = matches
+        .value_of_os("socket")
+        .map(PathBuf::from)
+        .unwrap_or_else(default_socket_path);
+
+    let listener = match bind(&socket) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("vibed: {}: {}", socket.display(), err);
+            process::exit(1);
+        }
+    };
+
+    let daemon = Daemon::new(Player::new());
+    let library = Library::new(matches.value_of_os("music-dir").unwrap());
+    if let Some(address) = matches.value_of("mpd") {
+        let listener = listen(address);
+        let server = MpdServer::new(daemon.clone(), library.clone());
+        thread::spawn(move || exit_on_error(server.serve(listener)));
+    }
+    #[cfg(feature = "http")]
+    if let Some(address) = matches.value_of("http") {
+        let listener = listen(address);
+        let server = vibe_daemon::HttpServer::new(daemon.clone(), library);
+        thread::spawn(move || exit_on_error(server.serve(listener)));
+    }
+    #[cfg(feature = "mpris")]
+    serve_mpris(daemon.clone());
+    exit_on_error(daemon.serve(listener));
+}
+
+/// Listen on a TCP address, the daemon doesn't start without it
+fn listen(address: &str) -> TcpListener {
+    match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("vibed: {}: {}", address, err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Stop the daemon when one of its servers fails
+fn exit_on_error(result: std::io::Result<()>) {
+    if let Err(err) = result {
+        eprintln!("vibed: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Expose the player on the session bus, a daemon without session bus is only controlled on its sockets
+#[cfg(feature = "mpris")]
+fn serve_mpris(daemon: Daemon) {
+    use dbus::blocking::Connection;
+    use vibe_daemon::mpris::Mpris;
+
+    thread::spawn(move || {
+        let result =
+            Connection::new_session().and_then(|connection| Mpris::new(daemon).serve(&connection));
+        if let Err(err) = result {
+            eprintln!("vibed: MPRIS: {}", err);
+        }
+    });
+}
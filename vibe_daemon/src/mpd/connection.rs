@@ -0,0 +1,630 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::{never, select, unbounded, Receiver};
+use vibe_core::library::LibraryEntry;
+use vibe_core::Metadata;
+use vibe_engine::player::{Player, PlayerEvent, PlayerState};
+use vibe_engine::queue::{RepeatMode, ShuffleMode, Track};
+
+use super::protocol::{
+    parse_bool, parse_int, parse_range, parse_time, tokenize, Ack, ACK_ERROR_ARG,
+    ACK_ERROR_NO_EXIST, ACK_ERROR_SYSTEM, ACK_ERROR_UNKNOWN,
+};
+use super::{MpdServer, MPD_VERSION};
+
+/// Commands understood, listed by `commands`
+const COMMANDS: [&str; 34] = [
+    "add",
+    "addid",
+    "clear",
+    "close",
+    "command_list_begin",
+    "command_list_end",
+    "command_list_ok_begin",
+    "commands",
+    "consume",
+    "currentsong",
+    "delete",
+    "deleteid",
+    "idle",
+    "lsinfo",
+    "next",
+    "noidle",
+    "notcommands",
+    "pause",
+    "ping",
+    "play",
+    "playid",
+    "playlistid",
+    "playlistinfo",
+    "previous",
+    "random",
+    "repeat",
+    "seek",
+    "seekcur",
+    "seekid",
+    "setvol",
+    "single",
+    "status",
+    "stop",
+    "tagtypes",
+];
+
+/// Subsystems reported by `idle`
+const SUBSYSTEMS: [&str; 4] = ["player", "playlist", "options", "mixer"];
+
+/// Subsystems changed by the events of the player, reported by the next `idle`
+#[derive(Default)]
+struct Seen {
+    player: bool,
+    playlist: bool,
+    options: bool,
+    mixer: bool,
+}
+
+/// Result of a command
+enum Reply {
+    /// Lines sent before `OK`
+    Done(String),
+    /// The client asked to close the connection
+    Close,
+}
+
+/// Client of the MPD server
+pub(super) struct Connection {
+    server: MpdServer,
+    writer: TcpStream,
+    lines: Receiver<String>,
+    events: Receiver<PlayerEvent>,
+    seen: Seen,
+}
+
+impl Connection {
+    /// Start reading the commands of a client on a thread
+    pub(super) fn new(server: MpdServer, stream: TcpStream) -> io::Result<Self> {
+        let (sender, lines) = unbounded();
+        let reader = BufReader::new(stream.try_clone()?);
+        // The lines stop with the connection, which ends the session
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let sent = line.map(|line| sender.send(line).is_ok());
+                if !matches!(sent, Ok(true)) {
+                    break;
+                }
+            }
+        });
+
+        let events = server.daemon.player().events();
+        Ok(Self {
+            server,
+            writer: stream,
+            lines,
+            events,
+            seen: Seen::default(),
+        })
+    }
+
+    /// Answer the commands until the client leaves
+    pub(super) fn run(mut self) -> io::Result<()> {
+        self.write(&format!("OK MPD {}\n", MPD_VERSION))?;
+        while let Ok(line) = self.lines.recv() {
+            self.collect_events();
+            let words = match tokenize(&line) {
+                Ok(words) if words.is_empty() => {
+                    self.write(&Ack::new(ACK_ERROR_UNKNOWN, "No command given").to_line(0, ""))?;
+                    continue;
+                }
+                Ok(words) => words,
+                Err(ack) => {
+                    self.write(&ack.to_line(0, ""))?;
+                    continue;
+                }
+            };
+
+            let reply = match words[0].as_str() {
+                "command_list_begin" => self.run_list(false)?,
+                "command_list_ok_begin" => self.run_list(true)?,
+                "idle" => self.idle(&words[1..])?,
+                _ => match self.execute(&words) {
+                    Ok(Reply::Done(output)) => Some(output + "OK\n"),
+                    Ok(Reply::Close) => None,
+                    Err(ack) => Some(ack.to_line(0, &words[0])),
+                },
+            };
+            match reply {
+                Some(reply) => self.write(&reply)?,
+                None => break,
+            }
+        }
+        // The thread reading the lines holds the socket open too
+        self.writer.shutdown(Shutdown::Both)
+    }
+
+    /// Run the commands until `command_list_end`, they stop at the first error
+    fn run_list(&mut self, list_ok: bool) -> io::Result<Option<String>> {
+        let mut commands = Vec::new();
+        loop {
+            match self.lines.recv() {
+                Ok(line) if line.trim() == "command_list_end" => break,
+                Ok(line) => commands.push(line),
+                Err(_) => return Ok(None),
+            }
+        }
+
+        let mut output = String::new();
+        for (index, line) in commands.iter().enumerate() {
+            let words = tokenize(line)
+                .and_then(|words| match words.is_empty() {
+                    true => Err(Ack::new(ACK_ERROR_UNKNOWN, "No command given")),
+                    false => Ok(words),
+                })
+                .map_err(|ack| ack.to_line(index, ""));
+            let result = words.and_then(|words| {
+                self.execute(&words)
+                    .map_err(|ack| ack.to_line(index, &words[0]))
+            });
+            match result {
+                Ok(Reply::Done(lines)) => {
+                    output.push_str(&lines);
+                    if list_ok {
+                        output.push_str("list_OK\n");
+                    }
+                }
+                Ok(Reply::Close) => return Ok(None),
+                Err(ack) => {
+                    output.push_str(&ack);
+                    return Ok(Some(output));
+                }
+            }
+        }
+        output.push_str("OK\n");
+        Ok(Some(output))
+    }
+
+    /// Wait for a change of the given subsystems, or of any of them
+    fn idle(&mut self, subsystems: &[String]) -> io::Result<Option<String>> {
+        if let Some(unknown) = subsystems
+            .iter()
+            .find(|name| !SUBSYSTEMS.contains(&name.as_str()))
+        {
+            let ack = Ack::new(
+                ACK_ERROR_ARG,
+                format!("Unrecognized idle event: {}", unknown),
+            );
+            return Ok(Some(ack.to_line(0, "idle")));
+        }
+        let wanted = |name: &str| subsystems.is_empty() || subsystems.iter().any(|s| s == name);
+
+        loop {
+            self.collect_events();
+            let changed: Vec<&str> = self
+                .changes()
+                .into_iter()
+                .filter(|name| wanted(name))
+                .collect();
+            if !changed.is_empty() {
+                self.mark_seen(&changed);
+                let mut output = String::new();
+                for name in changed {
+                    let _ = writeln!(output, "changed: {}", name);
+                }
+                return Ok(Some(output + "OK\n"));
+            }
+
+            select! {
+                recv(self.lines) -> line => {
+                    return match line {
+                        Ok(line) if line.trim() == "noidle" => Ok(Some("OK\n".to_owned())),
+                        // Only `noidle` is allowed during `idle`
+                        _ => Ok(None),
+                    };
+                }
+                recv(self.events) -> event => match event {
+                    Ok(event) => self.seen_event(&event),
+                    // Without a player only `noidle` ends the wait
+                    Err(_) => self.events = never(),
+                },
+            }
+        }
+    }
+
+    /// Apply the events of the player received since the last command
+    fn collect_events(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            self.seen_event(&event);
+        }
+    }
+
+    fn seen_event(&mut self, event: &PlayerEvent) {
+        match event {
+            // The position moves on its own, the clients compute it from `elapsed`
+            PlayerEvent::Position(_) => {}
+            PlayerEvent::QueueChanged => self.seen.playlist = true,
+            PlayerEvent::RepeatChanged(_) | PlayerEvent::ShuffleChanged(_) => {
+                self.seen.options = true
+            }
+            PlayerEvent::VolumeChanged(_) => self.seen.mixer = true,
+            _ => self.seen.player = true,
+        }
+    }
+
+    /// Get the subsystems that changed since the client last saw them
+    fn changes(&self) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.seen.player {
+            changed.push("player");
+        }
+        if self.seen.playlist {
+            changed.push("playlist");
+        }
+        if self.seen.options {
+            changed.push("options");
+        }
+        if self.seen.mixer {
+            changed.push("mixer");
+        }
+        changed
+    }
+
+    /// Remember that the client has been told of the changes of some subsystems
+    fn mark_seen(&mut self, subsystems: &[&str]) {
+        for name in subsystems {
+            match *name {
+                "player" => self.seen.player = false,
+                "playlist" => self.seen.playlist = false,
+                "options" => self.seen.options = false,
+                "mixer" => self.seen.mixer = false,
+                _ => {}
+            }
+        }
+    }
+
+    #[inline]
+    fn player(&self) -> &Player {
+        self.server.daemon.player()
+    }
+
+    /// Run a command, `words` holds its name and its arguments
+    fn execute(&mut self, words: &[String]) -> Result<Reply, Ack> {
+        let (command, args) = (words[0].as_str(), &words[1..]);
+        let mut output = String::new();
+        let player = self.player().clone();
+
+        match command {
+            "ping" => check_args(command, args, 0, 0)?,
+            "close" => return Ok(Reply::Close),
+            "commands" => {
+                for name in COMMANDS.iter() {
+                    let _ = writeln!(output, "command: {}", name);
+                }
+            }
+            "notcommands" => {}
+            "status" => {
+                check_args(command, args, 0, 0)?;
+                self.write_status(&mut output);
+            }
+            "currentsong" => {
+                check_args(command, args, 0, 0)?;
+                let queue = player.queue();
+                if let (Some(index), Some(track)) = (queue.current(), queue.current_track()) {
+                    self.write_track(&mut output, track, index);
+                }
+            }
+            "play" | "playid" => {
+                check_args(command, args, 0, 1)?;
+                match args.first() {
+                    Some(arg) => {
+                        let index = song_index(&player, command, arg)?;
+                        if !player.play_index(index) {
+                            return Err(Ack::new(ACK_ERROR_ARG, "Bad song index"));
+                        }
+                    }
+                    None => player.play_stream(),
+                }
+            }
+            "pause" => {
+                check_args(command, args, 0, 1)?;
+                let pause = match args.first() {
+                    Some(arg) => parse_bool(arg)?,
+                    None => player.state() == PlayerState::Playing,
+                };
+                if pause {
+                    player.pause_stream();
+                } else if player.state() == PlayerState::Paused {
+                    player.play_stream();
+                }
+            }
+            "stop" => player.stop_stream(),
+            "next" => player.next_track(),
+            "previous" => player.previous_track(),
+            "seek" | "seekid" => {
+                check_args(command, args, 2, 2)?;
+                let index = song_index(&player, command, &args[0])?;
+                let time = parse_time(&args[1])?;
+                if player.current_index() != Some(index) && !player.play_index(index) {
+                    return Err(Ack::new(ACK_ERROR_ARG, "Bad song index"));
+                }
+                seek(&player, time)?;
+            }
+            "seekcur" => {
+                check_args(command, args, 1, 1)?;
+                let time = parse_time(&args[0])?;
+                // A sign moves from the current position
+                let time = if args[0].starts_with('+') || args[0].starts_with('-') {
+                    player.position().as_secs_f64() + time
+                } else {
+                    time
+                };
+                if !player.state().is_active() {
+                    return Err(Ack::new(ACK_ERROR_SYSTEM, "Not playing"));
+                }
+                seek(&player, time.max(0.0))?;
+            }
+            "add" => {
+                check_args(command, args, 1, 1)?;
+                for path in self.find_songs(&args[0])? {
+                    player.enqueue(path);
+                }
+            }
+            "addid" => {
+                check_args(command, args, 1, 2)?;
+                let path = self.find_song(&args[0])?;
+                let index = match args.get(1) {
+                    Some(arg) => parse_int(arg)?.min(player.queue().len()),
+                    None => player.queue().len(),
+                };
+                let id = player.insert_track(index, path);
+                let _ = writeln!(output, "Id: {}", id);
+            }
+            "delete" => {
+                check_args(command, args, 1, 1)?;
+                let (start, end) = parse_range(&args[0])?;
+                let end = end.unwrap_or_else(|| player.queue().len());
+                if start >= end || end > player.queue().len() {
+                    return Err(Ack::new(ACK_ERROR_ARG, "Bad song index"));
+                }
+                for index in (start..end).rev() {
+                    player.remove_track(index);
+                }
+            }
+            "deleteid" => {
+                check_args(command, args, 1, 1)?;
+                let index = song_index(&player, command, &args[0])?;
+                player.remove_track(index);
+            }
+            "clear" => player.clear_queue(),
+            "playlistinfo" | "playlistid" => {
+                check_args(command, args, 0, 1)?;
+                let queue = player.queue();
+                let (start, end) = match args.first() {
+                    Some(arg) => parse_range(arg)?,
+                    None => (0, None),
+                };
+                let end = end.unwrap_or_else(|| queue.len()).min(queue.len());
+                if !args.is_empty() && start >= queue.len() {
+                    return Err(Ack::new(ACK_ERROR_ARG, "Bad song index"));
+                }
+                for (index, track) in queue.tracks().iter().enumerate().take(end).skip(start) {
+                    self.write_track(&mut output, track, index);
+                }
+            }
+            "lsinfo" => {
+                check_args(command, args, 0, 1)?;
+                let dir = args.first().map(|uri| uri.trim_matches('/')).unwrap_or("");
+                let library = &self.server.library;
+                let entries = library
+                    .list(dir)
+                    .map_err(|_| Ack::new(ACK_ERROR_NO_EXIST, "No such directory"))?;
+                for entry in entries {
+                    match entry {
+                        LibraryEntry::Directory(path) => {
+                            let _ = writeln!(output, "directory: {}", path.to_string_lossy());
+                        }
+                        LibraryEntry::Track(path) => {
+                            let path = library.resolve(path).map_err(no_such_song)?;
+                            let info = self.server.song_info(&path);
+                            self.write_song(&mut output, &path, &info.metadata, info.duration);
+                        }
+                    }
+                }
+            }
+            "repeat" => {
+                check_args(command, args, 1, 1)?;
+                player.set_repeat_mode(match (parse_bool(&args[0])?, player.repeat_mode()) {
+                    (false, _) => RepeatMode::Off,
+                    (true, RepeatMode::One) => RepeatMode::One,
+                    (true, _) => RepeatMode::All,
+                });
+            }
+            "single" => {
+                check_args(command, args, 1, 1)?;
+                player.set_repeat_mode(match (parse_bool(&args[0])?, player.repeat_mode()) {
+                    (true, _) => RepeatMode::One,
+                    (false, RepeatMode::Off) => RepeatMode::Off,
+                    (false, _) => RepeatMode::All,
+                });
+            }
+            "random" => {
+                check_args(command, args, 1, 1)?;
+                player.set_shuffle_mode(match parse_bool(&args[0])? {
+                    true => ShuffleMode::NoRepeat,
+                    false => ShuffleMode::Off,
+                });
+            }
+            "consume" => {
+                check_args(command, args, 1, 1)?;
+                if parse_bool(&args[0])? {
+                    return Err(Ack::new(ACK_ERROR_SYSTEM, "Consume mode is not supported"));
+                }
+            }
+            "setvol" => {
+                check_args(command, args, 1, 1)?;
+                let volume = parse_int(&args[0])?;
+                if volume > 100 {
+                    return Err(Ack::new(ACK_ERROR_ARG, "Invalid volume value"));
+                }
+                self.player().set_volume(volume as f32 / 100.0);
+            }
+            "tagtypes" => {
+                for tag in ["Artist", "Album", "Title"] {
+                    let _ = writeln!(output, "tagtype: {}", tag);
+                }
+            }
+            "noidle" => {}
+            _ => {
+                return Err(Ack::new(
+                    ACK_ERROR_UNKNOWN,
+                    format!("unknown command \"{}\"", command),
+                ))
+            }
+        }
+        Ok(Reply::Done(output))
+    }
+
+    /// Write the answer of `status`
+    fn write_status(&self, output: &mut String) {
+        let player = self.player();
+        let status = self.server.daemon.status();
+        let queue = player.queue();
+        let version = self.server.playlist_version(&queue_ids(player));
+        let repeat = player.repeat_mode();
+
+        let state = match player.state() {
+            PlayerState::Playing | PlayerState::Loading => "play",
+            PlayerState::Paused => "pause",
+            _ => "stop",
+        };
+        let volume = (player.volume() * 100.0).round();
+        let _ = writeln!(output, "volume: {}", volume);
+        let _ = writeln!(output, "repeat: {}", (repeat != RepeatMode::Off) as u8);
+        let _ = writeln!(
+            output,
+            "random: {}",
+            (player.shuffle_mode() != ShuffleMode::Off) as u8
+        );
+        let _ = writeln!(output, "single: {}", (repeat == RepeatMode::One) as u8);
+        let _ = writeln!(output, "consume: 0");
+        let _ = writeln!(output, "playlist: {}", version);
+        let _ = writeln!(output, "playlistlength: {}", queue.len());
+        let _ = writeln!(output, "state: {}", state);
+
+        if let (Some(index), Some(track)) = (queue.current(), queue.current_track()) {
+            let _ = writeln!(output, "song: {}", index);
+            let _ = writeln!(output, "songid: {}", track.id());
+            if player.state().is_active() {
+                let elapsed = player.position().as_secs_f64();
+                let duration = status.duration().map(|duration| duration.as_secs_f64());
+                let _ = writeln!(
+                    output,
+                    "time: {}:{}",
+                    elapsed.round(),
+                    duration.unwrap_or(0.0).round()
+                );
+                let _ = writeln!(output, "elapsed: {:.3}", elapsed);
+                if let Some(duration) = duration {
+                    let _ = writeln!(output, "duration: {:.3}", duration);
+                }
+            }
+        }
+        if let Some(error) = status.error() {
+            let _ = writeln!(output, "error: {}", error);
+        }
+    }
+
+    /// Write the tags of a song of the queue, with its position
+    fn write_track(&self, output: &mut String, track: &Track, index: usize) {
+        self.write_song(output, track.path(), track.metadata(), track.duration());
+        let _ = writeln!(output, "Pos: {}", index);
+        let _ = writeln!(output, "Id: {}", track.id());
+    }
+
+    /// Write the tags of a song
+    fn write_song(
+        &self,
+        output: &mut String,
+        path: &Path,
+        metadata: &Metadata,
+        duration: Option<Duration>,
+    ) {
+        let _ = writeln!(output, "file: {}", self.server.uri(path));
+        for (key, value) in [
+            ("Title", metadata.title()),
+            ("Artist", metadata.artist()),
+            ("Album", metadata.album()),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(output, "{}: {}", key, value);
+            }
+        }
+        if let Some(duration) = duration {
+            let _ = writeln!(output, "Time: {}", duration.as_secs_f64().round());
+            let _ = writeln!(output, "duration: {:.3}", duration.as_secs_f64());
+        }
+    }
+
+    /// Get the files of a song or of every song under a directory of the library
+    fn find_songs(&self, uri: &str) -> Result<Vec<PathBuf>, Ack> {
+        let library = &self.server.library;
+        library
+            .find_songs(uri.trim_matches('/'))
+            .map_err(no_such_song)
+    }
+
+    /// Get the file of a song of the library
+    fn find_song(&self, uri: &str) -> Result<PathBuf, Ack> {
+        let library = &self.server.library;
+        library
+            .find_song(uri.trim_matches('/'))
+            .map_err(no_such_song)
+    }
+
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.writer.write_all(text.as_bytes())
+    }
+}
+
+/// Get the ids of the tracks of the queue of a player
+fn queue_ids(player: &Player) -> Vec<u64> {
+    player.queue().tracks().iter().map(Track::id).collect()
+}
+
+/// Get the position in the queue of the song of a command, the commands ending with `id` take its id
+fn song_index(player: &Player, command: &str, arg: &str) -> Result<usize, Ack> {
+    if !command.ends_with("id") {
+        return parse_int(arg);
+    }
+    let id = parse_int(arg)? as u64;
+    player
+        .queue()
+        .index_of(id)
+        .ok_or_else(|| Ack::new(ACK_ERROR_NO_EXIST, "No such song"))
+}
+
+/// Check the number of arguments of a command
+fn check_args(command: &str, args: &[String], min: usize, max: usize) -> Result<(), Ack> {
+    if args.len() < min || args.len() > max {
+        return Err(Ack::new(
+            ACK_ERROR_ARG,
+            format!("wrong number of arguments for \"{}\"", command),
+        ));
+    }
+    Ok(())
+}
+
+/// Move the playback to a position in seconds
+fn seek(player: &Player, time: f64) -> Result<(), Ack> {
+    if time < 0.0 {
+        return Err(Ack::new(ACK_ERROR_ARG, "Negative position"));
+    }
+    player.seek_stream(Duration::from_secs_f64(time));
+    Ok(())
+}
+
+fn no_such_song(_: io::Error) -> Ack {
+    Ack::new(ACK_ERROR_NO_EXIST, "No such song")
+}
@@ -0,0 +1,106 @@
+mod connection;
+mod protocol;
+
+use std::collections::HashMap;
+use std::io;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use vibe_core::library::Library;
+use vibe_engine::queue::TrackInfo;
+
+use self::connection::Connection;
+pub use self::protocol::{tokenize, Ack};
+use crate::Daemon;
+
+/// Version of the protocol announced to the clients
+pub const MPD_VERSION: &str = "0.21.0";
+
+/// Last queue seen by the clients, and its version
+#[derive(Default)]
+struct Playlist {
+    tracks: Vec<u64>,
+    version: u32,
+}
+
+/// Tags of the songs of the library already read, with the modification time of their file
+type SongCache = HashMap<PathBuf, (Option<SystemTime>, Arc<TrackInfo>)>;
+
+/// Server of a subset of the Music Player Daemon protocol, for the existing MPD clients
+///
+/// The songs are the files of a library, the songs of the queue keep the id of their track
+#[derive(Clone)]
+pub struct MpdServer {
+    daemon: Daemon,
+    library: Library,
+    playlist: Arc<Mutex<Playlist>>,
+    songs: Arc<Mutex<SongCache>>,
+}
+
+impl MpdServer {
+    /// Serve the player of a daemon, with the songs of a library
+    pub fn new(daemon: Daemon, library: Library) -> Self {
+        Self {
+            daemon,
+            library,
+            playlist: Arc::new(Mutex::new(Playlist::default())),
+            songs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Accept the clients of a socket, each one on its own thread
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            thread::spawn(move || {
+                if let Ok(connection) = Connection::new(server, stream) {
+                    // A client leaving without `close` is not an error of the server
+                    let _ = connection.run();
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the version of the queue, which changes with the tracks of the queue
+    fn playlist_version(&self, tracks: &[u64]) -> u32 {
+        let mut playlist = self.playlist.lock().unwrap();
+        if playlist.tracks != tracks {
+            playlist.tracks = tracks.to_vec();
+            playlist.version += 1;
+        }
+        playlist.version
+    }
+
+    /// Get the tags of a song of the library, read again only once its file has changed
+    fn song_info(&self, path: &Path) -> Arc<TrackInfo> {
+        let modified = path.metadata().and_then(|file| file.modified()).ok();
+        if let Some((time, info)) = self.songs.lock().unwrap().get(path) {
+            if *time == modified {
+                return Arc::clone(info);
+            }
+        }
+
+        // The pictures are left out, the clients only list the tags
+        let mut info = TrackInfo::read(path);
+        info.metadata.remove_pictures();
+        let info = Arc::new(info);
+        self.songs
+            .lock()
+            .unwrap()
+            .insert(path.to_owned(), (modified, Arc::clone(&info)));
+        info
+    }
+
+    /// Get the URI of a file, relative to the library if it is in it
+    fn uri(&self, path: &Path) -> String {
+        path.strip_prefix(self.library.root())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt::Display;
+
+/// The command isn't valid, or has the wrong arguments
+pub const ACK_ERROR_ARG: u32 = 2;
+/// The command is unknown
+pub const ACK_ERROR_UNKNOWN: u32 = 5;
+/// The song or the directory doesn't exist
+pub const ACK_ERROR_NO_EXIST: u32 = 50;
+/// The player failed to do the command
+pub const ACK_ERROR_SYSTEM: u32 = 52;
+
+/// Error of a command, sent as an `ACK` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ack {
+    pub code: u32,
+    pub message: String,
+}
+
+impl Ack {
+    /// Create an error with one of the codes of this module
+    pub fn new<S: Into<String>>(code: u32, message: S) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Format the error of a command, `index` is its position in a command list
+    pub fn to_line(&self, index: usize, command: &str) -> String {
+        format!(
+            "ACK [{}@{}] {{{}}} {}\n",
+            self.code, index, command, self.message
+        )
+    }
+}
+
+impl Display for Ack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Split a command line in its words, the quoted words can hold spaces and `\` escapes
+pub fn tokenize(line: &str) -> Result<Vec<String>, Ack> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut word = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(escaped) => word.push(escaped),
+                        None => return Err(Ack::new(ACK_ERROR_ARG, "Missing closing '\"'")),
+                    },
+                    Some(c) => word.push(c),
+                    None => return Err(Ack::new(ACK_ERROR_ARG, "Missing closing '\"'")),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Read a whole number argument
+pub fn parse_int(arg: &str) -> Result<usize, Ack> {
+    arg.parse()
+        .map_err(|_| Ack::new(ACK_ERROR_ARG, format!("Integer expected: {}", arg)))
+}
+
+/// Read a boolean argument, `0` or `1`
+pub fn parse_bool(arg: &str) -> Result<bool, Ack> {
+    match arg {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(Ack::new(
+            ACK_ERROR_ARG,
+            format!("Boolean (0/1) expected: {}", arg),
+        )),
+    }
+}
+
+/// Read a time in seconds
+pub fn parse_time(arg: &str) -> Result<f64, Ack> {
+    match arg.parse::<f64>() {
+        Ok(time) if time.is_finite() => Ok(time),
+        _ => Err(Ack::new(ACK_ERROR_ARG, format!("Number expected: {}", arg))),
+    }
+}
+
+/// Read a range of positions `START:END`, or a single position
+pub fn parse_range(arg: &str) -> Result<(usize, Option<usize>), Ack> {
+    match arg.split_once(':') {
+        Some((start, "")) => Ok((parse_int(start)?, None)),
+        Some((start, end)) => Ok((parse_int(start)?, Some(parse_int(end)?))),
+        None => {
+            let position = parse_int(arg)?;
+            Ok((position, Some(position + 1)))
+        }
+    }
+}
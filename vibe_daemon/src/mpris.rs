@@ -0,0 +1,380 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::blocking::stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged;
+use dbus::blocking::Connection;
+use dbus::channel::{MatchingReceiver, Sender};
+use dbus::message::{MatchRule, SignalArgs};
+use dbus::{Message, MethodErr};
+use dbus_crossroads::{Crossroads, IfaceToken};
+use vibe_engine::player::{PlayerEvent, PlayerState};
+use vibe_engine::queue::{RepeatMode, ShuffleMode};
+
+use crate::Daemon;
+
+/// Name of the player on the bus
+pub const BUS_NAME: &str = "org.mpris.MediaPlayer2.vibe";
+/// Path of the object of the player
+pub const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+/// Interface describing the application
+pub const ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+/// Interface controlling the playback
+pub const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// Track id of the metadata when no track is played
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+/// Time waited for a call before looking at the events of the player
+const EVENT_POLL: Duration = Duration::from_millis(100);
+
+/// Builder of the `Seeked` signals, shared by the methods moving the position
+type SeekedSignal = Arc<dyn Fn(&dbus::Path, &(i64,)) -> Message + Send + Sync>;
+
+/// Bridge of a daemon to the MPRIS D-Bus interfaces, for the media keys and the desktop widgets
+///
+/// The times are in microseconds, the track ids are the positions of the tracks in the queue
+pub struct Mpris {
+    daemon: Daemon,
+}
+
+impl Mpris {
+    /// Expose the player of a daemon
+    pub fn new(daemon: Daemon) -> Self {
+        Self { daemon }
+    }
+
+    /// Take the name of the player on a bus and answer the calls until the connection is lost
+    pub fn serve(&self, connection: &Connection) -> Result<(), dbus::Error> {
+        let events = self.daemon.player().events();
+        connection.request_name(BUS_NAME, false, true, false)?;
+
+        let mut crossroads = Crossroads::new();
+        let root = register_root(&mut crossroads);
+        let player = register_player(&mut crossroads);
+        crossroads.insert(OBJECT_PATH, &[root, player], self.daemon.clone());
+        connection.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |message, connection| {
+                // Only fails for messages without a serial, which the bus never delivers
+                let _ = crossroads.handle_message(message, connection);
+                true
+            }),
+        );
+
+        loop {
+            connection.process(EVENT_POLL)?;
+
+            let mut changed = PropMap::new();
+            while let Ok(event) = events.try_recv() {
+                match event {
+                    PlayerEvent::StateChanged(state) => {
+                        changed.insert(
+                            "PlaybackStatus".to_owned(),
+                            Variant(Box::new(playback_status(state).to_owned())),
+                        );
+                    }
+                    PlayerEvent::TrackChanged { .. } | PlayerEvent::QueueEnded => {
+                        changed.insert(
+                            "Metadata".to_owned(),
+                            Variant(Box::new(metadata(&self.daemon))),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            if !changed.is_empty() {
+                let signal = PropertiesPropertiesChanged {
+                    interface_name: PLAYER_INTERFACE.to_owned(),
+                    changed_properties: changed,
+                    invalidated_properties: Vec::new(),
+                };
+                let path = dbus::Path::from(OBJECT_PATH);
+                // A full output queue means the connection is lost, seen by the next `process`
+                let _ = connection.send(signal.to_emit_message(&path));
+            }
+        }
+    }
+}
+
+/// Describe the application, which can't be raised or quit from the bus
+fn register_root(crossroads: &mut Crossroads) -> IfaceToken<Daemon> {
+    crossroads.register(ROOT_INTERFACE, |builder| {
+        builder.method("Raise", (), (), |_, _: &mut Daemon, ()| Ok(()));
+        builder.method("Quit", (), (), |_, _: &mut Daemon, ()| Ok(()));
+        builder
+            .property("Identity")
+            .emits_changed_const()
+            .get(|_, _| Ok("vibe".to_owned()));
+        builder
+            .property("CanQuit")
+            .emits_changed_const()
+            .get(|_, _| Ok(false));
+        builder
+            .property("CanRaise")
+            .emits_changed_const()
+            .get(|_, _| Ok(false));
+        builder
+            .property("HasTrackList")
+            .emits_changed_const()
+            .get(|_, _| Ok(false));
+        builder
+            .property("SupportedUriSchemes")
+            .emits_changed_const()
+            .get(|_, _| Ok(vec!["file".to_owned()]));
+        builder
+            .property("SupportedMimeTypes")
+            .emits_changed_const()
+            .get(|_, _| {
+                Ok(["audio/flac", "audio/mpeg", "audio/ogg", "audio/wav"]
+                    .iter()
+                    .map(|mime| mime.to_string())
+                    .collect::<Vec<_>>())
+            });
+    })
+}
+
+/// Control the playback of the player of the daemon
+fn register_player(crossroads: &mut Crossroads) -> IfaceToken<Daemon> {
+    crossroads.register(PLAYER_INTERFACE, |builder| {
+        let seeked: SeekedSignal = Arc::from(
+            builder
+                .signal::<(i64,), _>("Seeked", ("Position",))
+                .msg_fn(),
+        );
+
+        builder.method("Play", (), (), |_, daemon: &mut Daemon, ()| {
+            daemon.player().play_stream();
+            Ok(())
+        });
+        builder.method("Pause", (), (), |_, daemon: &mut Daemon, ()| {
+            daemon.player().pause_stream();
+            Ok(())
+        });
+        builder.method("PlayPause", (), (), |_, daemon: &mut Daemon, ()| {
+            daemon.status().toggle_play(daemon.player());
+            Ok(())
+        });
+        builder.method("Stop", (), (), |_, daemon: &mut Daemon, ()| {
+            daemon.player().stop_stream();
+            Ok(())
+        });
+        builder.method("Next", (), (), |_, daemon: &mut Daemon, ()| {
+            daemon.player().next_track();
+            Ok(())
+        });
+        builder.method("Previous", (), (), |_, daemon: &mut Daemon, ()| {
+            daemon.player().previous_track();
+            Ok(())
+        });
+
+        let signal = Arc::clone(&seeked);
+        builder.method(
+            "Seek",
+            ("Offset",),
+            (),
+            move |context, daemon: &mut Daemon, (offset,): (i64,)| {
+                let player = daemon.player();
+                if !player.state().is_active() {
+                    return Ok(());
+                }
+                let position = micros(player.position()).saturating_add(offset).max(0);
+                // Seeking past the end goes to the next track
+                match daemon.status().duration() {
+                    Some(duration) if position > micros(duration) => player.next_track(),
+                    _ => {
+                        player.seek_stream(Duration::from_micros(position as u64));
+                        context.push_msg(signal(context.path(), &(position,)));
+                    }
+                }
+                Ok(())
+            },
+        );
+        builder.method(
+            "SetPosition",
+            ("TrackId", "Position"),
+            (),
+            move |context, daemon: &mut Daemon, (track, position): (dbus::Path, i64)| {
+                let status = daemon.status();
+                // A call meant for a track that is no longer played is ignored
+                if *track != track_id(status.index()) || position < 0 {
+                    return Ok(());
+                }
+                if status
+                    .duration()
+                    .is_some_and(|duration| position > micros(duration))
+                {
+                    return Ok(());
+                }
+                daemon
+                    .player()
+                    .seek_stream(Duration::from_micros(position as u64));
+                context.push_msg(seeked(context.path(), &(position,)));
+                Ok(())
+            },
+        );
+        builder.method(
+            "OpenUri",
+            ("Uri",),
+            (),
+            |_, _: &mut Daemon, (_,): (String,)| -> Result<(), MethodErr> {
+                Err(MethodErr::failed("opening a URI is not supported"))
+            },
+        );
+
+        builder
+            .property("PlaybackStatus")
+            .get(|_, daemon| Ok(playback_status(daemon.player().state()).to_owned()));
+        builder
+            .property("LoopStatus")
+            .get(|_, daemon| {
+                let status = match daemon.player().repeat_mode() {
+                    RepeatMode::Off => "None",
+                    RepeatMode::One => "Track",
+                    RepeatMode::All => "Playlist",
+                };
+                Ok(status.to_owned())
+            })
+            .set(|_, daemon, status: String| {
+                let repeat = match status.as_str() {
+                    "None" => RepeatMode::Off,
+                    "Track" => RepeatMode::One,
+                    "Playlist" => RepeatMode::All,
+                    _ => return Err(MethodErr::invalid_arg(&status)),
+                };
+                daemon.player().set_repeat_mode(repeat);
+                Ok(Some(status))
+            });
+        builder
+            .property("Shuffle")
+            .get(|_, daemon| Ok(daemon.player().shuffle_mode() != ShuffleMode::Off))
+            .set(|_, daemon, shuffle: bool| {
+                daemon.player().set_shuffle_mode(match shuffle {
+                    true => ShuffleMode::NoRepeat,
+                    false => ShuffleMode::Off,
+                });
+                Ok(Some(shuffle))
+            });
+        builder
+            .property("Metadata")
+            .get(|_, daemon| Ok(metadata(daemon)));
+        builder
+            .property("Volume")
+            .get(|_, daemon| Ok(daemon.player().volume() as f64))
+            .set(|_, daemon, volume: f64| {
+                let player = daemon.player();
+                player.set_volume(volume as f32);
+                Ok(Some(player.volume() as f64))
+            });
+        builder
+            .property("Position")
+            .emits_changed_false()
+            .get(|_, daemon| {
+                let player = daemon.player();
+                match player.state().is_active() {
+                    true => Ok(micros(player.position())),
+                    false => Ok(0),
+                }
+            });
+        for name in ["Rate", "MinimumRate", "MaximumRate"] {
+            builder
+                .property(name)
+                .emits_changed_const()
+                .get(|_, _| Ok(1.0));
+        }
+        builder
+            .property("CanGoNext")
+            .emits_changed_false()
+            .get(|_, daemon| Ok(!daemon.player().queue().is_empty()));
+        builder
+            .property("CanGoPrevious")
+            .emits_changed_false()
+            .get(|_, daemon| Ok(!daemon.player().queue().is_empty()));
+        builder
+            .property("CanPlay")
+            .emits_changed_false()
+            .get(|_, daemon| Ok(!daemon.player().queue().is_empty()));
+        builder
+            .property("CanPause")
+            .emits_changed_false()
+            .get(|_, daemon| Ok(daemon.player().state().is_active()));
+        builder
+            .property("CanSeek")
+            .emits_changed_false()
+            .get(|_, daemon| Ok(daemon.player().state().is_active()));
+        builder
+            .property("CanControl")
+            .emits_changed_const()
+            .get(|_, _| Ok(true));
+    })
+}
+
+/// Get the MPRIS name of a state of the player
+fn playback_status(state: PlayerState) -> &'static str {
+    match state {
+        PlayerState::Playing | PlayerState::Loading => "Playing",
+        PlayerState::Paused => "Paused",
+        _ => "Stopped",
+    }
+}
+
+/// Get the id of the track at a position of the queue
+fn track_id(index: Option<usize>) -> String {
+    match index {
+        Some(index) => format!("/org/vibe/Track/{}", index),
+        None => NO_TRACK.to_owned(),
+    }
+}
+
+/// Get the metadata of the track being played, with the `mpris` and `xesam` keys
+fn metadata(daemon: &Daemon) -> PropMap {
+    let status = daemon.status();
+    let mut metadata = PropMap::new();
+    let mut insert = |key: &str, value: Box<dyn RefArg>| {
+        metadata.insert(key.to_owned(), Variant(value));
+    };
+
+    insert(
+        "mpris:trackid",
+        Box::new(dbus::Path::from(track_id(status.index()))),
+    );
+    if let Some(duration) = status.duration() {
+        insert("mpris:length", Box::new(micros(duration)));
+    }
+    if let Some(title) = status.title() {
+        insert("xesam:title", Box::new(title));
+    }
+    if let Some(artist) = status.metadata().artist() {
+        insert("xesam:artist", Box::new(vec![artist.to_owned()]));
+    }
+    if let Some(album) = status.metadata().album() {
+        insert("xesam:album", Box::new(album.to_owned()));
+    }
+    if let Some(track) = status.track() {
+        insert("xesam:url", Box::new(file_url(track.path())));
+    }
+    metadata
+}
+
+/// Get the `file://` URL of a path, relative paths are taken from the working directory
+fn file_url(path: &Path) -> String {
+    let path = std::env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|_| path.to_owned());
+    let mut url = "file://".to_owned();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+#[inline]
+/// Convert a time to the microseconds of MPRIS
+fn micros(time: Duration) -> i64 {
+    time.as_micros() as i64
+}
@@ -0,0 +1,232 @@
+#[cfg(test)]
+
+mod tests_daemon {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+    use vibe_client::protocol::{METHOD_NOT_FOUND, PARSE_ERROR, PLAYER_ERROR};
+    use vibe_client::{Client, ClientError, Event, State};
+    use vibe_daemon::{bind, Daemon};
+    use vibe_engine::player::Player;
+    use vibe_engine::sink::{Capture, CaptureSink};
+    use vibe_engine::stream::{FadeConfig, OutputFormat};
+
+    const SOUND: &str = "../vibe_core/tests/sounds/Test1.wav";
+
+    /// Daemon playing into a capture sink, rendered on a thread while the test runs
+    struct TestDaemon {
+        socket: PathBuf,
+        capture: Capture,
+        rendering: Arc<AtomicBool>,
+    }
+
+    impl TestDaemon {
+        fn start(name: &str) -> Self {
+            let socket =
+                std::env::temp_dir().join(format!("vibe_daemon_{}_{}", std::process::id(), name));
+            let (sink, capture) = CaptureSink::new(OutputFormat {
+                sample_rate: 48000,
+                channels: 2,
+            });
+            let daemon = Daemon::new(Player::with_sink(sink, FadeConfig::default()));
+            let listener = bind(&socket).unwrap();
+            thread::spawn(move || daemon.serve(listener));
+
+            Self {
+                socket,
+                capture,
+                rendering: Arc::new(AtomicBool::new(false)),
+            }
+        }
+
+        fn connect(&self) -> Client {
+            Client::connect(&self.socket).unwrap()
+        }
+
+        /// Render the output ten times faster than real time
+        fn render(&self) {
+            let capture = self.capture.clone();
+            let rendering = Arc::clone(&self.rendering);
+            rendering.store(true, Ordering::SeqCst);
+            thread::spawn(move || {
+                while rendering.load(Ordering::SeqCst) {
+                    capture.advance(Duration::from_millis(10));
+                    thread::sleep(Duration::from_millis(1));
+                }
+            });
+        }
+    }
+
+    impl Drop for TestDaemon {
+        fn drop(&mut self) {
+            self.rendering.store(false, Ordering::SeqCst);
+            let _ = std::fs::remove_file(&self.socket);
+        }
+    }
+
+    fn rpc_code(result: Result<impl std::fmt::Debug, ClientError>) -> i64 {
+        match result {
+            Err(ClientError::RpcError(error)) => error.code,
+            result => panic!("expected an error, got {:?}", result),
+        }
+    }
+
+    #[test]
+
+    fn test_daemon_control() {
+        let daemon = TestDaemon::start("control");
+        let mut client = daemon.connect();
+
+        let status = client.status().unwrap();
+        assert_eq!(status.state, State::Empty);
+        assert_eq!(status.index, None);
+        assert_eq!(status.queue_length, 0);
+
+        assert_eq!(client.enqueue(SOUND).unwrap(), 0);
+        assert_eq!(client.enqueue(SOUND).unwrap(), 1);
+        assert_eq!(rpc_code(client.enqueue("missing.wav")), PLAYER_ERROR);
+        assert_eq!(rpc_code(client.play_index(2)), PLAYER_ERROR);
+        assert_eq!(rpc_code(client.seek(Duration::from_secs(1))), PLAYER_ERROR);
+
+        client.play().unwrap();
+        client.seek(Duration::from_secs(1)).unwrap();
+        client.pause().unwrap();
+        // The seek goes through the output
+        let mut status = client.status().unwrap();
+        while status.position < 1.0 {
+            assert!(daemon.capture.elapsed() < Duration::from_secs(10));
+            daemon.capture.advance(Duration::from_millis(10));
+            thread::sleep(Duration::from_millis(1));
+            status = client.status().unwrap();
+        }
+        assert_eq!(status.state, State::Paused);
+        assert_eq!(status.queue_length, 2);
+        assert_eq!(status.index, Some(0));
+        assert_eq!(status.title.as_deref(), Some("Test1.wav"));
+        assert!((status.duration.unwrap() - 3.0).abs() < 0.1);
+
+        client.play_index(1).unwrap();
+        client.stop().unwrap();
+        let status = client.status().unwrap();
+        assert_eq!(status.state, State::Stopped);
+        assert_eq!(status.index, Some(1));
+        assert_eq!(status.position, 0.0);
+
+        client.clear().unwrap();
+        assert_eq!(client.status().unwrap().queue_length, 0);
+    }
+
+    #[test]
+
+    fn test_daemon_events() {
+        let daemon = TestDaemon::start("events");
+        let mut client = daemon.connect();
+        let mut events = daemon.connect().subscribe().unwrap();
+        events.set_timeout(Some(Duration::from_secs(10))).unwrap();
+
+        client.enqueue(SOUND).unwrap();
+        client.play().unwrap();
+        daemon.render();
+
+        let mut received = Vec::new();
+        for event in &mut events {
+            let event = event.unwrap();
+            // The player ends once the end of the queue is sent
+            let done = event
+                == Event::State {
+                    state: State::Ended,
+                };
+            received.push(event);
+            if done {
+                break;
+            }
+        }
+        assert_eq!(received[0], Event::QueueChanged);
+        assert_eq!(
+            received[1],
+            Event::State {
+                state: State::Loading
+            }
+        );
+        assert!(received.contains(&Event::State {
+            state: State::Playing
+        }));
+        assert!(received
+            .iter()
+            .any(|event| matches!(event, Event::TrackChanged { index: 0, .. })));
+        assert!(received
+            .iter()
+            .any(|event| matches!(event, Event::Position { .. })));
+        assert!(received
+            .iter()
+            .any(|event| matches!(event, Event::TrackEnded { index: 0, .. })));
+        assert!(received.contains(&Event::QueueEnded));
+
+        // Another client sees the end of the queue
+        assert_eq!(client.status().unwrap().state, State::Ended);
+    }
+
+    #[test]
+
+    fn test_daemon_protocol() {
+        let daemon = TestDaemon::start("protocol");
+        let mut stream = UnixStream::connect(&daemon.socket).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut response = || -> Value {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            serde_json::from_str(&line).unwrap()
+        };
+
+        stream.write_all(b"not json\n").unwrap();
+        let error = response();
+        assert_eq!(error["error"]["code"], PARSE_ERROR);
+        assert_eq!(error["id"], Value::Null);
+
+        stream
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"dance\",\"id\":1}\n")
+            .unwrap();
+        let error = response();
+        assert_eq!(error["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(error["id"], 1);
+
+        // A request without id gets no response
+        stream
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"pause\"}\n")
+            .unwrap();
+        stream
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"status\",\"id\":\"a\"}\n")
+            .unwrap();
+        let status = response();
+        assert_eq!(status["id"], "a");
+        assert_eq!(status["result"]["state"], json!("empty"));
+
+        // The events are sent once to a connection subscribed twice
+        for id in 1..=2 {
+            let request = json!({"jsonrpc": "2.0", "method": "subscribe", "id": id});
+            stream
+                .write_all(format!("{}\n", request).as_bytes())
+                .unwrap();
+            assert_eq!(response()["id"], id);
+        }
+        let mut client = daemon.connect();
+        client.enqueue(SOUND).unwrap();
+        client.clear().unwrap();
+        assert_eq!(response()["params"], json!({"type": "queue_changed"}));
+        assert_eq!(response()["params"], json!({"type": "queue_changed"}));
+        stream
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"status\",\"id\":\"b\"}\n")
+            .unwrap();
+        assert_eq!(response()["id"], "b");
+
+        // The socket of a running daemon isn't replaced
+        let error = bind(&daemon.socket).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AddrInUse);
+    }
+}
@@ -0,0 +1,339 @@
+#[cfg(test)]
+
+mod tests_http {
+    use std::fs;
+    use std::io::{Cursor, Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use serde_json::{json, Value};
+    use tungstenite::Message;
+    use vibe_core::encoder::{Encoder, EncoderFormat, EncoderSpec, WavSampleFormat};
+    use vibe_core::library::Library;
+    use vibe_core::{Metadata, Picture};
+    use vibe_daemon::{Daemon, HttpServer};
+    use vibe_engine::player::Player;
+    use vibe_engine::sink::{Capture, CaptureSink};
+    use vibe_engine::stream::{FadeConfig, OutputFormat};
+
+    const SOUNDS: &str = "../vibe_core/tests/sounds";
+
+    /// HTTP server of a library of test sounds, playing into a capture sink
+    struct TestServer {
+        address: SocketAddr,
+        library: PathBuf,
+        capture: Capture,
+    }
+
+    impl TestServer {
+        /// Library of `Test1.wav` and of `Album/Test1.flac` and `Album/Test1.wav`
+        fn start(name: &str) -> Self {
+            let library =
+                std::env::temp_dir().join(format!("vibe_http_{}_{}", std::process::id(), name));
+            fs::create_dir_all(library.join("Album")).unwrap();
+            let sounds = PathBuf::from(SOUNDS);
+            fs::copy(sounds.join("Test1.wav"), library.join("Test1.wav")).unwrap();
+            fs::copy(sounds.join("Test1.flac"), library.join("Album/Test1.flac")).unwrap();
+            fs::copy(sounds.join("Test1.wav"), library.join("Album/Test1.wav")).unwrap();
+
+            let (sink, capture) = CaptureSink::new(OutputFormat {
+                sample_rate: 48000,
+                channels: 2,
+            });
+            let daemon = Daemon::new(Player::with_sink(sink, FadeConfig::default()));
+            let server = HttpServer::new(daemon, Library::new(&library));
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            thread::spawn(move || server.serve(listener));
+
+            Self {
+                address,
+                library,
+                capture,
+            }
+        }
+
+        /// Send a request, returns the status, the headers and the body of the response
+        fn send(&self, method: &str, path: &str, body: Option<Value>) -> (u16, String, Vec<u8>) {
+            let mut stream = TcpStream::connect(self.address).unwrap();
+            let body = body.map(|body| body.to_string()).unwrap_or_default();
+            write!(
+                stream,
+                "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+                 Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                method,
+                path,
+                body.len(),
+                body
+            )
+            .unwrap();
+
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).unwrap();
+            let end = response
+                .windows(4)
+                .position(|window| window == b"\r\n\r\n")
+                .unwrap();
+            let head = String::from_utf8(response[..end].to_vec()).unwrap();
+            let status = head[9..12].parse().unwrap();
+            (status, head, response[end + 4..].to_vec())
+        }
+
+        /// Send a request, returns the status and the JSON body of the response
+        fn request(&self, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+            let (status, _, body) = self.send(method, path, body);
+            let body = match body.as_slice() {
+                b"" => Value::Null,
+                body => serde_json::from_slice(body).unwrap(),
+            };
+            (status, body)
+        }
+
+        /// Get a file, returns its content type and its content
+        fn get_file(&self, path: &str) -> (String, Vec<u8>) {
+            let (status, head, body) = self.send("GET", path, None);
+            assert_eq!(status, 200, "GET {}", path);
+            let content_type = head
+                .lines()
+                .find_map(|line| line.strip_prefix("Content-Type: "))
+                .unwrap()
+                .to_owned();
+            (content_type, body)
+        }
+
+        fn get(&self, path: &str) -> Value {
+            let (status, body) = self.request("GET", path, None);
+            assert_eq!(status, 200, "GET {}: {}", path, body);
+            body
+        }
+
+        fn post(&self, path: &str, body: Value) -> u16 {
+            self.request("POST", path, Some(body)).0
+        }
+
+        /// Render the output until a condition on the status is met
+        fn wait_status<F: Fn(&Value) -> bool>(&self, condition: F) -> Value {
+            let start = Instant::now();
+            loop {
+                let status = self.get("/api/status");
+                if condition(&status) {
+                    return status;
+                }
+                assert!(start.elapsed() < Duration::from_secs(10));
+                self.capture.advance(Duration::from_millis(10));
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    impl Drop for TestServer {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.library);
+        }
+    }
+
+    #[test]
+
+    fn test_http_library() {
+        let server = TestServer::start("library");
+
+        let root = server.get("/api/library");
+        assert_eq!(
+            root,
+            json!([
+                {"type": "directory", "path": "Album", "name": "Album"},
+                {"type": "track", "path": "Test1.wav", "name": "Test1.wav"},
+            ])
+        );
+        let album = server.get("/api/library?path=%2FAlbum");
+        assert_eq!(album[0]["path"], "Album/Test1.flac");
+        assert_eq!(album.as_array().unwrap().len(), 2);
+        assert_eq!(server.request("GET", "/api/library?path=..", None).0, 404);
+
+        assert_eq!(server.get("/api/queue"), json!([]));
+        let (status, added) = server.request("POST", "/api/queue", Some(json!({"path": "Album"})));
+        assert_eq!(status, 200);
+        assert_eq!(added["index"], 0);
+        let (_, added) = server.request("POST", "/api/queue", Some(json!({"path": "Test1.wav"})));
+        assert_eq!(added["index"], 2);
+        assert_eq!(
+            server.post("/api/queue", json!({"path": "Missing.wav"})),
+            404
+        );
+
+        let queue = server.get("/api/queue");
+        let paths: Vec<&str> = queue
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|song| song["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["Album/Test1.flac", "Album/Test1.wav", "Test1.wav"]);
+        assert_eq!(queue[1]["index"], 1);
+        assert_eq!(queue[1]["title"], "Test1.wav");
+        assert!((queue[1]["duration"].as_f64().unwrap() - 3.0).abs() < 0.1);
+
+        assert_eq!(server.request("DELETE", "/api/queue/0", None).0, 204);
+        assert_eq!(server.request("DELETE", "/api/queue/5", None).0, 404);
+        assert_eq!(server.get("/api/queue").as_array().unwrap().len(), 2);
+        assert_eq!(server.request("DELETE", "/api/queue", None).0, 204);
+        assert_eq!(server.get("/api/queue"), json!([]));
+    }
+
+    #[test]
+
+    fn test_http_control() {
+        let server = TestServer::start("control");
+        assert_eq!(server.get("/api/status")["state"], "empty");
+        server.post("/api/queue", json!({"path": "Album"}));
+
+        let (status, error) = server.request("POST", "/api/play", Some(json!({"index": 5})));
+        assert_eq!(status, 409);
+        assert!(error["error"].is_string());
+        assert_eq!(server.post("/api/seek", json!({"position": 1.0})), 409);
+        assert_eq!(server.request("POST", "/api/play", None).0, 204);
+        assert_eq!(server.post("/api/seek", json!({"position": 1.5})), 204);
+        assert_eq!(server.post("/api/seek", json!({"position": "end"})), 400);
+        assert_eq!(server.request("POST", "/api/pause", None).0, 204);
+
+        // The seek goes through the output
+        let status = server.wait_status(|status| status["position"].as_f64().unwrap() >= 1.5);
+        assert_eq!(status["state"], "paused");
+        assert_eq!(status["index"], 0);
+        assert_eq!(status["queue_length"], 2);
+
+        assert_eq!(server.request("POST", "/api/next", None).0, 204);
+        server.wait_status(|status| status["index"] == 1);
+        assert_eq!(server.request("POST", "/api/stop", None).0, 204);
+        assert_eq!(server.get("/api/status")["state"], "stopped");
+
+        assert_eq!(server.request("GET", "/api/play", None).0, 405);
+        assert_eq!(server.request("GET", "/api/dance", None).0, 404);
+        let (status, _) = server.request("POST", "/api/queue", None);
+        assert_eq!(status, 400);
+        let mut stream = TcpStream::connect(server.address).unwrap();
+        write!(
+            stream,
+            "POST /api/play HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Content-Length: 3\r\n\r\n{{{{{{"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400"));
+
+        // The bodies are limited, whether their length is given or not
+        let path = "a".repeat(100 * 1024);
+        let (status, error) = server.request("POST", "/api/queue", Some(json!({ "path": path })));
+        assert_eq!(status, 413);
+        assert!(error["error"].is_string());
+        let mut stream = TcpStream::connect(server.address).unwrap();
+        write!(
+            stream,
+            "POST /api/queue HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+             Transfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            path.len(),
+            path
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 413"));
+    }
+
+    #[test]
+
+    fn test_http_events() {
+        let server = TestServer::start("events");
+        // A plain request isn't a WebSocket
+        assert_eq!(server.request("GET", "/api/events", None).0, 400);
+
+        let url = format!("ws://{}/api/events", server.address);
+        let (mut socket, _) = tungstenite::connect(url).unwrap();
+        server.post("/api/queue", json!({"path": "Test1.wav"}));
+        server.request("POST", "/api/play", None);
+        let capture = server.capture.clone();
+        thread::spawn(move || {
+            while capture.elapsed() < Duration::from_secs(10) {
+                capture.advance(Duration::from_millis(10));
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        let mut events = Vec::new();
+        loop {
+            let event: Value = match socket.read().unwrap() {
+                Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                _ => continue,
+            };
+            let done = event == json!({"type": "state", "state": "ended"});
+            events.push(event);
+            if done {
+                break;
+            }
+        }
+        assert_eq!(events[0], json!({"type": "queue_changed"}));
+        assert_eq!(events[1], json!({"type": "state", "state": "loading"}));
+        assert!(events.contains(&json!({"type": "state", "state": "playing"})));
+        assert!(events
+            .iter()
+            .any(|event| event["type"] == "track_changed" && event["index"] == 0));
+        assert!(events.iter().any(|event| event["type"] == "position"));
+        assert!(events.contains(&json!({"type": "queue_ended"})));
+    }
+
+    #[test]
+
+    fn test_http_web_client() {
+        let server = TestServer::start("web");
+        let (content_type, page) = server.get_file("/");
+        assert!(content_type.starts_with("text/html"));
+        let page = String::from_utf8(page).unwrap();
+        assert!(page.contains("/app.js") && page.contains("/style.css"));
+        assert_eq!(server.get_file("/index.html").1, page.as_bytes());
+        let (content_type, script) = server.get_file("/app.js");
+        assert!(content_type.starts_with("application/javascript"));
+        assert!(String::from_utf8(script).unwrap().contains("/api/events"));
+        assert!(server.get_file("/style.css").0.starts_with("text/css"));
+        assert_eq!(server.request("GET", "/missing.js", None).0, 404);
+        assert_eq!(server.request("POST", "/", None).0, 404);
+
+        // A track with a cover
+        let mut metadata = Metadata::new();
+        metadata.set("TITLE", "Covered");
+        metadata.push_picture(Picture {
+            picture_type: Picture::FRONT_COVER,
+            mime_type: "image/png".to_owned(),
+            description: "Cover".to_owned(),
+            data: (0..=255).collect(),
+        });
+        let spec = EncoderSpec {
+            sample_rate: 48000,
+            channels: 2,
+            format: EncoderFormat::Wav(WavSampleFormat::Pcm16),
+        };
+        let mut encoder = Encoder::with_metadata(Cursor::new(Vec::new()), spec, &metadata).unwrap();
+        encoder.write(&vec![0.0; 96000]).unwrap();
+        let data = encoder.finish().unwrap().into_inner();
+        fs::write(server.library.join("Covered.wav"), data).unwrap();
+
+        assert_eq!(server.request("GET", "/api/cover", None).0, 404);
+        server.post("/api/queue", json!({"path": "Test1.wav"}));
+        server.post("/api/queue", json!({"path": "Covered.wav"}));
+        server.request("POST", "/api/play", None);
+        server.wait_status(|status| status["state"] == "playing");
+        // The track has no picture
+        assert_eq!(server.request("GET", "/api/cover", None).0, 404);
+
+        server.post("/api/play", json!({"index": 1}));
+        server.wait_status(|status| status["title"] == "Covered");
+        let (content_type, cover) = server.get_file("/api/cover?track=1");
+        assert_eq!(content_type, "image/png");
+        assert_eq!(cover, (0..=255).collect::<Vec<u8>>());
+        assert_eq!(server.request("POST", "/api/cover", None).0, 405);
+        server.request("POST", "/api/stop", None);
+        assert_eq!(server.request("GET", "/api/cover", None).0, 404);
+    }
+}
@@ -0,0 +1,323 @@
+#[cfg(test)]
+
+mod tests_mpd {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::Duration;
+
+    use vibe_core::library::Library;
+    use vibe_daemon::{Daemon, MpdServer};
+    use vibe_engine::player::Player;
+    use vibe_engine::sink::{Capture, CaptureSink};
+    use vibe_engine::stream::{FadeConfig, OutputFormat};
+
+    const SOUNDS: &str = "../vibe_core/tests/sounds";
+
+    /// MPD server of a library of test sounds, playing into a capture sink
+    struct TestServer {
+        address: SocketAddr,
+        library: PathBuf,
+        capture: Capture,
+    }
+
+    impl TestServer {
+        /// Library of `Test1.wav` and of `Album/Test1.flac` and `Album/Test1.wav`
+        fn start(name: &str) -> Self {
+            let library =
+                std::env::temp_dir().join(format!("vibe_mpd_{}_{}", std::process::id(), name));
+            fs::create_dir_all(library.join("Album")).unwrap();
+            let sounds = PathBuf::from(SOUNDS);
+            fs::copy(sounds.join("Test1.wav"), library.join("Test1.wav")).unwrap();
+            fs::copy(sounds.join("Test1.flac"), library.join("Album/Test1.flac")).unwrap();
+            fs::copy(sounds.join("Test1.wav"), library.join("Album/Test1.wav")).unwrap();
+
+            let (sink, capture) = CaptureSink::new(OutputFormat {
+                sample_rate: 48000,
+                channels: 2,
+            });
+            let daemon = Daemon::new(Player::with_sink(sink, FadeConfig::default()));
+            let server = MpdServer::new(daemon, Library::new(&library));
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap();
+            thread::spawn(move || server.serve(listener));
+
+            Self {
+                address,
+                library,
+                capture,
+            }
+        }
+
+        fn connect(&self) -> MpdClient {
+            let stream = TcpStream::connect(self.address).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(10)))
+                .unwrap();
+            let mut client = MpdClient {
+                reader: BufReader::new(stream.try_clone().unwrap()),
+                stream,
+            };
+            assert!(client.line().starts_with("OK MPD "));
+            client
+        }
+    }
+
+    impl Drop for TestServer {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.library);
+        }
+    }
+
+    struct MpdClient {
+        stream: TcpStream,
+        reader: BufReader<TcpStream>,
+    }
+
+    impl MpdClient {
+        fn line(&mut self) -> String {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            line.trim_end().to_owned()
+        }
+
+        fn send(&mut self, command: &str) {
+            self.stream
+                .write_all(format!("{}\n", command).as_bytes())
+                .unwrap();
+        }
+
+        /// Read the lines of a response, up to its `OK` or its `ACK`
+        fn response(&mut self) -> Vec<String> {
+            let mut lines = Vec::new();
+            loop {
+                let line = self.line();
+                let done = line == "OK" || line.starts_with("ACK ");
+                lines.push(line);
+                if done {
+                    return lines;
+                }
+            }
+        }
+
+        fn command(&mut self, command: &str) -> Vec<String> {
+            self.send(command);
+            self.response()
+        }
+
+        /// Get the value of a field of the response of a command
+        fn field(&mut self, command: &str, key: &str) -> Option<String> {
+            let prefix = format!("{}: ", key);
+            self.command(command)
+                .into_iter()
+                .find_map(|line| line.strip_prefix(&prefix).map(str::to_owned))
+        }
+    }
+
+    fn files(lines: &[String]) -> Vec<&str> {
+        lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("file: "))
+            .collect()
+    }
+
+    #[test]
+
+    fn test_mpd_queue() {
+        let server = TestServer::start("queue");
+        let mut client = server.connect();
+
+        assert_eq!(client.command("ping"), ["OK"]);
+        let root = client.command("lsinfo");
+        assert_eq!(root[0], "directory: Album");
+        assert_eq!(files(&root), ["Test1.wav"]);
+        assert!(root.contains(&"Time: 3".to_owned()));
+        assert_eq!(
+            files(&client.command("lsinfo \"/Album\"")),
+            ["Album/Test1.flac", "Album/Test1.wav"]
+        );
+        assert_eq!(
+            client.command("lsinfo Missing"),
+            ["ACK [50@0] {lsinfo} No such directory"]
+        );
+
+        assert_eq!(client.command("add Album"), ["OK"]);
+        // The ids follow the tracks, whatever their position
+        assert_eq!(client.command("addid Test1.wav 0"), ["Id: 3", "OK"]);
+        assert_eq!(
+            client.command("add ../Test1.wav"),
+            ["ACK [50@0] {add} No such song"]
+        );
+        let queue = client.command("playlistinfo");
+        assert_eq!(
+            files(&queue),
+            ["Test1.wav", "Album/Test1.flac", "Album/Test1.wav"]
+        );
+        assert!(queue.contains(&"Pos: 2".to_owned()));
+        let ids: Vec<&str> = queue
+            .iter()
+            .filter_map(|line| line.strip_prefix("Id: "))
+            .collect();
+        assert_eq!(ids, ["3", "1", "2"]);
+        assert_eq!(
+            files(&client.command("playlistinfo 1:")),
+            ["Album/Test1.flac", "Album/Test1.wav"]
+        );
+
+        let version = client.field("status", "playlist").unwrap();
+        assert_eq!(client.command("deleteid 1"), ["OK"]);
+        assert_eq!(
+            client.command("deleteid 1"),
+            ["ACK [50@0] {deleteid} No such song"]
+        );
+        assert_eq!(client.command("delete 0:1"), ["OK"]);
+        let queue = client.command("playlistinfo");
+        assert_eq!(files(&queue), ["Album/Test1.wav"]);
+        assert!(queue.contains(&"Id: 2".to_owned()));
+        assert_eq!(
+            client.field("status", "playlistlength").as_deref(),
+            Some("1")
+        );
+        assert_ne!(client.field("status", "playlist").unwrap(), version);
+
+        assert_eq!(client.command("clear"), ["OK"]);
+        assert_eq!(client.command("playlistinfo"), ["OK"]);
+    }
+
+    #[test]
+
+    fn test_mpd_playback() {
+        let server = TestServer::start("playback");
+        let mut client = server.connect();
+
+        assert_eq!(client.field("status", "state").as_deref(), Some("stop"));
+        assert_eq!(client.command("currentsong"), ["OK"]);
+        client.command("add Test1.wav");
+        client.command("add Album/Test1.wav");
+
+        assert_eq!(client.command("playid 2"), ["OK"]);
+        assert_eq!(client.field("status", "state").as_deref(), Some("play"));
+        assert_eq!(client.field("status", "song").as_deref(), Some("1"));
+        assert_eq!(client.field("status", "songid").as_deref(), Some("2"));
+        assert_eq!(files(&client.command("currentsong")), ["Album/Test1.wav"]);
+
+        assert_eq!(client.command("seekcur 1.5"), ["OK"]);
+        assert_eq!(client.command("pause"), ["OK"]);
+        // The seek goes through the output
+        loop {
+            let elapsed: f64 = client.field("status", "elapsed").unwrap().parse().unwrap();
+            if elapsed >= 1.5 {
+                break;
+            }
+            assert!(server.capture.elapsed() < Duration::from_secs(10));
+            server.capture.advance(Duration::from_millis(10));
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(client.field("status", "state").as_deref(), Some("pause"));
+        assert_eq!(client.field("status", "duration").as_deref(), Some("3.000"));
+
+        assert_eq!(client.command("pause 0"), ["OK"]);
+        assert_eq!(client.field("status", "state").as_deref(), Some("play"));
+        assert_eq!(client.command("previous"), ["OK"]);
+        assert_eq!(client.field("status", "song").as_deref(), Some("0"));
+        assert_eq!(client.command("stop"), ["OK"]);
+        assert_eq!(client.field("status", "state").as_deref(), Some("stop"));
+        assert_eq!(
+            client.command("play 5"),
+            ["ACK [2@0] {play} Bad song index"]
+        );
+        assert_eq!(
+            client.command("playid 5"),
+            ["ACK [50@0] {playid} No such song"]
+        );
+
+        assert_eq!(client.command("repeat 1"), ["OK"]);
+        assert_eq!(client.command("single 1"), ["OK"]);
+        assert_eq!(client.field("status", "repeat").as_deref(), Some("1"));
+        assert_eq!(client.field("status", "single").as_deref(), Some("1"));
+        assert_eq!(client.command("random 1"), ["OK"]);
+        assert_eq!(client.field("status", "random").as_deref(), Some("1"));
+    }
+
+    #[test]
+
+    fn test_mpd_idle() {
+        let server = TestServer::start("idle");
+        let mut idle = server.connect();
+        let mut client = server.connect();
+
+        idle.send("idle playlist");
+        client.command("add Test1.wav");
+        assert_eq!(idle.response(), ["changed: playlist", "OK"]);
+
+        // The changes made between two `idle` are reported at once
+        client.command("play");
+        client.command("repeat 1");
+        assert_eq!(
+            idle.command("idle"),
+            ["changed: player", "changed: options", "OK"]
+        );
+
+        idle.send("idle mixer");
+        client.command("setvol 50");
+        assert_eq!(idle.response(), ["changed: mixer", "OK"]);
+
+        idle.send("idle");
+        idle.send("noidle");
+        assert_eq!(idle.response(), ["OK"]);
+        assert_eq!(
+            idle.command("idle database"),
+            ["ACK [2@0] {idle} Unrecognized idle event: database"]
+        );
+    }
+
+    #[test]
+
+    fn test_mpd_commands() {
+        let server = TestServer::start("commands");
+        let mut client = server.connect();
+
+        assert_eq!(
+            client.command("dance"),
+            ["ACK [5@0] {dance} unknown command \"dance\""]
+        );
+        assert_eq!(
+            client.command("play 1 2"),
+            ["ACK [2@0] {play} wrong number of arguments for \"play\""]
+        );
+        assert_eq!(
+            client.command("add \"Test1.wav"),
+            ["ACK [2@0] {} Missing closing '\"'"]
+        );
+        assert_eq!(client.field("status", "volume").as_deref(), Some("100"));
+        assert_eq!(client.command("setvol 50"), ["OK"]);
+        assert_eq!(client.field("status", "volume").as_deref(), Some("50"));
+        assert_eq!(
+            client.command("setvol 150"),
+            ["ACK [2@0] {setvol} Invalid volume value"]
+        );
+        assert!(client
+            .command("commands")
+            .contains(&"command: playlistinfo".to_owned()));
+
+        client.send("command_list_ok_begin");
+        client.send("add Test1.wav");
+        client.send("addid \"Album/Test1.wav\"");
+        client.send("command_list_end");
+        assert_eq!(client.response(), ["list_OK", "Id: 2", "list_OK", "OK"]);
+
+        // The list stops at its first error
+        client.send("command_list_begin");
+        client.send("clear");
+        client.send("add Missing.wav");
+        client.send("add Test1.wav");
+        client.send("command_list_end");
+        assert_eq!(client.response(), ["ACK [50@1] {add} No such song"]);
+        assert_eq!(client.command("playlistinfo"), ["OK"]);
+
+        client.send("close");
+        assert_eq!(client.line(), "");
+    }
+}
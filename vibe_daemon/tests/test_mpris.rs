@@ -0,0 +1,266 @@
+#[cfg(test)]
+
+mod tests_mpris {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use dbus::arg::{prop_cast, PropMap};
+    use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+    use dbus::blocking::{Connection, Proxy};
+    use dbus::channel::{Channel, MatchingReceiver};
+    use dbus::message::MatchRule;
+    use dbus::Message;
+    use vibe_daemon::mpris::{Mpris, BUS_NAME, OBJECT_PATH, PLAYER_INTERFACE, ROOT_INTERFACE};
+    use vibe_daemon::Daemon;
+    use vibe_engine::player::Player;
+    use vibe_engine::queue::RepeatMode;
+    use vibe_engine::sink::{Capture, CaptureSink};
+    use vibe_engine::stream::{FadeConfig, OutputFormat};
+
+    const SOUND: &str = "../vibe_core/tests/sounds/Test1.wav";
+
+    const BUS_CONFIG: &str = r#"<!DOCTYPE busconfig PUBLIC
+ "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:tmpdir=/tmp</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#;
+
+    /// Private session bus, stopped with the test
+    struct TestBus {
+        process: Child,
+        address: String,
+    }
+
+    impl TestBus {
+        /// Start `dbus-daemon`, none if it isn't installed
+        fn start() -> Option<Self> {
+            let config =
+                std::env::temp_dir().join(format!("vibe_mpris_{}.conf", std::process::id()));
+            std::fs::write(&config, BUS_CONFIG).unwrap();
+            let mut process = Command::new("dbus-daemon")
+                .arg(format!("--config-file={}", config.display()))
+                .args(["--nofork", "--print-address"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+
+            let mut address = String::new();
+            let stdout = process.stdout.take().unwrap();
+            BufReader::new(stdout).read_line(&mut address).unwrap();
+            let _ = std::fs::remove_file(&config);
+            Some(Self {
+                process,
+                address: address.trim().to_owned(),
+            })
+        }
+
+        fn connect(&self) -> Connection {
+            let mut channel = Channel::open_private(&self.address).unwrap();
+            channel.register().unwrap();
+            Connection::from(channel)
+        }
+    }
+
+    impl Drop for TestBus {
+        fn drop(&mut self) {
+            let _ = self.process.kill();
+            let _ = self.process.wait();
+        }
+    }
+
+    fn proxy(connection: &Connection) -> Proxy<'_, &Connection> {
+        connection.with_proxy(BUS_NAME, OBJECT_PATH, Duration::from_secs(5))
+    }
+
+    fn call(connection: &Connection, method: &str) {
+        let (): () = proxy(connection)
+            .method_call(PLAYER_INTERFACE, method, ())
+            .unwrap();
+    }
+
+    fn status(connection: &Connection) -> String {
+        proxy(connection)
+            .get(PLAYER_INTERFACE, "PlaybackStatus")
+            .unwrap()
+    }
+
+    fn metadata(connection: &Connection) -> PropMap {
+        proxy(connection).get(PLAYER_INTERFACE, "Metadata").unwrap()
+    }
+
+    fn track_id(metadata: &PropMap) -> String {
+        prop_cast::<dbus::Path>(metadata, "mpris:trackid")
+            .unwrap()
+            .to_string()
+    }
+
+    /// Wait for a condition checked over the bus, rendering the output meanwhile
+    fn wait_until<F: FnMut() -> bool>(capture: &Capture, mut condition: F) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            capture.advance(Duration::from_millis(10));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Collect the signals of the player
+    fn listen(connection: &Connection) -> Arc<Mutex<Vec<Message>>> {
+        let signals = Arc::new(Mutex::new(Vec::new()));
+        let rule = MatchRule::new_signal("org.freedesktop.DBus.Properties", "PropertiesChanged")
+            .with_path(OBJECT_PATH);
+        let seeked = MatchRule::new_signal(PLAYER_INTERFACE, "Seeked").with_path(OBJECT_PATH);
+        for rule in [rule, seeked] {
+            connection.add_match_no_cb(&rule.match_str()).unwrap();
+            let signals = Arc::clone(&signals);
+            connection.start_receive(
+                rule,
+                Box::new(move |message, _| {
+                    signals.lock().unwrap().push(message);
+                    true
+                }),
+            );
+        }
+        signals
+    }
+
+    #[test]
+
+    fn test_mpris_player() {
+        let bus = match TestBus::start() {
+            Some(bus) => bus,
+            None => {
+                eprintln!("dbus-daemon isn't installed, skipping the MPRIS test");
+                return;
+            }
+        };
+        let (sink, capture) = CaptureSink::new(OutputFormat {
+            sample_rate: 48000,
+            channels: 2,
+        });
+        let player = Player::with_sink(sink, FadeConfig::default());
+        player.enqueue(SOUND);
+        player.enqueue(SOUND);
+        let daemon = Daemon::new(player.clone());
+        let server = bus.connect();
+        thread::spawn(move || Mpris::new(daemon).serve(&server));
+
+        let client = bus.connect();
+        let signals = listen(&client);
+        // The name is taken once the server runs
+        let start = Instant::now();
+        while proxy(&client)
+            .get::<String>(ROOT_INTERFACE, "Identity")
+            .is_err()
+        {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(status(&client), "Stopped");
+        assert_eq!(
+            track_id(&metadata(&client)),
+            "/org/mpris/MediaPlayer2/TrackList/NoTrack"
+        );
+        let can_play: bool = proxy(&client).get(PLAYER_INTERFACE, "CanPlay").unwrap();
+        assert!(can_play);
+
+        call(&client, "PlayPause");
+        assert_eq!(status(&client), "Playing");
+        wait_until(&capture, || {
+            prop_cast::<i64>(&metadata(&client), "mpris:length").is_some()
+        });
+        let playing = metadata(&client);
+        assert_eq!(track_id(&playing), "/org/vibe/Track/0");
+        assert_eq!(
+            prop_cast::<String>(&playing, "xesam:title").map(String::as_str),
+            Some("Test1.wav")
+        );
+        assert!((prop_cast::<i64>(&playing, "mpris:length").unwrap() - 3_000_000).abs() < 100_000);
+        assert!(prop_cast::<String>(&playing, "xesam:url")
+            .unwrap()
+            .starts_with("file:///"));
+
+        call(&client, "PlayPause");
+        assert_eq!(status(&client), "Paused");
+        let (): () = proxy(&client)
+            .method_call(PLAYER_INTERFACE, "Seek", (1_000_000i64,))
+            .unwrap();
+        wait_until(&capture, || {
+            let position: i64 = proxy(&client).get(PLAYER_INTERFACE, "Position").unwrap();
+            position >= 1_000_000
+        });
+
+        // A position for another track is ignored
+        let other = dbus::Path::from("/org/vibe/Track/1");
+        let (): () = proxy(&client)
+            .method_call(PLAYER_INTERFACE, "SetPosition", (other, 0i64))
+            .unwrap();
+        let current = dbus::Path::from("/org/vibe/Track/0");
+        let (): () = proxy(&client)
+            .method_call(PLAYER_INTERFACE, "SetPosition", (current, 2_000_000i64))
+            .unwrap();
+        wait_until(&capture, || {
+            let position: i64 = proxy(&client).get(PLAYER_INTERFACE, "Position").unwrap();
+            position >= 2_000_000
+        });
+
+        proxy(&client)
+            .set(PLAYER_INTERFACE, "Volume", 0.5f64)
+            .unwrap();
+        let volume: f64 = proxy(&client).get(PLAYER_INTERFACE, "Volume").unwrap();
+        assert_eq!(volume, 0.5);
+        proxy(&client)
+            .set(PLAYER_INTERFACE, "Volume", 2.0f64)
+            .unwrap();
+        let volume: f64 = proxy(&client).get(PLAYER_INTERFACE, "Volume").unwrap();
+        assert_eq!(volume, 1.0);
+        proxy(&client)
+            .set(PLAYER_INTERFACE, "LoopStatus", "Track".to_owned())
+            .unwrap();
+        assert_eq!(player.repeat_mode(), RepeatMode::One);
+
+        call(&client, "Next");
+        wait_until(&capture, || {
+            track_id(&metadata(&client)) == "/org/vibe/Track/1"
+        });
+        call(&client, "Stop");
+        assert_eq!(status(&client), "Stopped");
+
+        // The changes are signaled to the widgets
+        wait_until(&capture, || {
+            client.process(Duration::from_millis(10)).unwrap();
+            let signals = signals.lock().unwrap();
+            let seeks: Vec<i64> = signals
+                .iter()
+                .filter(|signal| signal.member().as_deref() == Some("Seeked"))
+                .filter_map(|signal| signal.get1())
+                .collect();
+            let changes = signals
+                .iter()
+                .filter(|signal| signal.member().as_deref() == Some("PropertiesChanged"))
+                .filter_map(|signal| signal.get2::<String, PropMap>().1)
+                .filter_map(|changed| prop_cast::<String>(&changed, "PlaybackStatus").cloned())
+                .collect::<Vec<_>>();
+            // The first seek is from where the playback was paused
+            seeks.len() == 2
+                && seeks[0] >= 1_000_000
+                && seeks[1] == 2_000_000
+                && changes.contains(&"Paused".to_owned())
+                && changes.last().map(String::as_str) == Some("Stopped")
+        });
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Shape of the filter of an equalizer band
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterType {
+    /// Boost or cut around the frequency
+    Peaking,
+    /// Boost or cut below the frequency
+    LowShelf,
+    /// Boost or cut above the frequency
+    HighShelf,
+    /// Remove the content below the frequency
+    HighPass,
+    /// Remove the content above the frequency
+    LowPass,
+}
+
+/// Band of an equalizer
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Band {
+    pub filter: FilterType,
+    /// Center or corner frequency in Hz
+    pub frequency: f32,
+    /// Gain in dB, unused by the pass filters
+    #[serde(default)]
+    pub gain: f32,
+    /// Quality factor, the higher the narrower
+    #[serde(default = "default_q")]
+    pub q: f32,
+}
+
+#[inline]
+fn default_q() -> f32 {
+    std::f32::consts::FRAC_1_SQRT_2
+}
+
+impl Band {
+    #[inline]
+    /// Create a new band
+    pub fn new(filter: FilterType, frequency: f32, gain: f32, q: f32) -> Self {
+        Self {
+            filter,
+            frequency,
+            gain,
+            q,
+        }
+    }
+}
+
+/// Coefficients of a second order filter, normalized by `a0`
+///
+/// The formulas are the ones of the Audio EQ Cookbook by Robert Bristow-Johnson
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+/// Memory of a biquad for one channel
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BiquadState {
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    /// Compute the coefficients of a band for the given sample rate
+    pub fn new(band: &Band, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1) as f64;
+        // Keep the frequency under Nyquist so the filter stays stable
+        let frequency = (band.frequency as f64).max(1.0).min(sample_rate * 0.49);
+        let q = (band.q as f64).max(1e-3);
+
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a = 10f64.powf(band.gain as f64 / 40.0);
+
+        let (b0, b1, b2, a0, a1, a2) = match band.filter {
+            FilterType::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            FilterType::LowShelf => {
+                let sqrt = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos + sqrt),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - sqrt),
+                    (a + 1.0) + (a - 1.0) * cos + sqrt,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - sqrt,
+                )
+            }
+            FilterType::HighShelf => {
+                let sqrt = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos + sqrt),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - sqrt),
+                    (a + 1.0) - (a - 1.0) * cos + sqrt,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - sqrt,
+                )
+            }
+            FilterType::HighPass => (
+                (1.0 + cos) / 2.0,
+                -(1.0 + cos),
+                (1.0 + cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            FilterType::LowPass => (
+                (1.0 - cos) / 2.0,
+                1.0 - cos,
+                (1.0 - cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+        };
+
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    #[inline]
+    /// Filter one sample, using the transposed direct form II
+    pub(crate) fn process(&self, state: &mut BiquadState, input: f64) -> f64 {
+        let output = self.b0 * input + state.z1;
+        state.z1 = self.b1 * input - self.a1 * output + state.z2;
+        state.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+
+    /// Get the gain of the filter at the given frequency, as a linear factor
+    pub fn magnitude(&self, frequency: f32, sample_rate: u32) -> f32 {
+        let w = 2.0 * PI * frequency as f64 / sample_rate.max(1) as f64;
+        // Evaluate the transfer function on the unit circle, z^-1 = cos(w) - i sin(w)
+        let (sin1, cos1) = w.sin_cos();
+        let (sin2, cos2) = (2.0 * w).sin_cos();
+
+        let numerator = (
+            self.b0 + self.b1 * cos1 + self.b2 * cos2,
+            -(self.b1 * sin1 + self.b2 * sin2),
+        );
+        let denominator = (
+            1.0 + self.a1 * cos1 + self.a2 * cos2,
+            -(self.a1 * sin1 + self.a2 * sin2),
+        );
+
+        let numerator = numerator.0.hypot(numerator.1);
+        let denominator = denominator.0.hypot(denominator.1);
+        (numerator / denominator) as f32
+    }
+
+    #[inline]
+    /// Get the gain of the filter at the given frequency, in dB
+    pub fn magnitude_db(&self, frequency: f32, sample_rate: u32) -> f32 {
+        20.0 * self.magnitude(frequency, sample_rate).log10()
+    }
+}
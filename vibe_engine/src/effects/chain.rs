@@ -0,0 +1,388 @@
+use crossbeam::channel::{bounded, Receiver, Sender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::{error::Error, fmt::Display};
+
+use super::AudioProcessor;
+use crate::stream::OutputFormat;
+
+/// Maximum number of processors in a chain, the audio side never grows its storage
+pub const MAX_EFFECTS: usize = 32;
+
+/// Number of edits the audio side can have waiting
+const COMMAND_CAPACITY: usize = 64;
+
+/// An error encountered while editing an effect chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainError {
+    /// The chain already holds `MAX_EFFECTS` processors.
+    Full,
+    /// No processor at the given index.
+    InvalidIndex(usize),
+    /// The audio side has too many edits waiting, the output isn't rendering.
+    Busy,
+}
+
+impl Error for ChainError {}
+
+impl Display for ChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChainError::Full => write!(f, "the effect chain is full"),
+            ChainError::InvalidIndex(index) => write!(f, "no effect at index {}", index),
+            ChainError::Busy => write!(f, "the output isn't applying the effect edits"),
+        }
+    }
+}
+
+/// Processor that couldn't be added to a chain, given back with the reason
+pub type Rejected = (ChainError, Box<dyn AudioProcessor>);
+
+/// Edits sent from the control side to the audio side
+enum ChainCommand {
+    Insert(usize, Box<dyn AudioProcessor>),
+    Remove(usize),
+    Replace(usize, Box<dyn AudioProcessor>),
+    Move(usize, usize),
+    Bypass(usize, bool),
+    Parameter(usize, usize, f32),
+    Clear,
+}
+
+/// Processor of the chain with its bypass flag
+struct Slot {
+    processor: Box<dyn AudioProcessor>,
+    bypass: bool,
+}
+
+/// Description of the processors kept by the control side
+struct ChainState {
+    tx_commands: Sender<ChainCommand>,
+    /// Processors removed from the audio side, dropped by the engine thread or on the next edit
+    /// so the audio thread never frees memory
+    rx_garbage: Receiver<Box<dyn AudioProcessor>>,
+    format: OutputFormat,
+    latencies: Vec<usize>,
+    bypassed: Vec<bool>,
+}
+
+impl ChainState {
+    /// Send an edit to the audio side and drop the processors it gave back
+    ///
+    /// The edit is given back if the audio side isn't applying the edits
+    fn send(&mut self, command: ChainCommand) -> Result<(), ChainCommand> {
+        while self.rx_garbage.try_recv().is_ok() {}
+        match self.tx_commands.try_send(command) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(command)) => Err(command),
+            // The audio side is gone with its output, there is nothing left to edit
+            Err(TrySendError::Disconnected(_)) => Ok(()),
+        }
+    }
+
+    /// Prepare a processor for the audio side
+    fn prepare(&self, processor: &mut Box<dyn AudioProcessor>) -> usize {
+        processor.set_format(self.format);
+        processor.latency()
+    }
+}
+
+/// Ordered list of processors applied to the output, edited from the control thread
+///
+/// Every handle edits the same chain, the edits are applied by the audio thread
+/// at the start of the next block
+#[derive(Clone)]
+pub struct EffectChain {
+    state: Arc<Mutex<ChainState>>,
+}
+
+impl EffectChain {
+    /// Create an empty chain for the given format
+    ///
+    /// Returns the control side and the audio side of the chain
+    pub fn new(format: OutputFormat) -> (Self, ChainProcessor) {
+        let (tx_commands, rx_commands) = bounded(COMMAND_CAPACITY);
+        let (tx_garbage, rx_garbage) = bounded(MAX_EFFECTS + COMMAND_CAPACITY);
+
+        let chain = Self {
+            state: Arc::new(Mutex::new(ChainState {
+                tx_commands,
+                rx_garbage,
+                format,
+                latencies: Vec::with_capacity(MAX_EFFECTS),
+                bypassed: Vec::with_capacity(MAX_EFFECTS),
+            })),
+        };
+        let processor = ChainProcessor {
+            slots: Vec::with_capacity(MAX_EFFECTS),
+            rx_commands,
+            tx_garbage,
+        };
+
+        (chain, processor)
+    }
+
+    /// Get the processors given back by the audio side, to be dropped by the engine thread
+    pub(crate) fn garbage(&self) -> Receiver<Box<dyn AudioProcessor>> {
+        self.state.lock().unwrap().rx_garbage.clone()
+    }
+
+    #[inline]
+    /// Get the format the processors are prepared for
+    pub fn format(&self) -> OutputFormat {
+        self.state.lock().unwrap().format
+    }
+
+    #[inline]
+    /// Get the number of processors
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().latencies.len()
+    }
+
+    #[inline]
+    /// Returns true if the chain has no processor
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    /// Add a processor at the end of the chain, it is given back with the error if it can't be added
+    pub fn push(&self, processor: Box<dyn AudioProcessor>) -> Result<(), Rejected> {
+        self.insert(usize::MAX, processor)
+    }
+
+    /// Insert a processor at the given index, the index is clamped to the end of the chain
+    ///
+    /// The processor is given back with the error if it can't be added
+    pub fn insert(
+        &self,
+        index: usize,
+        mut processor: Box<dyn AudioProcessor>,
+    ) -> Result<(), Rejected> {
+        let mut state = self.state.lock().unwrap();
+        if state.latencies.len() >= MAX_EFFECTS {
+            return Err((ChainError::Full, processor));
+        }
+
+        let index = index.min(state.latencies.len());
+        let latency = state.prepare(&mut processor);
+        if let Err(ChainCommand::Insert(_, processor)) =
+            state.send(ChainCommand::Insert(index, processor))
+        {
+            return Err((ChainError::Busy, processor));
+        }
+        state.latencies.insert(index, latency);
+        state.bypassed.insert(index, false);
+        Ok(())
+    }
+
+    /// Remove the processor at the given index
+    pub fn remove(&self, index: usize) -> Result<(), ChainError> {
+        let mut state = self.state.lock().unwrap();
+        if index >= state.latencies.len() {
+            return Err(ChainError::InvalidIndex(index));
+        }
+
+        state
+            .send(ChainCommand::Remove(index))
+            .map_err(|_| ChainError::Busy)?;
+        state.latencies.remove(index);
+        state.bypassed.remove(index);
+        Ok(())
+    }
+
+    /// Replace the processor at the given index, it is given back with the error if it can't be
+    /// replaced
+    pub fn replace(
+        &self,
+        index: usize,
+        mut processor: Box<dyn AudioProcessor>,
+    ) -> Result<(), Rejected> {
+        let mut state = self.state.lock().unwrap();
+        if index >= state.latencies.len() {
+            return Err((ChainError::InvalidIndex(index), processor));
+        }
+
+        let latency = state.prepare(&mut processor);
+        if let Err(ChainCommand::Replace(_, processor)) =
+            state.send(ChainCommand::Replace(index, processor))
+        {
+            return Err((ChainError::Busy, processor));
+        }
+        state.latencies[index] = latency;
+        Ok(())
+    }
+
+    /// Move a processor from one index to another
+    pub fn move_effect(&self, from: usize, to: usize) -> Result<(), ChainError> {
+        let mut state = self.state.lock().unwrap();
+        let len = state.latencies.len();
+        if let Some(index) = [from, to].iter().find(|&&index| index >= len) {
+            return Err(ChainError::InvalidIndex(*index));
+        }
+
+        state
+            .send(ChainCommand::Move(from, to))
+            .map_err(|_| ChainError::Busy)?;
+        let latency = state.latencies.remove(from);
+        state.latencies.insert(to, latency);
+        let bypass = state.bypassed.remove(from);
+        state.bypassed.insert(to, bypass);
+        Ok(())
+    }
+
+    /// Skip or apply again the processor at the given index
+    pub fn set_bypass(&self, index: usize, bypass: bool) -> Result<(), ChainError> {
+        let mut state = self.state.lock().unwrap();
+        if index >= state.bypassed.len() {
+            return Err(ChainError::InvalidIndex(index));
+        }
+
+        state
+            .send(ChainCommand::Bypass(index, bypass))
+            .map_err(|_| ChainError::Busy)?;
+        state.bypassed[index] = bypass;
+        Ok(())
+    }
+
+    #[inline]
+    /// Returns true if the processor at the given index is skipped
+    pub fn is_bypassed(&self, index: usize) -> Option<bool> {
+        self.state.lock().unwrap().bypassed.get(index).copied()
+    }
+
+    /// Change a parameter of the processor at the given index, see `AudioProcessor::set_parameter`
+    pub fn set_parameter(&self, index: usize, id: usize, value: f32) -> Result<(), ChainError> {
+        let mut state = self.state.lock().unwrap();
+        if index >= state.latencies.len() {
+            return Err(ChainError::InvalidIndex(index));
+        }
+
+        state
+            .send(ChainCommand::Parameter(index, id, value))
+            .map_err(|_| ChainError::Busy)
+    }
+
+    /// Remove every processor
+    pub fn clear(&self) -> Result<(), ChainError> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .send(ChainCommand::Clear)
+            .map_err(|_| ChainError::Busy)?;
+        state.latencies.clear();
+        state.bypassed.clear();
+        Ok(())
+    }
+
+    /// Get the delay added by the processors applied, in frames
+    pub fn latency(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state
+            .latencies
+            .iter()
+            .zip(&state.bypassed)
+            .filter(|(_, &bypass)| !bypass)
+            .map(|(latency, _)| latency)
+            .sum()
+    }
+}
+
+/// Audio side of an effect chain
+///
+/// The edits made with the `EffectChain` are applied at the start of each block
+/// without allocating nor freeing memory
+pub struct ChainProcessor {
+    slots: Vec<Slot>,
+    rx_commands: Receiver<ChainCommand>,
+    tx_garbage: Sender<Box<dyn AudioProcessor>>,
+}
+
+impl ChainProcessor {
+    /// Apply the edits sent by the control side
+    fn apply_commands(&mut self) {
+        while let Ok(command) = self.rx_commands.try_recv() {
+            match command {
+                ChainCommand::Insert(index, processor) => {
+                    let index = index.min(self.slots.len());
+                    if self.slots.len() < self.slots.capacity() {
+                        self.slots.insert(
+                            index,
+                            Slot {
+                                processor,
+                                bypass: false,
+                            },
+                        );
+                    } else {
+                        self.discard(processor);
+                    }
+                }
+                ChainCommand::Remove(index) => {
+                    if index < self.slots.len() {
+                        let slot = self.slots.remove(index);
+                        self.discard(slot.processor);
+                    }
+                }
+                ChainCommand::Replace(index, processor) => match self.slots.get_mut(index) {
+                    Some(slot) => {
+                        let previous = std::mem::replace(&mut slot.processor, processor);
+                        self.discard(previous);
+                    }
+                    None => self.discard(processor),
+                },
+                ChainCommand::Move(from, to) => {
+                    if from < self.slots.len() && to < self.slots.len() {
+                        let slot = self.slots.remove(from);
+                        self.slots.insert(to, slot);
+                    }
+                }
+                ChainCommand::Bypass(index, bypass) => {
+                    if let Some(slot) = self.slots.get_mut(index) {
+                        slot.bypass = bypass;
+                        if bypass {
+                            slot.processor.reset();
+                        }
+                    }
+                }
+                ChainCommand::Parameter(index, id, value) => {
+                    if let Some(slot) = self.slots.get_mut(index) {
+                        slot.processor.set_parameter(id, value);
+                    }
+                }
+                ChainCommand::Clear => {
+                    while let Some(slot) = self.slots.pop() {
+                        self.discard(slot.processor);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Give a processor back to the control side to be dropped there
+    fn discard(&self, processor: Box<dyn AudioProcessor>) {
+        // Dropped here only if the control side is gone or not keeping up
+        let _ = self.tx_garbage.try_send(processor);
+    }
+}
+
+impl AudioProcessor for ChainProcessor {
+    fn process(&mut self, block: &mut [f32]) {
+        self.apply_commands();
+
+        for slot in self.slots.iter_mut().filter(|slot| !slot.bypass) {
+            slot.processor.process(block);
+        }
+    }
+
+    fn reset(&mut self) {
+        for slot in self.slots.iter_mut() {
+            slot.processor.reset();
+        }
+    }
+
+    fn latency(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| !slot.bypass)
+            .map(|slot| slot.processor.latency())
+            .sum()
+    }
+}
@@ -0,0 +1,184 @@
+use super::biquad::{Band, Biquad, BiquadState, FilterType};
+use super::{AudioProcessor, EqMode, EqPreset};
+use crate::stream::OutputFormat;
+
+/// Center frequencies of the bands of the graphic equalizer, in Hz
+pub const GRAPHIC_FREQUENCIES: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+
+/// Quality factor of the graphic bands, about one octave wide
+const GRAPHIC_Q: f32 = 1.41;
+
+/// Format used until the processor is given the format of the output
+const DEFAULT_FORMAT: OutputFormat = OutputFormat {
+    sample_rate: 44100,
+    channels: 2,
+};
+
+/// Equalizer made of a chain of biquad filters
+///
+/// The parameters can be changed while playing through `AudioProcessor::set_parameter`:
+/// `Equalizer::PREAMP` and the ids given by `Equalizer::band_parameter`
+#[derive(Debug, Clone)]
+pub struct Equalizer {
+    bands: Vec<Band>,
+    /// Gain applied before the filters, in dB
+    preamp: f32,
+    format: OutputFormat,
+    filters: Vec<Biquad>,
+    /// State of every band for every channel, grouped by band
+    states: Vec<BiquadState>,
+}
+
+impl Equalizer {
+    /// Id of the preamp parameter, in dB
+    pub const PREAMP: usize = 0;
+    /// Band parameter of the gain, in dB
+    pub const GAIN: usize = 0;
+    /// Band parameter of the frequency, in Hz
+    pub const FREQUENCY: usize = 1;
+    /// Band parameter of the quality factor
+    pub const Q: usize = 2;
+
+    /// Create an equalizer from any set of bands
+    pub fn parametric(bands: Vec<Band>) -> Self {
+        let mut equalizer = Self {
+            bands,
+            preamp: 0.0,
+            format: DEFAULT_FORMAT,
+            filters: Vec::new(),
+            states: Vec::new(),
+        };
+        equalizer.set_format(DEFAULT_FORMAT);
+        equalizer
+    }
+
+    /// Create a 10-band graphic equalizer from the gain of each band in dB
+    pub fn graphic(gains: [f32; 10]) -> Self {
+        Self::parametric(graphic_bands(gains))
+    }
+
+    /// Create an equalizer from a preset
+    pub fn from_preset(preset: &EqPreset) -> Self {
+        let bands = match &preset.mode {
+            EqMode::Graphic { gains } => graphic_bands(*gains),
+            EqMode::Parametric { bands } => bands.clone(),
+        };
+
+        let mut equalizer = Self::parametric(bands);
+        equalizer.preamp = preset.preamp;
+        equalizer
+    }
+
+    #[inline]
+    /// Get the id of a parameter of a band, for `AudioProcessor::set_parameter`
+    pub fn band_parameter(band: usize, parameter: usize) -> usize {
+        1 + band * 3 + parameter
+    }
+
+    #[inline]
+    /// Get the bands
+    pub fn bands(&self) -> &[Band] {
+        &self.bands
+    }
+
+    #[inline]
+    /// Get the preamp in dB
+    pub fn preamp(&self) -> f32 {
+        self.preamp
+    }
+
+    #[inline]
+    /// Set the preamp in dB
+    pub fn set_preamp(&mut self, preamp: f32) {
+        self.preamp = preamp;
+    }
+
+    /// Replace a band, returns false if the index is out of the equalizer
+    pub fn set_band(&mut self, index: usize, band: Band) -> bool {
+        match self.bands.get_mut(index) {
+            Some(current) => {
+                *current = band;
+                self.filters[index] = Biquad::new(&band, self.format.sample_rate);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the gain of the equalizer at the given frequency, preamp included, in dB
+    pub fn response_db(&self, frequency: f32) -> f32 {
+        let filters: f32 = self
+            .filters
+            .iter()
+            .map(|filter| filter.magnitude_db(frequency, self.format.sample_rate))
+            .sum();
+        self.preamp + filters
+    }
+}
+
+impl Default for Equalizer {
+    fn default() -> Self {
+        Self::graphic([0.0; 10])
+    }
+}
+
+impl AudioProcessor for Equalizer {
+    fn process(&mut self, block: &mut [f32]) {
+        let channels = self.format.channels;
+        let preamp = 10f64.powf(self.preamp as f64 / 20.0);
+
+        for frame in block.chunks_mut(channels) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let mut value = *sample as f64 * preamp;
+                for (filter, states) in self.filters.iter().zip(self.states.chunks_mut(channels)) {
+                    value = filter.process(&mut states[channel], value);
+                }
+                *sample = value as f32;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        for state in self.states.iter_mut() {
+            *state = BiquadState::default();
+        }
+    }
+
+    fn set_format(&mut self, format: OutputFormat) {
+        self.format = format;
+        self.filters = self
+            .bands
+            .iter()
+            .map(|band| Biquad::new(band, format.sample_rate))
+            .collect();
+        self.states = vec![BiquadState::default(); self.bands.len() * format.channels];
+    }
+
+    fn set_parameter(&mut self, id: usize, value: f32) {
+        if id == Self::PREAMP {
+            self.preamp = value;
+            return;
+        }
+
+        let (index, parameter) = ((id - 1) / 3, (id - 1) % 3);
+        if let Some(mut band) = self.bands.get(index).copied() {
+            match parameter {
+                Self::GAIN => band.gain = value,
+                Self::FREQUENCY => band.frequency = value,
+                _ => band.q = value,
+            }
+            self.set_band(index, band);
+        }
+    }
+}
+
+/// Get the peaking bands of the graphic equalizer
+fn graphic_bands(gains: [f32; 10]) -> Vec<Band> {
+    GRAPHIC_FREQUENCIES
+        .iter()
+        .zip(gains.iter())
+        .map(|(&frequency, &gain)| Band::new(FilterType::Peaking, frequency, gain, GRAPHIC_Q))
+        .collect()
+}
@@ -0,0 +1,46 @@
+use super::AudioProcessor;
+
+/// Constant gain applied to every channel
+#[derive(Debug, Clone, Copy)]
+pub struct Gain {
+    gain: f32,
+}
+
+impl Gain {
+    /// Id of the gain parameter, as a linear factor
+    pub const GAIN: usize = 0;
+
+    #[inline]
+    /// Create a new gain from a linear factor
+    pub fn new(gain: f32) -> Self {
+        Self { gain }
+    }
+
+    #[inline]
+    /// Create a new gain from a value in decibels
+    pub fn from_db(db: f32) -> Self {
+        Self::new(10f32.powf(db / 20.0))
+    }
+
+    #[inline]
+    /// Get the linear factor
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+}
+
+impl AudioProcessor for Gain {
+    #[inline]
+    fn process(&mut self, block: &mut [f32]) {
+        for sample in block.iter_mut() {
+            *sample *= self.gain;
+        }
+    }
+
+    #[inline]
+    fn set_parameter(&mut self, id: usize, value: f32) {
+        if id == Self::GAIN {
+            self.gain = value;
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::AudioProcessor;
+use crate::stream::OutputFormat;
+
+/// Highest number of channels measured, the others are ignored
+pub const MAX_METER_CHANNELS: usize = 8;
+
+/// Peaks written by the audio thread and read by the control side
+#[derive(Default)]
+struct Peaks {
+    /// Bits of the highest absolute sample of each channel
+    peaks: [AtomicU32; MAX_METER_CHANNELS],
+    channels: AtomicUsize,
+}
+
+/// Processor measuring the peak level of each channel, the audio goes through untouched
+///
+/// The peaks are read from the `Levels` handle on another thread, without locking
+pub struct LevelMeter {
+    shared: Arc<Peaks>,
+    channels: usize,
+}
+
+/// Handle reading the peaks measured by a `LevelMeter`
+#[derive(Clone)]
+pub struct Levels {
+    shared: Arc<Peaks>,
+}
+
+impl LevelMeter {
+    /// Create a meter and the handle reading its peaks
+    pub fn new() -> (Self, Levels) {
+        let shared = Arc::new(Peaks::default());
+        let levels = Levels {
+            shared: Arc::clone(&shared),
+        };
+        (
+            Self {
+                shared,
+                channels: 0,
+            },
+            levels,
+        )
+    }
+}
+
+impl Levels {
+    /// Get the highest absolute sample of each channel since the last call
+    pub fn peaks(&self) -> Vec<f32> {
+        let channels = self.shared.channels.load(Ordering::Relaxed);
+        self.shared.peaks[..channels.min(MAX_METER_CHANNELS)]
+            .iter()
+            .map(|peak| f32::from_bits(peak.swap(0, Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+impl AudioProcessor for LevelMeter {
+    fn process(&mut self, block: &mut [f32]) {
+        for (channel, peak) in self.shared.peaks.iter().enumerate().take(self.channels) {
+            let block_peak = block
+                .iter()
+                .skip(channel)
+                .step_by(self.channels)
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            // The positive floats are ordered like their bits
+            peak.fetch_max(block_peak.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    fn set_format(&mut self, format: OutputFormat) {
+        self.channels = format.channels;
+        self.shared
+            .channels
+            .store(format.channels, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,15 @@
+mod biquad;
+mod chain;
+mod equalizer;
+mod gain;
+mod meter;
+mod preset;
+mod processor;
+
+pub use self::biquad::{Band, Biquad, FilterType};
+pub use self::chain::{ChainError, ChainProcessor, EffectChain, Rejected, MAX_EFFECTS};
+pub use self::equalizer::{Equalizer, GRAPHIC_FREQUENCIES};
+pub use self::gain::Gain;
+pub use self::meter::{LevelMeter, Levels, MAX_METER_CHANNELS};
+pub use self::preset::{EqMode, EqPreset, PresetError};
+pub use self::processor::AudioProcessor;
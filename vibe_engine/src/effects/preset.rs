@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt::Display, fs, path::Path};
+
+use super::biquad::Band;
+
+/// Presets shipped with the engine
+const BUILTIN_PRESETS: [&str; 5] = [
+    include_str!("../../presets/flat.toml"),
+    include_str!("../../presets/rock.toml"),
+    include_str!("../../presets/pop.toml"),
+    include_str!("../../presets/classical.toml"),
+    include_str!("../../presets/bass_boost.toml"),
+];
+
+/// An error encountered while reading or writing an equalizer preset.
+#[derive(Debug)]
+pub enum PresetError {
+    /// I/O error.
+    IOError(std::io::Error),
+    /// The preset is not valid TOML or misses fields.
+    ParseError(toml::de::Error),
+    /// The preset could not be written as TOML.
+    SerializeError(toml::ser::Error),
+    /// A value of the preset is out of range.
+    InvalidValue(String),
+}
+
+impl Error for PresetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PresetError::IOError(err) => Some(err),
+            PresetError::ParseError(err) => Some(err),
+            PresetError::SerializeError(err) => Some(err),
+            PresetError::InvalidValue(_) => None,
+        }
+    }
+}
+
+impl Display for PresetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PresetError::IOError(err) => write!(f, "IO error: {}", err),
+            PresetError::ParseError(err) => write!(f, "parse error: {}", err),
+            PresetError::SerializeError(err) => write!(f, "serialize error: {}", err),
+            PresetError::InvalidValue(err) => write!(f, "invalid value: {}", err),
+        }
+    }
+}
+
+/// Bands of an equalizer preset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum EqMode {
+    /// Gains in dB of the 10 bands of the graphic equalizer
+    Graphic { gains: [f32; 10] },
+    /// Free set of bands
+    Parametric { bands: Vec<Band> },
+}
+
+/// Named equalizer settings, stored as TOML
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EqPreset {
+    pub name: String,
+    /// Gain applied before the filters, in dB
+    #[serde(default)]
+    pub preamp: f32,
+    #[serde(flatten)]
+    pub mode: EqMode,
+}
+
+impl EqPreset {
+    #[inline]
+    /// Create a graphic preset leaving the sound untouched
+    pub fn flat() -> Self {
+        Self {
+            name: "Flat".into(),
+            preamp: 0.0,
+            mode: EqMode::Graphic { gains: [0.0; 10] },
+        }
+    }
+
+    /// Get the presets shipped with the engine
+    pub fn builtin() -> Vec<Self> {
+        BUILTIN_PRESETS
+            .iter()
+            .map(|preset| Self::from_toml(preset).expect("Invalid builtin preset"))
+            .collect()
+    }
+
+    /// Read a preset from a TOML document
+    pub fn from_toml(document: &str) -> Result<Self, PresetError> {
+        let preset: Self = toml::from_str(document).map_err(PresetError::ParseError)?;
+        preset.validate()?;
+        Ok(preset)
+    }
+
+    #[inline]
+    /// Write the preset as a TOML document
+    pub fn to_toml(&self) -> Result<String, PresetError> {
+        toml::to_string(self).map_err(PresetError::SerializeError)
+    }
+
+    #[inline]
+    /// Read a preset from a TOML file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PresetError> {
+        let document = fs::read_to_string(path).map_err(PresetError::IOError)?;
+        Self::from_toml(&document)
+    }
+
+    #[inline]
+    /// Write the preset to a TOML file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PresetError> {
+        fs::write(path, self.to_toml()?).map_err(PresetError::IOError)
+    }
+
+    /// Check the values the filters can't work with
+    fn validate(&self) -> Result<(), PresetError> {
+        if let EqMode::Parametric { bands } = &self.mode {
+            for band in bands {
+                if band.frequency.is_nan() || band.frequency <= 0.0 {
+                    return Err(PresetError::InvalidValue(format!(
+                        "frequency {}",
+                        band.frequency
+                    )));
+                }
+                if band.q.is_nan() || band.q <= 0.0 {
+                    return Err(PresetError::InvalidValue(format!("q {}", band.q)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,26 @@
+use crate::stream::OutputFormat;
+
+/// Processing stage applied to the decoded audio before it reaches the output
+///
+/// `process` runs on the audio thread and must not allocate nor block,
+/// every buffer has to be prepared in `set_format`
+pub trait AudioProcessor: Send {
+    /// Process a block of interleaved samples in place
+    fn process(&mut self, block: &mut [f32]);
+
+    /// Clear the internal state, called when the source changes or is moved
+    fn reset(&mut self) {}
+
+    /// Delay added to the audio, in frames
+    fn latency(&self) -> usize {
+        0
+    }
+
+    /// Prepare the processor for a sample rate and channel count
+    ///
+    /// Called before the first block and each time the format changes
+    fn set_format(&mut self, _format: OutputFormat) {}
+
+    /// Change a parameter of the processor, the meaning of `id` is up to the processor
+    fn set_parameter(&mut self, _id: usize, _value: f32) {}
+}
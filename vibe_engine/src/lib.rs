@@ -1,2 +1,6 @@
+pub mod effects;
+pub mod mixer;
 pub mod player;
+pub mod queue;
+pub mod sink;
 pub mod stream;
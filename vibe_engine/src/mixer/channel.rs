@@ -0,0 +1,58 @@
+use std::f32::consts::FRAC_PI_2;
+
+/// Settings of a source of the mixer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelSettings {
+    /// Linear gain of the source
+    pub gain: f32,
+    /// Balance between the left and the right channels, from -1 (left) to 1 (right)
+    pub pan: f32,
+    /// The source keeps playing silently
+    pub mute: bool,
+    /// Once a source is soloed, only the soloed sources are heard
+    pub solo: bool,
+    /// Start the source again when it is over, ignored for the main source
+    pub looped: bool,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            pan: 0.0,
+            mute: false,
+            solo: false,
+            looped: false,
+        }
+    }
+}
+
+impl ChannelSettings {
+    /// Get the level of the source, zero if it is muted or another source is soloed
+    #[inline]
+    pub fn level(&self, any_solo: bool) -> f32 {
+        if self.mute || (any_solo && !self.solo) {
+            0.0
+        } else {
+            self.gain
+        }
+    }
+
+    /// Get the gains of the left and the right channels
+    ///
+    /// The centre keeps both channels at full level, panning fades the opposite
+    /// channel out with an equal-power curve
+    pub fn pan_gains(&self) -> (f32, f32) {
+        let pan = if self.pan.is_nan() {
+            0.0
+        } else {
+            self.pan.clamp(-1.0, 1.0)
+        };
+
+        if pan >= 0.0 {
+            ((pan * FRAC_PI_2).cos(), 1.0)
+        } else {
+            (1.0, (pan * FRAC_PI_2).cos())
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use crossbeam::channel::{bounded, Receiver, Sender};
+use std::io::{Read, Seek};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use vibe_core::decoder::Decoder;
+
+use super::ChannelSettings;
+use crate::effects::AudioProcessor;
+use crate::stream::{fade_frames, Converter, Fade, OutputFormat};
+
+/// Id of the source played by the stream, the other sources are layered on top of it
+pub const MAIN_SOURCE: u64 = 0;
+
+/// Maximum number of sources layered on the main one, the audio side never grows its storage
+pub const MAX_SOURCES: usize = 16;
+
+/// Number of edits the audio side can have waiting
+const COMMAND_CAPACITY: usize = 64;
+
+/// Duration of the ramp applied when the level of a source changes, so it doesn't click
+const LEVEL_RAMP: Duration = Duration::from_millis(10);
+
+/// Edits sent from the control side to the audio side
+enum MixerCommand {
+    Add(u64, Box<Converter>, ChannelSettings),
+    Remove(u64),
+    Settings(u64, ChannelSettings),
+    Clear,
+}
+
+/// Source of the audio side with the envelope of its level
+struct Layer {
+    id: u64,
+    source: Box<Converter>,
+    channel: Channel,
+    ended: bool,
+}
+
+/// Settings of a source as used by the audio side
+struct Channel {
+    settings: ChannelSettings,
+    level: Fade,
+    pan: (f32, f32),
+}
+
+impl Channel {
+    fn new(settings: ChannelSettings, any_solo: bool) -> Self {
+        Self {
+            settings,
+            level: Fade::new(settings.level(any_solo)),
+            pan: settings.pan_gains(),
+        }
+    }
+
+    /// Move the level toward the level of the settings
+    fn update(&mut self, any_solo: bool, ramp: usize) {
+        self.level.start(self.settings.level(any_solo), ramp);
+        self.pan = self.settings.pan_gains();
+    }
+
+    /// Apply the level and the balance to a frame, the balance only applies to stereo outputs
+    #[inline]
+    fn apply(&mut self, frame: &mut [f32]) {
+        let level = self.level.next_gain();
+        for sample in frame.iter_mut() {
+            *sample *= level;
+        }
+        if frame.len() == 2 {
+            frame[0] *= self.pan.0;
+            frame[1] *= self.pan.1;
+        }
+    }
+}
+
+/// Description of the sources kept by the control side
+struct MixerState {
+    tx_commands: Sender<MixerCommand>,
+    /// Sources removed from the audio side or over, dropped here so the audio thread never frees memory
+    rx_garbage: Receiver<(u64, Box<Converter>)>,
+    format: OutputFormat,
+    next_id: u64,
+    main: ChannelSettings,
+    sources: Vec<(u64, ChannelSettings)>,
+}
+
+impl MixerState {
+    /// Send an edit to the audio side
+    fn send(&mut self, command: MixerCommand) {
+        // The audio side is gone with its output, there is nothing left to edit
+        let _ = self.tx_commands.send(command);
+        self.collect();
+    }
+
+    /// Drop the sources given back by the audio side and forget the ones that are over
+    fn collect(&mut self) {
+        while let Ok((id, _)) = self.rx_garbage.try_recv() {
+            self.sources.retain(|(source, _)| *source != id);
+        }
+    }
+
+    fn settings_mut(&mut self, id: u64) -> Option<&mut ChannelSettings> {
+        if id == MAIN_SOURCE {
+            return Some(&mut self.main);
+        }
+        self.sources
+            .iter_mut()
+            .find(|(source, _)| *source == id)
+            .map(|(_, settings)| settings)
+    }
+}
+
+/// Sources played together on one output, edited from the control thread
+///
+/// The main source is the one played by the stream, the other sources are
+/// decoded alongside it and removed once they are over
+#[derive(Clone)]
+pub struct Mixer {
+    state: Arc<Mutex<MixerState>>,
+}
+
+impl Mixer {
+    /// Create a mixer for the given format
+    ///
+    /// Returns the control side and the audio side of the mixer
+    pub fn new(format: OutputFormat) -> (Self, MixerProcessor) {
+        let (tx_commands, rx_commands) = bounded(COMMAND_CAPACITY);
+        let (tx_garbage, rx_garbage) = bounded(MAX_SOURCES + COMMAND_CAPACITY);
+
+        let mixer = Self {
+            state: Arc::new(Mutex::new(MixerState {
+                tx_commands,
+                rx_garbage,
+                format,
+                next_id: MAIN_SOURCE + 1,
+                main: ChannelSettings::default(),
+                sources: Vec::with_capacity(MAX_SOURCES),
+            })),
+        };
+        let processor = MixerProcessor {
+            main: Channel::new(ChannelSettings::default(), false),
+            layers: Vec::with_capacity(MAX_SOURCES),
+            frame: vec![0.0; format.channels],
+            ramp: fade_frames(LEVEL_RAMP, format.sample_rate),
+            rx_commands,
+            tx_garbage,
+        };
+
+        (mixer, processor)
+    }
+
+    #[inline]
+    /// Get the format of the output
+    pub fn format(&self) -> OutputFormat {
+        self.state.lock().unwrap().format
+    }
+
+    /// Play a decoder on top of the main source, returns its id
+    ///
+    /// The decoder is converted to the output format on the calling thread.
+    /// Returns None if the mixer already plays `MAX_SOURCES` sources
+    pub fn add<R>(&self, decoder: Decoder<R>, settings: ChannelSettings) -> Option<u64>
+    where
+        R: Read + Seek + Send + 'static,
+    {
+        let mut state = self.state.lock().unwrap();
+        state.collect();
+        if state.sources.len() >= MAX_SOURCES {
+            return None;
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        let source = Box::new(Converter::new(decoder, state.format));
+        state.sources.push((id, settings));
+        state.send(MixerCommand::Add(id, source, settings));
+        Some(id)
+    }
+
+    /// Stop and remove a source, the main source can't be removed
+    pub fn remove(&self, id: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.collect();
+        let len = state.sources.len();
+        state.sources.retain(|(source, _)| *source != id);
+        if state.sources.len() == len {
+            return false;
+        }
+
+        state.send(MixerCommand::Remove(id));
+        true
+    }
+
+    /// Remove every source but the main one
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.sources.clear();
+        state.send(MixerCommand::Clear);
+    }
+
+    /// Get the ids of the sources still playing, without the main source
+    pub fn sources(&self) -> Vec<u64> {
+        let mut state = self.state.lock().unwrap();
+        state.collect();
+        state.sources.iter().map(|(id, _)| *id).collect()
+    }
+
+    #[inline]
+    /// Get the settings of a source
+    pub fn settings(&self, id: u64) -> Option<ChannelSettings> {
+        let mut state = self.state.lock().unwrap();
+        state.collect();
+        state.settings_mut(id).copied()
+    }
+
+    /// Replace the settings of a source
+    pub fn set_settings(&self, id: u64, settings: ChannelSettings) -> bool {
+        self.edit(id, |current| *current = settings)
+    }
+
+    #[inline]
+    /// Set the linear gain of a source
+    pub fn set_gain(&self, id: u64, gain: f32) -> bool {
+        self.edit(id, |settings| settings.gain = gain)
+    }
+
+    #[inline]
+    /// Set the balance of a source, from -1 (left) to 1 (right)
+    pub fn set_pan(&self, id: u64, pan: f32) -> bool {
+        self.edit(id, |settings| settings.pan = pan)
+    }
+
+    #[inline]
+    /// Silence a source or make it heard again
+    pub fn set_mute(&self, id: u64, mute: bool) -> bool {
+        self.edit(id, |settings| settings.mute = mute)
+    }
+
+    #[inline]
+    /// Solo a source, only the soloed sources are heard
+    pub fn set_solo(&self, id: u64, solo: bool) -> bool {
+        self.edit(id, |settings| settings.solo = solo)
+    }
+
+    /// Change the settings of a source and send them to the audio side
+    fn edit<F>(&self, id: u64, edit: F) -> bool
+    where
+        F: FnOnce(&mut ChannelSettings),
+    {
+        let mut state = self.state.lock().unwrap();
+        state.collect();
+        let settings = match state.settings_mut(id) {
+            Some(settings) => {
+                edit(settings);
+                *settings
+            }
+            None => return false,
+        };
+
+        state.send(MixerCommand::Settings(id, settings));
+        true
+    }
+}
+
+/// Audio side of a mixer
+///
+/// Applies the settings of the main source to the block and adds the other sources,
+/// without allocating nor freeing memory
+pub struct MixerProcessor {
+    main: Channel,
+    layers: Vec<Layer>,
+    /// Frame decoded from a layer
+    frame: Vec<f32>,
+    /// Length of the level ramps, in frames
+    ramp: usize,
+    rx_commands: Receiver<MixerCommand>,
+    tx_garbage: Sender<(u64, Box<Converter>)>,
+}
+
+impl MixerProcessor {
+    #[inline]
+    /// Check if any source is layered on the main one
+    pub(crate) fn is_active(&self) -> bool {
+        !self.layers.is_empty()
+    }
+
+    /// Apply the edits sent by the control side
+    fn apply_commands(&mut self) {
+        let mut changed = false;
+        while let Ok(command) = self.rx_commands.try_recv() {
+            changed = true;
+            match command {
+                MixerCommand::Add(id, source, settings) => {
+                    if self.layers.len() < self.layers.capacity() {
+                        let any_solo = self.any_solo() || settings.solo;
+                        self.layers.push(Layer {
+                            id,
+                            source,
+                            channel: Channel::new(settings, any_solo),
+                            ended: false,
+                        });
+                    } else {
+                        self.discard(id, source);
+                    }
+                }
+                MixerCommand::Remove(id) => {
+                    if let Some(index) = self.layers.iter().position(|layer| layer.id == id) {
+                        let layer = self.layers.remove(index);
+                        self.discard(layer.id, layer.source);
+                    }
+                }
+                MixerCommand::Settings(id, settings) => {
+                    if id == MAIN_SOURCE {
+                        self.main.settings = settings;
+                    } else if let Some(layer) = self.layers.iter_mut().find(|layer| layer.id == id)
+                    {
+                        layer.channel.settings = settings;
+                    }
+                }
+                MixerCommand::Clear => {
+                    while let Some(layer) = self.layers.pop() {
+                        self.discard(layer.id, layer.source);
+                    }
+                }
+            }
+        }
+
+        // A solo changes the level of every source
+        if changed {
+            let any_solo = self.any_solo();
+            self.main.update(any_solo, self.ramp);
+            for layer in self.layers.iter_mut() {
+                layer.channel.update(any_solo, self.ramp);
+            }
+        }
+    }
+
+    fn any_solo(&self) -> bool {
+        self.main.settings.solo || self.layers.iter().any(|layer| layer.channel.settings.solo)
+    }
+
+    /// Give a source back to the control side to be dropped there
+    fn discard(&self, id: u64, source: Box<Converter>) {
+        // Dropped here only if the control side is gone or not keeping up
+        let _ = self.tx_garbage.try_send((id, source));
+    }
+
+    /// Remove the sources that are over
+    fn remove_ended(&mut self) {
+        let mut index = 0;
+        while index < self.layers.len() {
+            if self.layers[index].ended {
+                let layer = self.layers.remove(index);
+                self.discard(layer.id, layer.source);
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+impl AudioProcessor for MixerProcessor {
+    fn process(&mut self, block: &mut [f32]) {
+        self.apply_commands();
+
+        let channels = self.frame.len().max(1);
+        for frame in block.chunks_mut(channels) {
+            self.main.apply(frame);
+
+            for layer in self.layers.iter_mut().filter(|layer| !layer.ended) {
+                let mut playing = layer.source.next_frame(&mut self.frame);
+                if !playing && layer.channel.settings.looped {
+                    playing = layer.source.seek(Duration::from_secs(0))
+                        && layer.source.next_frame(&mut self.frame);
+                }
+                if !playing {
+                    layer.ended = true;
+                    continue;
+                }
+
+                layer.channel.apply(&mut self.frame);
+                for (sample, value) in frame.iter_mut().zip(&self.frame) {
+                    *sample += value;
+                }
+            }
+        }
+
+        self.remove_ended();
+    }
+}
@@ -0,0 +1,5 @@
+mod channel;
+mod mixer;
+
+pub use self::channel::ChannelSettings;
+pub use self::mixer::{Mixer, MixerProcessor, MAIN_SOURCE, MAX_SOURCES};
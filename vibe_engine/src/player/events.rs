@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use super::PlayerState;
+use crate::queue::{RepeatMode, ShuffleMode, Track};
+
+/// Events emitted by the player
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerEvent {
+    /// The player moved to another state
+    StateChanged(PlayerState),
+    /// Position in the track being played, sent periodically during the playback
+    Position(Duration),
+    /// A new track of the queue is being played
+    TrackChanged { index: usize, track: Track },
+    /// The track has been played until its end
+    TrackEnded { index: usize, track: Track },
+    /// The last track of the queue is over
+    QueueEnded,
+    /// Tracks were added to the queue, removed from it or moved in it
+    QueueChanged,
+    /// The repeat mode of the queue changed
+    RepeatChanged(RepeatMode),
+    /// The shuffle mode of the queue changed
+    ShuffleChanged(ShuffleMode),
+    /// The volume of the output changed, as a linear factor
+    VolumeChanged(f32),
+    /// A track couldn't be opened or the output failed
+    Error(String),
+}
@@ -1,3 +1,9 @@
+mod events;
 mod player;
+mod state;
+mod status;
 
+pub use self::events::PlayerEvent;
 pub use self::player::Player;
+pub use self::state::PlayerState;
+pub use self::status::{format_time, PlayerStatus, SEEK_STEP};
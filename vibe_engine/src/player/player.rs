@@ -1,51 +1,812 @@
-use std::io::{Read, Seek};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Duration;
 
+use crossbeam::channel::{unbounded, Receiver, RecvTimeoutError, Sender};
 use vibe_core::decoder::Decoder;
 
-use crate::stream::AudioStream;
+use super::{PlayerEvent, PlayerState};
+use crate::effects::EffectChain;
+use crate::mixer::Mixer;
+use crate::queue::{Queue, RepeatMode, ShuffleMode, Track};
+use crate::sink::{CpalSink, OutputSink};
+use crate::stream::{
+    AudioStream, FadeConfig, LoopConfig, ReplayGainConfig, ReplayGainMode, SpeedConfig, SpeedMode,
+    StreamEvent,
+};
 
+/// Interval between two position events during the playback
+const POSITION_INTERVAL: Duration = Duration::from_millis(200);
+
+/// State shared by all the handles of a player
+struct PlayerInner {
+    /// Output opened with the first track and kept until the player is dropped
+    stream: Option<AudioStream>,
+    /// Sink given to the output when it is opened, or the error of the output once it failed to open
+    sink: Result<Box<dyn OutputSink>, String>,
+    queue: Queue,
+    state: PlayerState,
+    /// Id of the source being played, the events of the previous sources are ignored
+    source: Option<u64>,
+    /// Track opened by the stream to follow the current one
+    preloaded: Option<Track>,
+    /// Id of the source of the preloaded track
+    next_source: Option<u64>,
+    /// Number of times the playback was stopped or replaced, checked after opening a file
+    loads: u64,
+    /// Number of changes of the preloaded track, only the last one is given to the stream
+    preloads: u64,
+    fades: FadeConfig,
+    crossfade: Duration,
+    replay_gain: ReplayGainConfig,
+    speed: SpeedConfig,
+    looping: LoopConfig,
+    volume: f32,
+    subscribers: Vec<Sender<PlayerEvent>>,
+}
+
+impl PlayerInner {
+    /// Send an event to every subscriber still listening
+    fn emit(&mut self, event: PlayerEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Move to another state and notify the subscribers
+    fn set_state(&mut self, state: PlayerState) {
+        if self.state != state {
+            self.state = state;
+            self.emit(PlayerEvent::StateChanged(state));
+        }
+    }
+
+    /// Stop the playback, the output stays open
+    fn stop(&mut self) {
+        if let Some(stream) = self.stream.as_ref() {
+            stream.stop();
+        }
+        // The A-B region belongs to the track
+        self.looping.region = None;
+        self.source = None;
+        self.preloaded = None;
+        self.next_source = None;
+        self.loads += 1;
+    }
+}
+
+/// Handle on a player, the output is closed once every handle is dropped
 #[derive(Clone)]
 pub struct Player {
-    stream: Option<AudioStream>,
+    inner: Arc<Mutex<PlayerInner>>,
 }
 
 impl Player {
     #[inline]
     /// Create a new empty player
     pub fn new() -> Self {
-        Self { stream: None }
+        Self::with_fades(FadeConfig::default())
     }
 
     #[inline]
-    /// Create a new stream inside the player
-    pub fn create_stream<R>(&mut self, decoder: Decoder<R>)
+    /// Create a new empty player using the given fade envelopes
+    pub fn with_fades(fades: FadeConfig) -> Self {
+        Self::with_sink(CpalSink::<f32>::new(), fades)
+    }
+
+    /// Create a new empty player playing into the given sink, opened with the first track
+    pub fn with_sink<S>(sink: S, fades: FadeConfig) -> Self
+    where
+        S: OutputSink,
+    {
+        let inner = PlayerInner {
+            stream: None,
+            sink: Ok(Box::new(sink)),
+            queue: Queue::new(),
+            state: PlayerState::Empty,
+            source: None,
+            preloaded: None,
+            next_source: None,
+            loads: 0,
+            preloads: 0,
+            fades,
+            crossfade: Duration::from_secs(0),
+            replay_gain: ReplayGainConfig::default(),
+            speed: SpeedConfig::default(),
+            looping: LoopConfig::default(),
+            volume: 1.0,
+            subscribers: Vec::new(),
+        };
+
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Load a decoder in the player, paused
+    ///
+    /// Returns the error of the output if it can't be opened
+    pub fn create_stream<R>(&mut self, decoder: Decoder<R>) -> Result<(), String>
     where
         R: Read + Seek + Send + 'static,
     {
-        self.stream = Some(AudioStream::new::<f32, R>(decoder));
+        let mut inner = self.inner.lock().unwrap();
+        inner.stop();
+        inner.set_state(PlayerState::Loading);
+
+        match open_stream(&self.inner, &mut inner) {
+            Ok(stream) => {
+                inner.source = Some(stream.load(decoder));
+                inner.set_state(PlayerState::Paused);
+                Ok(())
+            }
+            Err(error) => {
+                fail_output(&mut inner, error.clone());
+                Err(error)
+            }
+        }
     }
 
-    #[inline]
     /// Play the stream
+    ///
+    /// After a stop or the end of the queue the current track, or the first one, is played again
     pub fn play_stream(&self) {
-        if self.stream.is_some() {
-            self.stream.as_ref().unwrap().play();
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            PlayerState::Playing | PlayerState::Loading => {}
+            PlayerState::Paused => {
+                if let Some(stream) = inner.stream.as_ref() {
+                    stream.play();
+                }
+                inner.set_state(PlayerState::Playing);
+            }
+            _ => {
+                let index = match inner.queue.current() {
+                    Some(index) => Some(index),
+                    None if !inner.queue.is_empty() => inner.queue.next_track(),
+                    None => return,
+                };
+                play_from(&self.inner, inner, index);
+            }
         }
     }
 
     #[inline]
     /// Pause the stream
     pub fn pause_stream(&self) {
-        if self.stream.is_some() {
-            self.stream.as_ref().unwrap().pause();
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == PlayerState::Playing {
+            if let Some(stream) = inner.stream.as_ref() {
+                stream.pause();
+            }
+            inner.set_state(PlayerState::Paused);
         }
     }
 
     #[inline]
     /// Stop the stream
     pub fn stop_stream(&self) {
-        if self.stream.is_some() {
-            self.stream.as_ref().unwrap().stop();
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state.is_active() {
+            inner.stop();
+            inner.set_state(PlayerState::Stopped);
+        }
+    }
+
+    #[inline]
+    /// Seek the stream to the given position
+    pub fn seek_stream(&self, pos: Duration) {
+        if let Some(stream) = self.inner.lock().unwrap().stream.as_ref() {
+            stream.seek(pos);
+        }
+    }
+
+    #[inline]
+    /// Get the state of the player
+    pub fn state(&self) -> PlayerState {
+        self.inner.lock().unwrap().state
+    }
+
+    #[inline]
+    /// Get the position in the track being played
+    pub fn position(&self) -> Duration {
+        let inner = self.inner.lock().unwrap();
+        match (inner.source, inner.stream.as_ref()) {
+            (Some(_), Some(stream)) => stream.position(),
+            _ => Duration::from_secs(0),
+        }
+    }
+
+    #[inline]
+    /// Get the effect chain applied to the output, the output is opened if needed
+    ///
+    /// Returns the error of the output if it can't be opened
+    pub fn effects(&self) -> Result<EffectChain, String> {
+        let mut inner = self.inner.lock().unwrap();
+        open_stream(&self.inner, &mut inner).map(|stream| stream.effects())
+    }
+
+    #[inline]
+    /// Get the mixer layering other sources on the track being played, the output is opened if needed
+    ///
+    /// Returns the error of the output if it can't be opened
+    pub fn mixer(&self) -> Result<Mixer, String> {
+        let mut inner = self.inner.lock().unwrap();
+        open_stream(&self.inner, &mut inner).map(|stream| stream.mixer())
+    }
+
+    #[inline]
+    /// Get a receiver for the events of the player
+    pub fn events(&self) -> Receiver<PlayerEvent> {
+        let (tx, rx) = unbounded();
+        self.inner.lock().unwrap().subscribers.push(tx);
+        rx
+    }
+
+    #[inline]
+    /// Get a copy of the play queue
+    pub fn queue(&self) -> Queue {
+        self.inner.lock().unwrap().queue.clone()
+    }
+
+    #[inline]
+    /// Get the index of the track being played
+    pub fn current_index(&self) -> Option<usize> {
+        self.inner.lock().unwrap().queue.current()
+    }
+
+    #[inline]
+    /// Add a file at the end of the queue, returns the id of its track
+    pub fn enqueue<P: Into<PathBuf>>(&self, path: P) -> u64 {
+        // The tags are read before locking the player
+        let track = Track::new(path);
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.queue.append(track);
+        inner.emit(PlayerEvent::QueueChanged);
+        refresh_preload(&self.inner, inner);
+        id
+    }
+
+    #[inline]
+    /// Insert a file in the queue at the given index, returns the id of its track
+    pub fn insert_track<P: Into<PathBuf>>(&self, index: usize, path: P) -> u64 {
+        let track = Track::new(path);
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.queue.insert(index, track);
+        inner.emit(PlayerEvent::QueueChanged);
+        refresh_preload(&self.inner, inner);
+        id
+    }
+
+    /// Remove a track from the queue, the playback stops if it is the current track
+    pub fn remove_track(&self, index: usize) -> Option<Track> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.queue.current() == Some(index) {
+            inner.stop();
+            inner.set_state(PlayerState::Stopped);
+        }
+        let track = inner.queue.remove(index);
+        if track.is_some() {
+            inner.emit(PlayerEvent::QueueChanged);
+        }
+        refresh_preload(&self.inner, inner);
+        track
+    }
+
+    #[inline]
+    /// Move a track of the queue to another index
+    pub fn move_track(&self, from: usize, to: usize) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let moved = inner.queue.move_track(from, to);
+        if moved {
+            inner.emit(PlayerEvent::QueueChanged);
+        }
+        refresh_preload(&self.inner, inner);
+        moved
+    }
+
+    /// Stop the playback and remove all the tracks from the queue
+    pub fn clear_queue(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.stop();
+        inner.queue.clear();
+        inner.set_state(PlayerState::Empty);
+        inner.emit(PlayerEvent::QueueChanged);
+    }
+
+    #[inline]
+    /// Get the repeat mode of the queue
+    pub fn repeat_mode(&self) -> RepeatMode {
+        self.inner.lock().unwrap().queue.repeat()
+    }
+
+    #[inline]
+    /// Set the repeat mode of the queue
+    pub fn set_repeat_mode(&self, repeat: RepeatMode) {
+        let mut inner = self.inner.lock().unwrap();
+        let changed = inner.queue.repeat() != repeat;
+        inner.queue.set_repeat(repeat);
+        if changed {
+            inner.emit(PlayerEvent::RepeatChanged(repeat));
+        }
+        refresh_preload(&self.inner, inner);
+    }
+
+    #[inline]
+    /// Get the shuffle mode of the queue
+    pub fn shuffle_mode(&self) -> ShuffleMode {
+        self.inner.lock().unwrap().queue.shuffle()
+    }
+
+    #[inline]
+    /// Set the shuffle mode of the queue
+    pub fn set_shuffle_mode(&self, shuffle: ShuffleMode) {
+        let mut inner = self.inner.lock().unwrap();
+        let changed = inner.queue.shuffle() != shuffle;
+        inner.queue.set_shuffle(shuffle);
+        if changed {
+            inner.emit(PlayerEvent::ShuffleChanged(shuffle));
+        }
+        refresh_preload(&self.inner, inner);
+    }
+
+    #[inline]
+    /// Get the duration of the crossfade between consecutive tracks
+    pub fn crossfade(&self) -> Duration {
+        self.inner.lock().unwrap().crossfade
+    }
+
+    #[inline]
+    /// Set the duration of the crossfade between consecutive tracks, zero disables it
+    pub fn set_crossfade(&self, duration: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.crossfade = duration;
+        if let Some(stream) = inner.stream.as_ref() {
+            stream.set_crossfade(duration);
+        }
+    }
+
+    #[inline]
+    /// Get the volume of the output as a linear factor
+    pub fn volume(&self) -> f32 {
+        self.inner.lock().unwrap().volume
+    }
+
+    #[inline]
+    /// Set the volume of the output, kept between silence and the level of the files
+    pub fn set_volume(&self, volume: f32) {
+        let mut inner = self.inner.lock().unwrap();
+        let volume = volume.clamp(0.0, 1.0);
+        let changed = inner.volume != volume;
+        inner.volume = volume;
+        if let Some(stream) = inner.stream.as_ref() {
+            stream.set_volume(volume);
+        }
+        if changed {
+            inner.emit(PlayerEvent::VolumeChanged(volume));
         }
     }
+
+    #[inline]
+    /// Get the settings of the gain applied from the ReplayGain tags
+    pub fn replay_gain(&self) -> ReplayGainConfig {
+        self.inner.lock().unwrap().replay_gain
+    }
+
+    #[inline]
+    /// Set the gain applied from the ReplayGain tags, the track being played is updated
+    pub fn set_replay_gain(&self, config: ReplayGainConfig) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.replay_gain = config;
+        if let Some(stream) = inner.stream.as_ref() {
+            stream.set_replay_gain(config);
+        }
+    }
+
+    #[inline]
+    /// Get the ReplayGain values used to level the tracks
+    pub fn replay_gain_mode(&self) -> ReplayGainMode {
+        self.replay_gain().mode
+    }
+
+    #[inline]
+    /// Choose the ReplayGain values used to level the tracks, keeping the other settings
+    pub fn set_replay_gain_mode(&self, mode: ReplayGainMode) {
+        let config = ReplayGainConfig {
+            mode,
+            ..self.replay_gain()
+        };
+        self.set_replay_gain(config);
+    }
+
+    #[inline]
+    /// Get the settings of the playback speed and pitch
+    pub fn speed_config(&self) -> SpeedConfig {
+        self.inner.lock().unwrap().speed
+    }
+
+    #[inline]
+    /// Set the playback speed and pitch, the track being played is updated
+    ///
+    /// The position keeps following the track, whatever the speed
+    pub fn set_speed_config(&self, config: SpeedConfig) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.speed = config;
+        if let Some(stream) = inner.stream.as_ref() {
+            stream.set_speed(config);
+        }
+    }
+
+    #[inline]
+    /// Get the playback speed, 1 is the original speed
+    pub fn speed(&self) -> f64 {
+        self.speed_config().speed
+    }
+
+    #[inline]
+    /// Set the playback speed, between `MIN_SPEED` and `MAX_SPEED`
+    pub fn set_speed(&self, speed: f64) {
+        let config = SpeedConfig {
+            speed,
+            ..self.speed_config()
+        };
+        self.set_speed_config(config);
+    }
+
+    #[inline]
+    /// Get the pitch shift in semitones
+    pub fn pitch(&self) -> f64 {
+        self.speed_config().pitch
+    }
+
+    #[inline]
+    /// Shift the pitch by the given semitones without changing the speed, up to `MAX_PITCH`
+    pub fn set_pitch(&self, semitones: f64) {
+        let config = SpeedConfig {
+            pitch: semitones,
+            ..self.speed_config()
+        };
+        self.set_speed_config(config);
+    }
+
+    #[inline]
+    /// Get how the playback speed is changed
+    pub fn speed_mode(&self) -> SpeedMode {
+        self.speed_config().mode
+    }
+
+    #[inline]
+    /// Choose whether the playback speed keeps the pitch or changes it too
+    pub fn set_speed_mode(&self, mode: SpeedMode) {
+        let config = SpeedConfig {
+            mode,
+            ..self.speed_config()
+        };
+        self.set_speed_config(config);
+    }
+
+    #[inline]
+    /// Get the settings of the region played in a loop
+    pub fn loop_config(&self) -> LoopConfig {
+        self.inner.lock().unwrap().looping
+    }
+
+    #[inline]
+    /// Set the region played in a loop, the track being played is updated
+    pub fn set_loop_config(&self, config: LoopConfig) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.looping = config;
+        if let Some(stream) = inner.stream.as_ref() {
+            stream.set_loop(config);
+        }
+    }
+
+    #[inline]
+    /// Get the positions of the A and B points of the track being played
+    pub fn loop_region(&self) -> Option<(Duration, Duration)> {
+        self.loop_config().region
+    }
+
+    /// Play the track between the A and B positions in a loop, until the track changes
+    ///
+    /// The playback moves to A if it is already past B. Returns false if B isn't after A
+    pub fn set_loop(&self, start: Duration, end: Duration) -> bool {
+        if end <= start {
+            return false;
+        }
+
+        let config = LoopConfig {
+            region: Some((start, end)),
+            ..self.loop_config()
+        };
+        self.set_loop_config(config);
+        if self.position() >= end {
+            self.seek_stream(start);
+        }
+        true
+    }
+
+    #[inline]
+    /// Stop looping the A-B region, the track plays on to its end
+    pub fn clear_loop(&self) {
+        let config = self.loop_config().without_region();
+        self.set_loop_config(config);
+    }
+
+    #[inline]
+    /// Set the length of the crossfade at the seam of the loops
+    pub fn set_loop_crossfade(&self, crossfade: Duration) {
+        let config = LoopConfig {
+            crossfade,
+            ..self.loop_config()
+        };
+        self.set_loop_config(config);
+    }
+
+    #[inline]
+    /// Choose whether the loop points stored in the files, such as WAV `smpl` loops, are played
+    pub fn set_file_loops(&self, enabled: bool) {
+        let config = LoopConfig {
+            file_loops: enabled,
+            ..self.loop_config()
+        };
+        self.set_loop_config(config);
+    }
+
+    /// Play the track of the queue at the given index
+    pub fn play_index(&self, index: usize) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.queue.set_current(Some(index)) {
+            return false;
+        }
+        play_from(&self.inner, inner, Some(index));
+        true
+    }
+
+    /// Play the next track of the queue
+    pub fn next_track(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        let index = inner.queue.next_track();
+        play_from(&self.inner, inner, index);
+    }
+
+    /// Play the previous track of the queue
+    pub fn previous_track(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        let index = inner.queue.previous_track();
+        play_from(&self.inner, inner, index);
+    }
+}
+
+/// Get the output of the player, opening it on first use
+///
+/// The sink is consumed by the first opening, once it failed the same error is returned
+fn open_stream(
+    shared: &Arc<Mutex<PlayerInner>>,
+    inner: &mut PlayerInner,
+) -> Result<AudioStream, String> {
+    if let Some(stream) = inner.stream.as_ref() {
+        return Ok(stream.clone());
+    }
+
+    if let Err(error) = &inner.sink {
+        return Err(error.clone());
+    }
+
+    // The stream keeps the output from now on
+    let sink = std::mem::replace(&mut inner.sink, Err(String::new())).unwrap();
+    let stream = match AudioStream::with_sink(sink, inner.fades) {
+        Ok(stream) => stream,
+        Err(err) => {
+            let error = format!("output: {}", err);
+            inner.sink = Err(error.clone());
+            return Err(error);
+        }
+    };
+    stream.set_crossfade(inner.crossfade);
+    stream.set_replay_gain(inner.replay_gain);
+    stream.set_speed(inner.speed);
+    stream.set_loop(inner.looping);
+    stream.set_volume(inner.volume);
+    watch_stream(Arc::downgrade(shared), stream.events());
+    inner.stream = Some(stream.clone());
+    Ok(stream)
+}
+
+/// Report an output that can't be opened, nothing can be played
+fn fail_output(inner: &mut PlayerInner, error: String) {
+    inner.emit(PlayerEvent::Error(error));
+    inner.set_state(PlayerState::Error);
+}
+
+/// Replace the current source by the track at `index`, skipping the tracks that can't be opened
+///
+/// The files are opened without the lock, another call can stop or replace the playback meanwhile
+fn play_from<'a>(
+    shared: &'a Arc<Mutex<PlayerInner>>,
+    mut inner: MutexGuard<'a, PlayerInner>,
+    mut index: Option<usize>,
+) {
+    inner.stop();
+    let load = inner.loads;
+
+    // Bound the number of tries, repeating the queue could loop over unreadable tracks forever
+    let mut tries = inner.queue.len();
+    let mut failed = false;
+    while let Some(current) = index {
+        if tries == 0 {
+            inner.queue.set_current(None);
+            break;
+        }
+        tries -= 1;
+
+        inner.set_state(PlayerState::Loading);
+        let track = inner.queue.get(current).unwrap().clone();
+
+        drop(inner);
+        let decoder = open_track(track.path());
+        inner = shared.lock().unwrap();
+        // Another call stopped or replaced the playback meanwhile, the track may have moved
+        let current = match inner.queue.index_of(track.id()) {
+            Some(current) if inner.loads == load => current,
+            _ => return,
+        };
+
+        match decoder {
+            Ok(decoder) => {
+                let stream = match open_stream(shared, &mut inner) {
+                    Ok(stream) => stream,
+                    Err(error) => return fail_output(&mut inner, error),
+                };
+                inner.source = Some(stream.load(decoder));
+                stream.play();
+
+                inner.emit(PlayerEvent::TrackChanged {
+                    index: current,
+                    track,
+                });
+                inner.set_state(PlayerState::Playing);
+                refresh_preload(shared, inner);
+                return;
+            }
+            Err(error) => {
+                failed = true;
+                inner.emit(PlayerEvent::Error(format!(
+                    "{}: {}",
+                    track.path().display(),
+                    error
+                )));
+            }
+        }
+
+        index = inner.queue.next_track();
+    }
+
+    inner.emit(PlayerEvent::QueueEnded);
+    inner.set_state(if failed {
+        PlayerState::Error
+    } else {
+        PlayerState::Ended
+    });
+}
+
+/// Give the stream the track following the current one, once the queue has changed
+///
+/// The file is opened without the lock, it's dropped if the playback or the queue changed meanwhile
+fn refresh_preload(shared: &Arc<Mutex<PlayerInner>>, mut inner: MutexGuard<'_, PlayerInner>) {
+    if inner.source.is_none() {
+        return;
+    }
+
+    // A preload still opening its file is outdated
+    inner.preloads += 1;
+    let (load, preload) = (inner.loads, inner.preloads);
+    let next = inner
+        .queue
+        .upcoming()
+        .and_then(|index| inner.queue.get(index))
+        .cloned();
+    if next == inner.preloaded {
+        return;
+    }
+
+    drop(inner);
+    let decoder = next
+        .as_ref()
+        .and_then(|track| open_track(track.path()).ok());
+    let mut inner = shared.lock().unwrap();
+    if inner.loads != load || inner.preloads != preload {
+        return;
+    }
+
+    let stream = inner.stream.clone().unwrap();
+    inner.next_source = match decoder {
+        Some(decoder) => Some(stream.queue_next(decoder)),
+        None => {
+            stream.clear_next();
+            None
+        }
+    };
+    inner.preloaded = next;
+}
+
+/// Open an audio file of the queue
+fn open_track(path: &Path) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    Decoder::new(BufReader::new(file)).map_err(|_| String::from("unsupported audio format"))
+}
+
+/// Follow the events of the output until the player is dropped
+///
+/// The queue advances when the stream moves to the preloaded track or reaches its end,
+/// the position is sent periodically while playing
+fn watch_stream(shared: Weak<Mutex<PlayerInner>>, events: Receiver<StreamEvent>) {
+    std::thread::spawn(move || loop {
+        let event = events.recv_timeout(POSITION_INTERVAL);
+
+        let shared = match shared.upgrade() {
+            Some(shared) => shared,
+            None => break,
+        };
+        let mut inner = shared.lock().unwrap();
+
+        match event {
+            Err(RecvTimeoutError::Timeout) => {
+                if inner.state == PlayerState::Playing && inner.source.is_some() {
+                    if let Some(position) = inner.stream.as_ref().map(AudioStream::position) {
+                        inner.emit(PlayerEvent::Position(position));
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+            Ok(StreamEvent::Advanced(id)) => {
+                if inner.next_source != Some(id) {
+                    continue;
+                }
+
+                end_track(&mut inner);
+                inner.looping.region = None;
+                inner.source = Some(id);
+                inner.next_source = None;
+                inner.preloaded = None;
+                if let Some(index) = inner.queue.advance() {
+                    let track = inner.queue.get(index).unwrap().clone();
+                    inner.emit(PlayerEvent::TrackChanged { index, track });
+                }
+                refresh_preload(&shared, inner);
+            }
+            Ok(StreamEvent::Ended(id)) => {
+                if inner.source != Some(id) {
+                    continue;
+                }
+
+                if end_track(&mut inner) {
+                    let index = inner.queue.advance();
+                    play_from(&shared, inner, index);
+                } else {
+                    // The source wasn't loaded from the queue
+                    inner.stop();
+                    inner.set_state(PlayerState::Ended);
+                }
+            }
+            Ok(StreamEvent::Error(error)) => {
+                inner.stop();
+                inner.emit(PlayerEvent::Error(error));
+                inner.set_state(PlayerState::Error);
+            }
+        }
+    });
+}
+
+/// Notify the end of the current track of the queue, returns false without current track
+fn end_track(inner: &mut PlayerInner) -> bool {
+    let index = match inner.queue.current() {
+        Some(index) => index,
+        None => return false,
+    };
+
+    let track = inner.queue.get(index).unwrap().clone();
+    inner.emit(PlayerEvent::TrackEnded { index, track });
+    true
 }
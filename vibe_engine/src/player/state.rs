@@ -0,0 +1,27 @@
+/// Playback state of the player
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PlayerState {
+    /// No track has been loaded
+    #[default]
+    Empty,
+    /// A track is being opened
+    Loading,
+    /// A track is being played
+    Playing,
+    /// The playback is paused and can be resumed
+    Paused,
+    /// The playback has been stopped, playing again starts the current track over
+    Stopped,
+    /// The last track of the queue is over
+    Ended,
+    /// The track couldn't be played
+    Error,
+}
+
+impl PlayerState {
+    #[inline]
+    /// Returns true if a stream is loaded, playing or not
+    pub fn is_active(&self) -> bool {
+        matches!(self, PlayerState::Playing | PlayerState::Paused)
+    }
+}
@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use vibe_core::Metadata;
+
+use super::{Player, PlayerEvent, PlayerState};
+use crate::queue::{RepeatMode, ShuffleMode, Track};
+
+/// Offset in seconds of a seek of the front-ends, backward or forward
+pub const SEEK_STEP: f64 = 5.0;
+
+/// Format a position as `m:ss`, or `h:mm:ss` past an hour
+pub fn format_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// What a front-end shows of a player, kept up to date with the events of the player
+///
+/// The front-ends give it every event they receive and act on the player through it,
+/// so the state, the position and the tags of the track being played are tracked once
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerStatus {
+    state: PlayerState,
+    index: Option<usize>,
+    track: Option<Track>,
+    metadata: Metadata,
+    duration: Option<Duration>,
+    position: Duration,
+    repeat: RepeatMode,
+    shuffle: ShuffleMode,
+    error: Option<String>,
+}
+
+impl PlayerStatus {
+    /// Get the current status of a player
+    pub fn new(player: &Player) -> Self {
+        let queue = player.queue();
+        let mut status = Self {
+            state: player.state(),
+            index: None,
+            track: None,
+            metadata: Metadata::new(),
+            duration: None,
+            position: Duration::from_secs(0),
+            repeat: player.repeat_mode(),
+            shuffle: player.shuffle_mode(),
+            error: None,
+        };
+        if let (Some(index), Some(track)) = (queue.current(), queue.current_track()) {
+            status.set_track(index, track.clone());
+            status.position = player.position();
+        }
+        status
+    }
+
+    /// Update the status with an event of the player
+    pub fn handle_event(&mut self, event: &PlayerEvent) {
+        match event {
+            PlayerEvent::StateChanged(state) => {
+                self.state = *state;
+                if !state.is_active() {
+                    self.position = Duration::from_secs(0);
+                }
+            }
+            PlayerEvent::Position(position) => self.position = *position,
+            PlayerEvent::TrackChanged { index, track } => {
+                self.set_track(*index, track.clone());
+                self.error = None;
+            }
+            PlayerEvent::Error(error) => self.error = Some(error.clone()),
+            PlayerEvent::RepeatChanged(repeat) => self.repeat = *repeat,
+            PlayerEvent::ShuffleChanged(shuffle) => self.shuffle = *shuffle,
+            PlayerEvent::TrackEnded { .. }
+            | PlayerEvent::QueueEnded
+            | PlayerEvent::QueueChanged
+            | PlayerEvent::VolumeChanged(_) => {}
+        }
+    }
+
+    /// Take the tags and the duration of the track being played, read when it was queued
+    fn set_track(&mut self, index: usize, track: Track) {
+        self.metadata = track.metadata().clone();
+        self.duration = track.duration();
+        self.index = Some(index);
+        self.track = Some(track);
+        self.position = Duration::from_secs(0);
+    }
+
+    #[inline]
+    /// Get the playback state
+    pub fn state(&self) -> PlayerState {
+        self.state
+    }
+
+    #[inline]
+    /// Returns true if a track is being played
+    pub fn is_playing(&self) -> bool {
+        self.state == PlayerState::Playing
+    }
+
+    #[inline]
+    /// Get the index in the queue of the track being played
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    #[inline]
+    /// Get the track being played
+    pub fn track(&self) -> Option<&Track> {
+        self.track.as_ref()
+    }
+
+    #[inline]
+    /// Get the tags of the track being played
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Get the title of the track being played, its file name if it has no title tag
+    pub fn title(&self) -> Option<String> {
+        let track = self.track.as_ref()?;
+        let title = self.metadata.title().map(str::to_owned).or_else(|| {
+            track
+                .path()
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        });
+        Some(title.unwrap_or_else(|| track.path().display().to_string()))
+    }
+
+    #[inline]
+    /// Get the duration of the track being played, if known
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    #[inline]
+    /// Get the position in the track being played
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+
+    /// Get the position and the duration of the track being played, as `m:ss / m:ss`
+    pub fn time(&self) -> String {
+        match self.duration {
+            Some(duration) => format!("{} / {}", format_time(self.position), format_time(duration)),
+            None => format_time(self.position),
+        }
+    }
+
+    /// Get the part of the track already played, from 0.0 to 1.0
+    pub fn progress(&self) -> f64 {
+        match self.duration {
+            Some(duration) if duration > Duration::from_secs(0) => {
+                (self.position.as_secs_f64() / duration.as_secs_f64()).min(1.0)
+            }
+            _ => 0.0,
+        }
+    }
+
+    #[inline]
+    /// Get the repeat mode of the queue
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    #[inline]
+    /// Get the shuffle mode of the queue
+    pub fn shuffle(&self) -> ShuffleMode {
+        self.shuffle
+    }
+
+    #[inline]
+    /// Get the last error of the player, cleared when the next track starts
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Pause the playback, or play if it isn't playing
+    pub fn toggle_play(&self, player: &Player) {
+        if self.is_playing() {
+            player.pause_stream();
+        } else {
+            player.play_stream();
+        }
+    }
+
+    /// Switch the player to the next repeat mode
+    pub fn cycle_repeat(&mut self, player: &Player) {
+        self.repeat = self.repeat.next();
+        player.set_repeat_mode(self.repeat);
+    }
+
+    /// Switch the player to the next shuffle mode
+    pub fn cycle_shuffle(&mut self, player: &Player) {
+        self.shuffle = self.shuffle.next();
+        player.set_shuffle_mode(self.shuffle);
+    }
+
+    /// Move the playback forward, or backward for a negative offset in seconds
+    ///
+    /// Moving past the end of the track plays the next one
+    pub fn seek_by(&mut self, player: &Player, offset: f64) {
+        let position = (player.position().as_secs_f64() + offset).max(0.0);
+        match self.duration {
+            Some(duration) if position >= duration.as_secs_f64() => player.next_track(),
+            _ => {
+                self.position = Duration::from_secs_f64(position);
+                player.seek_stream(self.position);
+            }
+        }
+    }
+
+    /// Move the playback to a part of the track, from 0.0 to 1.0
+    pub fn seek_to(&mut self, player: &Player, progress: f64) {
+        if let Some(duration) = self.duration {
+            self.position = duration.mul_f64(progress.clamp(0.0, 1.0));
+            player.seek_stream(self.position);
+        }
+    }
+}
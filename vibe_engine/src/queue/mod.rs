@@ -0,0 +1,7 @@
+mod modes;
+mod queue;
+mod track;
+
+pub use self::modes::{RepeatMode, ShuffleMode};
+pub use self::queue::Queue;
+pub use self::track::{Track, TrackInfo};
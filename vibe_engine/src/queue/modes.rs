@@ -0,0 +1,45 @@
+/// How the queue continues once the current track is over
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RepeatMode {
+    /// The queue stops after the last track
+    #[default]
+    Off,
+    /// The current track is played again
+    One,
+    /// The queue starts again after the last track
+    All,
+}
+
+impl RepeatMode {
+    /// Get the mode following this one, to cycle through the modes with a single button
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+}
+
+/// Order in which the tracks of the queue are played
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShuffleMode {
+    /// Tracks are played in the order of the queue
+    #[default]
+    Off,
+    /// Any other track can be picked, the queue never ends
+    Random,
+    /// Tracks are picked randomly but none is played twice before all have been played
+    NoRepeat,
+}
+
+impl ShuffleMode {
+    /// Get the mode following this one, to cycle through the modes with a single button
+    pub fn next(self) -> Self {
+        match self {
+            ShuffleMode::Off => ShuffleMode::NoRepeat,
+            ShuffleMode::NoRepeat => ShuffleMode::Random,
+            ShuffleMode::Random => ShuffleMode::Off,
+        }
+    }
+}
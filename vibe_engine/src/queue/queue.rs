@@ -0,0 +1,356 @@
+use rand::Rng;
+
+use super::{RepeatMode, ShuffleMode, Track};
+
+/// Ordered list of tracks with a cursor on the track being played
+#[derive(Debug, Clone, Default)]
+pub struct Queue {
+    tracks: Vec<Track>,
+    current: Option<usize>,
+    repeat: RepeatMode,
+    shuffle: ShuffleMode,
+    /// Tracks not played yet in `ShuffleMode::NoRepeat`
+    unplayed: Vec<usize>,
+    /// Tracks played before the current one when shuffling
+    history: Vec<usize>,
+    /// Track chosen to follow the current one, once asked for
+    upcoming: Option<Option<usize>>,
+    /// Id of the last track added, the ids are never reused
+    last_id: u64,
+}
+
+impl Queue {
+    #[inline]
+    /// Create a new empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    /// Get the number of tracks in the queue
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    #[inline]
+    /// Returns true if the queue has no track
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    #[inline]
+    /// Get the tracks in queue order
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    #[inline]
+    /// Get the track at the given index
+    pub fn get(&self, index: usize) -> Option<&Track> {
+        self.tracks.get(index)
+    }
+
+    #[inline]
+    /// Get the index of the track with the given id
+    pub fn index_of(&self, id: u64) -> Option<usize> {
+        self.tracks.iter().position(|track| track.id() == id)
+    }
+
+    #[inline]
+    /// Get the index of the current track
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    #[inline]
+    /// Get the current track
+    pub fn current_track(&self) -> Option<&Track> {
+        self.current.and_then(|index| self.tracks.get(index))
+    }
+
+    #[inline]
+    /// Get the repeat mode
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    #[inline]
+    /// Set the repeat mode
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.forget_upcoming();
+        self.repeat = repeat;
+    }
+
+    #[inline]
+    /// Get the shuffle mode
+    pub fn shuffle(&self) -> ShuffleMode {
+        self.shuffle
+    }
+
+    /// Set the shuffle mode, the tracks already played are forgotten
+    pub fn set_shuffle(&mut self, shuffle: ShuffleMode) {
+        self.forget_upcoming();
+        self.shuffle = shuffle;
+        self.history.clear();
+        self.refill_unplayed();
+
+        if let Some(current) = self.current {
+            self.unplayed.retain(|&i| i != current);
+        }
+    }
+
+    /// Set the current track, returns false if the index is out of the queue
+    pub fn set_current(&mut self, index: Option<usize>) -> bool {
+        match index {
+            Some(index) if index >= self.tracks.len() => false,
+            _ => {
+                self.forget_upcoming();
+                self.move_to(index);
+                true
+            }
+        }
+    }
+
+    /// Add a track at the end of the queue, returns the id given to the track
+    pub fn append(&mut self, track: Track) -> u64 {
+        self.insert(self.tracks.len(), track)
+    }
+
+    /// Insert a track at the given index, the index is clamped to the end of the queue
+    ///
+    /// Returns the id given to the track
+    pub fn insert(&mut self, index: usize, mut track: Track) -> u64 {
+        let index = index.min(self.tracks.len());
+        self.last_id += 1;
+        track.set_id(self.last_id);
+        self.tracks.insert(index, track);
+
+        self.remap(|i| Some(if i >= index { i + 1 } else { i }));
+        if self.shuffle == ShuffleMode::NoRepeat {
+            self.unplayed.push(index);
+        }
+        self.last_id
+    }
+
+    /// Remove the track at the given index
+    ///
+    /// Removing the current track leaves the queue without current track
+    pub fn remove(&mut self, index: usize) -> Option<Track> {
+        if index >= self.tracks.len() {
+            return None;
+        }
+
+        let track = self.tracks.remove(index);
+        self.remap(|i| match i {
+            i if i == index => None,
+            i if i > index => Some(i - 1),
+            i => Some(i),
+        });
+
+        Some(track)
+    }
+
+    /// Move a track from one index to another, the current track follows its entry
+    pub fn move_track(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.tracks.len() || to >= self.tracks.len() {
+            return false;
+        }
+
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+
+        self.remap(|i| {
+            Some(if i == from {
+                to
+            } else if from < i && i <= to {
+                i - 1
+            } else if to <= i && i < from {
+                i + 1
+            } else {
+                i
+            })
+        });
+
+        true
+    }
+
+    /// Remove all the tracks
+    pub fn clear(&mut self) {
+        self.upcoming = None;
+        self.tracks.clear();
+        self.current = None;
+        self.unplayed.clear();
+        self.history.clear();
+    }
+
+    /// Move to the next track and return its index
+    ///
+    /// Without current track the queue starts from the beginning,
+    /// after the last track the queue is over and has no current track
+    /// unless every track is repeated
+    pub fn next_track(&mut self) -> Option<usize> {
+        let next = match self.upcoming.take() {
+            Some(next) => next,
+            None => self.pick_next(),
+        };
+
+        self.move_to(next);
+        next
+    }
+
+    /// Get the track `advance` will move to without moving
+    ///
+    /// The choice is kept until the modes or the current track change
+    pub fn upcoming(&mut self) -> Option<usize> {
+        if let (RepeatMode::One, Some(current)) = (self.repeat, self.current) {
+            return Some(current);
+        }
+
+        match self.upcoming {
+            Some(next) => next,
+            None => {
+                let next = self.pick_next();
+                self.upcoming = Some(next);
+                next
+            }
+        }
+    }
+
+    /// Choose the track following the current one
+    fn pick_next(&mut self) -> Option<usize> {
+        match self.shuffle {
+            ShuffleMode::Off => match self.current {
+                Some(current) if current + 1 < self.tracks.len() => Some(current + 1),
+                Some(_) if self.repeat == RepeatMode::All => Some(0),
+                Some(_) => None,
+                None if !self.tracks.is_empty() => Some(0),
+                None => None,
+            },
+            ShuffleMode::Random => self.random_track(),
+            ShuffleMode::NoRepeat => {
+                if self.unplayed.is_empty()
+                    && (self.repeat == RepeatMode::All || self.current.is_none())
+                {
+                    self.refill_unplayed();
+                }
+                self.draw_unplayed()
+            }
+        }
+    }
+
+    /// Drop the choice of the upcoming track, a drawn track goes back to the unplayed ones
+    fn forget_upcoming(&mut self) {
+        if let Some(Some(index)) = self.upcoming.take() {
+            if self.shuffle == ShuffleMode::NoRepeat && !self.unplayed.contains(&index) {
+                self.unplayed.push(index);
+            }
+        }
+    }
+
+    /// Move to the previous track and return its index
+    ///
+    /// In order the first track stays the current one,
+    /// when shuffling the tracks are played back in reverse order
+    pub fn previous_track(&mut self) -> Option<usize> {
+        self.forget_upcoming();
+        match self.shuffle {
+            ShuffleMode::Off => {
+                self.current = self.current.map(|current| current.saturating_sub(1));
+            }
+            _ => {
+                if let Some(previous) = self.history.pop() {
+                    if let Some(current) = self.current {
+                        if self.shuffle == ShuffleMode::NoRepeat {
+                            self.unplayed.push(current);
+                        }
+                    }
+                    self.current = Some(previous);
+                }
+            }
+        }
+        self.current
+    }
+
+    /// Move to the track to play once the current one is over
+    ///
+    /// The current track is kept when repeating one track
+    pub fn advance(&mut self) -> Option<usize> {
+        match (self.repeat, self.current) {
+            (RepeatMode::One, Some(current)) => Some(current),
+            _ => self.next_track(),
+        }
+    }
+
+    /// Make `index` the current track, keeping track of the shuffle history
+    fn move_to(&mut self, index: Option<usize>) {
+        if self.shuffle != ShuffleMode::Off {
+            if let (Some(current), Some(_)) = (self.current, index) {
+                self.history.push(current);
+            }
+        }
+        if let Some(index) = index {
+            self.unplayed.retain(|&i| i != index);
+        }
+        self.current = index;
+    }
+
+    /// Pick any track, different from the current one when possible
+    fn random_track(&self) -> Option<usize> {
+        let len = self.tracks.len();
+        match (len, self.current) {
+            (0, _) => None,
+            (1, _) | (_, None) => Some(rand::thread_rng().gen_range(0..len)),
+            (_, Some(current)) => {
+                let index = rand::thread_rng().gen_range(0..len - 1);
+                Some(if index >= current { index + 1 } else { index })
+            }
+        }
+    }
+
+    /// Pick a track not played yet, different from the current one when possible
+    fn draw_unplayed(&mut self) -> Option<usize> {
+        let candidates = match self.current {
+            Some(current) if self.unplayed.len() > 1 && self.unplayed.contains(&current) => {
+                self.unplayed.len() - 1
+            }
+            _ => self.unplayed.len(),
+        };
+        if candidates == 0 {
+            return None;
+        }
+
+        let mut index = rand::thread_rng().gen_range(0..candidates);
+        if candidates < self.unplayed.len() {
+            // Skip the current track
+            let current = self.current.unwrap();
+            let position = self.unplayed.iter().position(|&i| i == current).unwrap();
+            if index >= position {
+                index += 1;
+            }
+        }
+        Some(self.unplayed.swap_remove(index))
+    }
+
+    /// Mark every track as not played
+    fn refill_unplayed(&mut self) {
+        self.unplayed.clear();
+        if self.shuffle == ShuffleMode::NoRepeat {
+            self.unplayed.extend(0..self.tracks.len());
+        }
+    }
+
+    /// Update the stored indexes after the tracks have been edited
+    fn remap<F>(&mut self, f: F)
+    where
+        F: Fn(usize) -> Option<usize>,
+    {
+        self.current = self.current.and_then(&f);
+        // In order the upcoming track depends on the edit, a removed one has to be chosen again
+        self.upcoming = match self.upcoming {
+            Some(Some(i)) if self.shuffle != ShuffleMode::Off => f(i).map(Some),
+            _ => None,
+        };
+        self.unplayed = self.unplayed.iter().filter_map(|&i| f(i)).collect();
+        self.history = self.history.iter().filter_map(|&i| f(i)).collect();
+    }
+}
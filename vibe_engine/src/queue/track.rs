@@ -0,0 +1,82 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use vibe_core::{decoder::Decoder, Metadata};
+
+/// Tags and duration of an audio file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackInfo {
+    pub metadata: Metadata,
+    pub duration: Option<Duration>,
+}
+
+impl TrackInfo {
+    /// Read the tags and the duration of a file, empty if it can't be decoded
+    pub fn read(path: &Path) -> Self {
+        let decoder = File::open(path)
+            .ok()
+            .and_then(|file| Decoder::new(BufReader::new(file)).ok());
+        match decoder {
+            Some(decoder) => Self {
+                metadata: decoder.metadata().clone(),
+                duration: decoder.info().duration(),
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+/// Entry of the play queue
+///
+/// The tags and the duration are read once when the track is created, the copies share them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    id: u64,
+    path: PathBuf,
+    info: Arc<TrackInfo>,
+}
+
+impl Track {
+    #[inline]
+    /// Create a new track from the path of an audio file, reading its tags and duration
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let info = Arc::new(TrackInfo::read(&path));
+        Self { id: 0, path, info }
+    }
+
+    #[inline]
+    /// Get the id given by the queue, it stays the same while the track is in the queue
+    ///
+    /// A track out of a queue has the id 0
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    #[inline]
+    /// Give the track its id in a queue
+    pub(super) fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+
+    #[inline]
+    /// Get the path of the audio file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    /// Get the tags of the audio file
+    pub fn metadata(&self) -> &Metadata {
+        &self.info.metadata
+    }
+
+    #[inline]
+    /// Get the duration of the audio file, if known
+    pub fn duration(&self) -> Option<Duration> {
+        self.info.duration
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use vibe_core::decoder::{duration_to_frames, frames_to_duration};
+
+use super::{ErrorCallback, OutputSink, RenderCallback, SinkError, SinkOutput};
+use crate::stream::OutputFormat;
+
+/// Number of frames rendered at once by `Capture::render_until_idle`
+const CAPTURE_BLOCK_FRAMES: usize = 512;
+
+/// Render callback of a capture and the number of frames it rendered
+struct CaptureState {
+    render: Option<RenderCallback>,
+    errors: Option<ErrorCallback>,
+    frames: u64,
+}
+
+/// Sink rendering the samples only when its `Capture` asks for them
+///
+/// The output runs on a virtual clock moved by the caller, so the samples produced
+/// by the engine don't depend on the timing of the threads
+pub struct CaptureSink {
+    format: OutputFormat,
+    state: Arc<Mutex<CaptureState>>,
+}
+
+impl CaptureSink {
+    /// Create a sink in the given format and the handle driving it
+    pub fn new(format: OutputFormat) -> (Self, Capture) {
+        let state = Arc::new(Mutex::new(CaptureState {
+            render: None,
+            errors: None,
+            frames: 0,
+        }));
+        let capture = Capture {
+            format,
+            state: Arc::clone(&state),
+        };
+
+        (Self { format, state }, capture)
+    }
+}
+
+impl OutputSink for CaptureSink {
+    fn open(self: Box<Self>) -> Result<Box<dyn SinkOutput>, SinkError> {
+        Ok(Box::new(CaptureOutput {
+            format: self.format,
+            state: self.state,
+        }))
+    }
+}
+
+/// Output of a capture sink
+struct CaptureOutput {
+    format: OutputFormat,
+    state: Arc<Mutex<CaptureState>>,
+}
+
+impl SinkOutput for CaptureOutput {
+    fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    fn start(&mut self, render: RenderCallback, on_error: ErrorCallback) -> Result<(), SinkError> {
+        let mut state = self.state.lock().unwrap();
+        state.render = Some(render);
+        state.errors = Some(on_error);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.render = None;
+        state.errors = None;
+    }
+}
+
+/// Handle rendering the samples of a `CaptureSink` on the calling thread
+#[derive(Clone)]
+pub struct Capture {
+    format: OutputFormat,
+    state: Arc<Mutex<CaptureState>>,
+}
+
+impl Capture {
+    #[inline]
+    /// Get the format of the samples
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    #[inline]
+    /// Get the number of frames rendered so far
+    pub fn frames(&self) -> u64 {
+        self.state.lock().unwrap().frames
+    }
+
+    #[inline]
+    /// Get the time rendered so far, the virtual clock of the output
+    pub fn elapsed(&self) -> Duration {
+        frames_to_duration(self.frames(), self.format.sample_rate)
+    }
+
+    /// Render the given number of frames and move the clock forward
+    ///
+    /// The output is silent once the stream is shut down
+    pub fn render(&self, frames: usize) -> Vec<f32> {
+        let mut samples = vec![0.0; frames * self.format.channels];
+        self.render_into(&mut samples);
+        samples
+    }
+
+    #[inline]
+    /// Render the frames of the given duration
+    pub fn advance(&self, duration: Duration) -> Vec<f32> {
+        self.render(duration_to_frames(duration, self.format.sample_rate) as usize)
+    }
+
+    /// Render blocks until the engine has nothing left to play, at most the given duration
+    ///
+    /// The silent block ending the playback isn't returned, but it moves the clock
+    pub fn render_until_idle(&self, max: Duration) -> Vec<f32> {
+        let max_frames = duration_to_frames(max, self.format.sample_rate) as usize;
+        let mut samples = Vec::new();
+        let mut block = vec![0.0; CAPTURE_BLOCK_FRAMES * self.format.channels];
+
+        let mut frames = 0;
+        while frames < max_frames {
+            let len = CAPTURE_BLOCK_FRAMES.min(max_frames - frames);
+            let block = &mut block[..len * self.format.channels];
+            if !self.render_into(block) {
+                break;
+            }
+            samples.extend_from_slice(block);
+            frames += len;
+        }
+        samples
+    }
+
+    /// Report an error as the output would, the stream sends it as an event
+    pub fn report_error(&self, error: &str) {
+        if let Some(errors) = self.state.lock().unwrap().errors.as_mut() {
+            errors(error.to_string());
+        }
+    }
+
+    /// Fill the block with the output, returns false if nothing was played
+    fn render_into(&self, block: &mut [f32]) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.frames += (block.len() / self.format.channels.max(1)) as u64;
+        match state.render.as_mut() {
+            Some(render) => render(block),
+            None => {
+                block.iter_mut().for_each(|sample| *sample = 0.0);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    Device, Stream, StreamConfig,
+};
+use std::marker::PhantomData;
+
+use super::{ErrorCallback, OutputSink, RenderCallback, SinkError, SinkOutput};
+use crate::stream::OutputFormat;
+
+/// Number of frames rendered at once into the buffer of the device
+const DEVICE_BLOCK_FRAMES: usize = 512;
+
+/// Sink playing the samples on the default output device, converted to `T`
+pub struct CpalSink<T> {
+    sample: PhantomData<fn() -> T>,
+}
+
+impl<T> CpalSink<T>
+where
+    T: cpal::Sample,
+{
+    #[inline]
+    /// Create a sink for the default output device, the device is opened with the stream
+    pub fn new() -> Self {
+        Self {
+            sample: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for CpalSink<T>
+where
+    T: cpal::Sample,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> OutputSink for CpalSink<T>
+where
+    T: cpal::Sample + 'static,
+{
+    fn open(self: Box<Self>) -> Result<Box<dyn SinkOutput>, SinkError> {
+        let host = cpal::default_host();
+        let device = host.default_output_device().ok_or(SinkError::NoDevice)?;
+
+        let config = device
+            .default_output_config()
+            .map_err(|err| SinkError::DeviceError(err.to_string()))?;
+        let config: StreamConfig = config.into();
+        let format = OutputFormat {
+            sample_rate: config.sample_rate.0,
+            channels: config.channels as usize,
+        };
+
+        Ok(Box::new(CpalOutput::<T> {
+            device,
+            config,
+            format,
+            stream: None,
+            sample: PhantomData,
+        }))
+    }
+}
+
+/// Output stream of a device
+struct CpalOutput<T> {
+    device: Device,
+    config: StreamConfig,
+    format: OutputFormat,
+    stream: Option<Stream>,
+    sample: PhantomData<fn() -> T>,
+}
+
+impl<T> SinkOutput for CpalOutput<T>
+where
+    T: cpal::Sample,
+{
+    fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    fn start(
+        &mut self,
+        mut render: RenderCallback,
+        mut on_error: ErrorCallback,
+    ) -> Result<(), SinkError> {
+        // The device buffer can have any length, the samples are rendered through a fixed one
+        let mut buffer = vec![0.0; DEVICE_BLOCK_FRAMES * self.format.channels];
+        let err_fn = move |err: cpal::StreamError| {
+            eprintln!("an error occurred on stream: {}", err);
+            on_error(err.to_string());
+        };
+
+        let stream = self
+            .device
+            .build_output_stream(
+                &self.config,
+                move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                    for output in data.chunks_mut(buffer.len()) {
+                        let buffer = &mut buffer[..output.len()];
+                        render(buffer);
+                        for (sample, value) in output.iter_mut().zip(buffer.iter()) {
+                            *sample = cpal::Sample::from::<f32>(value);
+                        }
+                    }
+                },
+                err_fn,
+            )
+            .map_err(|err| SinkError::DeviceError(err.to_string()))?;
+
+        stream
+            .play()
+            .map_err(|err| SinkError::DeviceError(err.to_string()))?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            stream.pause().expect("Stop error");
+        }
+    }
+}
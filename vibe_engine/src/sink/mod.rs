@@ -0,0 +1,12 @@
+mod capture;
+mod device;
+mod null;
+mod sink;
+mod wav;
+mod worker;
+
+pub use self::capture::{Capture, CaptureSink};
+pub use self::device::CpalSink;
+pub use self::null::NullSink;
+pub use self::sink::{ErrorCallback, OutputSink, RenderCallback, SinkError, SinkOutput};
+pub use self::wav::WavSink;
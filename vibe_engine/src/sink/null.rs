@@ -0,0 +1,71 @@
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use vibe_core::decoder::frames_to_duration;
+
+use super::worker::Worker;
+use super::{ErrorCallback, OutputSink, RenderCallback, SinkError, SinkOutput};
+use crate::stream::OutputFormat;
+
+/// Number of frames rendered at once
+const NULL_BLOCK_FRAMES: usize = 512;
+
+/// Sink discarding the samples, pulled at the pace of a real device
+///
+/// The whole engine runs as with a device, without producing any sound
+pub struct NullSink {
+    format: OutputFormat,
+}
+
+impl NullSink {
+    #[inline]
+    /// Create a sink pulling the samples in the given format
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl OutputSink for NullSink {
+    fn open(self: Box<Self>) -> Result<Box<dyn SinkOutput>, SinkError> {
+        Ok(Box::new(NullOutput {
+            format: self.format,
+            worker: None,
+        }))
+    }
+}
+
+/// Output of a null sink
+struct NullOutput {
+    format: OutputFormat,
+    worker: Option<Worker>,
+}
+
+impl SinkOutput for NullOutput {
+    fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    fn start(&mut self, mut render: RenderCallback, _: ErrorCallback) -> Result<(), SinkError> {
+        let format = self.format;
+        self.worker = Some(Worker::spawn(move |running| {
+            let mut block = vec![0.0; NULL_BLOCK_FRAMES * format.channels];
+            let started = Instant::now();
+            let mut frames = 0;
+
+            while running.load(Ordering::Acquire) {
+                render(&mut block);
+                frames += NULL_BLOCK_FRAMES as u64;
+
+                // Wait until a device would have played the block
+                let deadline = started + frames_to_duration(frames, format.sample_rate);
+                std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            }
+        }));
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut worker) = self.worker.take() {
+            worker.stop();
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::{error::Error, fmt::Display};
+
+use crate::stream::OutputFormat;
+
+/// Callback filling a block of interleaved samples with the output of the engine
+///
+/// Returns false if the engine had nothing to play, the block is silent then
+pub type RenderCallback = Box<dyn FnMut(&mut [f32]) -> bool + Send>;
+
+/// Callback reporting the errors of a running output
+pub type ErrorCallback = Box<dyn FnMut(String) + Send>;
+
+/// An error encountered while opening or running an output sink.
+#[derive(Debug)]
+pub enum SinkError {
+    /// No output device is available.
+    NoDevice,
+    /// The output device could not be configured or started.
+    DeviceError(String),
+    /// I/O error.
+    IOError(std::io::Error),
+}
+
+impl Error for SinkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SinkError::IOError(err) => Some(err),
+            SinkError::NoDevice | SinkError::DeviceError(_) => None,
+        }
+    }
+}
+
+impl Display for SinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SinkError::NoDevice => write!(f, "no output device available"),
+            SinkError::DeviceError(err) => write!(f, "device error: {}", err),
+            SinkError::IOError(err) => write!(f, "IO error: {}", err),
+        }
+    }
+}
+
+/// Destination of the samples rendered by an `AudioStream`
+///
+/// The sink is moved to the engine thread and opened there, so the output
+/// itself doesn't have to be sent across threads
+pub trait OutputSink: Send + 'static {
+    /// Open the output, called on the engine thread
+    fn open(self: Box<Self>) -> Result<Box<dyn SinkOutput>, SinkError>;
+}
+
+impl OutputSink for Box<dyn OutputSink> {
+    fn open(self: Box<Self>) -> Result<Box<dyn SinkOutput>, SinkError> {
+        (*self).open()
+    }
+}
+
+/// Output opened by a sink, pulling the samples from the engine once started
+pub trait SinkOutput {
+    /// Get the format of the samples pulled by the output
+    fn format(&self) -> OutputFormat;
+
+    /// Start pulling the samples, the callbacks are called from the thread driving the output
+    fn start(&mut self, render: RenderCallback, on_error: ErrorCallback) -> Result<(), SinkError>;
+
+    /// Stop pulling the samples, the output is complete once this returns
+    fn stop(&mut self);
+}
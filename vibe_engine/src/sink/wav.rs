@@ -0,0 +1,192 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use super::worker::Worker;
+use super::{ErrorCallback, OutputSink, RenderCallback, SinkError, SinkOutput};
+use crate::stream::OutputFormat;
+
+/// Number of frames rendered at once
+const WAV_BLOCK_FRAMES: usize = 4096;
+
+/// Wait between two blocks while the engine has nothing to play
+const IDLE_WAIT: Duration = Duration::from_millis(1);
+
+/// Length of the header written before the samples
+const HEADER_LEN: u64 = 56;
+
+/// Position of the length of the RIFF chunk
+const RIFF_LEN_OFFSET: u64 = 4;
+
+/// Position of the number of frames stored in the `fact` chunk
+const FACT_FRAMES_OFFSET: u64 = 44;
+
+/// Position of the length of the `data` chunk
+const DATA_LEN_OFFSET: u64 = 52;
+
+/// `WAVE_FORMAT_IEEE_FLOAT` format tag
+const FORMAT_FLOAT: u16 = 3;
+
+/// Sink writing the samples to a 32-bit float WAV file, as fast as they are rendered
+///
+/// Only the blocks where the engine plays something are written, so the file
+/// holds the playback without the time spent paused. The file is complete once
+/// the stream is shut down
+pub struct WavSink<W> {
+    writer: W,
+    format: OutputFormat,
+}
+
+impl WavSink<BufWriter<File>> {
+    /// Create the file at the given path, replacing an existing one
+    pub fn create<P: AsRef<Path>>(path: P, format: OutputFormat) -> Result<Self, SinkError> {
+        let file = File::create(path).map_err(SinkError::IOError)?;
+        Ok(Self::new(BufWriter::new(file), format))
+    }
+}
+
+impl<W> WavSink<W>
+where
+    W: Write + Seek + Send + 'static,
+{
+    #[inline]
+    /// Create a sink writing the samples in the given format to `writer`
+    pub fn new(writer: W, format: OutputFormat) -> Self {
+        Self { writer, format }
+    }
+}
+
+impl<W> OutputSink for WavSink<W>
+where
+    W: Write + Seek + Send + 'static,
+{
+    fn open(self: Box<Self>) -> Result<Box<dyn SinkOutput>, SinkError> {
+        Ok(Box::new(WavOutput {
+            writer: Some(self.writer),
+            format: self.format,
+            worker: None,
+        }))
+    }
+}
+
+/// Output of a WAV sink
+struct WavOutput<W> {
+    writer: Option<W>,
+    format: OutputFormat,
+    worker: Option<Worker>,
+}
+
+impl<W> SinkOutput for WavOutput<W>
+where
+    W: Write + Seek + Send + 'static,
+{
+    fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    fn start(
+        &mut self,
+        mut render: RenderCallback,
+        mut on_error: ErrorCallback,
+    ) -> Result<(), SinkError> {
+        let mut writer = self
+            .writer
+            .take()
+            .ok_or_else(|| SinkError::DeviceError("the output is already started".to_string()))?;
+        write_header(&mut writer, self.format).map_err(SinkError::IOError)?;
+
+        let channels = self.format.channels;
+        self.worker = Some(Worker::spawn(move |running| {
+            let mut block = vec![0.0f32; WAV_BLOCK_FRAMES * channels];
+            let mut bytes = Vec::with_capacity(block.len() * 4);
+            let mut data_len = 0u64;
+            let mut failed = false;
+
+            // The engine keeps running after an error, so the stream can still be controlled
+            while running.load(Ordering::Acquire) {
+                if !render(&mut block) {
+                    std::thread::sleep(IDLE_WAIT);
+                    continue;
+                }
+                if failed {
+                    continue;
+                }
+
+                bytes.clear();
+                for sample in block.iter() {
+                    bytes.extend_from_slice(&sample.to_le_bytes());
+                }
+                if HEADER_LEN + data_len + bytes.len() as u64 > u32::MAX as u64 {
+                    failed = true;
+                    on_error("the WAV file is full".to_string());
+                    continue;
+                }
+                match writer.write_all(&bytes) {
+                    Ok(()) => data_len += bytes.len() as u64,
+                    Err(err) => {
+                        failed = true;
+                        on_error(err.to_string());
+                    }
+                }
+            }
+
+            // The samples written before an error are still readable
+            if let Err(err) = finish(&mut writer, data_len, channels) {
+                on_error(err.to_string());
+            }
+        }));
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut worker) = self.worker.take() {
+            worker.stop();
+        }
+    }
+}
+
+/// Write the header of an empty file, the lengths are filled by `finish`
+fn write_header<W: Write>(writer: &mut W, format: OutputFormat) -> std::io::Result<()> {
+    let channels = format.channels as u16;
+    let block_align = channels * 4;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(HEADER_LEN as u32 - 8).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&FORMAT_FLOAT.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&format.sample_rate.to_le_bytes())?;
+    writer.write_all(&(format.sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&32u16.to_le_bytes())?;
+
+    // Files in a format other than PCM carry their number of frames
+    writer.write_all(b"fact")?;
+    writer.write_all(&4u32.to_le_bytes())?;
+    writer.write_all(&0u32.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&0u32.to_le_bytes())
+}
+
+/// Fill the lengths of the header once every sample is written
+fn finish<W: Write + Seek>(writer: &mut W, data_len: u64, channels: usize) -> std::io::Result<()> {
+    let frames = data_len / (channels as u64 * 4);
+    let fields = [
+        (RIFF_LEN_OFFSET, (HEADER_LEN - 8 + data_len) as u32),
+        (FACT_FRAMES_OFFSET, frames as u32),
+        (DATA_LEN_OFFSET, data_len as u32),
+    ];
+    for (offset, value) in fields.iter() {
+        writer.seek(SeekFrom::Start(*offset))?;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    writer.seek(SeekFrom::Start(HEADER_LEN + data_len))?;
+    writer.flush()
+}
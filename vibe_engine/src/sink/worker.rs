@@ -0,0 +1,42 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread::JoinHandle;
+
+/// Thread pulling the samples of an output until it is stopped
+pub(crate) struct Worker {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    /// Start the thread, `run` returns once the flag it is given is cleared
+    pub fn spawn<F>(run: F) -> Self
+    where
+        F: FnOnce(&AtomicBool) + Send + 'static,
+    {
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = Arc::clone(&running);
+        let thread = std::thread::spawn(move || run(&flag));
+
+        Self {
+            running,
+            thread: Some(thread),
+        }
+    }
+
+    /// Clear the flag of the thread and wait for it to return
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,235 @@
+use std::fmt;
+use std::io::{Read, Seek};
+use std::time::Duration;
+
+use vibe_core::{decoder::Decoder, ReplayGain, Sample};
+
+use super::looping::LoopReader;
+use super::stretch::TimeStretch;
+use super::{LoopConfig, ReplayGainConfig, SpeedConfig};
+
+/// Format of the samples expected by the output device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputFormat {
+    pub sample_rate: u32,
+    pub channels: usize,
+}
+
+/// Object safe view of a decoder
+pub(crate) trait SampleSource: Send {
+    /// Get the next sample, `None` at the end of the stream or on error
+    fn next_sample(&mut self) -> Option<Sample>;
+
+    /// Move to the given position, returns false on error
+    fn seek(&mut self, pos: Duration) -> bool;
+}
+
+impl<R> SampleSource for Decoder<R>
+where
+    R: Read + Seek + Send,
+{
+    #[inline]
+    fn next_sample(&mut self) -> Option<Sample> {
+        self.next().and_then(|sample| sample.ok())
+    }
+
+    #[inline]
+    fn seek(&mut self, pos: Duration) -> bool {
+        Decoder::seek(self, pos).is_ok()
+    }
+}
+
+/// Decoded audio resampled and channel-mapped to the output format
+///
+/// Resampling uses a linear interpolation between consecutive frames,
+/// the samples are passed through untouched when the sample rates match.
+/// The speed and the pitch are changed by time stretching the frames before
+/// resampling them at another rate, the loops are applied before both
+pub(crate) struct Converter {
+    reader: LoopReader,
+    input_channels: usize,
+    input_rate: u32,
+    /// Input frames consumed per output frame at the original speed
+    base_step: f64,
+    /// Stretched frames consumed per output frame
+    step: f64,
+    stretch: TimeStretch,
+    /// Position between the current and the next input frame
+    position: f64,
+    current: Vec<f32>,
+    next: Vec<f32>,
+    /// Number of input frames consumed, fractional while time stretching
+    frames_read: f64,
+    total_frames: Option<u64>,
+    input_done: bool,
+    ended: bool,
+    replay_gain: ReplayGain,
+    /// Gain applied to the output frames
+    gain: f32,
+}
+
+impl Converter {
+    /// Create a converter, everything is allocated here so the audio callback never allocates
+    pub fn new<R>(decoder: Decoder<R>, format: OutputFormat) -> Self
+    where
+        R: Read + Seek + Send + 'static,
+    {
+        let info = decoder.info();
+        let replay_gain = decoder.metadata().replay_gain();
+        let file_loop = decoder.loops().first().copied();
+        let input_channels = info.channels().max(1);
+        let input_rate = info.sample_rate();
+        let step = input_rate as f64 / format.sample_rate as f64;
+
+        let mut converter = Self {
+            reader: LoopReader::new(
+                Box::new(decoder),
+                input_rate,
+                input_channels,
+                format.channels,
+                file_loop,
+            ),
+            input_channels,
+            input_rate,
+            base_step: step,
+            step,
+            stretch: TimeStretch::new(input_rate, format.channels),
+            position: 0.0,
+            current: vec![0.0; format.channels],
+            next: vec![0.0; format.channels],
+            frames_read: 0.0,
+            total_frames: info
+                .duration()
+                .map(|duration| (duration.as_secs_f64() * input_rate as f64) as u64),
+            input_done: false,
+            ended: false,
+            replay_gain,
+            gain: 1.0,
+        };
+        converter.prime();
+        converter
+    }
+
+    /// Write the next output frame, returns false at the end of the stream
+    pub fn next_frame(&mut self, frame: &mut [f32]) -> bool {
+        if self.ended {
+            return false;
+        }
+
+        let position = self.position as f32;
+        for ((out, current), next) in frame.iter_mut().zip(&self.current).zip(&self.next) {
+            *out = (current + (next - current) * position) * self.gain;
+        }
+
+        self.position += self.step;
+        while self.position >= 1.0 {
+            self.position -= 1.0;
+            if self.input_done {
+                self.ended = true;
+                break;
+            }
+
+            std::mem::swap(&mut self.current, &mut self.next);
+            self.frames_read += self.stretch.tempo();
+            if let Some(frames) = self.reader.take_wrap(self.frames_read) {
+                self.frames_read -= frames as f64;
+            }
+            if !self.read_frame(false) {
+                self.input_done = true;
+                self.next.copy_from_slice(&self.current);
+            }
+        }
+
+        true
+    }
+
+    /// Set the gain from the ReplayGain values of the decoder
+    pub fn set_replay_gain(&mut self, config: &ReplayGainConfig) {
+        self.gain = config.gain(&self.replay_gain);
+    }
+
+    /// Set the playback speed and pitch, the change is heard at the next frame
+    pub fn set_speed(&mut self, config: &SpeedConfig) {
+        self.step = self.base_step * config.resample_ratio();
+        self.stretch.set_tempo(config.tempo());
+    }
+
+    /// Set the region played in a loop
+    pub fn set_loop(&mut self, config: &LoopConfig) {
+        self.reader.set_loop(config);
+    }
+
+    /// Number of output frames left at the current speed, if the duration of the file is known
+    ///
+    /// A source playing a loop never ends
+    pub fn remaining_frames(&self) -> Option<u64> {
+        if self.reader.is_looping() {
+            return None;
+        }
+        self.total_frames.map(|total| {
+            let remaining = (total as f64 - self.frames_read).max(0.0);
+            (remaining / (self.step * self.stretch.tempo())).round() as u64
+        })
+    }
+
+    /// Get the position in the decoded file
+    pub fn position(&self) -> Duration {
+        Duration::from_secs_f64(self.frames_read / self.input_rate.max(1) as f64)
+    }
+
+    /// Move to the given position, returns false on error
+    pub fn seek(&mut self, pos: Duration) -> bool {
+        if !self.reader.seek(pos) {
+            self.ended = true;
+            return false;
+        }
+
+        self.frames_read = (pos.as_secs_f64() * self.input_rate as f64).floor();
+        self.stretch.reset();
+        self.prime();
+        true
+    }
+
+    /// Read the first two frames
+    fn prime(&mut self) {
+        self.position = 0.0;
+        self.input_done = false;
+        self.ended = !self.read_frame(true);
+
+        if !self.ended && !self.read_frame(false) {
+            self.input_done = true;
+            self.next.copy_from_slice(&self.current);
+        }
+    }
+
+    /// Read one input frame into the current or the next frame, through the time stretching if needed
+    fn read_frame(&mut self, current: bool) -> bool {
+        let frame = if current {
+            &mut self.current
+        } else {
+            &mut self.next
+        };
+
+        let reader = &mut self.reader;
+        let mut read = |frame: &mut [f32]| reader.read(frame);
+        if self.stretch.is_active() {
+            self.stretch.next_frame(frame, read)
+        } else {
+            read(frame)
+        }
+    }
+}
+
+impl fmt::Debug for Converter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Converter")
+            .field("input_channels", &self.input_channels)
+            .field("input_rate", &self.input_rate)
+            .field("output_channels", &self.current.len())
+            .field("frames_read", &self.frames_read)
+            .field("ended", &self.ended)
+            .field("tempo", &self.stretch.tempo())
+            .field("gain", &self.gain)
+            .finish()
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+/// Durations of the fade envelopes applied around the stream controls
+#[derive(Debug, Clone, Copy)]
+pub struct FadeConfig {
+    /// Fade-out before pausing the stream
+    pub pause: Duration,
+    /// Fade-in after resuming the stream
+    pub resume: Duration,
+    /// Fade-out before stopping the stream
+    pub stop: Duration,
+    /// Fade-out before and fade-in after seeking
+    pub seek: Duration,
+}
+
+impl Default for FadeConfig {
+    fn default() -> Self {
+        Self {
+            pause: Duration::from_millis(30),
+            resume: Duration::from_millis(30),
+            stop: Duration::from_millis(50),
+            seek: Duration::from_millis(15),
+        }
+    }
+}
+
+/// Linear gain envelope applied frame by frame
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fade {
+    gain: f32,
+    target: f32,
+    step: f32,
+}
+
+impl Fade {
+    #[inline]
+    /// Create a new envelope starting at the given gain
+    pub fn new(gain: f32) -> Self {
+        Self {
+            gain,
+            target: gain,
+            step: 0.0,
+        }
+    }
+
+    #[inline]
+    /// Start moving the gain toward `target` over `frames` frames
+    pub fn start(&mut self, target: f32, frames: usize) {
+        self.target = target;
+        if frames == 0 {
+            self.gain = target;
+            self.step = 0.0;
+        } else {
+            self.step = (target - self.gain) / frames as f32;
+        }
+    }
+
+    #[inline]
+    /// Get the gain of the current frame and advance the envelope
+    pub fn next_gain(&mut self) -> f32 {
+        let gain = self.gain;
+        if !self.is_done() {
+            self.gain += self.step;
+            if (self.step > 0.0 && self.gain >= self.target)
+                || (self.step < 0.0 && self.gain <= self.target)
+            {
+                self.gain = self.target;
+            }
+        }
+        gain
+    }
+
+    #[inline]
+    /// Get the gain the envelope is moving toward
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    #[inline]
+    /// Returns true once the gain has reached the target
+    pub fn is_done(&self) -> bool {
+        self.gain == self.target
+    }
+
+    #[inline]
+    /// Returns true if the envelope is done and the output is silent
+    pub fn is_silent(&self) -> bool {
+        self.is_done() && self.gain == 0.0
+    }
+}
+
+/// Convert a fade duration into a number of frames
+#[inline]
+pub(crate) fn fade_frames(duration: Duration, sample_rate: u32) -> usize {
+    (duration.as_secs_f64() * sample_rate as f64).round() as usize
+}
@@ -0,0 +1,249 @@
+use std::f32::consts::FRAC_PI_2;
+use std::time::Duration;
+
+use vibe_core::decoder::{duration_to_frames, frames_to_duration};
+use vibe_core::transcode::map_channels;
+use vibe_core::LoopRegion;
+
+use super::convert::SampleSource;
+
+/// Longest crossfade at the seam of a loop
+const MAX_LOOP_CROSSFADE: Duration = Duration::from_millis(250);
+
+/// Settings of the region played in a loop
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LoopConfig {
+    /// Positions of the A and B points, the region between them is played in a loop
+    ///
+    /// The region belongs to the track being played, it is cleared when the track changes
+    pub region: Option<(Duration, Duration)>,
+    /// Loop the first loop stored in the file, such as a WAV `smpl` loop, when no region is set
+    pub file_loops: bool,
+    /// Length of the crossfade at the seam, zero jumps straight from B to A
+    ///
+    /// It is limited to 250 ms and to the audio available before A
+    pub crossfade: Duration,
+}
+
+impl LoopConfig {
+    /// Get the settings without the A-B region, for another track
+    #[inline]
+    pub fn without_region(&self) -> Self {
+        Self {
+            region: None,
+            ..*self
+        }
+    }
+}
+
+/// Reader of the frames of a source, looping over a region
+///
+/// The end of the region is blended with the frames preceding its start so the
+/// seam is smooth, those frames are read when the region is set
+pub(crate) struct LoopReader {
+    source: Box<dyn SampleSource>,
+    sample_rate: u32,
+    /// One frame of the source
+    input: Vec<f32>,
+    output_channels: usize,
+    /// Index of the next frame of the source
+    position: u64,
+    /// First loop stored in the file
+    file_loop: Option<LoopRegion>,
+    region: Option<LoopRegion>,
+    /// Length of the crossfade requested, in frames
+    crossfade: u64,
+    /// Frames preceding the start of the region, mapped on the output channels
+    head: Vec<f32>,
+    head_len: usize,
+    max_crossfade: usize,
+    /// Number of jumps back the playback hasn't reached yet, and the region of the last one
+    wraps: usize,
+    wrapped: Option<LoopRegion>,
+}
+
+impl LoopReader {
+    /// Create a reader, the buffer of the crossfade is allocated here
+    pub fn new(
+        source: Box<dyn SampleSource>,
+        sample_rate: u32,
+        input_channels: usize,
+        output_channels: usize,
+        file_loop: Option<LoopRegion>,
+    ) -> Self {
+        let max_crossfade = duration_to_frames(MAX_LOOP_CROSSFADE, sample_rate) as usize;
+        Self {
+            source,
+            sample_rate,
+            input: vec![0.0; input_channels],
+            output_channels,
+            position: 0,
+            file_loop,
+            region: None,
+            crossfade: 0,
+            head: vec![0.0; max_crossfade * output_channels],
+            head_len: 0,
+            max_crossfade,
+            wraps: 0,
+            wrapped: None,
+        }
+    }
+
+    /// Check if the source loops forever
+    #[inline]
+    pub fn is_looping(&self) -> bool {
+        self.region.is_some()
+    }
+
+    /// Apply the settings of the loop, the frames needed by the crossfade are read now
+    pub fn set_loop(&mut self, config: &LoopConfig) {
+        let region = match config.region {
+            Some((start, end)) => LoopRegion::from_duration(start, end, self.sample_rate),
+            None if config.file_loops => self.file_loop,
+            None => None,
+        };
+        let crossfade = duration_to_frames(config.crossfade, self.sample_rate);
+        if region == self.region && crossfade == self.crossfade {
+            return;
+        }
+
+        self.region = region;
+        self.crossfade = crossfade;
+        self.head_len = 0;
+        if let Some(region) = region {
+            let len = crossfade
+                .min(self.max_crossfade as u64)
+                .min(region.start)
+                .min(region.frames());
+            if len > 0 {
+                self.read_head(region.start - len, len as usize);
+            }
+        }
+    }
+
+    /// Read the next frame, jumping back to the start of the region at its end
+    pub fn read(&mut self, frame: &mut [f32]) -> bool {
+        if let Some(region) = self.region {
+            if self.position == region.end && !self.jump(region) {
+                return false;
+            }
+        }
+
+        if !self.read_frame(frame) {
+            // A region ending after the end of the source loops at the end of the source
+            let region = self.region;
+            match region {
+                Some(region) if self.position > region.start && self.jump(region) => {
+                    if !self.read_frame(frame) {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+        }
+
+        self.blend(frame);
+        self.position += 1;
+        true
+    }
+
+    /// Move the source to the given position, returns false on error
+    pub fn seek(&mut self, pos: Duration) -> bool {
+        self.wraps = 0;
+        self.position = duration_to_frames(pos, self.sample_rate);
+        self.source.seek(pos)
+    }
+
+    /// Get the length of the last region looped once the playback reaches its end
+    ///
+    /// The frames are read ahead of the playback, so the position moves back later than the source
+    pub fn take_wrap(&mut self, frames_read: f64) -> Option<u64> {
+        let region = self.wrapped?;
+        if self.wraps > 0 && frames_read >= region.end as f64 {
+            self.wraps -= 1;
+            Some(region.frames())
+        } else {
+            None
+        }
+    }
+
+    /// Move back to the start of the region
+    fn jump(&mut self, region: LoopRegion) -> bool {
+        if !self
+            .source
+            .seek(frames_to_duration(region.start, self.sample_rate))
+        {
+            return false;
+        }
+
+        self.position = region.start;
+        self.wraps += 1;
+        self.wrapped = Some(region);
+        true
+    }
+
+    /// Read the frames preceding the start of the region then come back to the current position
+    fn read_head(&mut self, start: u64, len: usize) {
+        let resume = self.position;
+        if !self
+            .source
+            .seek(frames_to_duration(start, self.sample_rate))
+        {
+            return;
+        }
+
+        let mut head = std::mem::take(&mut self.head);
+        let mut count = 0;
+        for frame in head.chunks_exact_mut(self.output_channels).take(len) {
+            if !self.read_frame(frame) {
+                break;
+            }
+            count += 1;
+        }
+        self.head = head;
+        // A partial head would blend with the wrong frames
+        self.head_len = if count == len { len } else { 0 };
+
+        // The playback goes on from the same frame, the source is at its end otherwise
+        if !self
+            .source
+            .seek(frames_to_duration(resume, self.sample_rate))
+        {
+            self.region = None;
+        }
+    }
+
+    /// Blend the frames preceding the end of the region with the frames preceding its start
+    fn blend(&self, frame: &mut [f32]) {
+        let region = match self.region {
+            Some(region) if self.head_len > 0 => region,
+            _ => return,
+        };
+        let fade_start = region.end - self.head_len as u64;
+        if self.position < fade_start || self.position >= region.end {
+            return;
+        }
+
+        // Equal-power curves keep the loudness constant across the seam
+        let index = (self.position - fade_start) as usize;
+        let progress = (index as f32 + 0.5) / self.head_len as f32 * FRAC_PI_2;
+        let (fade_out, fade_in) = (progress.cos(), progress.sin());
+        let head = &self.head[index * frame.len()..(index + 1) * frame.len()];
+        for (sample, head) in frame.iter_mut().zip(head) {
+            *sample = *sample * fade_out + head * fade_in;
+        }
+    }
+
+    /// Read one frame of the source and map it on the output channels
+    fn read_frame(&mut self, frame: &mut [f32]) -> bool {
+        for sample in self.input.iter_mut() {
+            match self.source.next_sample() {
+                Some(value) => *sample = value,
+                None => return false,
+            }
+        }
+
+        map_channels(&self.input, frame);
+        true
+    }
+}
@@ -1,3 +1,17 @@
+mod convert;
+mod fade;
+mod looping;
+mod replaygain;
+mod speed;
 mod stream;
+mod stretch;
 
-pub use self::stream::AudioStream;
+pub use self::convert::OutputFormat;
+pub use self::fade::FadeConfig;
+pub use self::looping::LoopConfig;
+pub use self::replaygain::{ReplayGainConfig, ReplayGainMode};
+pub use self::speed::{SpeedConfig, SpeedMode, MAX_PITCH, MAX_SPEED, MIN_SPEED};
+pub use self::stream::{AudioStream, StreamEvent};
+
+pub(crate) use self::convert::Converter;
+pub(crate) use self::fade::{fade_frames, Fade};
@@ -0,0 +1,66 @@
+use vibe_core::ReplayGain;
+
+/// Values of the ReplayGain tags used to level the tracks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayGainMode {
+    /// The tags are ignored
+    #[default]
+    Off,
+    /// Every track is played at the same loudness
+    Track,
+    /// The albums are played at the same loudness, keeping the differences between their tracks
+    Album,
+}
+
+/// Settings of the gain applied from the ReplayGain tags of the tracks
+///
+/// A track missing the values of the mode uses the values of the other mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayGainConfig {
+    pub mode: ReplayGainMode,
+    /// Gain added to the gain of the tags, in dB
+    pub preamp: f32,
+    /// Gain of the tracks without ReplayGain tags, in dB
+    pub fallback: f32,
+    /// Lower the gain so the peak of the track doesn't go over full scale
+    pub prevent_clipping: bool,
+}
+
+impl Default for ReplayGainConfig {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::default(),
+            preamp: 0.0,
+            fallback: 0.0,
+            prevent_clipping: true,
+        }
+    }
+}
+
+impl ReplayGainConfig {
+    /// Get the linear gain to apply to a track with the given ReplayGain values
+    pub fn gain(&self, values: &ReplayGain) -> f32 {
+        let track = (values.track_gain, values.track_peak);
+        let album = (values.album_gain, values.album_peak);
+        let (gain, peak) = match self.mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track if track.0.is_some() => track,
+            ReplayGainMode::Album if album.0.is_none() => track,
+            _ => album,
+        };
+
+        let gain = match gain {
+            Some(gain) => db_to_linear(gain + self.preamp),
+            None => db_to_linear(self.fallback),
+        };
+        match peak {
+            Some(peak) if self.prevent_clipping && peak > 0.0 => gain.min(1.0 / peak),
+            _ => gain,
+        }
+    }
+}
+
+#[inline]
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
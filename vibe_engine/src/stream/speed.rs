@@ -0,0 +1,72 @@
+/// Slowest playback speed
+pub const MIN_SPEED: f64 = 0.5;
+
+/// Fastest playback speed
+pub const MAX_SPEED: f64 = 2.0;
+
+/// Largest pitch shift, up or down, in semitones
+pub const MAX_PITCH: f64 = 12.0;
+
+/// How the playback speed is changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpeedMode {
+    /// The tempo changes and the pitch is kept
+    #[default]
+    TimeStretch,
+    /// The tempo and the pitch change together, as a tape played faster
+    Varispeed,
+}
+
+/// Settings of the playback speed and pitch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedConfig {
+    pub mode: SpeedMode,
+    /// Speed of the playback, 1 plays at the original tempo
+    pub speed: f64,
+    /// Pitch shift in semitones, independent of the speed
+    pub pitch: f64,
+}
+
+impl Default for SpeedConfig {
+    fn default() -> Self {
+        Self {
+            mode: SpeedMode::default(),
+            speed: 1.0,
+            pitch: 0.0,
+        }
+    }
+}
+
+impl SpeedConfig {
+    /// Get the speed within the supported range
+    pub fn clamped_speed(&self) -> f64 {
+        if self.speed.is_nan() {
+            1.0
+        } else {
+            self.speed.clamp(MIN_SPEED, MAX_SPEED)
+        }
+    }
+
+    /// Get the pitch shift within the supported range, in semitones
+    pub fn clamped_pitch(&self) -> f64 {
+        if self.pitch.is_nan() {
+            0.0
+        } else {
+            self.pitch.clamp(-MAX_PITCH, MAX_PITCH)
+        }
+    }
+
+    /// Get the factor applied to the resampling step, it scales both the tempo and the pitch
+    pub fn resample_ratio(&self) -> f64 {
+        let pitch = 2f64.powf(self.clamped_pitch() / 12.0);
+        match self.mode {
+            SpeedMode::TimeStretch => pitch,
+            SpeedMode::Varispeed => pitch * self.clamped_speed(),
+        }
+    }
+
+    /// Get the tempo change of the time stretching, so the resampling ends at the requested speed
+    pub fn tempo(&self) -> f64 {
+        self.clamped_speed() / self.resample_ratio()
+    }
+}
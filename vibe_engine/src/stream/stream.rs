@@ -1,21 +1,151 @@
-use cpal::{
-    traits::{DeviceTrait, HostTrait, StreamTrait},
-    Stream, StreamConfig,
+use crossbeam::channel::{
+    after, bounded, never, select, unbounded, Receiver, Sender, TrySendError,
 };
-use crossbeam::channel::{unbounded, Sender};
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_PI_2;
 use std::io::{Read, Seek};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::thread::JoinHandle;
+use std::time::Duration;
 use vibe_core::decoder::Decoder;
 
+use super::convert::{Converter, OutputFormat};
+use super::fade::{fade_frames, Fade, FadeConfig};
+use super::{LoopConfig, ReplayGainConfig, SpeedConfig};
+use crate::effects::{AudioProcessor, ChainProcessor, EffectChain};
+use crate::mixer::{Mixer, MixerProcessor};
+use crate::sink::{CpalSink, OutputSink, SinkError, SinkOutput};
+
+/// Extra time given to the audio callback to finish a fade-out
+const FADE_TIMEOUT_MARGIN: Duration = Duration::from_millis(100);
+
+/// Number of commands the audio callback can have waiting
+const COMMAND_CAPACITY: usize = 64;
+
+/// Delay before trying again to give the audio callback the commands its queue refused
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Number of sources the audio callback can give back at once, a command replaces up to two
+const GARBAGE_CAPACITY: usize = 2 * COMMAND_CAPACITY;
+
+/// Length of the ramp smoothing the changes of volume
+const VOLUME_RAMP: Duration = Duration::from_millis(20);
+
+/// Number of frames given at once to the effect chain
+const BLOCK_FRAMES: usize = 512;
+
 #[derive(Debug)]
 pub enum Controls {
     Pause,
     Play,
     Stop,
+    Seek(Duration),
+    Load(u64, Converter),
+    Next(Option<(u64, Converter)>),
+    Crossfade(Duration),
+    ReplayGain(ReplayGainConfig),
+    Speed(SpeedConfig),
+    Loop(LoopConfig),
+    Volume(f32),
+    Sync(Sender<()>),
+    Shutdown,
 }
 
+/// Events sent by the audio callback
+///
+/// The sources are identified by the id returned when they are loaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// The current source is over and the next one, with the given id, has taken its place
+    Advanced(u64),
+    /// The source with the given id has no more samples
+    Ended(u64),
+    /// The output device reported an error
+    Error(String),
+}
+
+/// Commands sent from the control thread to the audio callback
+#[derive(Debug)]
+enum StreamCommand {
+    /// Move the gain to the target over the duration
+    Fade(f32, Duration),
+    /// Fade out over the duration, the following commands wait until the output is silent
+    FadeOut(Duration),
+    /// Fade out, seek the decoder to the position and fade back in over the duration
+    Seek(Duration, Duration),
+    /// Give back the source moved by the engine thread, with true if the seek failed
+    Seeked(u64, Converter, bool),
+    /// Give back the next source, with its id, moved to its start along with the given source
+    Rewound(u64, u64, Converter),
+    /// Replace the source being played
+    Load(u64, Converter),
+    /// Drop the sources, the output stays silent
+    Unload,
+    /// Replace the source played after the current one
+    Next(Option<(u64, Converter)>),
+    /// Set the length of the crossfade between the current and the next source
+    Crossfade(Duration),
+    /// Change the gain applied from the ReplayGain tags of the sources
+    ReplayGain(ReplayGainConfig),
+    /// Change the playback speed and pitch of the sources
+    Speed(SpeedConfig),
+    /// Change the region played in a loop
+    Loop(LoopConfig),
+    /// Move the volume of the output to the given factor
+    Volume(f32),
+}
+
+/// Source handed by the audio callback to the engine thread to be moved there
+#[derive(Debug)]
+struct SourceSeek {
+    id: u64,
+    source: Converter,
+    pos: Duration,
+    /// Next source partly played by the crossfade in progress, moved back to its start
+    next: Option<(u64, Converter)>,
+}
+
+impl SourceSeek {
+    /// Seek the sources on the engine thread, returns false on error
+    fn apply(&mut self) -> bool {
+        if let Some((_, next)) = self.next.as_mut() {
+            next.seek(Duration::from_secs(0));
+        }
+        self.source.seek(self.pos)
+    }
+}
+
+/// Engine thread shared by the handles of a stream, shut down with the last one
+struct Engine {
+    tx_stream: Sender<Controls>,
+    next_id: AtomicU64,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.tx_stream.send(Controls::Shutdown);
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Handle on the engine thread owning the output stream
+///
+/// The output stays open while any handle is alive, the sources are swapped with `load`
 #[derive(Clone)]
 pub struct AudioStream {
-    tx_stream: Sender<Controls>,
+    engine: Arc<Engine>,
+    rx_events: Receiver<StreamEvent>,
+    format: OutputFormat,
+    effects: EffectChain,
+    mixer: Mixer,
+    /// Position of the current decoder in milliseconds, updated by the audio callback
+    position: Arc<AtomicU64>,
 }
 
 impl AudioStream {
@@ -23,115 +153,856 @@ impl AudioStream {
     /// Returns a new thread containing a stream.
     pub fn new<T, R>(decoder: Decoder<R>) -> Self
     where
-        T: cpal::Sample,
+        T: cpal::Sample + 'static,
+        R: Read + Seek + Send + 'static,
+    {
+        Self::with_fades::<T, R>(decoder, FadeConfig::default())
+    }
+
+    #[inline]
+    /// Returns a new thread containing a stream using the given fade envelopes.
+    pub fn with_fades<T, R>(decoder: Decoder<R>, fades: FadeConfig) -> Self
+    where
+        T: cpal::Sample + 'static,
         R: Read + Seek + Send + 'static,
     {
+        let stream = Self::open::<T>(fades);
+        stream.load(decoder);
+        stream
+    }
+
+    /// Start the engine thread and open the default output device without source
+    pub fn open<T>(fades: FadeConfig) -> Self
+    where
+        T: cpal::Sample + 'static,
+    {
+        Self::with_sink(CpalSink::<T>::new(), fades).expect("Stream error")
+    }
+
+    /// Start the engine thread and open the given output without source
+    pub fn with_sink<S>(sink: S, fades: FadeConfig) -> Result<Self, SinkError>
+    where
+        S: OutputSink,
+    {
+        let sink: Box<dyn OutputSink> = Box::new(sink);
         let (tx, rx) = unbounded();
+        let (tx_events, rx_events) = unbounded();
+        let (tx_output, rx_output) = bounded(1);
+        let position = Arc::new(AtomicU64::new(0));
+        let callback_position = Arc::clone(&position);
 
-        std::thread::spawn(move || {
-            let stream = create_stream::<T, R>(decoder);
+        let thread = std::thread::spawn(move || {
+            let (tx_fade, rx_fade) = bounded(COMMAND_CAPACITY);
+            let mut commands = CommandQueue::new(tx_fade);
+            let (tx_done, rx_done) = bounded(1);
+            let (tx_garbage, mut rx_garbage) = bounded(GARBAGE_CAPACITY);
+            let (tx_seek, mut rx_seek) = bounded(1);
+            let output = start_output(
+                sink,
+                rx_fade,
+                tx_done,
+                tx_events,
+                tx_garbage,
+                tx_seek,
+                callback_position,
+            );
+            let (mut output, mut rx_effects) = match output {
+                Ok((output, effects, mixer)) => {
+                    let rx_effects = effects.garbage();
+                    tx_output
+                        .send(Ok((output.format(), effects, mixer)))
+                        .unwrap();
+                    (output, rx_effects)
+                }
+                Err(err) => {
+                    tx_output.send(Err(err)).unwrap();
+                    return;
+                }
+            };
 
-            stream.pause().expect("Pause error");
+            // The output keeps running, a paused stream is a silent one
+            let mut playing = false;
 
-            println!("Wait for reception");
-            while let Ok(res) = rx.recv() {
-                println!("{:?}", res);
+            loop {
+                let retry = if commands.is_waiting() {
+                    after(RETRY_INTERVAL)
+                } else {
+                    never()
+                };
+                let res = select! {
+                    recv(rx) -> res => match res {
+                        Ok(res) => res,
+                        Err(_) => break,
+                    },
+                    // The sources replaced by the audio callback are dropped here
+                    recv(rx_garbage) -> source => {
+                        // The audio callback is gone with its output
+                        if source.is_err() {
+                            rx_garbage = never();
+                        }
+                        continue;
+                    }
+                    // The processors removed from the effect chain are dropped here
+                    recv(rx_effects) -> processor => {
+                        if processor.is_err() {
+                            rx_effects = never();
+                        }
+                        continue;
+                    }
+                    // The decoders are moved here, away from the audio callback
+                    recv(rx_seek) -> seek => {
+                        match seek {
+                            Ok(mut seek) => {
+                                let failed = !seek.apply();
+                                if let Some((id, next)) = seek.next {
+                                    commands.send(StreamCommand::Rewound(seek.id, id, next));
+                                }
+                                commands.send(StreamCommand::Seeked(seek.id, seek.source, failed));
+                            }
+                            Err(_) => rx_seek = never(),
+                        }
+                        continue;
+                    }
+                    // The commands refused by the full queue of the audio callback are sent again
+                    recv(retry) -> _ => {
+                        commands.flush();
+                        continue;
+                    }
+                };
                 match res {
                     Controls::Pause => {
-                        stream.pause().expect("Pause error");
+                        if playing {
+                            commands.send(StreamCommand::FadeOut(fades.pause));
+                            playing = false;
+                        }
                     }
                     Controls::Play => {
-                        stream.play().expect("Play error");
+                        if !playing {
+                            commands.send(StreamCommand::Fade(1.0, fades.resume));
+                            playing = true;
+                        }
                     }
                     Controls::Stop => {
-                        stream.pause().expect("Stop error");
-                        drop(stream);
+                        if playing {
+                            commands.send(StreamCommand::FadeOut(fades.stop));
+                            playing = false;
+                        }
+                        commands.send(StreamCommand::Unload);
+                    }
+                    Controls::Seek(pos) => {
+                        commands.send(StreamCommand::Seek(pos, fades.seek));
+                    }
+                    Controls::Load(id, source) => {
+                        if playing {
+                            commands.send(StreamCommand::FadeOut(fades.stop));
+                            playing = false;
+                        }
+                        commands.send(StreamCommand::Load(id, source));
+                    }
+                    Controls::Next(next) => {
+                        commands.send(StreamCommand::Next(next));
+                    }
+                    Controls::Crossfade(duration) => {
+                        commands.send(StreamCommand::Crossfade(duration));
+                    }
+                    Controls::ReplayGain(config) => {
+                        commands.send(StreamCommand::ReplayGain(config));
+                    }
+                    Controls::Speed(config) => {
+                        commands.send(StreamCommand::Speed(config));
+                    }
+                    Controls::Loop(config) => {
+                        commands.send(StreamCommand::Loop(config));
+                    }
+                    Controls::Volume(volume) => {
+                        commands.send(StreamCommand::Volume(volume));
+                    }
+                    Controls::Sync(tx_sync) => commands.sync(tx_sync),
+                    Controls::Shutdown => {
+                        if playing {
+                            fade_out(&mut commands, &rx_done, fades.stop);
+                        }
                         break;
                     }
                 }
             }
+
+            output.stop();
         });
 
-        Self { tx_stream: tx }
+        let (format, effects, mixer) = match rx_output.recv().expect("Stream error") {
+            Ok(output) => output,
+            Err(err) => {
+                let _ = thread.join();
+                return Err(err);
+            }
+        };
+
+        Ok(Self {
+            engine: Arc::new(Engine {
+                tx_stream: tx,
+                next_id: AtomicU64::new(0),
+                thread: Mutex::new(Some(thread)),
+            }),
+            rx_events,
+            format,
+            effects,
+            mixer,
+            position,
+        })
+    }
+
+    #[inline]
+    /// Get the position in the decoder being played
+    pub fn position(&self) -> Duration {
+        Duration::from_millis(self.position.load(Ordering::Relaxed))
+    }
+
+    #[inline]
+    /// Get the format of the output
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    #[inline]
+    /// Get the effect chain applied to the output
+    pub fn effects(&self) -> EffectChain {
+        self.effects.clone()
+    }
+
+    #[inline]
+    /// Get the mixer layering other sources on the source being played
+    pub fn mixer(&self) -> Mixer {
+        self.mixer.clone()
+    }
+
+    #[inline]
+    /// Get a receiver for the events of the stream
+    ///
+    /// The channel is disconnected once the engine is shut down
+    pub fn events(&self) -> Receiver<StreamEvent> {
+        self.rx_events.clone()
     }
 
     #[inline]
     /// Send Play command
     pub fn play(&self) {
-        self.tx_stream.send(Controls::Play).unwrap();
+        self.send(Controls::Play);
     }
 
     #[inline]
     /// Send Pause command
     pub fn pause(&self) {
-        self.tx_stream.send(Controls::Pause).unwrap()
+        self.send(Controls::Pause)
     }
 
     #[inline]
-    /// Send Stop command
+    /// Send Stop command, the sources are dropped but the output stays open
     pub fn stop(&self) {
-        self.tx_stream.send(Controls::Stop).unwrap()
+        self.send(Controls::Stop)
+    }
+
+    #[inline]
+    /// Send Seek command
+    pub fn seek(&self, pos: Duration) {
+        self.send(Controls::Seek(pos))
+    }
+
+    /// Replace the source being played, returns the id of the new source
+    ///
+    /// The stream is paused until the next Play command
+    pub fn load<R>(&self, decoder: Decoder<R>) -> u64
+    where
+        R: Read + Seek + Send + 'static,
+    {
+        let id = self.next_id();
+        let source = Converter::new(decoder, self.format);
+        self.send(Controls::Load(id, source));
+        id
+    }
+
+    /// Open the decoder to play once the current one is over, returns the id of the new source
+    ///
+    /// The decoder is converted to the output format on the calling thread
+    pub fn queue_next<R>(&self, decoder: Decoder<R>) -> u64
+    where
+        R: Read + Seek + Send + 'static,
+    {
+        let id = self.next_id();
+        let next = Converter::new(decoder, self.format);
+        self.send(Controls::Next(Some((id, next))));
+        id
+    }
+
+    #[inline]
+    /// Forget the decoder queued with `queue_next`
+    pub fn clear_next(&self) {
+        self.send(Controls::Next(None))
+    }
+
+    #[inline]
+    /// Set the duration of the crossfade with the next decoder, zero plays it right after the current one
+    pub fn set_crossfade(&self, duration: Duration) {
+        self.send(Controls::Crossfade(duration))
+    }
+
+    #[inline]
+    /// Set the gain applied from the ReplayGain tags, the sources already loaded are updated
+    pub fn set_replay_gain(&self, config: ReplayGainConfig) {
+        self.send(Controls::ReplayGain(config))
+    }
+
+    #[inline]
+    /// Set the volume of the output as a linear factor, applied after the effect chain
+    pub fn set_volume(&self, volume: f32) {
+        self.send(Controls::Volume(volume.max(0.0)))
+    }
+
+    #[inline]
+    /// Set the playback speed and pitch, the sources already loaded are updated
+    pub fn set_speed(&self, config: SpeedConfig) {
+        self.send(Controls::Speed(config))
+    }
+
+    #[inline]
+    /// Set the region played in a loop
+    ///
+    /// The A-B region applies to the source being played, the sources loaded later only get the other settings
+    pub fn set_loop(&self, config: LoopConfig) {
+        self.send(Controls::Loop(config))
+    }
+
+    /// Wait until the engine thread has given the commands sent before to the output
+    ///
+    /// The output applies them from its next block, so an output driven by the caller,
+    /// such as a `CaptureSink`, renders them at a known position
+    pub fn sync(&self) {
+        let (tx_sync, rx_sync) = bounded(1);
+        self.send(Controls::Sync(tx_sync));
+        let _ = rx_sync.recv();
+    }
+
+    #[inline]
+    /// Get a new source id
+    fn next_id(&self) -> u64 {
+        self.engine.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    #[inline]
+    /// Send a command to the engine thread
+    fn send(&self, control: Controls) {
+        self.engine.tx_stream.send(control).unwrap()
     }
 }
 
-fn write_data<T, R>(output: &mut [T], channels: usize, ended: &mut bool, decoder: &mut Decoder<R>)
-where
-    T: cpal::Sample,
-    R: Read + Seek,
-{
-    for frame in output.chunks_mut(channels) {
-        let value = decoder.next();
+/// Ask the audio callback to fade out and wait until the output is silent
+fn fade_out(commands: &mut CommandQueue, rx_done: &Receiver<()>, duration: Duration) {
+    // Discard a notification left by a previous fade
+    while rx_done.try_recv().is_ok() {}
 
-        if (value).is_none() {
-            *ended = true;
+    commands.send(StreamCommand::FadeOut(duration));
+    // The audio callback isn't taking commands, no fade is coming
+    if !commands.is_waiting() {
+        let _ = rx_done.recv_timeout(duration + FADE_TIMEOUT_MARGIN);
+    }
+}
+
+/// Commands of the engine thread for the audio callback
+///
+/// The commands refused by the full queue of the callback wait here, in order,
+/// so the engine thread never blocks on an output that isn't rendering
+struct CommandQueue {
+    tx_fade: Sender<StreamCommand>,
+    pending: VecDeque<StreamCommand>,
+    /// Syncs answered once the commands sent before them are given to the callback
+    syncs: Vec<Sender<()>>,
+}
+
+impl CommandQueue {
+    fn new(tx_fade: Sender<StreamCommand>) -> Self {
+        Self {
+            tx_fade,
+            pending: VecDeque::new(),
+            syncs: Vec::new(),
         }
+    }
+
+    #[inline]
+    /// Returns true if some commands are waiting for room in the queue of the callback
+    fn is_waiting(&self) -> bool {
+        !self.pending.is_empty()
+    }
 
-        if *ended {
-            let value: T = cpal::Sample::from::<f32>(&0.0);
-            for sample in frame.iter_mut() {
-                *sample = value;
+    /// Send a command after the ones still waiting
+    fn send(&mut self, command: StreamCommand) {
+        self.pending.push_back(command);
+        self.flush();
+    }
+
+    /// Answer a sync once the commands sent before are given to the callback
+    fn sync(&mut self, tx_sync: Sender<()>) {
+        self.syncs.push(tx_sync);
+        self.flush();
+    }
+
+    /// Give the waiting commands to the callback, as many as its queue takes
+    fn flush(&mut self) {
+        while let Some(command) = self.pending.pop_front() {
+            match self.tx_fade.try_send(command) {
+                Ok(()) => {}
+                Err(TrySendError::Full(command)) => {
+                    self.pending.push_front(command);
+                    return;
+                }
+                // The callback is gone with its output, nothing is left to play the commands
+                Err(TrySendError::Disconnected(_)) => self.pending.clear(),
             }
+        }
+        for tx_sync in self.syncs.drain(..) {
+            let _ = tx_sync.send(());
+        }
+    }
+}
+
+/// State owned by the audio callback
+struct StreamState {
+    source: Option<Converter>,
+    source_id: u64,
+    next: Option<(u64, Converter)>,
+    sample_rate: u32,
+    ended: bool,
+    fade: Fade,
+    /// Volume applied to the output after the effect chain
+    volume: Fade,
+    /// Length of the crossfade with the next source
+    crossfade_frames: usize,
+    /// Position and length of the crossfade in progress
+    crossfade: Option<(usize, usize)>,
+    replay_gain: ReplayGainConfig,
+    speed: SpeedConfig,
+    looping: LoopConfig,
+    frame: Vec<f32>,
+    next_frame: Vec<f32>,
+    effects: ChainProcessor,
+    mixer: MixerProcessor,
+    rx_fade: Receiver<StreamCommand>,
+    tx_done: Sender<()>,
+    tx_events: Sender<StreamEvent>,
+    /// Sources replaced by the commands, dropped by the engine thread so the audio thread never frees memory
+    tx_garbage: Sender<Converter>,
+    /// Sources to seek, moved by the engine thread so the audio thread never waits on a decoder
+    tx_seek: Sender<SourceSeek>,
+    position: Arc<AtomicU64>,
+    /// The commands wait until the fade-out in progress is over
+    hold: bool,
+    pending_seek: Option<Duration>,
+    after_seek: (f32, usize),
+    /// Id of the source being moved by the engine thread, the output stays silent until it's back
+    seeking: Option<u64>,
+    /// The next source was given along with the current one
+    seeking_next: bool,
+}
+
+impl StreamState {
+    /// Apply the commands sent by the control thread
+    fn handle_commands(&mut self) {
+        while !self.hold {
+            let command = match self.rx_fade.try_recv() {
+                Ok(command) => command,
+                Err(_) => break,
+            };
+
+            match command {
+                StreamCommand::Fade(target, duration) => {
+                    self.fade_to(target, duration);
+                }
+                StreamCommand::FadeOut(duration) => {
+                    self.fade_to(0.0, duration);
+                    self.hold = true;
+                }
+                StreamCommand::Seek(pos, duration) => {
+                    let frames = fade_frames(duration, self.sample_rate);
+                    if !self.is_seeking() {
+                        self.after_seek = (self.fade.target(), frames);
+                        self.fade.start(0.0, frames);
+                    }
+                    self.pending_seek = Some(pos);
+                }
+                StreamCommand::Seeked(id, source, failed) => {
+                    self.install_seek(id, source, failed);
+                }
+                StreamCommand::Rewound(seek_id, id, next) => {
+                    if self.seeking == Some(seek_id) && self.seeking_next {
+                        self.seeking_next = false;
+                        self.next = Some((id, next));
+                    } else {
+                        self.discard(Some(next));
+                    }
+                }
+                StreamCommand::Load(id, mut source) => {
+                    source.set_replay_gain(&self.replay_gain);
+                    source.set_speed(&self.speed);
+                    self.looping = self.looping.without_region();
+                    source.set_loop(&self.looping);
+                    let previous = self.source.replace(source);
+                    self.discard(previous);
+                    self.source_id = id;
+                    let next = self.next.take().map(|(_, next)| next);
+                    self.discard(next);
+                    self.reset();
+                }
+                StreamCommand::Unload => {
+                    let previous = self.source.take();
+                    self.discard(previous);
+                    let next = self.next.take().map(|(_, next)| next);
+                    self.discard(next);
+                    self.reset();
+                }
+                StreamCommand::Next(mut next) => {
+                    if let Some((_, source)) = next.as_mut() {
+                        source.set_replay_gain(&self.replay_gain);
+                        source.set_speed(&self.speed);
+                        source.set_loop(&self.looping.without_region());
+                    }
+                    self.crossfade = None;
+                    self.seeking_next = false;
+                    let previous = std::mem::replace(&mut self.next, next);
+                    self.discard(previous.map(|(_, next)| next));
+                }
+                StreamCommand::Crossfade(duration) => {
+                    self.crossfade_frames = fade_frames(duration, self.sample_rate);
+                }
+                StreamCommand::ReplayGain(config) => {
+                    self.replay_gain = config;
+                    if let Some(source) = self.source.as_mut() {
+                        source.set_replay_gain(&config);
+                    }
+                    if let Some((_, next)) = self.next.as_mut() {
+                        next.set_replay_gain(&config);
+                    }
+                }
+                StreamCommand::Speed(config) => {
+                    self.speed = config;
+                    if let Some(source) = self.source.as_mut() {
+                        source.set_speed(&config);
+                    }
+                    if let Some((_, next)) = self.next.as_mut() {
+                        next.set_speed(&config);
+                    }
+                }
+                StreamCommand::Loop(config) => {
+                    self.looping = config;
+                    if let Some(source) = self.source.as_mut() {
+                        source.set_loop(&config);
+                    }
+                    if let Some((_, next)) = self.next.as_mut() {
+                        next.set_loop(&config.without_region());
+                    }
+                }
+                StreamCommand::Volume(volume) => {
+                    let frames = fade_frames(VOLUME_RAMP, self.sample_rate);
+                    self.volume.start(volume, frames);
+                }
+            }
+        }
+    }
+
+    /// Move the gain to the target, once the seek in progress is over if there is one
+    fn fade_to(&mut self, target: f32, duration: Duration) {
+        let frames = fade_frames(duration, self.sample_rate);
+        if self.is_seeking() {
+            self.after_seek = (target, frames);
         } else {
-            let value = value.unwrap().expect("Steam Error");
-            let value: T = cpal::Sample::from::<f32>(&value);
-            for sample in frame.iter_mut() {
-                *sample = value;
+            self.fade.start(target, frames);
+        }
+    }
+
+    /// Give a source back to the engine thread to be dropped there
+    fn discard(&self, source: Option<Converter>) {
+        if let Some(source) = source {
+            // Dropped here only if the engine thread is gone or not keeping up
+            let _ = self.tx_garbage.try_send(source);
+        }
+    }
+
+    /// Forget the playback of the previous source
+    fn reset(&mut self) {
+        self.ended = false;
+        self.crossfade = None;
+        self.effects.reset();
+        if self.is_seeking() {
+            self.pending_seek = None;
+            self.seeking = None;
+            self.seeking_next = false;
+            self.fade_in_after_seek();
+        }
+    }
+
+    #[inline]
+    /// Returns true from the seek command until the moved source is played
+    fn is_seeking(&self) -> bool {
+        self.pending_seek.is_some() || self.seeking.is_some()
+    }
+
+    /// Move the gain back to the target it had before the seek
+    fn fade_in_after_seek(&mut self) {
+        let (target, frames) = self.after_seek;
+        self.fade.start(target, frames);
+    }
+
+    /// Get the gain of the next frame, seeking the decoder once the seek fade-out is over
+    fn next_gain(&mut self) -> f32 {
+        if self.fade.is_silent() {
+            if self.seeking.is_none() {
+                if let Some(pos) = self.pending_seek.take() {
+                    self.seek(pos);
+                }
+            }
+
+            // The commands held by the fade-out apply from this frame
+            if self.hold && self.fade.is_silent() {
+                self.hold = false;
+                let _ = self.tx_done.try_send(());
+                self.handle_commands();
             }
         }
+
+        self.fade.next_gain()
+    }
+
+    /// Give the current source to the engine thread to be moved, a crossfade in progress is cancelled
+    fn seek(&mut self, pos: Duration) {
+        let source = match self.source.take() {
+            Some(source) => source,
+            None => {
+                self.fade_in_after_seek();
+                return;
+            }
+        };
+        let crossfade = self.crossfade.take();
+        let next = match crossfade {
+            Some(_) => self.next.take(),
+            None => None,
+        };
+        let seeking_next = next.is_some();
+
+        let seek = SourceSeek {
+            id: self.source_id,
+            source,
+            pos,
+            next,
+        };
+        match self.tx_seek.try_send(seek) {
+            Ok(()) => {
+                self.seeking = Some(self.source_id);
+                self.seeking_next = seeking_next;
+            }
+            Err(err) => {
+                let full = matches!(err, TrySendError::Full(_));
+                let seek = err.into_inner();
+                self.source = Some(seek.source);
+                if seeking_next {
+                    self.next = seek.next;
+                }
+                self.crossfade = crossfade;
+                // The engine thread is still busy with the previous seek, this one is tried again
+                if full {
+                    self.pending_seek = Some(pos);
+                } else {
+                    self.fade_in_after_seek();
+                }
+            }
+        }
+    }
+
+    /// Play the source moved by the engine thread, unless it was replaced in the meantime
+    fn install_seek(&mut self, id: u64, source: Converter, failed: bool) {
+        if self.seeking != Some(id) {
+            self.discard(Some(source));
+            return;
+        }
+
+        self.seeking = None;
+        self.source = Some(source);
+        self.ended = false;
+        self.effects.reset();
+        if failed {
+            self.end();
+        }
+
+        // A seek sent in the meantime moves the source again
+        if self.pending_seek.is_none() {
+            self.fade_in_after_seek();
+        }
+    }
+
+    /// Decode the next frame into `frame`, mixing in the next source during a crossfade
+    fn next_frame(&mut self) -> bool {
+        if self.ended {
+            return false;
+        }
+        let source = match self.source.as_mut() {
+            Some(source) => source,
+            None => return false,
+        };
+
+        if self.crossfade.is_none() && self.next.is_some() && self.crossfade_frames > 0 {
+            if let Some(remaining) = source.remaining_frames() {
+                if remaining <= self.crossfade_frames as u64 {
+                    self.crossfade = Some((0, remaining.max(1) as usize));
+                }
+            }
+        }
+
+        if !source.next_frame(&mut self.frame) {
+            return self.switch_to_next();
+        }
+
+        if let (Some((position, length)), Some((_, next))) = (self.crossfade, self.next.as_mut()) {
+            if !next.next_frame(&mut self.next_frame) {
+                self.next_frame.iter_mut().for_each(|sample| *sample = 0.0);
+            }
+
+            // Equal-power curves keep the loudness constant across the transition
+            let progress = ((position as f32 + 0.5) / length as f32).min(1.0) * FRAC_PI_2;
+            let (fade_out, fade_in) = (progress.cos(), progress.sin());
+            for (sample, next) in self.frame.iter_mut().zip(&self.next_frame) {
+                *sample = *sample * fade_out + next * fade_in;
+            }
+            self.crossfade = Some((position + 1, length));
+        }
+
+        true
+    }
+
+    /// Replace the finished source by the next one, ends the stream if there is none
+    fn switch_to_next(&mut self) -> bool {
+        self.crossfade = None;
+        match self.next.take() {
+            Some((id, next)) => {
+                self.looping = self.looping.without_region();
+                let previous = self.source.replace(next);
+                self.discard(previous);
+                let source = self.source.as_mut().unwrap();
+                self.source_id = id;
+                let _ = self.tx_events.send(StreamEvent::Advanced(id));
+                if source.next_frame(&mut self.frame) {
+                    return true;
+                }
+                self.end();
+                false
+            }
+            None => {
+                self.end();
+                false
+            }
+        }
+    }
+
+    /// Mark the stream as ended and notify the control side
+    fn end(&mut self) {
+        if !self.ended {
+            self.ended = true;
+            let _ = self.tx_events.send(StreamEvent::Ended(self.source_id));
+        }
     }
 }
 
-fn create_stream<T, R>(mut decoder: Decoder<R>) -> Stream
-where
-    T: cpal::Sample,
-    R: Read + Seek + Send + 'static,
-{
-    let host = cpal::default_host();
-    let device = host
-        .default_output_device()
-        .expect("no output device available");
+/// Render the next frames of the stream into `output`, returns false if nothing was played
+fn render(output: &mut [f32], state: &mut StreamState) -> bool {
+    state.handle_commands();
+
+    let channels = state.frame.len();
+    let mut active = false;
+    for block in output.chunks_mut(BLOCK_FRAMES * channels) {
+        for frame in block.chunks_mut(channels) {
+            let gain = state.next_gain();
+            // Nothing is decoded while the output is silent
+            let playing = gain != 0.0 && state.next_frame();
+            active |= playing;
+
+            for (sample, value) in frame.iter_mut().zip(&state.frame) {
+                *sample = if playing { value * gain } else { 0.0 };
+            }
+        }
 
-    println!("Device: {:?}", device.name().unwrap());
+        state.mixer.process(block);
+        state.effects.process(block);
+        active |= state.mixer.is_active();
 
-    let config = device.default_output_config().unwrap();
-    let config: StreamConfig = config.into();
+        for frame in block.chunks_mut(channels) {
+            let volume = state.volume.next_gain();
+            frame.iter_mut().for_each(|sample| *sample *= volume);
+        }
+    }
 
-    let channels = 1;
+    if let Some(source) = state.source.as_ref() {
+        let position = source.position().as_millis() as u64;
+        state.position.store(position, Ordering::Relaxed);
+    }
+    active
+}
 
-    let err_fn = |err| eprintln!("an error occurred on stream: {}", err);
+/// Open the output of the sink and start rendering the stream into it
+fn start_output(
+    sink: Box<dyn OutputSink>,
+    rx_fade: Receiver<StreamCommand>,
+    tx_done: Sender<()>,
+    tx_events: Sender<StreamEvent>,
+    tx_garbage: Sender<Converter>,
+    tx_seek: Sender<SourceSeek>,
+    position: Arc<AtomicU64>,
+) -> Result<(Box<dyn SinkOutput>, EffectChain, Mixer), SinkError> {
+    let mut output = sink.open()?;
+    let format = output.format();
+    if format.channels == 0 || format.sample_rate == 0 {
+        return Err(SinkError::DeviceError(format!(
+            "invalid format {} Hz, {} channels",
+            format.sample_rate, format.channels
+        )));
+    }
+    let channels = format.channels;
+    let (effects, chain_processor) = EffectChain::new(format);
+    let (mixer, mixer_processor) = Mixer::new(format);
 
-    let mut ended = false;
+    let tx_errors = tx_events.clone();
+    let on_error = move |err: String| {
+        let _ = tx_errors.send(StreamEvent::Error(err));
+    };
 
-    let stream = device
-        .build_output_stream(
-            &config,
-            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
-                write_data(data, channels, &mut ended, decoder.by_ref())
-            },
-            err_fn,
-        )
-        .unwrap();
+    let mut state = StreamState {
+        source: None,
+        source_id: 0,
+        next: None,
+        sample_rate: format.sample_rate,
+        ended: false,
+        fade: Fade::new(0.0),
+        volume: Fade::new(1.0),
+        crossfade_frames: 0,
+        crossfade: None,
+        replay_gain: ReplayGainConfig::default(),
+        speed: SpeedConfig::default(),
+        looping: LoopConfig::default(),
+        frame: vec![0.0; channels],
+        next_frame: vec![0.0; channels],
+        effects: chain_processor,
+        mixer: mixer_processor,
+        rx_fade,
+        tx_done,
+        tx_events,
+        tx_garbage,
+        tx_seek,
+        position,
+        hold: false,
+        pending_seek: None,
+        after_seek: (0.0, 0),
+        seeking: None,
+        seeking_next: false,
+    };
 
-    println!("Stream built");
-    stream
+    output.start(
+        Box::new(move |block| render(block, &mut state)),
+        Box::new(on_error),
+    )?;
+    Ok((output, effects, mixer))
 }
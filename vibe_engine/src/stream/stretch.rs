@@ -0,0 +1,301 @@
+use std::f32::consts::PI;
+
+/// Length of the segments overlapped by the time stretching, in seconds
+const SEGMENT_SECONDS: f64 = 0.04;
+
+/// Distance around the ideal position searched for the best matching segment, in seconds
+const SEEK_SECONDS: f64 = 0.008;
+
+/// Slowest tempo change
+const MIN_TEMPO: f64 = 0.25;
+
+/// Fastest tempo change
+const MAX_TEMPO: f64 = 4.0;
+
+/// Rate at which the segments are compared, the frames in between are skipped
+const CORRELATION_RATE: u32 = 12000;
+
+/// Time stretching changing the tempo without changing the pitch
+///
+/// Uses WSOLA: segments of the input are overlapped with Hann windows at a fixed
+/// output hop, each one taken near its ideal input position where it best continues
+/// the previous one. Everything is allocated up front for the audio callback
+pub(crate) struct TimeStretch {
+    channels: usize,
+    /// Half the length of a segment, the distance between two output segments
+    hop: usize,
+    /// Distance searched around the ideal position
+    seek: usize,
+    /// Distance between the frames compared
+    stride: usize,
+    tempo: f64,
+    /// Rising half of the Hann window, the falling half is its complement
+    window: Vec<f32>,
+    /// Interleaved input frames, the first one is the oldest kept
+    input: Vec<f32>,
+    /// Number of frames in the input buffer
+    len: usize,
+    /// Number of frames of the input buffer read from the source, the others are silence
+    real_len: usize,
+    source_done: bool,
+    /// Ideal position of the next segment in the input buffer
+    ideal: f64,
+    /// Position continuing the previous segment without discontinuity
+    natural: usize,
+    /// Second half of the previous segment, already windowed
+    tail: Vec<f32>,
+    /// Frames ready to be played
+    output: Vec<f32>,
+    output_len: usize,
+    output_pos: usize,
+    started: bool,
+    finished: bool,
+}
+
+impl TimeStretch {
+    /// Create a time stretcher for frames of the given sample rate and channel count
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let rate = sample_rate.max(1) as f64;
+        let hop = ((rate * SEGMENT_SECONDS / 2.0) as usize).max(1);
+        let seek = (rate * SEEK_SECONDS) as usize;
+        let capacity = (MAX_TEMPO as usize + 2) * hop + 4 * seek + 2;
+        let window = (0..hop)
+            .map(|i| 0.5 - 0.5 * (PI * i as f32 / hop as f32).cos())
+            .collect();
+
+        Self {
+            channels,
+            hop,
+            seek,
+            stride: ((sample_rate / CORRELATION_RATE) as usize).max(1),
+            tempo: 1.0,
+            window,
+            input: vec![0.0; capacity * channels],
+            len: 0,
+            real_len: 0,
+            source_done: false,
+            ideal: 0.0,
+            natural: 0,
+            tail: vec![0.0; hop * channels],
+            output: vec![0.0; hop * channels],
+            output_len: 0,
+            output_pos: 0,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Set the tempo change, 2 plays twice as fast
+    pub fn set_tempo(&mut self, tempo: f64) {
+        let tempo = tempo.clamp(MIN_TEMPO, MAX_TEMPO);
+        self.tempo = if (tempo - 1.0).abs() < 1e-9 {
+            1.0
+        } else {
+            tempo
+        };
+    }
+
+    #[inline]
+    pub fn tempo(&self) -> f64 {
+        self.tempo
+    }
+
+    /// The frames go through the stretcher while the tempo changes or frames are buffered
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.tempo != 1.0 || self.started
+    }
+
+    /// Forget the buffered frames, after a seek
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.real_len = 0;
+        self.source_done = false;
+        self.ideal = 0.0;
+        self.natural = 0;
+        self.output_len = 0;
+        self.output_pos = 0;
+        self.started = false;
+        self.finished = false;
+    }
+
+    /// Write the next stretched frame, reading the source with `read`
+    ///
+    /// Returns false once the source is over and the buffered frames are played
+    pub fn next_frame<F>(&mut self, frame: &mut [f32], mut read: F) -> bool
+    where
+        F: FnMut(&mut [f32]) -> bool,
+    {
+        if self.output_pos == self.output_len && (self.finished || !self.next_segment(&mut read)) {
+            return false;
+        }
+
+        let start = self.output_pos * self.channels;
+        frame.copy_from_slice(&self.output[start..start + self.channels]);
+        self.output_pos += 1;
+        true
+    }
+
+    /// Overlap the next segment with the previous one into the output
+    fn next_segment<F>(&mut self, read: &mut F) -> bool
+    where
+        F: FnMut(&mut [f32]) -> bool,
+    {
+        // Without tempo change the segments follow each other, which gives back the input
+        let identity = self.tempo == 1.0;
+        if identity {
+            self.ideal = self.natural as f64;
+        }
+
+        let ideal = self.ideal as usize;
+        let (low, high) = if !self.started || identity {
+            (ideal, ideal)
+        } else {
+            (ideal.saturating_sub(self.seek), ideal + self.seek)
+        };
+        self.fill(high + 2 * self.hop, read);
+
+        if self.source_done && ideal >= self.real_len {
+            return self.finish();
+        }
+
+        let position = if low == high {
+            low
+        } else {
+            self.best_position(low, high)
+        };
+
+        let channels = self.channels;
+        let hop = self.hop;
+        for i in 0..hop {
+            let rising = self.window[i];
+            for channel in 0..channels {
+                let index = i * channels + channel;
+                let first = self.input[(position + i) * channels + channel];
+                let second = self.input[(position + hop + i) * channels + channel];
+                self.output[index] = if self.started {
+                    self.tail[index] + first * rising
+                } else {
+                    first
+                };
+                self.tail[index] = second * (1.0 - rising);
+            }
+        }
+        self.output_len = self.played_len(position);
+        self.output_pos = 0;
+        self.started = true;
+
+        self.natural = position + hop;
+        if !identity {
+            self.ideal += hop as f64 * self.tempo;
+        }
+        self.discard();
+        self.output_len > 0
+    }
+
+    /// Play the end of the last segment
+    fn finish(&mut self) -> bool {
+        self.finished = true;
+        if !self.started {
+            return false;
+        }
+
+        self.output.copy_from_slice(&self.tail);
+        self.output_len = self.played_len(self.natural);
+        self.output_pos = 0;
+        self.output_len > 0
+    }
+
+    /// Number of frames of the segment at `position` to play, the silence after the source is skipped
+    fn played_len(&mut self, position: usize) -> usize {
+        if !self.source_done {
+            return self.hop;
+        }
+
+        let len = self.hop.min(self.real_len.saturating_sub(position));
+        if len < self.hop {
+            self.finished = true;
+        }
+        len
+    }
+
+    /// Read the source until the buffer holds `len` frames, silence follows the end of the source
+    fn fill<F>(&mut self, len: usize, read: &mut F)
+    where
+        F: FnMut(&mut [f32]) -> bool,
+    {
+        let len = len.min(self.input.len() / self.channels);
+        while self.len < len {
+            let frame = &mut self.input[self.len * self.channels..(self.len + 1) * self.channels];
+            if !self.source_done && read(frame) {
+                self.real_len += 1;
+            } else {
+                self.source_done = true;
+                frame.iter_mut().for_each(|sample| *sample = 0.0);
+            }
+            self.len += 1;
+        }
+    }
+
+    /// Find the segment between `low` and `high` most similar to the natural continuation
+    fn best_position(&self, low: usize, high: usize) -> usize {
+        let mut best = low;
+        let mut best_score = f32::NEG_INFINITY;
+        let mut candidate = low;
+        while candidate <= high {
+            let score = self.similarity(candidate);
+            if score > best_score {
+                best = candidate;
+                best_score = score;
+            }
+            candidate += self.stride;
+        }
+
+        // Refine around the best coarse position
+        let coarse = best;
+        for candidate in
+            coarse.saturating_sub(self.stride).max(low)..=(coarse + self.stride).min(high)
+        {
+            let score = self.similarity(candidate);
+            if score > best_score {
+                best = candidate;
+                best_score = score;
+            }
+        }
+        best
+    }
+
+    /// Normalized correlation of the first half of the segment at `position` with the natural continuation
+    fn similarity(&self, position: usize) -> f32 {
+        let channels = self.channels;
+        let mut correlation = 0.0;
+        let mut energy = 0.0;
+        for i in (0..self.hop).step_by(self.stride) {
+            let candidate = (position + i) * channels;
+            let reference = (self.natural + i) * channels;
+            for channel in 0..channels {
+                let value = self.input[candidate + channel];
+                correlation += value * self.input[reference + channel];
+                energy += value * value;
+            }
+        }
+        correlation / (energy + 1e-9).sqrt()
+    }
+
+    /// Drop the frames no segment can start from anymore
+    fn discard(&mut self) {
+        let oldest = (self.ideal as usize).saturating_sub(self.seek);
+        let count = self.natural.min(oldest).min(self.len);
+        if count == 0 {
+            return;
+        }
+
+        let channels = self.channels;
+        self.input
+            .copy_within(count * channels..self.len * channels, 0);
+        self.len -= count;
+        self.real_len = self.real_len.saturating_sub(count);
+        self.natural -= count;
+        self.ideal -= count as f64;
+    }
+}
@@ -0,0 +1,164 @@
+#[cfg(test)]
+
+mod tests_effects {
+    use vibe_engine::effects::{
+        AudioProcessor, ChainError, EffectChain, Gain, LevelMeter, MAX_EFFECTS,
+    };
+    use vibe_engine::stream::OutputFormat;
+
+    const FORMAT: OutputFormat = OutputFormat {
+        sample_rate: 44100,
+        channels: 2,
+    };
+
+    /// Delay of one frame, used to check the order of the processors
+    struct Delay {
+        previous: Vec<f32>,
+    }
+
+    impl AudioProcessor for Delay {
+        fn process(&mut self, block: &mut [f32]) {
+            let channels = self.previous.len();
+            for frame in block.chunks_mut(channels) {
+                for (sample, previous) in frame.iter_mut().zip(self.previous.iter_mut()) {
+                    std::mem::swap(sample, previous);
+                }
+            }
+        }
+
+        fn reset(&mut self) {
+            self.previous.iter_mut().for_each(|sample| *sample = 0.0);
+        }
+
+        fn latency(&self) -> usize {
+            1
+        }
+
+        fn set_format(&mut self, format: OutputFormat) {
+            self.previous = vec![0.0; format.channels];
+        }
+    }
+
+    fn delay() -> Box<dyn AudioProcessor> {
+        Box::new(Delay {
+            previous: Vec::new(),
+        })
+    }
+
+    #[test]
+
+    fn test_effects_chain() {
+        let (chain, mut processor) = EffectChain::new(FORMAT);
+        assert!(chain.is_empty());
+
+        let mut block = vec![1.0, -1.0, 0.5, -0.5];
+        processor.process(&mut block);
+        assert_eq!(block, vec![1.0, -1.0, 0.5, -0.5]);
+
+        assert!(chain.push(Box::new(Gain::new(0.5))).is_ok());
+        assert!(chain.insert(0, delay()).is_ok());
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain.latency(), 1);
+
+        let mut block = vec![1.0, -1.0, 0.5, -0.5];
+        processor.process(&mut block);
+        assert_eq!(block, vec![0.0, 0.0, 0.5, -0.5]);
+
+        // The state is cleared by a reset
+        processor.reset();
+        let mut block = vec![1.0, -1.0];
+        processor.process(&mut block);
+        assert_eq!(block, vec![0.0, 0.0]);
+
+        assert!(chain.set_bypass(0, true).is_ok());
+        assert_eq!(chain.is_bypassed(0), Some(true));
+        assert_eq!(chain.latency(), 0);
+        assert!(chain.set_parameter(1, Gain::GAIN, 2.0).is_ok());
+
+        let mut block = vec![1.0, -1.0];
+        processor.process(&mut block);
+        assert_eq!(block, vec![2.0, -2.0]);
+
+        assert!(chain.replace(1, Box::new(Gain::from_db(0.0))).is_ok());
+        assert!(chain.remove(0).is_ok());
+        assert_eq!(chain.remove(1), Err(ChainError::InvalidIndex(1)));
+        let (error, _) = chain.replace(1, Box::new(Gain::new(1.0))).unwrap_err();
+        assert_eq!(error, ChainError::InvalidIndex(1));
+
+        let mut block = vec![1.0, -1.0];
+        processor.process(&mut block);
+        assert_eq!(block, vec![1.0, -1.0]);
+    }
+
+    #[test]
+
+    fn test_effects_order() {
+        let (chain, mut processor) = EffectChain::new(FORMAT);
+        chain.push(Box::new(Gain::new(0.5))).ok().unwrap();
+        chain.push(delay()).ok().unwrap();
+        chain.push(Box::new(Gain::new(4.0))).ok().unwrap();
+        assert!(chain.set_bypass(1, true).is_ok());
+
+        // The bypass flag follows the processor
+        assert!(chain.move_effect(1, 0).is_ok());
+        assert_eq!(chain.is_bypassed(0), Some(true));
+        assert_eq!(chain.is_bypassed(1), Some(false));
+        assert_eq!(chain.move_effect(0, 3), Err(ChainError::InvalidIndex(3)));
+
+        let mut block = vec![1.0, 1.0];
+        processor.process(&mut block);
+        assert_eq!(block, vec![2.0, 2.0]);
+
+        assert!(chain.clear().is_ok());
+        assert!(chain.is_empty());
+        let mut block = vec![1.0, 1.0];
+        processor.process(&mut block);
+        assert_eq!(block, vec![1.0, 1.0]);
+    }
+
+    #[test]
+
+    fn test_effects_capacity() {
+        let (chain, mut processor) = EffectChain::new(FORMAT);
+        for _ in 0..MAX_EFFECTS {
+            assert!(chain.push(Box::new(Gain::new(1.0))).is_ok());
+        }
+
+        // The full chain gives the processor back
+        let (error, _) = chain.push(Box::new(Gain::new(1.0))).unwrap_err();
+        assert_eq!(error, ChainError::Full);
+        assert_eq!(chain.len(), MAX_EFFECTS);
+
+        // The edits are refused without blocking while the audio side isn't processing
+        let mut edits = 0;
+        while chain.set_parameter(0, Gain::GAIN, 1.0).is_ok() {
+            edits += 1;
+            assert!(edits < 1000);
+        }
+        assert_eq!(chain.set_bypass(0, true), Err(ChainError::Busy));
+        assert_eq!(chain.is_bypassed(0), Some(false));
+        let (error, _) = chain.replace(0, delay()).unwrap_err();
+        assert_eq!(error, ChainError::Busy);
+        assert_eq!(chain.latency(), 0);
+
+        processor.process(&mut [0.0, 0.0]);
+        assert!(chain.set_bypass(0, true).is_ok());
+    }
+
+    #[test]
+
+    fn test_effects_meter() {
+        let (mut meter, levels) = LevelMeter::new();
+        assert!(levels.peaks().is_empty());
+
+        meter.set_format(FORMAT);
+        let mut block = vec![0.25, -0.5, -0.75, 0.125];
+        meter.process(&mut block);
+        assert_eq!(block, vec![0.25, -0.5, -0.75, 0.125]);
+        meter.process(&mut [0.5, 0.0]);
+
+        // The peaks are reset once read
+        assert_eq!(levels.peaks(), vec![0.75, 0.5]);
+        assert_eq!(levels.peaks(), vec![0.0, 0.0]);
+    }
+}
@@ -0,0 +1,200 @@
+#[cfg(test)]
+
+mod tests_equalizer {
+    use vibe_engine::effects::{
+        AudioProcessor, Band, Biquad, EqMode, EqPreset, Equalizer, FilterType, GRAPHIC_FREQUENCIES,
+    };
+    use vibe_engine::stream::OutputFormat;
+
+    const SAMPLE_RATE: u32 = 48000;
+    const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+    fn assert_db(value: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (value - expected).abs() <= tolerance,
+            "{} dB instead of {} dB",
+            value,
+            expected
+        );
+    }
+
+    fn response(filter: FilterType, gain: f32, q: f32, frequency: f32) -> f32 {
+        let band = Band::new(filter, 1000.0, gain, q);
+        Biquad::new(&band, SAMPLE_RATE).magnitude_db(frequency, SAMPLE_RATE)
+    }
+
+    #[test]
+
+    fn test_equalizer_peaking() {
+        assert_db(response(FilterType::Peaking, 6.0, 1.0, 1000.0), 6.0, 0.01);
+        assert_db(response(FilterType::Peaking, -6.0, 1.0, 1000.0), -6.0, 0.01);
+        assert_db(response(FilterType::Peaking, 6.0, 1.0, 10.0), 0.0, 0.01);
+        assert_db(response(FilterType::Peaking, 6.0, 1.0, 20000.0), 0.0, 0.1);
+    }
+
+    #[test]
+
+    fn test_equalizer_shelves() {
+        // Half of the gain at the corner frequency
+        assert_db(
+            response(FilterType::LowShelf, 6.0, BUTTERWORTH_Q, 1000.0),
+            3.0,
+            0.01,
+        );
+        assert_db(
+            response(FilterType::HighShelf, 6.0, BUTTERWORTH_Q, 1000.0),
+            3.0,
+            0.01,
+        );
+
+        assert_db(
+            response(FilterType::LowShelf, 6.0, BUTTERWORTH_Q, 20.0),
+            6.0,
+            0.05,
+        );
+        assert_db(
+            response(FilterType::LowShelf, 6.0, BUTTERWORTH_Q, 20000.0),
+            0.0,
+            0.05,
+        );
+        assert_db(
+            response(FilterType::HighShelf, 6.0, BUTTERWORTH_Q, 20.0),
+            0.0,
+            0.05,
+        );
+        assert_db(
+            response(FilterType::HighShelf, 6.0, BUTTERWORTH_Q, 20000.0),
+            6.0,
+            0.05,
+        );
+    }
+
+    #[test]
+
+    fn test_equalizer_pass() {
+        // Butterworth response, -3 dB at the cutoff and 12 dB per octave
+        assert_db(
+            response(FilterType::LowPass, 0.0, BUTTERWORTH_Q, 1000.0),
+            -3.01,
+            0.01,
+        );
+        assert_db(
+            response(FilterType::HighPass, 0.0, BUTTERWORTH_Q, 1000.0),
+            -3.01,
+            0.01,
+        );
+
+        assert_db(
+            response(FilterType::LowPass, 0.0, BUTTERWORTH_Q, 50.0),
+            0.0,
+            0.01,
+        );
+        assert_db(
+            response(FilterType::HighPass, 0.0, BUTTERWORTH_Q, 15000.0),
+            0.0,
+            0.05,
+        );
+        assert!(response(FilterType::LowPass, 0.0, BUTTERWORTH_Q, 10000.0) < -30.0);
+        assert!(response(FilterType::HighPass, 0.0, BUTTERWORTH_Q, 100.0) < -30.0);
+    }
+
+    #[test]
+
+    fn test_equalizer_graphic() {
+        let flat = Equalizer::default();
+        assert_eq!(flat.bands().len(), GRAPHIC_FREQUENCIES.len());
+        for &frequency in &[20.0, 100.0, 1000.0, 5000.0, 18000.0] {
+            assert_db(flat.response_db(frequency), 0.0, 1e-4);
+        }
+
+        let mut gains = [0.0; 10];
+        gains[5] = 12.0;
+        let mut equalizer = Equalizer::graphic(gains);
+        assert_db(equalizer.response_db(1000.0), 12.0, 0.01);
+        assert_db(equalizer.response_db(31.0), 0.0, 0.1);
+
+        equalizer.set_preamp(-12.0);
+        assert_db(equalizer.response_db(1000.0), 0.0, 0.01);
+
+        // Gain of the sixth band, then preamp
+        equalizer.set_parameter(Equalizer::band_parameter(5, Equalizer::GAIN), 6.0);
+        equalizer.set_parameter(Equalizer::PREAMP, 0.0);
+        assert_db(equalizer.response_db(1000.0), 6.0, 0.01);
+        equalizer.set_parameter(Equalizer::band_parameter(5, Equalizer::FREQUENCY), 2000.0);
+        assert_eq!(equalizer.bands()[5].frequency, 2000.0);
+    }
+
+    #[test]
+
+    fn test_equalizer_process() {
+        let mut equalizer =
+            Equalizer::parametric(vec![Band::new(FilterType::Peaking, 1000.0, -6.0, 1.0)]);
+        equalizer.set_format(OutputFormat {
+            sample_rate: SAMPLE_RATE,
+            channels: 2,
+        });
+
+        // Stereo sine at the center frequency, the right channel inverted
+        let frames = SAMPLE_RATE as usize;
+        let mut block: Vec<f32> = (0..frames)
+            .flat_map(|i| {
+                let value =
+                    (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / SAMPLE_RATE as f32).sin();
+                vec![value, -value]
+            })
+            .collect();
+        equalizer.process(&mut block);
+
+        // Skip the transient before measuring the amplitude
+        let tail = &block[block.len() / 2..];
+        let left = tail.iter().step_by(2).fold(0f32, |max, s| max.max(s.abs()));
+        let right = tail
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .fold(0f32, |max, s| max.max(s.abs()));
+        assert_db(20.0 * left.log10(), -6.0, 0.05);
+        assert_db(20.0 * right.log10(), -6.0, 0.05);
+
+        equalizer.reset();
+        let mut silence = vec![0.0; 64];
+        equalizer.process(&mut silence);
+        assert!(silence.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+
+    fn test_equalizer_presets() {
+        let graphic = EqPreset {
+            name: "Loud".into(),
+            preamp: -3.0,
+            mode: EqMode::Graphic {
+                gains: [3.0, 2.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 3.0],
+            },
+        };
+        let parsed = EqPreset::from_toml(&graphic.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, graphic);
+
+        let parametric = EqPreset {
+            name: "Voice".into(),
+            preamp: 0.0,
+            mode: EqMode::Parametric {
+                bands: vec![
+                    Band::new(FilterType::HighPass, 80.0, 0.0, BUTTERWORTH_Q),
+                    Band::new(FilterType::Peaking, 3000.0, 4.0, 2.0),
+                ],
+            },
+        };
+        let parsed = EqPreset::from_toml(&parametric.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, parametric);
+        assert_eq!(Equalizer::from_preset(&parsed).bands().len(), 2);
+
+        let document = "name = \"Bad\"\nmode = \"parametric\"\n\n[[bands]]\nfilter = \"peaking\"\nfrequency = -10.0\n";
+        assert!(EqPreset::from_toml(document).is_err());
+        assert!(EqPreset::from_toml("name = \"Bad\"\nmode = \"unknown\"").is_err());
+
+        let builtin = EqPreset::builtin();
+        assert!(builtin.contains(&EqPreset::flat()));
+        assert!(builtin.iter().any(|preset| preset.name == "Bass boost"));
+    }
+}
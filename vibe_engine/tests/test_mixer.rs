@@ -0,0 +1,178 @@
+#[cfg(test)]
+
+mod tests_mixer {
+    use std::{fs::File, io::BufReader};
+    use vibe_core::decoder::Decoder;
+    use vibe_engine::effects::AudioProcessor;
+    use vibe_engine::mixer::{ChannelSettings, Mixer, MixerProcessor, MAIN_SOURCE, MAX_SOURCES};
+    use vibe_engine::stream::OutputFormat;
+
+    /// Format of the test file, so the sources are played untouched
+    const FORMAT: OutputFormat = OutputFormat {
+        sample_rate: 48000,
+        channels: 2,
+    };
+
+    /// Frames of a block, longer than the ramps of the levels
+    const BLOCK_FRAMES: usize = 1024;
+
+    fn decoder() -> Decoder<BufReader<File>> {
+        let file = File::open("tests/sounds/Test1.wav").expect("File not found");
+        Decoder::new(BufReader::new(file)).expect("Decoding error")
+    }
+
+    fn samples() -> Vec<f32> {
+        decoder().map(|sample| sample.unwrap()).collect()
+    }
+
+    /// Process a block filled with the given main source value
+    fn process(processor: &mut MixerProcessor, main: f32) -> Vec<f32> {
+        let mut block = vec![main; BLOCK_FRAMES * FORMAT.channels];
+        processor.process(&mut block);
+        block
+    }
+
+    fn assert_close(values: &[f32], expected: &[f32]) {
+        assert_eq!(values.len(), expected.len());
+        for (value, expected) in values.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-6,
+                "{} instead of {}",
+                value,
+                expected
+            );
+        }
+    }
+
+    #[test]
+
+    fn test_mixer_layers() {
+        let (mixer, mut processor) = Mixer::new(FORMAT);
+        let samples = samples();
+
+        // The main source is kept and the layers are added with their gain
+        let first = mixer.add(decoder(), ChannelSettings::default()).unwrap();
+        let settings = ChannelSettings {
+            gain: 0.5,
+            ..ChannelSettings::default()
+        };
+        let second = mixer.add(decoder(), settings).unwrap();
+        assert_ne!(first, MAIN_SOURCE);
+        assert_eq!(mixer.sources(), vec![first, second]);
+        assert_eq!(mixer.settings(second), Some(settings));
+
+        let block = process(&mut processor, 0.25);
+        let expected: Vec<f32> = samples[..block.len()]
+            .iter()
+            .map(|sample| 0.25 + sample * 1.5)
+            .collect();
+        assert_close(&block, &expected);
+
+        // The removed source stops, the other one goes on
+        assert!(mixer.remove(first));
+        assert!(!mixer.remove(first));
+        let block = process(&mut processor, 0.0);
+        let expected: Vec<f32> = samples[block.len()..2 * block.len()]
+            .iter()
+            .map(|sample| sample * 0.5)
+            .collect();
+        assert_close(&block, &expected);
+    }
+
+    #[test]
+
+    fn test_mixer_mute_solo() {
+        let (mixer, mut processor) = Mixer::new(FORMAT);
+        let layer = mixer.add(decoder(), ChannelSettings::default()).unwrap();
+
+        // Once the ramp is over the muted main source is silent
+        assert!(mixer.set_mute(MAIN_SOURCE, true));
+        assert!(mixer.set_mute(layer, true));
+        process(&mut processor, 1.0);
+        assert!(process(&mut processor, 1.0)
+            .iter()
+            .all(|&sample| sample == 0.0));
+
+        // Soloing the main source silences the layer
+        mixer.set_mute(MAIN_SOURCE, false);
+        mixer.set_mute(layer, false);
+        assert!(mixer.set_solo(MAIN_SOURCE, true));
+        process(&mut processor, 1.0);
+        assert_close(&process(&mut processor, 0.5), &vec![0.5; BLOCK_FRAMES * 2]);
+
+        // Soloing the layer too makes it heard again
+        mixer.set_solo(layer, true);
+        process(&mut processor, 0.0);
+        assert!(process(&mut processor, 0.0)
+            .iter()
+            .any(|&sample| sample != 0.0));
+
+        assert!(!mixer.set_solo(layer + 1, true));
+        assert_eq!(mixer.settings(layer + 1), None);
+    }
+
+    #[test]
+
+    fn test_mixer_pan() {
+        let settings = ChannelSettings::default();
+        assert_eq!(settings.pan_gains(), (1.0, 1.0));
+        let left = ChannelSettings {
+            pan: -1.0,
+            ..settings
+        };
+        let (gain_left, gain_right) = left.pan_gains();
+        assert_eq!(gain_left, 1.0);
+        assert!(gain_right.abs() < 1e-6);
+
+        let (mixer, mut processor) = Mixer::new(FORMAT);
+        mixer.set_pan(MAIN_SOURCE, 1.0);
+        mixer.set_gain(MAIN_SOURCE, 0.5);
+        process(&mut processor, 1.0);
+        let block = process(&mut processor, 1.0);
+        for frame in block.chunks(2) {
+            assert!(frame[0].abs() < 1e-6);
+            assert!((frame[1] - 0.5).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+
+    fn test_mixer_end() {
+        let (mixer, mut processor) = Mixer::new(FORMAT);
+        let frames = samples().len() / FORMAT.channels;
+        let once = mixer.add(decoder(), ChannelSettings::default()).unwrap();
+        let settings = ChannelSettings {
+            looped: true,
+            ..ChannelSettings::default()
+        };
+        let looped = mixer.add(decoder(), settings).unwrap();
+
+        // The source played once is removed at its end, the looped one goes on
+        for _ in 0..frames / BLOCK_FRAMES + 2 {
+            process(&mut processor, 0.0);
+        }
+        assert_eq!(mixer.sources(), vec![looped]);
+        assert!(process(&mut processor, 0.0)
+            .iter()
+            .any(|&sample| sample != 0.0));
+        assert!(!mixer.remove(once));
+
+        mixer.clear();
+        assert!(mixer.sources().is_empty());
+        process(&mut processor, 0.0);
+        assert!(process(&mut processor, 0.0)
+            .iter()
+            .all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+
+    fn test_mixer_limit() {
+        let (mixer, _processor) = Mixer::new(FORMAT);
+        for _ in 0..MAX_SOURCES {
+            assert!(mixer.add(decoder(), ChannelSettings::default()).is_some());
+        }
+        assert_eq!(mixer.add(decoder(), ChannelSettings::default()), None);
+        assert_eq!(mixer.sources().len(), MAX_SOURCES);
+    }
+}
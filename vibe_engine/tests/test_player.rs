@@ -2,24 +2,323 @@
 
 mod tests_player {
 
+    use crossbeam::channel::Receiver;
     use std::fs::File;
+    use std::io::Cursor;
+    use std::time::Duration;
     use vibe_core::decoder::Decoder;
-    use vibe_engine::player::Player;
+    use vibe_engine::player::{format_time, Player, PlayerEvent, PlayerState, PlayerStatus};
+    use vibe_engine::queue::{RepeatMode, ShuffleMode};
+    use vibe_engine::sink::{Capture, CaptureSink, WavSink};
+    use vibe_engine::stream::{FadeConfig, OutputFormat};
+
+    /// Time rendered at each step of the output
+    const STEP: Duration = Duration::from_millis(10);
+
+    /// Longest time rendered while waiting for an event
+    const MAX_RENDER: Duration = Duration::from_secs(20);
+
+    /// Create a player rendering into a capture sink
+    fn player() -> (Player, Capture) {
+        let (sink, capture) = CaptureSink::new(OutputFormat {
+            sample_rate: 48000,
+            channels: 2,
+        });
+        (Player::with_sink(sink, FadeConfig::default()), capture)
+    }
+
+    /// Render the output until the player sends an event matching `last`, returns the events received
+    fn run_until<F>(capture: &Capture, events: &Receiver<PlayerEvent>, last: F) -> Vec<PlayerEvent>
+    where
+        F: Fn(&PlayerEvent) -> bool,
+    {
+        let mut received = Vec::new();
+        let start = capture.elapsed();
+        while capture.elapsed() - start < MAX_RENDER {
+            capture.advance(STEP);
+            // The player reacts to the events of the stream on its own thread
+            std::thread::sleep(Duration::from_millis(1));
+
+            for event in events.try_iter() {
+                let done = last(&event);
+                received.push(event);
+                if done {
+                    return received;
+                }
+            }
+        }
+        panic!("no matching event, received {:?}", received);
+    }
 
     #[test]
 
     fn test_player() {
-        let mut player = Player::new();
+        let (mut player, capture) = player();
         let file = File::open("tests/sounds/Test1.mp3").expect("File not found");
         let decoder = Decoder::new(file).expect("Decoding error");
-        player.create_stream(decoder);
+        assert!(player.create_stream(decoder).is_ok());
         player.play_stream();
 
-        std::thread::spawn(move || {
-            std::thread::sleep(std::time::Duration::from_millis(1000));
-            player.pause_stream();
-        });
+        // The player sends its commands to the stream on its own
+        while capture.advance(STEP).iter().all(|&sample| sample == 0.0) {
+            assert!(capture.elapsed() < MAX_RENDER);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        player.pause_stream();
+        assert_eq!(player.state(), PlayerState::Paused);
+
+        // The output is silent once the pause command has gone through
+        let position = player.position();
+        capture.render_until_idle(MAX_RENDER);
+        assert!(capture.advance(STEP).iter().all(|&sample| sample == 0.0));
+        assert!(player.position() >= position);
+    }
+
+    #[test]
+
+    fn test_player_queue() {
+        let (player, capture) = player();
+        let events = player.events();
+
+        player.enqueue("tests/sounds/Test1.wav");
+        player.enqueue("tests/sounds/Missing.wav");
+        player.enqueue("tests/sounds/Test1.ogg");
+        player.next_track();
+
+        // The missing file is skipped when the first track ends
+        let indexes: Vec<usize> = run_until(&capture, &events, |event| {
+            matches!(event, PlayerEvent::QueueEnded)
+        })
+        .into_iter()
+        .filter_map(|event| match event {
+            PlayerEvent::TrackChanged { index, .. } => Some(index),
+            _ => None,
+        })
+        .collect();
+        assert_eq!(indexes, vec![0, 2]);
+        assert_eq!(player.current_index(), None);
+    }
+
+    #[test]
+
+    fn test_player_crossfade() {
+        let (player, capture) = player();
+        let events = player.events();
+
+        player.set_crossfade(Duration::from_secs(1));
+        assert_eq!(player.crossfade(), Duration::from_secs(1));
+
+        player.enqueue("tests/sounds/Test1.wav");
+        player.enqueue("tests/sounds/Test1.ogg");
+        player.next_track();
+
+        // The second track is preloaded and mixed into the end of the first one
+        let indexes: Vec<usize> = run_until(&capture, &events, |event| {
+            matches!(event, PlayerEvent::QueueEnded)
+        })
+        .into_iter()
+        .filter_map(|event| match event {
+            PlayerEvent::TrackChanged { index, .. } => Some(index),
+            _ => None,
+        })
+        .collect();
+        assert_eq!(indexes, vec![0, 1]);
+        assert_eq!(player.current_index(), None);
+    }
+
+    #[test]
+
+    fn test_player_state() {
+        let (player, capture) = player();
+        let events = player.events();
+        assert_eq!(player.state(), PlayerState::Empty);
+
+        player.enqueue("tests/sounds/Test1.wav");
+        player.play_stream();
+        assert_eq!(player.state(), PlayerState::Playing);
+        player.pause_stream();
+        assert_eq!(player.state(), PlayerState::Paused);
+        player.play_stream();
+        player.stop_stream();
+        assert_eq!(player.state(), PlayerState::Stopped);
+        assert_eq!(player.position(), std::time::Duration::from_secs(0));
+
+        // Playing after a stop starts the track over, until the end of the queue
+        player.play_stream();
+        let mut states = Vec::new();
+        let mut position = None;
+        for event in run_until(&capture, &events, |event| {
+            matches!(event, PlayerEvent::QueueEnded)
+        }) {
+            match event {
+                PlayerEvent::StateChanged(state) => states.push(state),
+                PlayerEvent::Position(pos) => position = Some(pos),
+                _ => {}
+            }
+        }
+        assert_eq!(
+            states,
+            vec![
+                PlayerState::Loading,
+                PlayerState::Playing,
+                PlayerState::Paused,
+                PlayerState::Playing,
+                PlayerState::Stopped,
+                PlayerState::Loading,
+                PlayerState::Playing,
+            ]
+        );
+        assert!(position.is_some());
+        assert_eq!(player.state(), PlayerState::Ended);
+    }
+
+    #[test]
+
+    fn test_player_error() {
+        let (player, _capture) = player();
+        let events = player.events();
+
+        player.enqueue("tests/sounds/Missing.wav");
+        player.play_stream();
+        assert_eq!(player.state(), PlayerState::Error);
+
+        let errors = events
+            .try_iter()
+            .filter(|event| matches!(event, PlayerEvent::Error(_)))
+            .count();
+        assert_eq!(errors, 1);
+    }
+
+    #[test]
+
+    fn test_player_status() {
+        let (player, capture) = player();
+        let events = player.events();
+        let mut status = PlayerStatus::new(&player);
+        assert_eq!(status.state(), PlayerState::Empty);
+        assert_eq!(status.title(), None);
+        assert_eq!(status.progress(), 0.0);
+
+        player.enqueue("tests/sounds/Test1.wav");
+        status.toggle_play(&player);
+        for event in run_until(&capture, &events, |event| {
+            matches!(event, PlayerEvent::Position(_))
+        }) {
+            status.handle_event(&event);
+        }
+        assert!(status.is_playing());
+        assert_eq!(status.index(), Some(0));
+        assert_eq!(status.title().as_deref(), Some("Test1.wav"));
+        let duration = status.duration().unwrap();
+        assert!(status.position() > Duration::from_secs(0));
+        assert!(status.progress() > 0.0 && status.progress() < 1.0);
+
+        // The seeks are kept within the track
+        status.seek_to(&player, 0.5);
+        assert_eq!(status.position(), duration / 2);
+        status.seek_by(&player, -1000.0);
+        assert_eq!(status.position(), Duration::from_secs(0));
+        assert_eq!(status.time(), format!("0:00 / {}", format_time(duration)));
+
+        status.cycle_repeat(&player);
+        status.cycle_shuffle(&player);
+        assert_eq!(player.repeat_mode(), RepeatMode::All);
+        assert_eq!(player.shuffle_mode(), ShuffleMode::NoRepeat);
+        assert_eq!(PlayerStatus::new(&player).repeat(), RepeatMode::All);
+
+        // A status created during the playback starts with the track being played
+        let current = PlayerStatus::new(&player);
+        assert_eq!(current.track(), status.track());
+        assert_eq!(current.duration(), Some(duration));
+
+        status.toggle_play(&player);
+        for event in events.try_iter() {
+            status.handle_event(&event);
+        }
+        assert_eq!(status.state(), PlayerState::Paused);
+    }
+
+    #[test]
+
+    fn test_player_format_time() {
+        assert_eq!(format_time(Duration::from_millis(999)), "0:00");
+        assert_eq!(format_time(Duration::from_secs(65)), "1:05");
+        assert_eq!(format_time(Duration::from_secs(3599)), "59:59");
+        assert_eq!(format_time(Duration::from_secs(3600 + 61)), "1:01:01");
+    }
+
+    #[test]
+
+    fn test_player_output_error() {
+        // The sink refuses an output without channels
+        let format = OutputFormat {
+            sample_rate: 48000,
+            channels: 0,
+        };
+        let sink = WavSink::new(Cursor::new(Vec::new()), format);
+        let player = Player::with_sink(sink, FadeConfig::default());
+        let events = player.events();
+
+        assert!(player.effects().is_err());
+        player.enqueue("tests/sounds/Test1.wav");
+        player.play_stream();
+        assert_eq!(player.state(), PlayerState::Error);
+        assert!(events
+            .try_iter()
+            .any(|event| matches!(event, PlayerEvent::Error(_))));
+
+        // The error is kept once the sink is gone
+        assert!(player.mixer().is_err());
+        player.play_index(0);
+        assert_eq!(player.state(), PlayerState::Error);
+    }
+
+    #[test]
+
+    fn test_player_queue_changed() {
+        let (player, _capture) = player();
+        let events = player.events();
+        let changes = || {
+            events
+                .try_iter()
+                .filter(|event| *event == PlayerEvent::QueueChanged)
+                .count()
+        };
+
+        player.enqueue("tests/sounds/Test1.wav");
+        player.insert_track(0, "tests/sounds/Test1.mp3");
+        assert_eq!(changes(), 2);
+        assert!(player.move_track(0, 1));
+        assert!(!player.move_track(0, 5));
+        assert_eq!(changes(), 1);
+        assert!(player.remove_track(0).is_some());
+        assert!(player.remove_track(5).is_none());
+        assert_eq!(changes(), 1);
+        player.clear_queue();
+        assert_eq!(changes(), 1);
+    }
+
+    #[test]
+
+    fn test_player_options_changed() {
+        let (player, _capture) = player();
+        let events = player.events();
 
-        std::thread::sleep(std::time::Duration::from_millis(3000));
+        // Only the settings that change are sent
+        player.set_repeat_mode(RepeatMode::All);
+        player.set_repeat_mode(RepeatMode::All);
+        player.set_shuffle_mode(ShuffleMode::Random);
+        player.set_volume(0.5);
+        player.set_volume(2.0);
+        player.set_volume(1.0);
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                PlayerEvent::RepeatChanged(RepeatMode::All),
+                PlayerEvent::ShuffleChanged(ShuffleMode::Random),
+                PlayerEvent::VolumeChanged(0.5),
+                PlayerEvent::VolumeChanged(1.0),
+            ]
+        );
     }
 }
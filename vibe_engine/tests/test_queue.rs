@@ -0,0 +1,165 @@
+#[cfg(test)]
+
+mod tests_queue {
+    use vibe_engine::queue::{Queue, RepeatMode, ShuffleMode, Track};
+
+    fn queue_of(paths: &[&str]) -> Queue {
+        let mut queue = Queue::new();
+        for path in paths {
+            queue.append(Track::new(*path));
+        }
+        queue
+    }
+
+    fn paths(queue: &Queue) -> Vec<&str> {
+        queue
+            .tracks()
+            .iter()
+            .map(|track| track.path().to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+
+    fn test_queue_navigation() {
+        let mut queue = queue_of(&["a", "b", "c"]);
+        assert_eq!(queue.current(), None);
+
+        assert_eq!(queue.next_track(), Some(0));
+        assert_eq!(queue.next_track(), Some(1));
+        assert_eq!(queue.current_track().map(Track::id), Some(2));
+        assert_eq!(queue.previous_track(), Some(0));
+        assert_eq!(queue.previous_track(), Some(0));
+        assert_eq!(queue.next_track(), Some(1));
+        assert_eq!(queue.next_track(), Some(2));
+        assert_eq!(queue.next_track(), None); // End of the queue
+        assert_eq!(queue.current(), None);
+    }
+
+    #[test]
+
+    fn test_queue_editing() {
+        let mut queue = queue_of(&["a", "b", "c"]);
+        queue.set_current(Some(1));
+
+        assert_eq!(queue.insert(0, Track::new("z")), 4);
+        assert_eq!(paths(&queue), vec!["z", "a", "b", "c"]);
+        assert_eq!(queue.current(), Some(2)); // Still on "b"
+
+        queue.insert(10, Track::new("d"));
+        assert_eq!(paths(&queue), vec!["z", "a", "b", "c", "d"]);
+
+        assert_eq!(queue.remove(0).map(|track| track.id()), Some(4));
+        assert_eq!(queue.current(), Some(1));
+        assert_eq!(queue.remove(10), None);
+
+        assert!(queue.move_track(1, 3));
+        assert_eq!(paths(&queue), vec!["a", "c", "d", "b"]);
+        assert_eq!(queue.current(), Some(3));
+
+        assert!(queue.move_track(2, 0));
+        assert_eq!(paths(&queue), vec!["d", "a", "c", "b"]);
+        assert_eq!(queue.current(), Some(3));
+
+        assert!(queue.move_track(3, 1));
+        assert_eq!(paths(&queue), vec!["d", "b", "a", "c"]);
+        assert_eq!(queue.current(), Some(1));
+        assert!(!queue.move_track(0, 4));
+
+        // The ids follow the tracks when they move
+        assert_eq!(queue.index_of(2), Some(1));
+        assert_eq!(queue.index_of(4), None);
+        assert_eq!(queue.remove(1).map(|track| track.id()), Some(2));
+        assert_eq!(queue.current(), None); // The current track was removed
+
+        assert!(!queue.set_current(Some(3)));
+        queue.clear();
+        assert!(queue.is_empty());
+        assert_eq!(queue.append(Track::new("e")), 6); // The ids are never reused
+    }
+
+    #[test]
+
+    fn test_queue_repeat() {
+        let mut queue = queue_of(&["a", "b"]);
+
+        queue.set_repeat(RepeatMode::One);
+        assert_eq!(queue.advance(), Some(0));
+        assert_eq!(queue.advance(), Some(0)); // The track is played again
+        assert_eq!(queue.next_track(), Some(1)); // Skipping still moves on
+        assert_eq!(queue.next_track(), None);
+
+        queue.set_repeat(RepeatMode::All);
+        assert_eq!(queue.advance(), Some(0));
+        assert_eq!(queue.advance(), Some(1));
+        assert_eq!(queue.advance(), Some(0)); // Back to the beginning
+    }
+
+    #[test]
+
+    fn test_queue_upcoming() {
+        let mut queue = queue_of(&["a", "b", "c"]);
+        assert_eq!(queue.upcoming(), Some(0));
+        assert_eq!(queue.current(), None); // Peeking doesn't move
+
+        queue.set_current(Some(1));
+        assert_eq!(queue.upcoming(), Some(2));
+        queue.insert(2, Track::new("z"));
+        assert_eq!(queue.upcoming(), Some(2)); // The inserted track follows now
+
+        queue.set_shuffle(ShuffleMode::Random);
+        let upcoming = queue.upcoming();
+        assert_eq!(queue.upcoming(), upcoming); // The choice is kept
+        assert_eq!(queue.advance(), upcoming);
+
+        queue.set_repeat(RepeatMode::One);
+        assert_eq!(queue.upcoming(), upcoming);
+    }
+
+    #[test]
+
+    fn test_queue_shuffle() {
+        let mut queue = queue_of(&["a", "b", "c", "d", "e"]);
+
+        queue.set_shuffle(ShuffleMode::NoRepeat);
+        let mut played: Vec<usize> = (0..5).map(|_| queue.advance().unwrap()).collect();
+        assert_eq!(queue.advance(), None); // Every track has been played once
+        played.sort_unstable();
+        assert_eq!(played, vec![0, 1, 2, 3, 4]);
+
+        queue.set_repeat(RepeatMode::All);
+        let first: Vec<usize> = (0..5).map(|_| queue.advance().unwrap()).collect();
+        let second: Vec<usize> = (0..5).map(|_| queue.advance().unwrap()).collect();
+        for round in [first, second].iter_mut() {
+            round.sort_unstable();
+            assert_eq!(round, &vec![0, 1, 2, 3, 4]);
+        }
+
+        // Previous goes back through the tracks actually played
+        let third = queue.advance();
+        queue.advance();
+        assert_eq!(queue.previous_track(), third);
+        assert!(queue.next_track().is_some());
+        assert_ne!(queue.current(), third);
+
+        queue.set_shuffle(ShuffleMode::Random);
+        for _ in 0..20 {
+            let current = queue.current();
+            assert_ne!(queue.advance(), current); // Never the same track twice in a row
+        }
+    }
+
+    #[test]
+
+    fn test_queue_track_info() {
+        // The tags and the duration are read with the track and shared by its copies
+        let track = Track::new("tests/sounds/Test1.flac");
+        assert!(track.duration().is_some());
+        let copy = track.clone();
+        assert!(std::ptr::eq(track.metadata(), copy.metadata()));
+
+        let missing = Track::new("tests/sounds/missing.flac");
+        assert_eq!(missing.duration(), None);
+        assert!(missing.metadata().is_empty());
+    }
+}
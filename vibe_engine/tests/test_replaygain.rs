@@ -0,0 +1,76 @@
+#[cfg(test)]
+
+mod tests_replaygain {
+    use vibe_core::ReplayGain;
+    use vibe_engine::stream::{ReplayGainConfig, ReplayGainMode};
+
+    const TAGS: ReplayGain = ReplayGain {
+        track_gain: Some(-6.0),
+        track_peak: Some(0.5),
+        album_gain: Some(-8.0),
+        album_peak: Some(0.9),
+    };
+
+    fn db(gain: f32) -> f32 {
+        20.0 * gain.log10()
+    }
+
+    fn assert_db(gain: f32, expected: f32) {
+        assert!(
+            (db(gain) - expected).abs() < 1e-3,
+            "{} dB instead of {} dB",
+            db(gain),
+            expected
+        );
+    }
+
+    #[test]
+
+    fn test_replaygain_modes() {
+        let mut config = ReplayGainConfig::default();
+        assert_eq!(config.mode, ReplayGainMode::Off);
+        assert_eq!(config.gain(&TAGS), 1.0);
+
+        config.mode = ReplayGainMode::Track;
+        assert_db(config.gain(&TAGS), -6.0);
+        config.mode = ReplayGainMode::Album;
+        assert_db(config.gain(&TAGS), -8.0);
+
+        // Each mode falls back on the values of the other one
+        let track_only = ReplayGain {
+            album_gain: None,
+            album_peak: None,
+            ..TAGS
+        };
+        assert_db(config.gain(&track_only), -6.0);
+        config.mode = ReplayGainMode::Track;
+        let album_only = ReplayGain {
+            track_gain: None,
+            track_peak: None,
+            ..TAGS
+        };
+        assert_db(config.gain(&album_only), -8.0);
+
+        // Untagged tracks get the fallback gain, without the preamp
+        config.preamp = 3.0;
+        config.fallback = -4.0;
+        assert_db(config.gain(&TAGS), -3.0);
+        assert_db(config.gain(&ReplayGain::default()), -4.0);
+    }
+
+    #[test]
+
+    fn test_replaygain_clipping() {
+        let mut config = ReplayGainConfig {
+            mode: ReplayGainMode::Track,
+            preamp: 14.0,
+            ..ReplayGainConfig::default()
+        };
+
+        // +8 dB on a peak of 0.5 is capped at full scale
+        assert_eq!(config.gain(&TAGS), 2.0);
+
+        config.prevent_clipping = false;
+        assert_db(config.gain(&TAGS), 8.0);
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+
+mod tests_sink {
+    use std::fs::File;
+    use std::io::{BufReader, Cursor};
+    use std::time::Duration;
+    use vibe_core::decoder::Decoder;
+    use vibe_engine::effects::Gain;
+    use vibe_engine::sink::{NullSink, SinkError, WavSink};
+    use vibe_engine::stream::{AudioStream, FadeConfig, OutputFormat, StreamEvent};
+
+    /// Format of the test file, so it is rendered untouched
+    const FORMAT: OutputFormat = OutputFormat {
+        sample_rate: 48000,
+        channels: 2,
+    };
+
+    /// Fades of zero length, the rendered samples are the samples of the file
+    const NO_FADES: FadeConfig = FadeConfig {
+        pause: Duration::from_secs(0),
+        resume: Duration::from_secs(0),
+        stop: Duration::from_secs(0),
+        seek: Duration::from_secs(0),
+    };
+
+    fn decoder() -> Decoder<BufReader<File>> {
+        let file = File::open("tests/sounds/Test1.wav").expect("File not found");
+        Decoder::new(BufReader::new(file)).expect("Decoding error")
+    }
+
+    fn decode(data: Vec<u8>) -> Vec<f32> {
+        let decoder = Decoder::new(Cursor::new(data)).expect("Decoding error");
+        assert_eq!(decoder.info().sample_rate(), FORMAT.sample_rate);
+        assert_eq!(decoder.info().channels(), FORMAT.channels);
+        decoder.map(|sample| sample.unwrap()).collect()
+    }
+
+    /// Play the test file to its end through a WAV sink and get the rendered samples
+    fn render(setup: impl FnOnce(&AudioStream)) -> Vec<f32> {
+        let path = std::env::temp_dir().join(format!("vibe_sink_{}.wav", std::process::id()));
+        let sink = WavSink::create(&path, FORMAT).unwrap();
+        let stream = AudioStream::with_sink(sink, NO_FADES).unwrap();
+        assert_eq!(stream.format(), FORMAT);
+
+        setup(&stream);
+        let id = stream.load(decoder());
+        stream.play();
+        let events = stream.events();
+        assert_eq!(
+            events.recv_timeout(Duration::from_secs(10)),
+            Ok(StreamEvent::Ended(id))
+        );
+
+        // The file is complete once the engine is shut down
+        drop(stream);
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        decode(data)
+    }
+
+    #[test]
+
+    fn test_sink_wav() {
+        let samples: Vec<f32> = decoder().map(|sample| sample.unwrap()).collect();
+        let rendered = render(|_| {});
+
+        // The last block is padded with silence
+        assert!(rendered.len() >= samples.len());
+        assert!(rendered.len() - samples.len() < 4096 * FORMAT.channels);
+        assert_eq!(&rendered[..samples.len()], &samples[..]);
+        assert!(rendered[samples.len()..]
+            .iter()
+            .all(|&sample| sample == 0.0));
+
+        // The effects are applied to the render
+        let halved = render(|stream| {
+            assert!(stream.effects().push(Box::new(Gain::new(0.5))).is_ok());
+        });
+        for (value, sample) in halved.iter().zip(&samples) {
+            assert!((value - sample * 0.5).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+
+    fn test_sink_null() {
+        let stream = AudioStream::with_sink(NullSink::new(FORMAT), NO_FADES).unwrap();
+        let id = stream.load(decoder());
+        stream.seek(Duration::from_millis(2800));
+        stream.play();
+
+        // The end of the file is played at the pace of a device
+        let events = stream.events();
+        assert_eq!(
+            events.recv_timeout(Duration::from_secs(5)),
+            Ok(StreamEvent::Ended(id))
+        );
+        assert!(stream.position() >= Duration::from_millis(2800));
+    }
+
+    #[test]
+
+    fn test_sink_error() {
+        let format = OutputFormat {
+            sample_rate: 48000,
+            channels: 0,
+        };
+        let sink = WavSink::new(Cursor::new(Vec::new()), format);
+        match AudioStream::with_sink(sink, NO_FADES) {
+            Err(SinkError::DeviceError(_)) => {}
+            _ => panic!("the format should be refused"),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+#[cfg(test)]
+
+mod tests_speed {
+    use vibe_engine::stream::{SpeedConfig, SpeedMode, MAX_PITCH, MAX_SPEED, MIN_SPEED};
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!(
+            (value - expected).abs() < 1e-9,
+            "{} instead of {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+
+    fn test_speed_time_stretch() {
+        let mut config = SpeedConfig::default();
+        assert_close(config.resample_ratio(), 1.0);
+        assert_close(config.tempo(), 1.0);
+
+        // The tempo changes, the pitch is kept
+        config.speed = 1.5;
+        assert_close(config.resample_ratio(), 1.0);
+        assert_close(config.tempo(), 1.5);
+
+        // An octave up is resampled twice as fast and stretched back to the speed
+        config.pitch = 12.0;
+        assert_close(config.resample_ratio(), 2.0);
+        assert_close(config.tempo(), 0.75);
+        assert_close(config.resample_ratio() * config.tempo(), 1.5);
+    }
+
+    #[test]
+
+    fn test_speed_varispeed() {
+        let mut config = SpeedConfig {
+            mode: SpeedMode::Varispeed,
+            speed: 0.5,
+            pitch: 0.0,
+        };
+        assert_close(config.resample_ratio(), 0.5);
+        assert_close(config.tempo(), 1.0);
+
+        // The pitch shift is added to the pitch change of the speed
+        config.pitch = -12.0;
+        assert_close(config.resample_ratio(), 0.25);
+        assert_close(config.tempo(), 2.0);
+        assert_close(config.resample_ratio() * config.tempo(), 0.5);
+    }
+
+    #[test]
+
+    fn test_speed_limits() {
+        let config = SpeedConfig {
+            mode: SpeedMode::TimeStretch,
+            speed: 10.0,
+            pitch: -40.0,
+        };
+        assert_close(config.clamped_speed(), MAX_SPEED);
+        assert_close(config.clamped_pitch(), -MAX_PITCH);
+
+        let config = SpeedConfig {
+            speed: 0.1,
+            pitch: f64::NAN,
+            ..SpeedConfig::default()
+        };
+        assert_close(config.clamped_speed(), MIN_SPEED);
+        assert_close(config.clamped_pitch(), 0.0);
+        assert_close(config.tempo(), MIN_SPEED);
+    }
+}
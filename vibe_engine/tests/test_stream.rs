@@ -1,107 +1,391 @@
 #[cfg(test)]
 
 mod tests_stream {
+    use crossbeam::channel::Receiver;
     use std::fs::File;
-    use vibe_core::decoder::Decoder;
-    use vibe_engine::stream::AudioStream;
+    use std::time::{Duration, Instant};
+    use vibe_core::decoder::{duration_to_frames, Decoder};
+    use vibe_engine::sink::{Capture, CaptureSink};
+    use vibe_engine::stream::{AudioStream, FadeConfig, OutputFormat, StreamEvent};
+
+    /// Fades of zero length, the rendered samples are the samples of the file
+    const NO_FADES: FadeConfig = FadeConfig {
+        pause: Duration::from_secs(0),
+        resume: Duration::from_secs(0),
+        stop: Duration::from_secs(0),
+        seek: Duration::from_secs(0),
+    };
+
+    /// Longest time rendered while waiting for the end of a file
+    const MAX_RENDER: Duration = Duration::from_secs(10);
+
+    /// Longest time waited for the engine thread to seek a decoder
+    const MAX_WAIT: Duration = Duration::from_secs(5);
+
+    fn decoder(path: &str) -> Decoder<File> {
+        let file = File::open(path).expect("File not found");
+        Decoder::new(file).expect("Decoding error")
+    }
+
+    /// Open a stream rendering in the format of the file, with the decoded samples of the file
+    fn open(path: &str, fades: FadeConfig) -> (AudioStream, Capture, Vec<f32>) {
+        let decoder = decoder(path);
+        let format = OutputFormat {
+            sample_rate: decoder.info().sample_rate(),
+            channels: decoder.info().channels(),
+        };
+        let samples = decoder.map(|sample| sample.unwrap()).collect();
+
+        let (sink, capture) = CaptureSink::new(format);
+        let stream = AudioStream::with_sink(sink, fades).unwrap();
+        assert_eq!(stream.format(), format);
+        (stream, capture, samples)
+    }
+
+    fn frames(capture: &Capture, duration: Duration) -> usize {
+        duration_to_frames(duration, capture.format().sample_rate) as usize
+            * capture.format().channels
+    }
+
+    /// Render single frames until the source moved by the engine thread for a seek is played
+    fn render_seek(stream: &AudioStream, capture: &Capture) {
+        let before = stream.position();
+        let start = Instant::now();
+        while stream.position() == before {
+            assert!(start.elapsed() < MAX_WAIT, "the seek never ended");
+            capture.render(1);
+        }
+    }
+
+    /// Render blocks until the stream sends an event
+    fn render_until_event(capture: &Capture, events: &Receiver<StreamEvent>) -> StreamEvent {
+        let start = Instant::now();
+        loop {
+            if let Ok(event) = events.try_recv() {
+                return event;
+            }
+            assert!(start.elapsed() < MAX_WAIT, "no event from the stream");
+            capture.advance(Duration::from_millis(10));
+        }
+    }
+
+    fn assert_close(values: &[f32], expected: &[f32]) {
+        assert_eq!(values.len(), expected.len());
+        for (index, (value, expected)) in values.iter().zip(expected).enumerate() {
+            assert!(
+                (value - expected).abs() < 1e-5,
+                "sample {}: {} instead of {}",
+                index,
+                value,
+                expected
+            );
+        }
+    }
+
+    /// Play a file with a pause in the middle and check every sample rendered
+    fn check_playback(path: &str) {
+        let (stream, capture, samples) = open(path, NO_FADES);
+        let events = stream.events();
+        let step = Duration::from_millis(500);
+        let len = frames(&capture, step);
+
+        // Nothing is played until the Play command
+        let id = stream.load(decoder(path));
+        stream.sync();
+        assert!(capture.advance(step).iter().all(|&sample| sample == 0.0));
+
+        stream.play();
+        stream.sync();
+        assert_eq!(capture.advance(step), &samples[..len]);
+        assert_eq!(stream.position(), step);
+
+        // The pause keeps the position, the playback goes on from the same sample
+        stream.pause();
+        stream.sync();
+        assert!(capture.advance(step).iter().all(|&sample| sample == 0.0));
+        assert_eq!(stream.position(), step);
+        stream.play();
+        stream.sync();
+        assert_eq!(capture.advance(step), &samples[len..2 * len]);
+
+        // The last block is padded with silence
+        let rest = capture.render_until_idle(MAX_RENDER);
+        assert!(rest.len() >= samples.len() - 2 * len);
+        assert_eq!(&rest[..samples.len() - 2 * len], &samples[2 * len..]);
+        assert!(rest[samples.len() - 2 * len..]
+            .iter()
+            .all(|&sample| sample == 0.0));
+        assert_eq!(events.try_recv(), Ok(StreamEvent::Ended(id)));
+
+        // The output stays open and silent after the end
+        assert!(capture.advance(step).iter().all(|&sample| sample == 0.0));
+        assert!(events.try_recv().is_err());
+    }
 
     #[test]
 
     fn test_stream_mp3() {
-        let file = File::open("tests/sounds/Test1.mp3").expect("File not found");
-        let decoder = Decoder::new(file).expect("Decoding error");
-        let info = decoder.info();
+        check_playback("tests/sounds/Test1.mp3");
+    }
 
-        let duration = info.duration().unwrap();
+    #[test]
 
-        let audio_stream = AudioStream::new::<f32, File>(decoder);
+    fn test_stream_wav() {
+        check_playback("tests/sounds/Test1.wav");
+    }
 
-        audio_stream.play();
+    #[test]
 
-        std::thread::spawn(move || {
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.pause();
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.play();
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.stop();
-        });
+    fn test_stream_ogg() {
+        check_playback("tests/sounds/Test1.ogg");
+    }
+
+    #[test]
 
-        std::thread::sleep(duration);
+    fn test_stream_flac() {
+        check_playback("tests/sounds/Test1.flac");
+    }
+
+    /// Get the samples expected from a linear fade starting at the given frame of the source
+    ///
+    /// The frames with a zero gain aren't decoded, they are silent and the source stays in place
+    fn faded(
+        samples: &[f32],
+        channels: usize,
+        start: usize,
+        frames: usize,
+        gains: &[f32],
+    ) -> Vec<f32> {
+        let mut expected = Vec::with_capacity(frames * channels);
+        let mut position = start;
+        for index in 0..frames {
+            let gain = gains.get(index).copied().unwrap_or(*gains.last().unwrap());
+            if gain == 0.0 {
+                expected.extend(std::iter::repeat_n(0.0, channels));
+            } else {
+                let frame = &samples[position * channels..(position + 1) * channels];
+                expected.extend(frame.iter().map(|sample| sample * gain));
+                position += 1;
+            }
+        }
+        expected
     }
 
     #[test]
 
-    fn test_stream_wav() {
-        let file = File::open("tests/sounds/Test1.wav").expect("File not found");
-        let decoder = Decoder::new(file).expect("Decoding error");
-        let info = decoder.info();
+    fn test_stream_fades() {
+        let fades = FadeConfig::default();
+        let (stream, capture, samples) = open("tests/sounds/Test1.wav", fades);
+        let channels = capture.format().channels;
+        let resume = frames(&capture, fades.resume) / channels;
+        let pause = frames(&capture, fades.pause) / channels;
+        let fade_in: Vec<f32> = (0..=resume)
+            .map(|index| index as f32 / resume as f32)
+            .collect();
+        let fade_out: Vec<f32> = (0..=pause)
+            .map(|index| 1.0 - index as f32 / pause as f32)
+            .collect();
 
-        let duration = info.duration().unwrap();
+        // The first frames fade in linearly from silence
+        stream.load(decoder("tests/sounds/Test1.wav"));
+        stream.play();
+        stream.sync();
+        let played = capture.render(resume + 100);
+        assert_close(
+            &played,
+            &faded(&samples, channels, 0, resume + 100, &fade_in),
+        );
+        let position = resume + 100 - 1;
 
-        let audio_stream = AudioStream::new::<f32, File>(decoder);
+        // The pause fades out then nothing is decoded
+        stream.pause();
+        stream.sync();
+        let paused = capture.render(pause + 100);
+        assert_close(
+            &paused,
+            &faded(&samples, channels, position, pause + 100, &fade_out),
+        );
+        let position = position + pause;
 
-        audio_stream.play();
+        // The playback fades in again from the next frame
+        stream.play();
+        stream.sync();
+        let resumed = capture.render(resume + 100);
+        assert_close(
+            &resumed,
+            &faded(&samples, channels, position, resume + 100, &fade_in),
+        );
+    }
 
-        std::thread::spawn(move || {
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.pause();
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.play();
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.stop();
-        });
+    #[test]
+
+    fn test_stream_seek() {
+        let (stream, capture, samples) = open("tests/sounds/Test1.wav", NO_FADES);
+        let step = Duration::from_millis(100);
+        let len = frames(&capture, step);
 
-        std::thread::sleep(duration);
+        stream.load(decoder("tests/sounds/Test1.wav"));
+        stream.play();
+        stream.sync();
+        assert_eq!(capture.advance(step), &samples[..len]);
+
+        // The playback goes on from the sample at the new position, once the engine thread
+        // has moved the decoder
+        let channels = capture.format().channels;
+        let position = Duration::from_millis(2000);
+        let start = frames(&capture, position) + channels;
+        stream.seek(position);
+        stream.sync();
+        render_seek(&stream, &capture);
+        assert_eq!(capture.advance(step), &samples[start..start + len]);
+        assert_eq!(stream.position(), position + step);
+
+        stream.seek(Duration::from_secs(0));
+        stream.sync();
+        render_seek(&stream, &capture);
+        assert_eq!(capture.advance(step), &samples[channels..channels + len]);
     }
 
     #[test]
 
-    fn test_stream_ogg() {
-        let file = File::open("tests/sounds/Test1.ogg").expect("File not found");
-        let decoder = Decoder::new(file).expect("Decoding error");
-        let info = decoder.info();
+    fn test_stream_seek_crossfade() {
+        let (stream, capture, samples) = open("tests/sounds/Test1.wav", NO_FADES);
+        let events = stream.events();
+        let step = Duration::from_millis(100);
+        let len = frames(&capture, step);
+        let channels = capture.format().channels;
+
+        stream.load(decoder("tests/sounds/Test1.wav"));
+        let next = stream.queue_next(decoder("tests/sounds/Test1.wav"));
+        stream.set_crossfade(Duration::from_secs(1));
+        stream.seek(Duration::from_millis(2500));
+        stream.play();
+        stream.sync();
+        render_seek(&stream, &capture);
+        capture.advance(step);
 
-        let duration = info.duration().unwrap();
+        // The seek cancels the crossfade in progress, the current source plays alone
+        let position = Duration::from_millis(1000);
+        let start = frames(&capture, position) + channels;
+        stream.seek(position);
+        stream.sync();
+        render_seek(&stream, &capture);
+        assert_eq!(capture.advance(step), &samples[start..start + len]);
 
-        let audio_stream = AudioStream::new::<f32, File>(decoder);
+        // The next source starts over with the new crossfade
+        capture.advance(Duration::from_millis(1950));
+        assert_eq!(events.try_recv(), Ok(StreamEvent::Advanced(next)));
+        let position = stream.position().as_millis();
+        assert!((1040..=1060).contains(&position), "{} ms", position);
+    }
 
-        audio_stream.play();
+    #[test]
 
-        std::thread::spawn(move || {
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.pause();
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.play();
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.stop();
-        });
+    fn test_stream_volume() {
+        let (stream, capture, samples) = open("tests/sounds/Test1.wav", NO_FADES);
+        let step = Duration::from_millis(100);
+        let len = frames(&capture, step);
 
-        std::thread::sleep(duration);
+        stream.load(decoder("tests/sounds/Test1.wav"));
+        stream.set_volume(0.5);
+        stream.play();
+        stream.sync();
+
+        // The volume ramps from full scale, then the samples are halved
+        let ramp = capture.advance(step);
+        assert_eq!(ramp[0], samples[0]);
+        let half: Vec<f32> = samples[len..2 * len].iter().map(|s| s * 0.5).collect();
+        assert_close(&capture.advance(step), &half);
+
+        stream.set_volume(0.0);
+        stream.sync();
+        capture.advance(step);
+        assert!(capture.advance(step).iter().all(|&sample| sample == 0.0));
     }
 
     #[test]
 
-    fn test_stream_flac() {
-        let file = File::open("tests/sounds/Test1.flac").expect("File not found");
-        let decoder = Decoder::new(file).expect("Decoding error");
-        let info = decoder.info();
+    fn test_stream_stop() {
+        let fades = FadeConfig::default();
+        let (stream, capture, _) = open("tests/sounds/Test1.wav", fades);
+        let events = stream.events();
+        let stop = frames(&capture, fades.stop);
+
+        stream.load(decoder("tests/sounds/Test1.wav"));
+        stream.play();
+        stream.sync();
+        capture.advance(Duration::from_millis(100));
 
-        let duration = info.duration().unwrap();
+        // The source is dropped at the end of the fade-out, without an end event
+        stream.stop();
+        stream.sync();
+        let stopped = capture.advance(Duration::from_millis(100));
+        assert!(stopped[..stop].iter().any(|&sample| sample != 0.0));
+        assert!(stopped[stop..].iter().all(|&sample| sample == 0.0));
+        stream.play();
+        stream.sync();
+        assert!(capture.render_until_idle(MAX_RENDER).is_empty());
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
 
-        let audio_stream = AudioStream::new::<f32, File>(decoder);
+    fn test_stream_busy_output() {
+        let (stream, _capture, _) = open("tests/sounds/Test1.wav", FadeConfig::default());
+        let events = stream.events();
+        stream.load(decoder("tests/sounds/Test1.wav"));
+        stream.play();
 
-        audio_stream.play();
+        // The output isn't rendering, the commands it can't take wait on the engine thread
+        for _ in 0..1000 {
+            stream.set_volume(0.5);
+        }
 
-        std::thread::spawn(move || {
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.pause();
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.play();
-            std::thread::sleep(std::time::Duration::from_millis(500));
-            audio_stream.stop();
+        // The shutdown doesn't wait for the fade-out of an output that isn't rendering
+        let start = Instant::now();
+        drop(stream);
+        assert!(events.recv().is_err());
+        assert!(start.elapsed() < MAX_WAIT);
+    }
+
+    #[test]
+
+    fn test_stream_load() {
+        let (sink, capture) = CaptureSink::new(OutputFormat {
+            sample_rate: 48000,
+            channels: 2,
         });
+        let audio_stream = AudioStream::with_sink(sink, FadeConfig::default()).unwrap();
+        let events = audio_stream.events();
+
+        // The same output plays the sources one after the other
+        for path in &["tests/sounds/Test1.ogg", "tests/sounds/Test1.wav"] {
+            let id = audio_stream.load(decoder(path));
+            audio_stream.play();
+            audio_stream.sync();
+
+            capture.advance(Duration::from_millis(500));
+            audio_stream.seek(Duration::from_secs(3600));
+            audio_stream.sync();
+            assert_eq!(
+                render_until_event(&capture, &events),
+                StreamEvent::Ended(id)
+            );
+        }
+
+        // The errors of the output are sent as events
+        capture.report_error("device lost");
+        assert_eq!(
+            events.try_recv(),
+            Ok(StreamEvent::Error("device lost".to_string()))
+        );
 
-        std::thread::sleep(duration);
+        // Dropping the last handle shuts the engine down
+        drop(audio_stream);
+        assert!(events.recv().is_err());
+        assert!(capture
+            .advance(Duration::from_millis(10))
+            .iter()
+            .all(|&sample| sample == 0.0));
     }
 }
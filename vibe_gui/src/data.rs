@@ -1,63 +1,65 @@
-use std::{fs::File, path::Path};
+use std::path::Path;
 
 use druid::{Command, Data, Env, EventCtx, FileDialogOptions, FileSpec, Lens, Target};
-use vibe_core::decoder::Decoder;
-use vibe_engine::player::Player;
+use vibe_engine::{
+    player::{Player, PlayerEvent, PlayerStatus},
+    queue::{RepeatMode, ShuffleMode},
+};
 
 #[derive(Clone, Data, Lens)]
 pub struct AppState {
     #[data(ignore)]
     player: Option<Player>,
 
-    play: bool,
-    stop: bool,
+    #[data(same_fn = "PartialEq::eq")]
+    status: PlayerStatus,
     filename: String,
     path: String,
     progress: f64,
-    duration: u64,
 }
 
 impl AppState {
     #[inline]
     /// Create a new State for the App
     pub fn new(player: Player) -> Self {
+        let status = PlayerStatus::new(&player);
+
         Self {
             player: Some(player),
-            play: false,
-            stop: false,
+            status,
             filename: "".into(),
             path: "".into(),
             progress: 0.0,
-            duration: 0,
         }
     }
 
     #[inline]
     /// Initialize player with the state
     pub fn initialize_player(&mut self) {
-        let path = self.path.as_str();
-        let file = File::open(path).expect("File not found");
-
-        self.stop = true;
-        self.play = false;
         self.set_filename();
 
-        let decoder = Decoder::new(file).expect("Decoding error");
-        let info = decoder.info();
-        let duration = info.duration().unwrap();
-
-        let mut player = self.player.as_ref().unwrap().clone();
+        let player = self.player.as_ref().unwrap();
+        player.clear_queue();
+        player.enqueue(self.path.as_str());
 
-        player.create_stream(decoder);
-
-        self.player = Some(player);
-        self.duration = duration.as_millis() as _;
+        self.progress = 0.0;
     }
 
     #[inline]
     /// Get the play/pause status
     pub fn get_play(&self) -> bool {
-        self.play
+        self.status.is_playing()
+    }
+
+    #[inline]
+    /// Update the state with an event of the player
+    pub fn handle_event(&mut self, event: &PlayerEvent) {
+        self.status.handle_event(event);
+        self.progress = self.status.progress();
+
+        if let PlayerEvent::Error(error) = event {
+            eprintln!("{}", error);
+        }
     }
 
     #[inline]
@@ -85,41 +87,44 @@ impl AppState {
     #[inline]
     /// Play the audio after clicking on button
     fn play_action(&mut self) {
-        if self.path.as_str() != "" {
-            self.play = !self.play;
-
-            if self.stop {
-                self.stop = false;
-
-                let path = self.path.as_str();
-                let file = File::open(path).expect("File not found");
-                let decoder = Decoder::new(file).expect("Decoding error");
-                let mut player = self.player.as_ref().unwrap().clone();
-
-                player.create_stream(decoder);
-
-                self.player = Some(player);
-                self.player.as_ref().unwrap().play_stream();
-            } else {
-                if self.play {
-                    self.player.as_ref().unwrap().play_stream();
-                } else {
-                    self.player.as_ref().unwrap().pause_stream();
-                }
-            }
+        if let Some(player) = self.player.as_ref() {
+            self.status.toggle_play(player);
         }
     }
 
     #[inline]
     /// Stop the audio after clicking on button
     fn stop_action(&mut self) {
-        if !self.stop {
-            self.stop = true;
-            self.play = false;
+        if let Some(player) = self.player.as_ref() {
+            player.stop_stream();
+        }
+    }
 
-            if self.player.is_some() {
-                self.player.as_ref().unwrap().stop_stream();
-            }
+    #[inline]
+    /// Get the repeat mode
+    pub fn get_repeat(&self) -> RepeatMode {
+        self.status.repeat()
+    }
+
+    #[inline]
+    /// Get the shuffle mode
+    pub fn get_shuffle(&self) -> ShuffleMode {
+        self.status.shuffle()
+    }
+
+    #[inline]
+    /// Switch to the next repeat mode after clicking on button
+    fn repeat_action(&mut self) {
+        if let Some(player) = self.player.as_ref() {
+            self.status.cycle_repeat(player);
+        }
+    }
+
+    #[inline]
+    /// Switch to the next shuffle mode after clicking on button
+    fn shuffle_action(&mut self) {
+        if let Some(player) = self.player.as_ref() {
+            self.status.cycle_shuffle(player);
         }
     }
 
@@ -151,4 +156,12 @@ impl AppState {
     pub fn toggle_stop(_ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
         data.stop_action();
     }
+
+    pub fn toggle_repeat(_ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
+        data.repeat_action();
+    }
+
+    pub fn toggle_shuffle(_ctx: &mut EventCtx, data: &mut Self, _env: &Env) {
+        data.shuffle_action();
+    }
 }
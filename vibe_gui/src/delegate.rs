@@ -1,7 +1,11 @@
-use druid::{commands, AppDelegate, Command, DelegateCtx, Env, Handled, Target};
+use druid::{commands, AppDelegate, Command, DelegateCtx, Env, Handled, Selector, Target};
+use vibe_engine::player::PlayerEvent;
 
 use crate::data::AppState;
 
+/// Command carrying an event of the player to the UI thread
+pub const PLAYER_EVENT: Selector<PlayerEvent> = Selector::new("vibe.player-event");
+
 pub struct Delegate;
 
 impl AppDelegate<AppState> for Delegate {
@@ -18,6 +22,10 @@ impl AppDelegate<AppState> for Delegate {
             data.initialize_player();
             return Handled::Yes;
         }
+        if let Some(event) = cmd.get(PLAYER_EVENT) {
+            data.handle_event(event);
+            return Handled::Yes;
+        }
         Handled::No
     }
 }
@@ -1,4 +1,4 @@
-use druid::{AppLauncher, WindowDesc};
+use druid::{AppLauncher, Target, WindowDesc};
 
 mod data;
 use data::AppState;
@@ -8,18 +8,33 @@ use vibe_engine::player::Player;
 use view::build_ui;
 
 mod delegate;
-use delegate::Delegate;
+use delegate::{Delegate, PLAYER_EVENT};
 
 pub fn main() {
     let main_window = WindowDesc::new(build_ui)
         .title("Vibe Player")
-        .window_size((400.0, 50.0));
+        .window_size((500.0, 50.0));
 
     let player = Player::new();
+    let events = player.events();
     let initial_state = AppState::new(player);
 
-    AppLauncher::with_window(main_window)
-        .delegate(Delegate)
+    let launcher = AppLauncher::with_window(main_window).delegate(Delegate);
+
+    // Forward the events of the player to the UI thread
+    let event_sink = launcher.get_external_handle();
+    std::thread::spawn(move || {
+        for event in events.iter() {
+            if event_sink
+                .submit_command(PLAYER_EVENT, event, Target::Auto)
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    launcher
         .launch(initial_state)
         .expect("Failed to launch application");
 }
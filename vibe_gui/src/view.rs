@@ -2,6 +2,7 @@ use druid::{
     widget::{Button, Flex, Label, ProgressBar},
     Widget, WidgetExt,
 };
+use vibe_engine::queue::{RepeatMode, ShuffleMode};
 
 use crate::data::*;
 
@@ -21,22 +22,48 @@ fn get_play_unicode(play: bool) -> String {
     }
 }
 
+/// Get repeat mode unicode
+fn get_repeat_unicode(repeat: RepeatMode) -> String {
+    match repeat {
+        RepeatMode::Off => String::from("\u{2192}"),
+        RepeatMode::One => String::from("\u{1f502}"),
+        RepeatMode::All => String::from("\u{1f501}"),
+    }
+}
+
+/// Get shuffle mode unicode
+fn get_shuffle_unicode(shuffle: ShuffleMode) -> String {
+    match shuffle {
+        ShuffleMode::Off => String::from("\u{2261}"),
+        ShuffleMode::Random => String::from("\u{1f3b2}"),
+        ShuffleMode::NoRepeat => String::from("\u{1f500}"),
+    }
+}
+
 /// Create the buttons
 fn buttons() -> impl Widget<AppState> {
     let plus_button = Button::new("\u{2795}");
     let play_pause_button: Button<AppState> =
         Button::dynamic(|data: &AppState, _| format!("{}", get_play_unicode(data.get_play())));
     let stop_button = Button::new("\u{23f9}");
+    let repeat_button: Button<AppState> =
+        Button::dynamic(|data: &AppState, _| get_repeat_unicode(data.get_repeat()));
+    let shuffle_button: Button<AppState> =
+        Button::dynamic(|data: &AppState, _| get_shuffle_unicode(data.get_shuffle()));
 
     let plus_controller = plus_button.on_click(AppState::select_path);
     let play_pause_controller = play_pause_button.on_click(AppState::toggle_play);
     let stop_controller = stop_button.on_click(AppState::toggle_stop);
+    let repeat_controller = repeat_button.on_click(AppState::toggle_repeat);
+    let shuffle_controller = shuffle_button.on_click(AppState::toggle_shuffle);
 
     Flex::row()
         .with_spacer(5.0)
         .with_child(plus_controller)
         .with_child(play_pause_controller)
         .with_child(stop_controller)
+        .with_child(repeat_controller)
+        .with_child(shuffle_controller)
 }
 
 /// Create the progress bar
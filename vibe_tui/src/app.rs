@@ -0,0 +1,295 @@
+use std::io;
+
+use crossbeam::channel::Receiver;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use tui::layout::Rect;
+use vibe_core::library::{Library, LibraryEntry};
+use vibe_engine::effects::{LevelMeter, Levels};
+use vibe_engine::player::{Player, PlayerEvent, PlayerStatus, SEEK_STEP};
+use vibe_engine::queue::Queue;
+
+use crate::browser::Browser;
+
+/// Step of the volume keys, as a linear factor
+const VOLUME_STEP: f32 = 0.05;
+
+/// Rows moved by the page keys
+const PAGE: isize = 10;
+
+/// Part of a peak kept at each refresh, so the meter falls back smoothly
+const METER_DECAY: f32 = 0.7;
+
+/// Pane receiving the navigation keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Library,
+    Queue,
+}
+
+/// State of the terminal player, updated with the keys and the events of the player
+pub struct App {
+    player: Player,
+    events: Receiver<PlayerEvent>,
+    status: PlayerStatus,
+    queue: Queue,
+    queue_selected: usize,
+    browser: Browser,
+    focus: Pane,
+    levels: Option<Levels>,
+    peaks: Vec<f32>,
+    message: Option<String>,
+    progress_area: Rect,
+    quit: bool,
+}
+
+impl App {
+    /// Create the player interface browsing a library
+    ///
+    /// The meter is added at the end of the effect chain of the player
+    pub fn new(player: Player, library: Library) -> io::Result<Self> {
+        let browser = Browser::new(library)?;
+        let (levels, message) = match player.effects() {
+            Ok(effects) => {
+                // A chain refusing the meter leaves the player without meter
+                let (meter, levels) = LevelMeter::new();
+                match effects.push(Box::new(meter)) {
+                    Ok(()) => (Some(levels), None),
+                    Err((error, _)) => (None, Some(error.to_string())),
+                }
+            }
+            Err(err) => (None, Some(err)),
+        };
+
+        Ok(Self {
+            events: player.events(),
+            status: PlayerStatus::new(&player),
+            queue: player.queue(),
+            player,
+            queue_selected: 0,
+            browser,
+            focus: Pane::Library,
+            levels,
+            peaks: Vec::new(),
+            message,
+            progress_area: Rect::default(),
+            quit: false,
+        })
+    }
+
+    /// Apply the events received from the player, called before each draw
+    pub fn update(&mut self) {
+        for event in self.events.try_iter() {
+            self.status.handle_event(&event);
+        }
+        self.queue = self.player.queue();
+        self.queue_selected = self.queue_selected.min(self.queue.len().saturating_sub(1));
+
+        if let Some(levels) = &self.levels {
+            let peaks = levels.peaks();
+            self.peaks.resize(peaks.len(), 0.0);
+            for (shown, peak) in self.peaks.iter_mut().zip(peaks) {
+                *shown = peak.max(*shown * METER_DECAY);
+            }
+        }
+    }
+
+    #[inline]
+    /// Get the player controlled
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    #[inline]
+    /// Get the status of the player, as of the last update
+    pub fn status(&self) -> &PlayerStatus {
+        &self.status
+    }
+
+    #[inline]
+    /// Get the play queue, as of the last update
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    #[inline]
+    /// Get the index of the selected track of the queue
+    pub fn queue_selected(&self) -> usize {
+        self.queue_selected
+    }
+
+    #[inline]
+    /// Get the library browser
+    pub fn browser(&self) -> &Browser {
+        &self.browser
+    }
+
+    #[inline]
+    /// Get the pane receiving the navigation keys
+    pub fn focus(&self) -> Pane {
+        self.focus
+    }
+
+    #[inline]
+    /// Get the peak level of each channel of the output
+    pub fn peaks(&self) -> &[f32] {
+        &self.peaks
+    }
+
+    #[inline]
+    /// Get the volume of the player as a linear factor
+    pub fn volume(&self) -> f32 {
+        self.player.volume()
+    }
+
+    #[inline]
+    /// Get the last error of the library, shown until the next key
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    #[inline]
+    /// Returns true once the user asked to quit
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    #[inline]
+    /// Set where the progress bar is drawn, for the clicks seeking in the track
+    pub fn set_progress_area(&mut self, area: Rect) {
+        self.progress_area = area;
+    }
+
+    /// Act on a key pressed
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        self.message = None;
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('c'), KeyModifiers::CONTROL)
+            | (KeyCode::Char('q'), _)
+            | (KeyCode::Esc, _) => self.quit = true,
+            (KeyCode::Tab, _) | (KeyCode::BackTab, _) => {
+                self.focus = match self.focus {
+                    Pane::Library => Pane::Queue,
+                    Pane::Queue => Pane::Library,
+                }
+            }
+            (KeyCode::Up, _) | (KeyCode::Char('k'), _) => self.move_selection(-1),
+            (KeyCode::Down, _) | (KeyCode::Char('j'), _) => self.move_selection(1),
+            (KeyCode::PageUp, _) => self.move_selection(-PAGE),
+            (KeyCode::PageDown, _) => self.move_selection(PAGE),
+            (KeyCode::Home, _) => self.move_selection(isize::MIN),
+            (KeyCode::End, _) => self.move_selection(isize::MAX),
+            (KeyCode::Enter, _) => self.activate(),
+            (KeyCode::Backspace, _) => {
+                let result = self.browser.parent();
+                self.report(result);
+            }
+            (KeyCode::Char('a'), _) => {
+                self.add_selection();
+            }
+            (KeyCode::Char('d'), _) | (KeyCode::Delete, _) if self.focus == Pane::Queue => {
+                self.player.remove_track(self.queue_selected);
+                self.update();
+            }
+            (KeyCode::Char(' '), _) => self.status.toggle_play(&self.player),
+            (KeyCode::Left, _) => self.status.seek_by(&self.player, -SEEK_STEP),
+            (KeyCode::Right, _) => self.status.seek_by(&self.player, SEEK_STEP),
+            (KeyCode::Char('n'), _) => self.player.next_track(),
+            (KeyCode::Char('p'), _) => self.player.previous_track(),
+            (KeyCode::Char('s'), _) => self.player.stop_stream(),
+            (KeyCode::Char('r'), _) => self.status.cycle_repeat(&self.player),
+            (KeyCode::Char('z'), _) => self.status.cycle_shuffle(&self.player),
+            (KeyCode::Char('+'), _) | (KeyCode::Char('='), _) => self.change_volume(VOLUME_STEP),
+            (KeyCode::Char('-'), _) => self.change_volume(-VOLUME_STEP),
+            _ => {}
+        }
+    }
+
+    /// Act on the mouse, a click on the progress bar seeks in the track
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let area = self.progress_area;
+        if mouse.kind == MouseEventKind::Down(MouseButton::Left)
+            && mouse.row >= area.y
+            && mouse.row < area.bottom()
+            && mouse.column >= area.x
+            && mouse.column < area.right()
+        {
+            let progress = f64::from(mouse.column - area.x) / f64::from(area.width);
+            self.status.seek_to(&self.player, progress);
+        }
+    }
+
+    /// Move the selection of the focused pane by a number of rows
+    fn move_selection(&mut self, offset: isize) {
+        let moved = |index: usize| -> usize {
+            if offset < 0 {
+                index.saturating_sub(offset.unsigned_abs())
+            } else {
+                index.saturating_add(offset as usize)
+            }
+        };
+        match self.focus {
+            Pane::Library => self.browser.select(moved(self.browser.selected())),
+            Pane::Queue => {
+                self.queue_selected =
+                    moved(self.queue_selected).min(self.queue.len().saturating_sub(1))
+            }
+        }
+    }
+
+    /// Open the selected directory, or play the selected track
+    fn activate(&mut self) {
+        match self.focus {
+            Pane::Library => match self.browser.selected_entry() {
+                Some(LibraryEntry::Directory(path)) => {
+                    let path = path.clone();
+                    let result = self.browser.open(path);
+                    self.report(result);
+                }
+                Some(LibraryEntry::Track(_)) => {
+                    let index = self.queue.len();
+                    if self.add_selection() {
+                        self.player.play_index(index);
+                    }
+                }
+                None => {}
+            },
+            Pane::Queue => {
+                self.player.play_index(self.queue_selected);
+            }
+        }
+    }
+
+    /// Add the tracks of the selected entry of the library to the queue
+    ///
+    /// Returns true if the tracks have been added
+    fn add_selection(&mut self) -> bool {
+        if self.focus != Pane::Library {
+            return false;
+        }
+        match self.browser.selected_tracks() {
+            Ok(tracks) => {
+                for track in tracks {
+                    self.player.enqueue(track);
+                }
+                self.update();
+                true
+            }
+            Err(err) => {
+                self.message = Some(err.to_string());
+                false
+            }
+        }
+    }
+
+    /// Change the volume, kept between silence and the level of the files
+    fn change_volume(&mut self, step: f32) {
+        self.player.set_volume(self.player.volume() + step);
+    }
+
+    /// Keep the error of an action on the library to show it
+    fn report(&mut self, result: io::Result<()>) {
+        if let Err(err) = result {
+            self.message = Some(err.to_string());
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use vibe_core::library::{Library, LibraryEntry};
+
+/// Directory of the library shown in the library pane
+pub struct Browser {
+    library: Library,
+    dir: PathBuf,
+    entries: Vec<LibraryEntry>,
+    selected: usize,
+}
+
+impl Browser {
+    /// Browse a library from its root
+    pub fn new(library: Library) -> io::Result<Self> {
+        let entries = library.list("")?;
+        Ok(Self {
+            library,
+            dir: PathBuf::new(),
+            entries,
+            selected: 0,
+        })
+    }
+
+    #[inline]
+    /// Get the library browsed
+    pub fn library(&self) -> &Library {
+        &self.library
+    }
+
+    #[inline]
+    /// Get the directory shown, relative to the root of the library
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    #[inline]
+    /// Get the subdirectories and the tracks of the directory shown
+    pub fn entries(&self) -> &[LibraryEntry] {
+        &self.entries
+    }
+
+    #[inline]
+    /// Get the index of the selected entry
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    #[inline]
+    /// Get the selected entry, if the directory isn't empty
+    pub fn selected_entry(&self) -> Option<&LibraryEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// Select an entry, the index is kept within the entries
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Show another directory of the library
+    pub fn open<P: Into<PathBuf>>(&mut self, dir: P) -> io::Result<()> {
+        let dir = dir.into();
+        self.entries = self.library.list(&dir)?;
+        self.dir = dir;
+        self.selected = 0;
+        Ok(())
+    }
+
+    /// Show the parent directory, with the directory left selected
+    pub fn parent(&mut self) -> io::Result<()> {
+        let parent = match self.dir.parent() {
+            Some(parent) => parent.to_owned(),
+            None => return Ok(()),
+        };
+        let left = self.dir.clone();
+        self.open(parent)?;
+        if let Some(index) = self.entries.iter().position(|entry| entry.path() == left) {
+            self.selected = index;
+        }
+        Ok(())
+    }
+
+    /// Get the files of the selected entry, every track under it for a directory
+    pub fn selected_tracks(&self) -> io::Result<Vec<PathBuf>> {
+        let paths = match self.selected_entry() {
+            Some(LibraryEntry::Directory(path)) => self.library.tracks(path)?,
+            Some(LibraryEntry::Track(path)) => vec![path.clone()],
+            None => Vec::new(),
+        };
+        paths
+            .into_iter()
+            .map(|path| self.library.resolve(path))
+            .collect()
+    }
+}
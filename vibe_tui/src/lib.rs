@@ -0,0 +1,6 @@
+pub mod app;
+pub mod browser;
+pub mod ui;
+
+pub use self::app::{App, Pane};
+pub use self::browser::Browser;
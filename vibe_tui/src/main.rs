@@ -0,0 +1,83 @@
+use std::io::{self, Stdout};
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use clap::{App as Command, Arg};
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use tui::backend::CrosstermBackend;
+use tui::Terminal;
+use vibe_core::library::Library;
+use vibe_engine::player::Player;
+use vibe_tui::{ui, App};
+
+/// Interval between two draws of the interface
+const REFRESH: Duration = Duration::from_millis(50);
+
+pub fn main() {
+    let matches = Command::new("vibe-tui")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Browse a music directory and play it in the terminal")
+        .arg(
+            Arg::with_name("DIRECTORY")
+                .help("Music directory")
+                .default_value("."),
+        )
+        .get_matches();
+    let root = PathBuf::from(matches.value_of_os("DIRECTORY").unwrap());
+
+    if let Err(err) = run(root) {
+        eprintln!("vibe-tui: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(root: PathBuf) -> Result<(), String> {
+    let library = Library::new(&root);
+    let mut app =
+        App::new(Player::new(), library).map_err(|err| format!("{}: {}", root.display(), err))?;
+
+    let mut terminal = Screen::enter().map_err(|err| err.to_string())?;
+    while !app.should_quit() {
+        app.update();
+        terminal
+            .0
+            .draw(|frame| ui::draw(frame, &mut app))
+            .map_err(|err| err.to_string())?;
+
+        if event::poll(REFRESH).map_err(|err| err.to_string())? {
+            match event::read().map_err(|err| err.to_string())? {
+                Event::Key(key) => app.handle_key(key),
+                Event::Mouse(mouse) => app.handle_mouse(mouse),
+                Event::Resize(..) => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Terminal in raw mode on the alternate screen, restored when dropped
+struct Screen(Terminal<CrosstermBackend<Stdout>>);
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        Ok(Screen(Terminal::new(CrosstermBackend::new(stdout))?))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = execute!(
+            self.0.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        );
+        let _ = self.0.show_cursor();
+    }
+}
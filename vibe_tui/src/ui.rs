@@ -0,0 +1,218 @@
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph};
+use tui::Frame;
+use vibe_core::library::LibraryEntry;
+use vibe_engine::player::PlayerState;
+use vibe_engine::queue::{RepeatMode, ShuffleMode};
+
+use crate::app::{App, Pane};
+
+/// Lowest level shown by the meter, in decibels
+const METER_FLOOR: f32 = -60.0;
+
+/// Highest number of channels shown by the meter
+const METER_ROWS: u16 = 2;
+
+const HELP: &str = "tab: pane  enter: open/play  a: add  d: remove  space: play/pause  \
+                    left/right: seek  n/p: next/previous  s: stop  +/-: volume  \
+                    r: repeat  z: shuffle  q: quit";
+
+/// Draw the whole interface
+pub fn draw<B: Backend>(frame: &mut Frame<B>, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length(4),
+            Constraint::Length(1),
+            Constraint::Length(METER_ROWS),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[0]);
+
+    draw_library(frame, app, panes[0]);
+    draw_queue(frame, app, panes[1]);
+    draw_now_playing(frame, app, rows[1]);
+    draw_progress(frame, app, rows[2]);
+    draw_meter(frame, app, rows[3]);
+
+    let help = match (app.message(), app.status().error()) {
+        (Some(message), _) | (None, Some(message)) => {
+            Span::styled(message.to_owned(), Style::default().fg(Color::Red))
+        }
+        (None, None) => Span::styled(HELP, Style::default().fg(Color::DarkGray)),
+    };
+    frame.render_widget(Paragraph::new(help), rows[4]);
+}
+
+/// Block around a pane, highlighted when it has the focus
+fn pane_block(title: String, focused: bool) -> Block<'static> {
+    let style = if focused {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(title)
+}
+
+/// Style of the selected row of a pane
+fn highlight_style() -> Style {
+    Style::default().add_modifier(Modifier::REVERSED)
+}
+
+fn draw_library<B: Backend>(frame: &mut Frame<B>, app: &App, area: Rect) {
+    let browser = app.browser();
+    let items: Vec<ListItem> = browser
+        .entries()
+        .iter()
+        .map(|entry| {
+            let name = entry
+                .path()
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match entry {
+                LibraryEntry::Directory(_) => ListItem::new(format!("{}/", name))
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+                LibraryEntry::Track(_) => ListItem::new(name),
+            }
+        })
+        .collect();
+
+    let title = format!(" Library: /{} ", browser.dir().display());
+    let list = List::new(items)
+        .block(pane_block(title, app.focus() == Pane::Library))
+        .highlight_style(highlight_style());
+    let mut state = ListState::default();
+    state.select(Some(browser.selected()).filter(|_| !browser.entries().is_empty()));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_queue<B: Backend>(frame: &mut Frame<B>, app: &App, area: Rect) {
+    let current = app.status().index();
+    let items: Vec<ListItem> = app
+        .queue()
+        .tracks()
+        .iter()
+        .enumerate()
+        .map(|(index, track)| {
+            let name = track
+                .path()
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if Some(index) == current {
+                ListItem::new(format!("> {}", name)).style(Style::default().fg(Color::Green))
+            } else {
+                ListItem::new(format!("  {}", name))
+            }
+        })
+        .collect();
+
+    let title = format!(" Queue: {} tracks ", app.queue().len());
+    let list = List::new(items)
+        .block(pane_block(title, app.focus() == Pane::Queue))
+        .highlight_style(highlight_style());
+    let mut state = ListState::default();
+    state.select(Some(app.queue_selected()).filter(|_| !app.queue().is_empty()));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_now_playing<B: Backend>(frame: &mut Frame<B>, app: &App, area: Rect) {
+    let status = app.status();
+    let metadata = status.metadata();
+    let title = match (status.title(), metadata.artist()) {
+        (Some(title), Some(artist)) => format!("{} - {}", artist, title),
+        (Some(title), None) => title,
+        (None, _) => "Nothing played".to_owned(),
+    };
+    let album = metadata.album().unwrap_or_default().to_owned();
+
+    let state = match status.state() {
+        PlayerState::Empty | PlayerState::Loading => "Loading",
+        PlayerState::Playing => "Playing",
+        PlayerState::Paused => "Paused",
+        PlayerState::Stopped => "Stopped",
+        PlayerState::Ended => "Ended",
+        PlayerState::Error => "Error",
+    };
+    let repeat = match status.repeat() {
+        RepeatMode::Off => "off",
+        RepeatMode::One => "one",
+        RepeatMode::All => "all",
+    };
+    let shuffle = match status.shuffle() {
+        ShuffleMode::Off => "off",
+        ShuffleMode::NoRepeat => "on",
+        ShuffleMode::Random => "random",
+    };
+    let modes = format!(
+        "{}  repeat: {}  shuffle: {}  volume: {:.0}%",
+        state,
+        repeat,
+        shuffle,
+        app.volume() * 100.0
+    );
+
+    let text = vec![
+        Spans::from(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Spans::from(album),
+        Spans::from(Span::styled(modes, Style::default().fg(Color::DarkGray))),
+    ];
+    let block = Block::default()
+        .borders(Borders::TOP)
+        .title(" Now playing ");
+    frame.render_widget(Paragraph::new(text).block(block), area);
+}
+
+fn draw_progress<B: Backend>(frame: &mut Frame<B>, app: &mut App, area: Rect) {
+    let status = app.status();
+    let label = status.time();
+    let gauge = Gauge::default()
+        .gauge_style(Style::default().fg(Color::Cyan).bg(Color::Black))
+        .ratio(status.progress())
+        .label(label)
+        .use_unicode(true);
+    frame.render_widget(gauge, area);
+    app.set_progress_area(area);
+}
+
+fn draw_meter<B: Backend>(frame: &mut Frame<B>, app: &App, area: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Length(1); METER_ROWS as usize])
+        .split(area);
+    for (row, peak) in rows.into_iter().zip(app.peaks()) {
+        let db = 20.0 * peak.log10();
+        let ratio = ((db - METER_FLOOR) / -METER_FLOOR).clamp(0.0, 1.0);
+        let label = if db > METER_FLOOR {
+            format!("{:.0} dB", db)
+        } else {
+            "-inf".to_owned()
+        };
+        let color = if *peak >= 1.0 {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(color).bg(Color::Black))
+            .ratio(f64::from(ratio))
+            .label(label)
+            .use_unicode(true);
+        frame.render_widget(gauge, row);
+    }
+}
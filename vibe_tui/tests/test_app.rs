@@ -0,0 +1,159 @@
+#[cfg(test)]
+
+mod tests_app {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use crossterm::event::{
+        KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    };
+    use tui::backend::TestBackend;
+    use tui::Terminal;
+    use vibe_core::library::{Library, LibraryEntry};
+    use vibe_engine::player::{Player, PlayerState};
+    use vibe_engine::sink::{Capture, CaptureSink};
+    use vibe_engine::stream::{FadeConfig, OutputFormat};
+    use vibe_tui::{ui, App, Pane};
+
+    const SOUNDS: &str = "../vibe_core/tests/sounds";
+
+    /// Time rendered at each step of the output
+    const STEP: Duration = Duration::from_millis(10);
+
+    /// Create a library with an album of two tracks and a track at its root
+    fn library(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("vibe_tui_{}_{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("Album")).unwrap();
+        fs::copy(format!("{}/Test1.wav", SOUNDS), root.join("Album/1.wav")).unwrap();
+        fs::copy(format!("{}/Test1.ogg", SOUNDS), root.join("Album/2.ogg")).unwrap();
+        fs::copy(format!("{}/Test1.flac", SOUNDS), root.join("Single.flac")).unwrap();
+        root
+    }
+
+    /// Create the interface on a player rendering into a capture sink
+    fn app(root: &PathBuf) -> (App, Capture) {
+        let (sink, capture) = CaptureSink::new(OutputFormat {
+            sample_rate: 48000,
+            channels: 2,
+        });
+        let player = Player::with_sink(sink, FadeConfig::default());
+        (App::new(player, Library::new(root)).unwrap(), capture)
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    /// Render the output until the player is playing, with the events applied
+    fn play(app: &mut App, capture: &Capture) {
+        while app.status().position() == Duration::from_secs(0) {
+            assert!(capture.elapsed() < Duration::from_secs(10));
+            capture.advance(STEP);
+            std::thread::sleep(Duration::from_millis(1));
+            app.update();
+        }
+    }
+
+    /// Get the text drawn on the terminal
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area().width as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.symbol.as_str())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+
+    fn test_app_browse() {
+        let root = library("browse");
+        let (mut app, _capture) = app(&root);
+        assert_eq!(app.focus(), Pane::Library);
+        assert_eq!(app.browser().entries().len(), 2);
+
+        // The album is opened, then left with the album selected
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.browser().dir(), PathBuf::from("Album"));
+        press(&mut app, KeyCode::End);
+        assert_eq!(
+            app.browser().selected_entry(),
+            Some(&LibraryEntry::Track(PathBuf::from("Album/2.ogg")))
+        );
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.browser().dir(), PathBuf::from(""));
+        assert_eq!(app.browser().selected(), 0);
+
+        // The whole album is added, then the single
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char('a'));
+        assert_eq!(app.queue().len(), 3);
+        assert_eq!(app.queue().tracks()[2].path(), root.join("Single.flac"));
+
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.focus(), Pane::Queue);
+        press(&mut app, KeyCode::PageDown);
+        assert_eq!(app.queue_selected(), 2);
+        press(&mut app, KeyCode::Delete);
+        assert_eq!(app.queue().len(), 2);
+        assert_eq!(app.queue_selected(), 1);
+
+        press(&mut app, KeyCode::Char('q'));
+        assert!(app.should_quit());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+
+    fn test_app_playback() {
+        let root = library("playback");
+        let (mut app, capture) = app(&root);
+
+        // Enter on a track adds it and plays it
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+        play(&mut app, &capture);
+        assert_eq!(app.status().state(), PlayerState::Playing);
+        assert_eq!(app.status().title().as_deref(), Some("Single.flac"));
+        assert_eq!(app.peaks().len(), 2);
+        assert!(app.peaks().iter().any(|&peak| peak > 0.0));
+
+        press(&mut app, KeyCode::Char('-'));
+        press(&mut app, KeyCode::Char('-'));
+        assert!((app.volume() - 0.9).abs() < 1e-6);
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| ui::draw(frame, &mut app)).unwrap();
+        let text = screen(&terminal);
+        assert!(text.contains("Library: /"));
+        assert!(text.contains("Queue: 1 tracks"));
+        assert!(text.contains("> Single.flac"));
+        assert!(text.contains("Playing  repeat: off  shuffle: off  volume: 90%"));
+        assert!(text.contains(" / 0:03"));
+
+        // A click in the middle of the progress bar, above the meter and the help line
+        app.handle_mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column: 50,
+            row: 16,
+            modifiers: KeyModifiers::NONE,
+        });
+        let half = app.status().duration().unwrap().as_secs_f64() / 2.0;
+        assert!((app.status().position().as_secs_f64() - half).abs() < 1e-3);
+
+        press(&mut app, KeyCode::Char(' '));
+        app.update();
+        assert_eq!(app.status().state(), PlayerState::Paused);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}